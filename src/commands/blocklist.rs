@@ -0,0 +1,234 @@
+use poise::serenity_prelude::{Attachment, Colour, CreateAttachment, CreateEmbed};
+
+use crate::commands::settings::update_settings;
+use crate::domain::settings::{BlockRule, MAX_BLOCKLIST_RULES};
+use crate::services::error::MusicError;
+use crate::services::settings_service::SettingsService;
+use crate::{Context, Error};
+
+const BLOCKLIST_COLOR: Colour = Colour::new(0xED4245);
+/// Largest file `/blocklist import` reads, in bytes. A full blocklist of
+/// maximum-length rules fits with room to spare.
+const MAX_IMPORT_BYTES: u32 = 256 * 1024;
+/// Room for rules under Discord's 4096 character description limit.
+const DESCRIPTION_BUDGET: usize = 4000;
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum RuleKind {
+    #[name = "URL"]
+    Url,
+    #[name = "Channel"]
+    Channel,
+    #[name = "Keyword"]
+    Keyword,
+}
+
+fn to_rule(kind: RuleKind, value: String) -> BlockRule {
+    let value = value.trim().to_string();
+    match kind {
+        RuleKind::Url => BlockRule::Url(value),
+        RuleKind::Channel => BlockRule::Channel(value),
+        RuleKind::Keyword => BlockRule::Keyword(value),
+    }
+}
+
+/// Manage tracks, channels, and keywords that can't be queued
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "remove", "list", "export", "import"),
+    subcommand_required,
+    default_member_permissions = "MANAGE_GUILD"
+)]
+pub async fn blocklist(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Block a track URL, channel name, or title keyword
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "What to match against"] kind: RuleKind,
+    #[description = "URL, channel name, or keyword"] value: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let rule = to_rule(kind, value).validated().map_err(MusicError::InvalidBlockRule)?;
+
+    let mut added = false;
+    let mut full = false;
+    update_settings(ctx.data(), guild_id, |s| {
+        if s.blocklist.contains(&rule) {
+            return;
+        }
+        if s.blocklist.len() >= MAX_BLOCKLIST_RULES {
+            full = true;
+            return;
+        }
+        s.blocklist.push(rule.clone());
+        added = true;
+    })
+    .await;
+    if full {
+        return Err(MusicError::BlocklistFull(MAX_BLOCKLIST_RULES).into());
+    }
+
+    let msg = if added {
+        format!("Blocked {rule}.")
+    } else {
+        format!("{rule} is already blocked.")
+    };
+    ctx.send(poise::CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Unblock a track URL, channel name, or title keyword
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "What the rule matches against"] kind: RuleKind,
+    #[description = "URL, channel name, or keyword"] value: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let rule = to_rule(kind, value);
+
+    let mut removed = false;
+    update_settings(ctx.data(), guild_id, |s| {
+        let before = s.blocklist.len();
+        s.blocklist.retain(|r| r != &rule);
+        removed = s.blocklist.len() != before;
+    })
+    .await;
+
+    let msg = if removed {
+        format!("Unblocked {rule}.")
+    } else {
+        format!("{rule} isn't on the blocklist.")
+    };
+    ctx.send(poise::CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Show the blocklist
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let settings = SettingsService::get(&ctx.data().guild_settings, guild_id).await;
+
+    let desc = if settings.blocklist.is_empty() {
+        "Nothing is blocked.".to_string()
+    } else {
+        let mut lines = Vec::new();
+        let mut chars = 0;
+        for (i, rule) in settings.blocklist.iter().enumerate() {
+            let line = format!("`{}.` {rule}", i + 1);
+            chars += line.chars().count() + 1;
+            if chars > DESCRIPTION_BUDGET {
+                break;
+            }
+            lines.push(line);
+        }
+        let hidden = settings.blocklist.len() - lines.len();
+        if hidden > 0 {
+            lines.push(format!("…and {hidden} more, see `/blocklist export` for all of them"));
+        }
+        lines.join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Blocklist")
+        .description(desc)
+        .colour(BLOCKLIST_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Download the blocklist as a JSON file another server can import
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn export(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let settings = SettingsService::get(&ctx.data().guild_settings, guild_id).await;
+
+    let json = serde_json::to_vec_pretty(&settings.blocklist)?;
+    let count = settings.blocklist.len();
+    let word = if count == 1 { "rule" } else { "rules" };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!("{count} {word}. Import the file elsewhere with `/blocklist import`."))
+            .attachment(CreateAttachment::bytes(json, "blocklist.json"))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Add the rules from an exported blocklist file, skipping ones already here
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn import(
+    ctx: Context<'_>,
+    #[description = "A file made by /blocklist export"] file: Attachment,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    if file.size > MAX_IMPORT_BYTES {
+        let reason = format!("it's over {} KB", MAX_IMPORT_BYTES / 1024);
+        return Err(MusicError::InvalidBlocklistFile(reason).into());
+    }
+    ctx.defer_ephemeral().await?;
+
+    let bytes = file.download().await.map_err(|e| {
+        tracing::warn!("Couldn't download blocklist import in guild {guild_id}: {e}");
+        MusicError::InvalidBlocklistFile("it couldn't be downloaded".to_string())
+    })?;
+    let rules = parse_import(&bytes).map_err(MusicError::InvalidBlocklistFile)?;
+
+    // Everything is checked before the settings are touched, and the merge
+    // below either adds every new rule or none
+    let total = rules.len();
+    let mut added = None;
+    update_settings(ctx.data(), guild_id, |s| {
+        let mut fresh: Vec<BlockRule> = Vec::new();
+        for rule in rules {
+            if !s.blocklist.contains(&rule) && !fresh.contains(&rule) {
+                fresh.push(rule);
+            }
+        }
+        if s.blocklist.len() + fresh.len() <= MAX_BLOCKLIST_RULES {
+            added = Some(fresh.len());
+            s.blocklist.extend(fresh);
+        }
+    })
+    .await;
+    let Some(added) = added else {
+        return Err(MusicError::BlocklistFull(MAX_BLOCKLIST_RULES).into());
+    };
+    tracing::info!("Imported {added} blocklist rules in guild {guild_id} by {}", ctx.author().id);
+
+    let word = if added == 1 { "rule" } else { "rules" };
+    let msg = format!(
+        "Imported {added} {word}. {} already on the blocklist or repeated in the file.",
+        total - added
+    );
+    ctx.send(poise::CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Parses and re-validates every rule in an exported file. Fails on the
+/// first bad entry, naming it.
+fn parse_import(bytes: &[u8]) -> Result<Vec<BlockRule>, String> {
+    let rules: Vec<BlockRule> =
+        serde_json::from_slice(bytes).map_err(|_| "it isn't a blocklist export".to_string())?;
+    if rules.len() > MAX_BLOCKLIST_RULES {
+        return Err(format!("it has more than {MAX_BLOCKLIST_RULES} rules"));
+    }
+    rules
+        .into_iter()
+        .enumerate()
+        .map(|(i, rule)| {
+            rule.validated()
+                .map_err(|reason| format!("entry {} is invalid, {reason}", i + 1))
+        })
+        .collect()
+}
@@ -0,0 +1,213 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use poise::serenity_prelude::{CreateActionRow, CreateEmbed, EditMessage, GuildId, Http, MessageId};
+use songbird::tracks::{PlayMode, TrackHandle};
+
+use crate::commands::now_playing::build_now_playing_components;
+use crate::commands::standby::{is_on_standby, STANDBY_NOTICE};
+use crate::commands::embeds::{numbered_line, now_playing_embed};
+use crate::domain::settings::{GuildSettings, NowPlayingMode};
+use crate::domain::track::{format_secs, Track};
+use crate::commands::cleanup::now_playing_lock;
+use crate::services::queue_service::QueueService;
+use crate::services::settings_service::SettingsService;
+use crate::Data;
+
+/// How often the board's progress line is refreshed between track changes.
+const BOARD_REFRESH: Duration = Duration::from_secs(30);
+/// Pending tracks listed on the board.
+const BOARD_UPCOMING: usize = 5;
+const PROGRESS_SEGMENTS: usize = 14;
+
+/// Board refreshes sent to Discord since startup, and those skipped because
+/// the board already showed the same thing.
+static BOARD_EDITS: AtomicU64 = AtomicU64::new(0);
+static BOARD_EDITS_SKIPPED: AtomicU64 = AtomicU64::new(0);
+
+/// `(sent, skipped)` board refreshes across all guilds, for `/debug`.
+pub fn board_edit_counts() -> (u64, u64) {
+    (BOARD_EDITS.load(Ordering::Relaxed), BOARD_EDITS_SKIPPED.load(Ordering::Relaxed))
+}
+
+/// Fingerprint of a rendered board, to tell whether an edit would change
+/// anything.
+fn render_hash(embed: &CreateEmbed, components: &[CreateActionRow]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_vec(&(embed, components))
+        .unwrap_or_default()
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Records that `message_id` now shows `rendered`. Returns `false` when it
+/// already did, so the edit can be skipped.
+pub(crate) async fn note_rendered(
+    data: &Data,
+    guild_id: GuildId,
+    message_id: MessageId,
+    embed: &CreateEmbed,
+    components: &[CreateActionRow],
+) -> bool {
+    let rendered = render_hash(embed, components);
+    let mut renders = data.board_renders.write().await;
+    renders.entry(guild_id).or_default().insert(message_id, rendered) != Some(rendered)
+}
+
+/// "1:23 ▬▬▬▬🔘▬▬▬▬▬ 3:45", or just the elapsed time when the length is unknown.
+fn progress_line(position: Duration, duration: Option<Duration>) -> String {
+    let elapsed = format_secs(position.as_secs());
+    let Some(duration) = duration.filter(|d| !d.is_zero()) else {
+        return format!("`{elapsed}`");
+    };
+
+    let ratio = (position.as_secs_f64() / duration.as_secs_f64()).clamp(0.0, 1.0);
+    let knob = ((ratio * PROGRESS_SEGMENTS as f64) as usize).min(PROGRESS_SEGMENTS - 1);
+    let bar: String = (0..PROGRESS_SEGMENTS)
+        .map(|i| if i == knob { "🔘" } else { "▬" })
+        .collect();
+    format!("`{elapsed}` {bar} `{}`", format_secs(duration.as_secs()))
+}
+
+fn board_embed(
+    track: &Track,
+    position: Duration,
+    upcoming: &[Track],
+    muted: bool,
+    standby: bool,
+    settings: &GuildSettings,
+) -> CreateEmbed {
+    let up_next = if upcoming.is_empty() {
+        "Nothing queued".to_string()
+    } else {
+        upcoming
+            .iter()
+            .enumerate()
+            .map(|(i, t)| numbered_line(i + 1, t))
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = now_playing_embed(track, None, settings)
+        .title("Session board")
+        .field("Progress", progress_line(position, track.duration), false);
+    let embed = if muted {
+        embed.field("Muted", "🔇 A moderator has muted me, so playback is on hold", false)
+    } else {
+        embed
+    };
+    let embed = if standby {
+        embed.field("Standby", STANDBY_NOTICE, false)
+    } else {
+        embed
+    };
+    embed.field("Up next", up_next, false)
+}
+
+/// Renders the board for `track`, reading progress and pause state from
+/// `handle` when there is one.
+pub async fn board_content(
+    data: &Data,
+    guild_id: GuildId,
+    track: &Track,
+    handle: Option<&TrackHandle>,
+    settings: &GuildSettings,
+) -> (CreateEmbed, Vec<CreateActionRow>) {
+    let info = match handle {
+        Some(handle) => handle.get_info().await.ok(),
+        None => None,
+    };
+    let position = info
+        .as_ref()
+        .map_or(Duration::ZERO, |info| track.elapsed(info.position));
+    let paused = info.is_some_and(|info| info.playing == PlayMode::Pause);
+    let repeating = data
+        .repeat_states
+        .read()
+        .await
+        .get(&guild_id)
+        .copied()
+        .unwrap_or(false);
+    let upcoming = QueueService::upcoming(&data.guild_queues, guild_id, BOARD_UPCOMING).await;
+    let muted = data.server_mutes.read().await.contains_key(&guild_id);
+    let standby = is_on_standby(data, guild_id).await;
+
+    (
+        board_embed(track, position, &upcoming, muted, standby, settings),
+        build_now_playing_components(guild_id, paused, repeating),
+    )
+}
+
+/// Re-renders the board in place in every channel it's posted in, if the
+/// guild uses one.
+pub(crate) async fn refresh_board(
+    manager: &songbird::Songbird,
+    http: &Http,
+    data: &Data,
+    guild_id: GuildId,
+) {
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    if settings.now_playing_mode != NowPlayingMode::Board {
+        return;
+    }
+
+    let np_lock = now_playing_lock(data, guild_id).await;
+    let _guard = np_lock.lock().await;
+
+    let Some(cards) = data.now_playing_messages.read().await.get(&guild_id).copied() else {
+        return;
+    };
+    let Some(track) = QueueService::current(&data.guild_queues, guild_id).await else {
+        return;
+    };
+    let handle = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.queue().current(),
+        None => None,
+    };
+
+    let (embed, components) =
+        board_content(data, guild_id, &track, handle.as_ref(), &settings).await;
+    for (channel_id, message_id) in cards.iter() {
+        if !note_rendered(data, guild_id, message_id, &embed, &components).await {
+            BOARD_EDITS_SKIPPED.fetch_add(1, Ordering::Relaxed);
+            continue;
+        }
+        BOARD_EDITS.fetch_add(1, Ordering::Relaxed);
+        let edit = EditMessage::new().embed(embed.clone()).components(components.clone());
+        if let Err(e) = channel_id.edit_message(http, message_id, edit).await {
+            tracing::debug!("Failed to refresh session board in {channel_id}: {e}");
+            // Try again on the next refresh
+            if let Some(renders) = data.board_renders.write().await.get_mut(&guild_id) {
+                renders.remove(&message_id);
+            }
+        }
+    }
+
+    // Forget boards that have since been replaced or deleted
+    if let Some(renders) = data.board_renders.write().await.get_mut(&guild_id) {
+        renders.retain(|id, _| cards.iter().any(|(_, card)| card == *id));
+    }
+}
+
+/// Keeps the session board's progress current until `session` ends.
+pub fn spawn_board_updater(
+    manager: Arc<songbird::Songbird>,
+    guild_id: GuildId,
+    session: u64,
+    http: Arc<Http>,
+    data: Data,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(BOARD_REFRESH);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            if data.sessions.read().await.get(&guild_id) != Some(&session) {
+                break;
+            }
+            refresh_board(&manager, &http, &data, guild_id).await;
+        }
+    });
+}
@@ -0,0 +1,216 @@
+//! Mirrors the playing track into the voice channel's status, or into the
+//! music channel's topic when the bot can't set the status.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use poise::serenity_prelude::{self as serenity, Cache, ChannelId, EditChannel, GuildId, Http};
+use tokio::sync::{Mutex, RwLock};
+
+use crate::domain::track::Track;
+use crate::Data;
+
+/// Shortest gap between two voice status updates for a guild. Track
+/// changes inside it are batched into one update with the latest track.
+const STATUS_INTERVAL: Duration = Duration::from_secs(15);
+/// Channel topic edits are limited to 2 per 10 minutes per channel.
+const TOPIC_INTERVAL: Duration = Duration::from_secs(5 * 60);
+/// Longest status text set, well under Discord's limits for both.
+const STATUS_MAX_CHARS: usize = 100;
+
+/// What each guild's voice channel status shows, and when it last changed.
+pub type ChannelStatuses = Arc<RwLock<HashMap<GuildId, Arc<Mutex<ChannelStatus>>>>>;
+
+/// Where a guild's status goes and what's waiting to go there.
+pub struct ChannelStatus {
+    http: Arc<Http>,
+    voice_channel: Option<ChannelId>,
+    text_channel: Option<ChannelId>,
+    /// Text to show next; empty clears it.
+    wanted: String,
+    /// What was last set, so an unchanged status isn't sent again.
+    shown: Option<String>,
+    /// The voice channel that status was set in, cleared if the bot moves.
+    status_channel: Option<ChannelId>,
+    last_sent: Option<Instant>,
+    /// A batched update is already waiting for the interval to pass.
+    flush_pending: bool,
+    /// The text channel whose topic is used instead, with its topic from
+    /// before, to put back when the session ends.
+    topic_fallback: Option<(ChannelId, Option<String>)>,
+    /// The session is over; nothing more is sent.
+    ended: bool,
+}
+
+impl ChannelStatus {
+    fn interval(&self) -> Duration {
+        if self.topic_fallback.is_some() { TOPIC_INTERVAL } else { STATUS_INTERVAL }
+    }
+}
+
+/// "🎵 Title — Artist", cut to fit.
+fn status_text(track: &Track) -> String {
+    let text = format!("🎵 {} — {}", track.title, track.artist);
+    if text.chars().count() <= STATUS_MAX_CHARS {
+        return text;
+    }
+    let mut cut: String = text.chars().take(STATUS_MAX_CHARS - 1).collect();
+    cut.push('…');
+    cut
+}
+
+/// Shows `track` as the guild's channel status, now or batched into the next
+/// update when one went out too recently. Failures are only logged, so
+/// playback never depends on it.
+pub(crate) async fn show_track(
+    http: &Arc<Http>,
+    cache: &Cache,
+    data: &Data,
+    guild_id: GuildId,
+    text_channel: Option<ChannelId>,
+    track: &Track,
+) {
+    let voice_channel = cache.guild(guild_id).and_then(|guild| {
+        guild.voice_states.get(&cache.current_user().id).and_then(|vs| vs.channel_id)
+    });
+    let state = {
+        let mut statuses = data.channel_statuses.write().await;
+        statuses
+            .entry(guild_id)
+            .or_insert_with(|| {
+                Arc::new(Mutex::new(ChannelStatus {
+                    http: http.clone(),
+                    voice_channel,
+                    text_channel,
+                    wanted: String::new(),
+                    shown: None,
+                    status_channel: None,
+                    last_sent: None,
+                    flush_pending: false,
+                    topic_fallback: None,
+                    ended: false,
+                }))
+            })
+            .clone()
+    };
+
+    let wait = {
+        let mut status = state.lock().await;
+        status.voice_channel = voice_channel.or(status.voice_channel);
+        status.text_channel = text_channel.or(status.text_channel);
+        status.wanted = status_text(track);
+        if status.flush_pending {
+            return;
+        }
+        status.flush_pending = true;
+        let interval = status.interval();
+        status
+            .last_sent
+            .map_or(Duration::ZERO, |sent| interval.saturating_sub(sent.elapsed()))
+    };
+
+    tokio::spawn(async move {
+        tokio::time::sleep(wait).await;
+        let mut status = state.lock().await;
+        status.flush_pending = false;
+        if !status.ended {
+            flush(&mut status, guild_id).await;
+        }
+    });
+}
+
+/// Sends the wanted status, falling back to the music channel's topic the
+/// first time the voice status can't be set.
+async fn flush(status: &mut ChannelStatus, guild_id: GuildId) {
+    if status.shown.as_deref() == Some(status.wanted.as_str()) {
+        return;
+    }
+    let text = status.wanted.clone();
+
+    if status.topic_fallback.is_none() {
+        let Some(voice_channel) = status.voice_channel else {
+            return;
+        };
+        // `/join` moved the bot, so the old channel's status is stale
+        if let Some(old) = status.status_channel.filter(|old| *old != voice_channel) {
+            let _ = set_voice_status(&status.http, old, "").await;
+            status.status_channel = None;
+        }
+        match set_voice_status(&status.http, voice_channel, &text).await {
+            Ok(()) => {
+                status.shown = Some(text);
+                status.status_channel = Some(voice_channel);
+                status.last_sent = Some(Instant::now());
+                return;
+            }
+            Err(e) => {
+                tracing::debug!("Couldn't set the voice channel status in guild {guild_id}: {e}");
+            }
+        }
+        let Some(text_channel) = status.text_channel else {
+            return;
+        };
+        let original = match text_channel.to_channel(&status.http).await {
+            Ok(channel) => channel.guild().and_then(|channel| channel.topic),
+            Err(e) => {
+                tracing::debug!("Couldn't read the topic of {text_channel}: {e}");
+                return;
+            }
+        };
+        status.topic_fallback = Some((text_channel, original));
+    }
+
+    let Some((text_channel, _)) = status.topic_fallback else {
+        return;
+    };
+    let edit = EditChannel::new().topic(text.as_str());
+    match text_channel.edit(&status.http, edit).await {
+        Ok(_) => {
+            status.shown = Some(text);
+            status.last_sent = Some(Instant::now());
+        }
+        Err(e) => tracing::debug!("Couldn't set the topic of {text_channel}: {e}"),
+    }
+}
+
+async fn set_voice_status(
+    http: &Http,
+    channel_id: ChannelId,
+    text: &str,
+) -> Result<(), serenity::Error> {
+    let body = serde_json::json!({ "status": text });
+    http.edit_voice_status(channel_id, &body, None).await
+}
+
+/// Clears the guild's status, or puts its music channel's topic back, when
+/// the session ends. Runs in the background, since a topic edit can wait out
+/// a rate limit.
+pub(crate) async fn clear_status(data: &Data, guild_id: GuildId) {
+    let Some(state) = data.channel_statuses.write().await.remove(&guild_id) else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut status = state.lock().await;
+        status.ended = true;
+        if status.shown.is_none() {
+            return;
+        }
+        match status.topic_fallback.clone() {
+            Some((text_channel, original)) => {
+                let edit = EditChannel::new().topic(original.unwrap_or_default());
+                if let Err(e) = text_channel.edit(&status.http, edit).await {
+                    tracing::debug!("Couldn't restore the topic of {text_channel}: {e}");
+                }
+            }
+            None => {
+                let Some(voice_channel) = status.status_channel else {
+                    return;
+                };
+                if let Err(e) = set_voice_status(&status.http, voice_channel, "").await {
+                    tracing::debug!("Couldn't clear the voice status in guild {guild_id}: {e}");
+                }
+            }
+        }
+    });
+}
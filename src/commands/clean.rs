@@ -0,0 +1,136 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use poise::serenity_prelude::{self as serenity, Cache, CreateMessage, GuildId, VoiceState};
+
+use crate::commands::play::{bot_voice_channel, remove_from_playback};
+use crate::domain::track::Requester;
+use crate::services::error::MusicError;
+use crate::services::queue_service::QueueService;
+use crate::services::settings_service::SettingsService;
+use crate::{Context, Data, Error};
+
+/// Removes pending tracks queued by users who are no longer in the bot's voice
+/// channel. Tracks the bot queued itself, for autoplay, a scheduled play or
+/// after a restart, only go with `autoplay`; tracks without a recorded
+/// requester are kept.
+///
+/// Returns `None` when the bot isn't connected, otherwise the number of
+/// tracks removed from people, how many distinct users they belonged to, and
+/// the number of the bot's own tracks removed.
+async fn remove_absent_requesters(
+    cache: &Cache,
+    manager: &Arc<songbird::Songbird>,
+    guild_id: GuildId,
+    data: &Data,
+    autoplay: bool,
+) -> Option<(usize, usize, usize)> {
+    let handler_lock = manager.get(guild_id)?;
+    let bot_channel = bot_voice_channel(manager, guild_id).await?;
+
+    let present: HashSet<_> = {
+        let guild = cache.guild(guild_id)?;
+        guild
+            .voice_states
+            .values()
+            .filter(|vs| vs.channel_id == Some(bot_channel))
+            .map(|vs| vs.user_id)
+            .collect()
+    };
+
+    let removed = QueueService::remove_where(&data.guild_queues, guild_id, |track| {
+        match track.requested_by {
+            Some(Requester::User(user_id)) => !present.contains(&user_id),
+            Some(_) => autoplay,
+            None => false,
+        }
+    })
+    .await;
+
+    let handle_ids: Vec<_> = removed.iter().filter_map(|t| t.handle_id).collect();
+    remove_from_playback(&handler_lock, &handle_ids).await;
+
+    let users: HashSet<_> = removed
+        .iter()
+        .filter_map(|t| t.requested_by.and_then(Requester::user))
+        .collect();
+    let from_users = removed
+        .iter()
+        .filter(|t| matches!(t.requested_by, Some(Requester::User(_))))
+        .count();
+    Some((from_users, users.len(), removed.len() - from_users))
+}
+
+fn removed_message(tracks: usize, users: usize, automatic: usize) -> String {
+    let track_word = |n| if n == 1 { "track" } else { "tracks" };
+    let mut parts = Vec::new();
+    if tracks > 0 {
+        let user_word = if users == 1 { "user" } else { "users" };
+        parts.push(format!("{tracks} {} from {users} absent {user_word}", track_word(tracks)));
+    }
+    if automatic > 0 {
+        parts.push(format!("{automatic} {} the bot queued itself", track_word(automatic)));
+    }
+    format!("Removed {}.", parts.join(" and "))
+}
+
+/// Remove queued tracks from people who left the voice channel
+#[poise::command(slash_command, guild_only)]
+pub async fn clean(
+    ctx: Context<'_>,
+    #[description = "Also remove the bot's own tracks: autoplay, scheduled plays and restores"]
+    autoplay: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    let autoplay = autoplay.unwrap_or(false);
+    let (tracks, users, removed_automatic) =
+        remove_absent_requesters(ctx.cache(), &manager, guild_id, ctx.data(), autoplay)
+            .await
+            .ok_or(MusicError::EmptyQueue)?;
+
+    if tracks + removed_automatic == 0 {
+        ctx.say("Everyone with tracks in the queue is still here.").await?;
+    } else {
+        ctx.say(removed_message(tracks, users, removed_automatic)).await?;
+    }
+    Ok(())
+}
+
+/// Runs the `/clean` logic when someone leaves the bot's channel and the guild
+/// has `auto_clean` enabled.
+pub async fn handle_voice_state_update(ctx: &serenity::Context, new: &VoiceState, data: &Data) {
+    let Some(guild_id) = new.guild_id else {
+        return;
+    };
+    if new.user_id == ctx.cache.current_user().id {
+        return;
+    }
+    if !SettingsService::get(&data.guild_settings, guild_id).await.auto_clean {
+        return;
+    }
+
+    let manager = songbird::get(ctx).await.expect("Songbird not registered");
+    if new.channel_id.is_some() && new.channel_id == bot_voice_channel(&manager, guild_id).await {
+        return;
+    }
+
+    let Some((tracks, users, _)) =
+        remove_absent_requesters(&ctx.cache, &manager, guild_id, data, false).await
+    else {
+        return;
+    };
+    if tracks == 0 {
+        return;
+    }
+
+    let text_channel = data.text_channels.read().await.get(&guild_id).copied();
+    if let Some(channel_id) = text_channel {
+        let msg = CreateMessage::new().content(removed_message(tracks, users, 0));
+        let _ = channel_id.send_message(&ctx.http, msg).await;
+    }
+}
@@ -0,0 +1,442 @@
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, Colour, CreateAllowedMentions, CreateEmbed, CreateMessage,
+    EditMessage, GuildId, Http, HttpError, MessageId, StatusCode, UserId, VoiceState,
+};
+use chrono::Utc;
+use songbird::Call;
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::commands::channel_status::clear_status;
+use crate::commands::play::{spawn_background_enqueue, RemainingPages};
+use crate::domain::incident::PlaybackIncident;
+use crate::domain::settings::{GuildSettings, NowPlayingMode};
+use crate::domain::track::{Requester, Track};
+use crate::infrastructure::enqueue_store::{EnqueueStore, PendingCollection};
+use crate::services::history_service::HistoryService;
+use crate::services::incident_service::IncidentService;
+use crate::services::now_playing_service::NowPlayingService;
+use crate::services::queue_service::QueueService;
+use crate::services::session_service::SessionService;
+use crate::services::settings_service::SettingsService;
+use crate::Data;
+
+/// Why a session ended, told to the music channel when the bot leaves.
+#[derive(Debug, Clone, Copy)]
+pub enum DisconnectReason {
+    /// Nothing played for the timeout.
+    Inactivity(Duration),
+    /// Nobody was listening for the timeout: the bot was alone in its
+    /// channel, or everyone left was deafened.
+    EveryoneLeft(Duration),
+    /// `/stop` or the Now Playing Stop button.
+    Stopped(UserId),
+    /// `/reset`.
+    Reset(UserId),
+    /// Someone else disconnected the bot, e.g. a moderator.
+    Kicked,
+    /// The voice connection failed and couldn't be restored.
+    VoiceError,
+}
+
+impl DisconnectReason {
+    /// The notice to post, or `None` when the guild turned idle notices off.
+    fn notice(&self, settings: &GuildSettings) -> Option<String> {
+        let minutes = |timeout: Duration| format!("{} minutes", timeout.as_secs() / 60);
+        match *self {
+            DisconnectReason::Inactivity(timeout) => settings.inactivity_text(&minutes(timeout)),
+            DisconnectReason::EveryoneLeft(timeout) => settings.inactivity_notice.then(|| {
+                format!("Left the voice channel — nobody was listening for {}.", minutes(timeout))
+            }),
+            DisconnectReason::Stopped(user) => {
+                Some(format!("Stopped by <@{user}> — left the voice channel."))
+            }
+            DisconnectReason::Reset(user) => {
+                Some(format!("Reset by <@{user}> — left the voice channel."))
+            }
+            DisconnectReason::Kicked => Some(
+                "Disconnected from the voice channel by a moderator, so the session ended."
+                    .to_string(),
+            ),
+            DisconnectReason::VoiceError => Some(
+                "Lost the voice connection, so the session ended. Use `/play` to start again."
+                    .to_string(),
+            ),
+        }
+    }
+}
+
+/// Returns the guild's Now Playing lock. Hold it while replacing or removing
+/// the Now Playing message so two track changes can't interleave.
+pub async fn now_playing_lock(data: &Data, guild_id: GuildId) -> Arc<Mutex<()>> {
+    NowPlayingService::lock(&data.now_playing_locks, guild_id).await
+}
+
+/// Takes an old Now Playing message down. If it can't be deleted, its buttons
+/// are stripped instead so two live control panels aren't left in the channel.
+pub async fn retire_now_playing_message(
+    http: &Http,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    unpin: bool,
+) {
+    if unpin {
+        let _ = channel_id.unpin(http, message_id).await;
+    }
+
+    match channel_id.delete_message(http, message_id).await {
+        Ok(()) => {}
+        // Already gone, e.g. deleted by a moderator
+        Err(serenity::Error::Http(HttpError::UnsuccessfulRequest(resp)))
+            if resp.status_code == StatusCode::NOT_FOUND => {}
+        Err(e) => {
+            tracing::warn!("Failed to delete Now Playing message, disabling it instead: {e}");
+            let edit = EditMessage::new().components(Vec::new());
+            if let Err(e) = channel_id.edit_message(http, message_id, edit).await {
+                tracing::warn!("Failed to disable Now Playing message: {e}");
+            }
+        }
+    }
+}
+
+/// Writes the live Now Playing messages to the store, if there is one. Call
+/// it after changing `now_playing_messages`.
+pub async fn persist_now_playing(data: &Data) {
+    let Some(store) = &data.now_playing_store else {
+        return;
+    };
+    let messages = data.now_playing_messages.read().await.clone();
+    store.save(&messages).await;
+}
+
+/// Marks Now Playing messages left over from before a restart as ended and
+/// strips their buttons, which nothing answers anymore.
+pub async fn retire_orphaned_messages(http: &Http, orphans: Vec<(GuildId, ChannelId, MessageId)>) {
+    let embed = CreateEmbed::new()
+        .title("Session ended")
+        .description("The bot restarted. Use `/play` to start a new session.")
+        .colour(Colour::new(0x5865F2));
+    let mut retired = 0;
+    for (guild_id, channel_id, message_id) in orphans {
+        let edit = EditMessage::new().embed(embed.clone()).components(Vec::new());
+        match channel_id.edit_message(http, message_id, edit).await {
+            Ok(_) => retired += 1,
+            // Deleted, or the channel or guild is gone
+            Err(e) => tracing::debug!(
+                "Skipping orphaned Now Playing message in guild {guild_id}: {e}"
+            ),
+        }
+    }
+    tracing::info!("Retired {retired} Now Playing message(s) left from before the restart");
+}
+
+/// Tells each music channel which collections a restart cut short, and
+/// holds them until the guild's session is back. Sessions aren't restored,
+/// so nothing rejoins voice on its own.
+pub async fn hold_unfinished_enqueues(
+    http: Arc<Http>,
+    store: Arc<EnqueueStore>,
+    unfinished: Vec<PendingCollection>,
+) {
+    for pending in &unfinished {
+        if pending.tracks.is_empty() && pending.pages.is_none() {
+            continue;
+        }
+        let mut notice = format!("The bot restarted while adding {}", collection_name(pending));
+        if let Some(user_id) = pending.requester {
+            notice.push_str(&format!(" for <@{user_id}>"));
+        }
+        notice.push_str(". The rest will be added the next time music plays here.");
+        let msg = CreateMessage::new()
+            .content(notice)
+            .allowed_mentions(CreateAllowedMentions::new());
+        if let Err(e) = pending.channel_id.send_message(&http, msg).await {
+            let guild_id = pending.guild_id;
+            tracing::debug!("Skipping unfinished enqueue notice in guild {guild_id}: {e}");
+        }
+    }
+    store.hold(unfinished).await;
+}
+
+/// Queues the collections a restart cut short in this guild, once a fresh
+/// session has started there. Each goes behind a ticket of its own, in the
+/// order they were requested.
+pub async fn resume_held_enqueues(
+    serenity_ctx: &serenity::Context,
+    data: &Data,
+    handler_lock: &Arc<Mutex<Call>>,
+    guild_id: GuildId,
+) {
+    let Some(store) = &data.enqueue_store else {
+        return;
+    };
+    let held = store.release(guild_id).await;
+    if held.is_empty() {
+        return;
+    }
+    let channel_id = data.text_channels.read().await.get(&guild_id).copied();
+    for pending in held {
+        if let Some(channel_id) = channel_id {
+            let notice = format!(
+                "Picking up {} where the restart left off.",
+                collection_name(&pending)
+            );
+            let _ = channel_id
+                .send_message(&serenity_ctx.http, CreateMessage::new().content(notice))
+                .await;
+        }
+        let remaining = pending.pages.map(|pages| {
+            let name = pending.name.clone().unwrap_or_default();
+            RemainingPages::resumed(pages, name)
+        });
+        spawn_background_enqueue(
+            data,
+            pending.tracks,
+            remaining,
+            handler_lock.clone(),
+            serenity_ctx,
+            Requester::Restored,
+            guild_id,
+        )
+        .await;
+    }
+}
+
+/// The collection as named in notices about it.
+fn collection_name(pending: &PendingCollection) -> String {
+    match &pending.name {
+        Some(name) => format!("**{name}**"),
+        None => "a collection".to_string(),
+    }
+}
+
+/// Drops a track that won't play from the domain queue, logs it as a
+/// playback incident, and tells the music channel, e.g. "Couldn't play
+/// **Song**, skipping it.".
+pub async fn drop_failed_track(
+    http: &Http,
+    data: &Data,
+    guild_id: GuildId,
+    handle_id: Uuid,
+    problem: &str,
+    error: String,
+) {
+    let Some(track) = QueueService::remove_by_handle(&data.guild_queues, guild_id, handle_id).await
+    else {
+        return;
+    };
+    tracing::warn!("{problem} track in guild {guild_id}: {} ({error})", track.url);
+    IncidentService::record(
+        &data.playback_incidents,
+        guild_id,
+        PlaybackIncident {
+            title: track.title.clone(),
+            url: track.url.clone(),
+            error,
+            at: Utc::now(),
+        },
+    )
+    .await;
+
+    let Some(channel_id) = data.text_channels.read().await.get(&guild_id).copied() else {
+        return;
+    };
+    let msg = CreateMessage::new().content(format!("{problem} **{}**, skipping it.", track.title));
+    let _ = channel_id.send_message(http, msg).await;
+}
+
+/// Turns the session board into a "Session ended" summary with no buttons.
+/// Returns `false` if the message couldn't be edited.
+async fn end_session_board(
+    http: &Http,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    last_track: Option<Track>,
+    left_queued: usize,
+    failed: usize,
+) -> bool {
+    let mut description = match last_track {
+        Some(track) => format!("Last played **{}** - {}", track.title, track.artist),
+        None => "Nothing was playing.".to_string(),
+    };
+    if left_queued > 0 {
+        description.push_str(&format!("\n`{left_queued}` tracks were still queued."));
+    }
+    if failed > 0 {
+        let word = if failed == 1 { "track" } else { "tracks" };
+        description.push_str(&format!("\n`{failed}` {word} failed to play."));
+    }
+
+    let embed = CreateEmbed::new()
+        .title("Session ended")
+        .description(description)
+        .colour(Colour::new(0x5865F2));
+    let edit = EditMessage::new().embed(embed).components(Vec::new());
+    match channel_id.edit_message(http, message_id, edit).await {
+        Ok(_) => true,
+        Err(e) => {
+            tracing::warn!("Failed to end session board: {e}");
+            false
+        }
+    }
+}
+
+/// Starts a new voice session for the guild and returns its id. Handlers tied
+/// to the session pass the id to [`cleanup_session`].
+pub async fn start_session(data: &Data, guild_id: GuildId) -> u64 {
+    SessionService::start(&data.sessions, guild_id).await
+}
+
+/// Runs [`cleanup_guild`] only if `session` is still the guild's live
+/// session, so the inactivity monitor and the disconnect handlers don't all
+/// tear it down. Returns whether this call did the cleanup.
+pub async fn cleanup_session(
+    guild_id: GuildId,
+    session: u64,
+    data: &Data,
+    http: &Http,
+    reason: DisconnectReason,
+) -> bool {
+    if !SessionService::end(&data.sessions, guild_id, session).await {
+        return false;
+    }
+
+    cleanup_guild(guild_id, data, http, reason).await;
+    true
+}
+
+/// Ends the session when the bot's own voice state drops out of its channel
+/// while the session is still live. Every leave the bot makes itself ends
+/// the session first, so this is someone else disconnecting it.
+pub async fn handle_voice_state_update(ctx: &serenity::Context, new: &VoiceState, data: &Data) {
+    if new.user_id != ctx.cache.current_user().id || new.channel_id.is_some() {
+        return;
+    }
+    let Some(guild_id) = new.guild_id else {
+        return;
+    };
+    // Updates can be handled out of order, so go by the cache's latest state
+    // in case the bot has already rejoined
+    let rejoined = ctx.cache.guild(guild_id).is_some_and(|guild| {
+        guild.voice_states.get(&new.user_id).is_some_and(|vs| vs.channel_id.is_some())
+    });
+    if rejoined {
+        return;
+    }
+    let Some(session) = data.sessions.read().await.get(&guild_id).copied() else {
+        return;
+    };
+    if cleanup_session(guild_id, session, data, &ctx.http, DisconnectReason::Kicked).await {
+        tracing::info!("Bot was disconnected from voice in guild {guild_id}, cleaned up");
+    }
+}
+
+/// Cancels background enqueue tasks, clears the queue, stops the inactivity
+/// monitor, deletes the "Now Playing" messages for a guild, and tells the
+/// music channel why the bot left. Call this whenever the bot disconnects
+/// (by command, inactivity, or being kicked).
+pub async fn cleanup_guild(guild_id: GuildId, data: &Data, http: &Http, reason: DisconnectReason) {
+    // End the session so its own disconnect handlers become no-ops
+    data.sessions.write().await.remove(&guild_id);
+
+    // Cancel all background enqueue tasks
+    if let Some(flags) = data.enqueue_cancels.write().await.remove(&guild_id) {
+        for flag in flags {
+            flag.store(true, Ordering::Relaxed);
+        }
+    }
+
+    // Wait out any enqueue iteration that passed its cancel check before the
+    // flags were set, so nothing lands in the queue after it's cleared.
+    let enqueue_lock = data.enqueue_locks.read().await.get(&guild_id).cloned();
+    if let Some(enqueue_lock) = enqueue_lock {
+        drop(enqueue_lock.lock().await);
+    }
+    // Cancelled enqueues leave their record to this
+    if let Some(store) = &data.enqueue_store {
+        store.forget_guild(guild_id).await;
+    }
+
+    // A session board is left behind as a summary, so note what it showed
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let last_track = QueueService::current(&data.guild_queues, guild_id).await;
+    let left_queued = QueueService::list(&data.guild_queues, guild_id).await.len();
+    let failed = IncidentService::get(&data.playback_incidents, guild_id).await.total();
+
+    // Clear track queue
+    QueueService::clear(&data.guild_queues, guild_id).await;
+
+    // Cancel inactivity monitor
+    if let Some(cancel) = data.inactivity_handles.write().await.remove(&guild_id) {
+        cancel.notify_one();
+    }
+
+    // Delete the "Now Playing" message, unpinning it first. A session board
+    // is kept as a summary instead.
+    {
+        let np_lock = now_playing_lock(data, guild_id).await;
+        let _guard = np_lock.lock().await;
+        let cards = data.now_playing_messages.write().await.remove(&guild_id);
+        if let Some(cards) = cards {
+            // Each channel on its own, so one that fails doesn't hold up the rest
+            for (channel_id, message_id) in cards.iter() {
+                let summarized = settings.now_playing_mode == NowPlayingMode::Board
+                    && end_session_board(
+                        http,
+                        channel_id,
+                        message_id,
+                        last_track.clone(),
+                        left_queued,
+                        failed,
+                    )
+                    .await;
+                if !summarized {
+                    retire_now_playing_message(
+                        http,
+                        channel_id,
+                        message_id,
+                        settings.pin_now_playing,
+                    )
+                    .await;
+                } else if settings.pin_now_playing {
+                    let _ = channel_id.unpin(http, message_id).await;
+                }
+            }
+            persist_now_playing(data).await;
+        }
+    }
+
+    // Clear repeat state
+    data.repeat_states.write().await.remove(&guild_id);
+
+    // Forget the channel Now Playing updates were bound to
+    let text_channel = data.text_channels.write().await.remove(&guild_id);
+
+    // A mute notice from this session has nothing left to resume
+    data.server_mutes.write().await.remove(&guild_id);
+
+    // Incidents are per session
+    IncidentService::clear(&data.playback_incidents, guild_id).await;
+    HistoryService::clear(&data.play_histories, guild_id).await;
+
+    // So is a listening party's lock
+    data.session_locks.write().await.remove(&guild_id);
+    data.standbys.write().await.remove(&guild_id);
+    data.board_renders.write().await.remove(&guild_id);
+    data.input_clocks.write().await.remove(&guild_id);
+    clear_status(data, guild_id).await;
+
+    if let (Some(channel_id), Some(notice)) = (text_channel, reason.notice(&settings)) {
+        let msg = CreateMessage::new()
+            .content(notice)
+            .allowed_mentions(CreateAllowedMentions::new());
+        // The channel may have been deleted since the session began
+        if let Err(e) = channel_id.send_message(http, msg).await {
+            tracing::warn!("Failed to post disconnect notice in {channel_id}: {e}");
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use poise::serenity_prelude::{Colour, CreateEmbed};
+
+use crate::commands::board::board_edit_counts;
+use crate::commands::play::autocomplete_counts;
+
+use crate::services::error::MusicError;
+use crate::services::incident_service::IncidentService;
+use crate::services::queue_service::QueueService;
+use crate::{Context, Error};
+
+const DEBUG_COLOR: Colour = Colour::new(0x99AAB5);
+
+/// Show the voice session's state and recent playback failures
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "MANAGE_GUILD",
+    required_permissions = "MANAGE_GUILD"
+)]
+pub async fn debug(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
+
+    let session = data.sessions.read().await.get(&guild_id).copied();
+    let text_channel = data.text_channels.read().await.get(&guild_id).copied();
+    let pending = QueueService::pending_len(&data.guild_queues, guild_id).await;
+    let muted = data.server_mutes.read().await.contains_key(&guild_id);
+    let incidents = IncidentService::get(&data.playback_incidents, guild_id).await;
+
+    let (board_edits, board_skips) = board_edit_counts();
+    let (autocomplete_hits, autocomplete_timeouts) = autocomplete_counts();
+
+    let state = format!(
+        "Session: {}\nVoice: {}\nMusic channel: {}\nPending tracks: `{pending}`\nServer-muted: {}\n\
+         Board refreshes (all servers): `{board_edits}` edited, `{board_skips}` unchanged\n\
+         Autocomplete searches (all servers): `{autocomplete_hits}` cached, \
+         `{autocomplete_timeouts}` timed out\n\
+         yt-dlp format: `{}`\nVoice bitrate: {}",
+        session.map_or("none".to_string(), |id| format!("`#{id}`")),
+        if data.voice.is_connected(guild_id) { "connected" } else { "not connected" },
+        text_channel.map_or("none".to_string(), |id| format!("<#{id}>")),
+        if muted { "yes" } else { "no" },
+        data.ytdlp_format,
+        data.voice_bitrate.map_or("songbird default".to_string(), |bps| format!("`{bps}` bps")),
+    );
+
+    let failures = if incidents.total() == 0 {
+        "None this session.".to_string()
+    } else {
+        let mut lines: Vec<String> = incidents
+            .iter()
+            .rev()
+            .map(|incident| {
+                format!(
+                    "<t:{}:T> [{}]({}) — {}",
+                    incident.at.timestamp(),
+                    incident.title,
+                    incident.url,
+                    incident.error
+                )
+            })
+            .collect();
+        let shown = lines.len();
+        if incidents.total() > shown {
+            lines.push(format!("…and {} earlier", incidents.total() - shown));
+        }
+        lines.join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Debug")
+        .description(state)
+        .field("Recent playback failures", truncate_field(failures), false)
+        .colour(DEBUG_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Embed field values are capped at 1024 characters.
+fn truncate_field(value: String) -> String {
+    if value.chars().count() <= 1024 {
+        return value;
+    }
+    let mut cut: String = value.chars().take(1020).collect();
+    cut.push_str("\n…");
+    cut
+}
@@ -0,0 +1,619 @@
+//! Embed builders shared by the commands, so every place a track shows up
+//! formats it the same way.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use poise::serenity_prelude::{
+    Cache, Colour, CreateEmbed, CreateEmbedAuthor, CreateEmbedFooter, GuildId, Http, UserId,
+};
+
+use crate::domain::playlist::PlaylistInfo;
+use crate::domain::settings::{format_offset, ArtworkStyle, GuildSettings};
+use crate::domain::track::{format_secs, Requester, Track, TrackSource};
+
+/// Used instead of the source colours when a guild turns them off.
+pub(crate) const NEUTRAL_COLOR: Colour = Colour::new(0x5865F2);
+
+/// Applies the guild's colour override, if any, on top of `fallback`.
+pub fn themed_colour(settings: &GuildSettings, fallback: Colour) -> Colour {
+    settings
+        .embed_color_override
+        .map(Colour::new)
+        .unwrap_or(fallback)
+}
+
+/// The source's icon, colour and name as the guild's embed settings show
+/// them: the source colour gives way to the neutral one or the override.
+pub fn source_info(
+    source: &TrackSource,
+    settings: &GuildSettings,
+) -> (&'static str, Colour, &'static str) {
+    let color = if settings.use_source_colors { source.colour() } else { NEUTRAL_COLOR };
+    (source.icon_url(), themed_colour(settings, color), source.display_name())
+}
+
+pub fn linked_title(track: &Track) -> String {
+    if track.url.is_empty() {
+        format!("**{}** - {}", track.title, track.artist)
+    } else {
+        format!("[**{}** - {}]({})", track.title, track.artist, track.url)
+    }
+}
+
+/// "from *Album*" line shown under a track, when the album is known.
+fn album_line(track: &Track) -> String {
+    match &track.album {
+        Some(album) => format!("\nfrom *{album}*"),
+        None => String::new(),
+    }
+}
+
+/// Length shown next to a track, `--:--` when it isn't known.
+pub fn duration_label(track: &Track) -> String {
+    track.formatted_duration().unwrap_or_else(|| "--:--".to_string())
+}
+
+/// Short source marker for track lists.
+pub fn source_tag(source: &TrackSource) -> &'static str {
+    match source {
+        TrackSource::Spotify => "[SP]",
+        TrackSource::YouTube => "[YT]",
+    }
+}
+
+/// Linked title and length, e.g. "[**Song** - Artist](url) - `3:07`".
+pub fn track_line(track: &Track) -> String {
+    format!("{} - `{}`", linked_title(track), duration_label(track))
+}
+
+/// One numbered entry of a track list, e.g. "`2.` [YT] [**Song** - Artist](url) - `3:07`".
+pub fn numbered_line(position: usize, track: &Track) -> String {
+    format!("`{position}.` {} {}", source_tag(&track.source), track_line(track))
+}
+
+/// Builds the "Added to the queue" embed for a single track.
+pub(crate) fn enqueue_embed(
+    track: &Track,
+    settings: &GuildSettings,
+    placement: &QueuePlacement,
+) -> CreateEmbed {
+    let (icon, color, source_name) = source_info(&track.source, settings);
+
+    let status = match placement {
+        QueuePlacement::StartingNow => "Up next (starting now)".to_string(),
+        QueuePlacement::AfterCollections => {
+            "Added after the playlist still being queued".to_string()
+        }
+        QueuePlacement::Queued {
+            position,
+            current,
+            wait,
+        } => {
+            let mut status = format!("Position #{position}");
+            if let Some(current) = current {
+                status.push_str(&format!("\nCurrently playing: **{current}**"));
+                if let Some(wait) = wait {
+                    status.push_str(&format!(
+                        " — your track plays in ~{}",
+                        format_secs(wait.as_secs())
+                    ));
+                }
+            }
+            status
+        }
+    };
+
+    let mut embed = CreateEmbed::new()
+        .description(format!(
+            "Added {} to the queue.{}\n\n{status}",
+            track_line(track),
+            album_line(track)
+        ))
+        .colour(color);
+
+    if !settings.compact_embeds {
+        embed = embed.author(CreateEmbedAuthor::new(source_name).icon_url(icon));
+    }
+
+    embed
+}
+
+/// Builds the "Now playing" embed. The requester goes in the footer when it
+/// could be resolved, otherwise it falls back to a mention in the description.
+pub fn now_playing_embed(
+    track: &Track,
+    requester: Option<CreateEmbedFooter>,
+    settings: &GuildSettings,
+) -> CreateEmbed {
+    let (_, color, _) = source_info(&track.source, settings);
+
+    let mut description = format!("{}{}", track_line(track), album_line(track));
+    if let (TrackSource::Spotify, Some(url)) = (&track.source, &track.resolved_url) {
+        description.push_str(&format!("\nPlaying via [YouTube](<{url}>)"));
+    }
+    if let (None, Some(requested_by)) = (&requester, track.requested_by) {
+        description.push_str(&format!("\n\nRequested by {requested_by}"));
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title("Now playing")
+        .description(description)
+        .colour(color);
+
+    let offset = settings.volume_offset(&track.source);
+    if offset != 0 {
+        let (_, _, source_name) = source_info(&track.source, settings);
+        embed = embed.field(
+            "Volume",
+            format!("{}% ({source_name} {})", 100 + offset, format_offset(offset)),
+            true,
+        );
+    }
+
+    if let Some(footer) = requester {
+        embed = embed.footer(footer);
+    }
+
+    if !settings.compact_embeds {
+        embed = with_artwork(embed, track, settings.artwork);
+    }
+
+    embed
+}
+
+/// Adds the track's artwork in the guild's chosen style, falling back to the
+/// standard thumbnail when the preferred variant is missing.
+fn with_artwork(embed: CreateEmbed, track: &Track, style: ArtworkStyle) -> CreateEmbed {
+    let standard = track.thumbnail_url.as_ref();
+    match style {
+        ArtworkStyle::Thumbnail => match standard {
+            Some(url) => embed.thumbnail(url),
+            None => embed,
+        },
+        ArtworkStyle::SmallThumbnail => match track.thumbnail_small.as_ref().or(standard) {
+            Some(url) => embed.thumbnail(url),
+            None => embed,
+        },
+        ArtworkStyle::Banner => match track.thumbnail_large.as_ref().or(standard) {
+            Some(url) => embed.image(url),
+            None => embed,
+        },
+    }
+}
+
+/// Builds the "Up next" embed shown by `/next`. `starts_in` is the estimated
+/// time until the track starts, when the current track's length is known.
+pub fn up_next_embed(
+    track: &Track,
+    requester: Option<CreateEmbedFooter>,
+    settings: &GuildSettings,
+    starts_in: Option<Duration>,
+) -> CreateEmbed {
+    let (_, color, _) = source_info(&track.source, settings);
+
+    let mut description = format!("{}{}", track_line(track), album_line(track));
+    if let (None, Some(requested_by)) = (&requester, track.requested_by) {
+        description.push_str(&format!("\n\nRequested by {requested_by}"));
+    }
+
+    let mut embed = CreateEmbed::new()
+        .title("Up next")
+        .description(description)
+        .colour(color);
+
+    if let Some(starts_in) = starts_in {
+        let starts_at = SystemTime::now() + starts_in;
+        let unix = starts_at
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        embed = embed.field("Starts", format!("<t:{unix}:R>"), true);
+    }
+
+    if let Some(footer) = requester {
+        embed = embed.footer(footer);
+    }
+
+    if !settings.compact_embeds {
+        embed = with_artwork(embed, track, settings.artwork);
+    }
+
+    embed
+}
+
+/// A member's display name and avatar, trying the cache before falling back
+/// to HTTP.
+async fn member_identity(
+    http: &Http,
+    cache: &Cache,
+    guild_id: GuildId,
+    user_id: UserId,
+) -> Option<(String, String)> {
+    let cached = cache.guild(guild_id).and_then(|guild| {
+        guild
+            .members
+            .get(&user_id)
+            .map(|member| (member.display_name().to_string(), member.face()))
+    });
+    match cached {
+        Some(resolved) => Some(resolved),
+        None => match guild_id.member(http, user_id).await {
+            Ok(member) => Some((member.display_name().to_string(), member.face())),
+            Err(e) => {
+                tracing::debug!("Could not resolve requester {user_id}: {e}");
+                None
+            }
+        },
+    }
+}
+
+/// Resolves a requester's display name and avatar for the footer. Tracks the
+/// bot queued itself name what queued them.
+pub async fn requester_footer(
+    http: &Http,
+    cache: &Cache,
+    guild_id: GuildId,
+    requester: Requester,
+) -> Option<CreateEmbedFooter> {
+    let Requester::User(user_id) = requester else {
+        return Some(CreateEmbedFooter::new(format!("Requested by {requester}")));
+    };
+    let (name, avatar) = member_identity(http, cache, guild_id, user_id).await?;
+    Some(CreateEmbedFooter::new(format!("Requested by {name}")).icon_url(avatar))
+}
+
+/// "Now playing: Title by Artist (3:45), requested by Name" as plain text,
+/// for screen readers that read embeds poorly. Names the requester rather
+/// than mentioning them, so nobody is pinged.
+pub async fn plain_now_playing(
+    http: &Http,
+    cache: &Cache,
+    guild_id: GuildId,
+    track: &Track,
+) -> String {
+    let mut line = format!("Now playing: {} by {}", track.title, track.artist);
+    if let Some(length) = track.formatted_duration() {
+        line.push_str(&format!(" ({length})"));
+    }
+    let name = match track.requested_by {
+        Some(Requester::User(user_id)) => {
+            member_identity(http, cache, guild_id, user_id).await.map(|(name, _)| name)
+        }
+        Some(requester) => Some(requester.to_string()),
+        None => None,
+    };
+    if let Some(name) = name {
+        line.push_str(&format!(", requested by {name}"));
+    }
+    line
+}
+
+/// Message text above a Now Playing card: the standby notice and the plain
+/// text announcement, whichever apply.
+pub fn card_content(standby_notice: Option<&str>, plain: Option<&str>) -> String {
+    [standby_notice, plain].into_iter().flatten().collect::<Vec<_>>().join("\n")
+}
+
+/// `12345` as `12,345`.
+pub(crate) fn group_digits(n: usize) -> String {
+    let digits = n.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % 3 == 0 {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// Notes on a collection's embed that the server's per-`/play` limit cut it
+/// short.
+pub(crate) fn note_collection_limit(embed: CreateEmbed, capped_at: Option<usize>) -> CreateEmbed {
+    let Some(limit) = capped_at else {
+        return embed;
+    };
+    let limit = group_digits(limit);
+    embed.field(
+        "Collection limit",
+        format!("This server lets one /play add up to {limit} tracks, so only the first {limit} were queued"),
+        false,
+    )
+}
+
+/// Builds the embed for a queued playlist or album. `rest` is everything
+/// after `first`, which was enqueued inline.
+pub(crate) fn collection_embed(
+    info: &PlaylistInfo,
+    url: &str,
+    source: &TrackSource,
+    settings: &GuildSettings,
+    first: &Track,
+    started: bool,
+    rest: &[Track],
+) -> CreateEmbed {
+    let (icon, color, source_name) = source_info(source, settings);
+    let name = &info.name;
+    let linked_name = if url.is_empty() {
+        format!("**{name}**")
+    } else {
+        format!("[**{name}**]({url})")
+    };
+
+    let count = rest.len() + 1;
+    let verb = if started { "Now starting" } else { "Queued" };
+    let progress = if info.still_listing {
+        format!(
+            "{verb} {} — fetching *{name}*, `{count}` tracks so far…",
+            linked_title(first)
+        )
+    } else if rest.is_empty() {
+        format!("{verb} {}", linked_title(first))
+    } else {
+        format!(
+            "{verb} {} — adding {} more in the background",
+            linked_title(first),
+            rest.len()
+        )
+    };
+
+    let mut description = format!("Added {linked_name}");
+    if let Some(owner) = &info.owner {
+        description.push_str(&format!(" by {owner}"));
+    }
+    let approx = if info.still_listing { "~" } else { "" };
+    description.push_str(&format!(
+        " with `{approx}{}` tracks to the queue.\n{progress}",
+        info.expected_tracks(count)
+    ));
+
+    let mut embed = CreateEmbed::new().description(description).colour(color);
+
+    // Sum of the durations we know; tracks without one are left out
+    let total: u64 = std::iter::once(first)
+        .chain(rest)
+        .filter_map(|track| track.duration)
+        .map(|d| d.as_secs())
+        .sum();
+    if total > 0 && !info.still_listing {
+        embed = embed.field("Total length", format!("`{}`", format_secs(total)), true);
+    }
+    if info.capped_at.is_some() {
+        embed = note_collection_limit(embed, info.capped_at);
+    } else if let Some(max) = info.truncated_at {
+        embed = embed.field(
+            "Truncated",
+            format!("Only the first {} tracks were added", group_digits(max)),
+            false,
+        );
+    }
+    if info.incomplete {
+        let expected = info
+            .track_count
+            .map(|n| format!(" of ~{n}"))
+            .unwrap_or_default();
+        embed = embed.field(
+            "Incomplete",
+            format!("Fetched {count}{expected} tracks — {source_name} returned an error"),
+            false,
+        );
+    } else {
+        // Only a full listing can be checked against the provider's count
+        let missing = info
+            .track_count
+            .filter(|_| info.truncated_at.is_none() && info.capped_at.is_none() && !info.still_listing)
+            .and_then(|n| n.checked_sub(count))
+            .unwrap_or(0);
+        let unplayable = missing.max(info.skipped);
+        if unplayable > 0 {
+            let word = if unplayable == 1 { "item" } else { "items" };
+            embed = embed.field("Unavailable", format!("`{unplayable}` unplayable {word} skipped"), true);
+        }
+    }
+
+    if !settings.compact_embeds {
+        embed = embed.author(CreateEmbedAuthor::new(source_name).icon_url(icon));
+        if let Some(image) = &info.image {
+            embed = embed.thumbnail(image);
+        }
+    }
+
+    embed
+}
+
+
+/// Where a track landed after `/play` queued it.
+pub(crate) enum QueuePlacement {
+    /// The queue was empty, so it starts right away.
+    StartingNow,
+    /// Waiting behind the track titled `current`. `position` is 1-based
+    /// among pending tracks.
+    Queued {
+        position: usize,
+        current: Option<String>,
+        wait: Option<Duration>,
+    },
+    /// Waiting to be added once the collections already being added are in.
+    AfterCollections,
+}
+
+/// Summarizes a batch of pasted links, listing the ones that couldn't be
+/// added.
+pub(crate) fn links_embed(
+    settings: &GuildSettings,
+    added: Option<(&Track, bool, usize)>,
+    resolved: usize,
+    rejects: &[String],
+) -> CreateEmbed {
+    let plural = |n: usize, word: &str| {
+        if n == 1 {
+            format!("`1` {word}")
+        } else {
+            format!("`{n}` {word}s")
+        }
+    };
+    let description = match added {
+        Some((first, started, total)) => {
+            let verb = if started { "Now starting" } else { "Queued" };
+            format!(
+                "Added {} from {}.\n{verb} {}",
+                plural(total, "track"),
+                plural(resolved, "link"),
+                linked_title(first)
+            )
+        }
+        None => "None of those links could be added.".to_string(),
+    };
+
+    let mut embed = CreateEmbed::new()
+        .description(description)
+        .colour(themed_colour(settings, NEUTRAL_COLOR));
+    if !rejects.is_empty() {
+        let mut list = String::new();
+        for reject in rejects {
+            // Embed field values are capped at 1024 characters
+            if list.chars().count() + reject.chars().count() + 1 > 1000 {
+                list.push_str("\n…");
+                break;
+            }
+            if !list.is_empty() {
+                list.push('\n');
+            }
+            list.push_str(reject);
+        }
+        embed = embed.field("Couldn't add", list, false);
+    }
+    embed
+}
+
+#[cfg(test)]
+mod tests {
+    use poise::serenity_prelude::UserId;
+
+    use super::*;
+
+    fn track(source: TrackSource, duration: Option<u64>) -> Track {
+        Track {
+            title: "Song".to_string(),
+            artist: "Artist".to_string(),
+            album: None,
+            url: "https://example.com/song".to_string(),
+            source,
+            duration: duration.map(Duration::from_secs),
+            thumbnail_url: None,
+            thumbnail_small: None,
+            thumbnail_large: None,
+            requested_by: None,
+            handle_id: None,
+            enqueued_at: None,
+            resolved_url: None,
+            loudness: None,
+            span: None,
+            chapters: Vec::new(),
+        }
+    }
+
+    fn description(embed: &CreateEmbed) -> String {
+        let json = serde_json::to_value(embed).unwrap();
+        json["description"].as_str().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn list_lines_tag_the_source_and_fall_back_on_unknown_lengths() {
+        assert_eq!(
+            numbered_line(2, &track(TrackSource::YouTube, Some(187))),
+            "`2.` [YT] [**Song** - Artist](https://example.com/song) - `3:07`"
+        );
+        assert_eq!(
+            numbered_line(10, &track(TrackSource::Spotify, None)),
+            "`10.` [SP] [**Song** - Artist](https://example.com/song) - `--:--`"
+        );
+    }
+
+    #[test]
+    fn enqueue_embed_shows_the_position_and_wait() {
+        let mut track = track(TrackSource::Spotify, Some(187));
+        track.album = Some("Album".to_string());
+        let placement = QueuePlacement::Queued {
+            position: 3,
+            current: Some("Other".to_string()),
+            wait: Some(Duration::from_secs(125)),
+        };
+
+        let embed = enqueue_embed(&track, &GuildSettings::default(), &placement);
+        assert_eq!(
+            description(&embed),
+            "Added [**Song** - Artist](https://example.com/song) - `3:07` to the queue.\n\
+             from *Album*\n\n\
+             Position #3\nCurrently playing: **Other** — your track plays in ~2:05"
+        );
+    }
+
+    #[test]
+    fn now_playing_embed_names_the_upload_and_falls_back_on_a_mention() {
+        let mut track = track(TrackSource::Spotify, Some(3725));
+        track.resolved_url = Some("https://youtu.be/aaaaaaaaaaa".to_string());
+        track.requested_by = Some(Requester::User(UserId::new(42)));
+
+        let embed = now_playing_embed(&track, None, &GuildSettings::default());
+        assert_eq!(
+            description(&embed),
+            "[**Song** - Artist](https://example.com/song) - `1:02:05`\n\
+             Playing via [YouTube](<https://youtu.be/aaaaaaaaaaa>)\n\n\
+             Requested by <@42>"
+        );
+    }
+
+    #[test]
+    fn collection_embed_counts_what_was_added() {
+        let info = PlaylistInfo {
+            name: "Mix".to_string(),
+            owner: Some("Someone".to_string()),
+            ..PlaylistInfo::default()
+        };
+        let first = track(TrackSource::YouTube, Some(60));
+        let rest = vec![track(TrackSource::YouTube, Some(60)); 2];
+
+        let embed = collection_embed(
+            &info,
+            "https://example.com/mix",
+            &TrackSource::YouTube,
+            &GuildSettings::default(),
+            &first,
+            false,
+            &rest,
+        );
+        assert_eq!(
+            description(&embed),
+            "Added [**Mix**](https://example.com/mix) by Someone with `3` tracks to the queue.\n\
+             Queued [**Song** - Artist](https://example.com/song) — adding 2 more in the background"
+        );
+    }
+
+    #[test]
+    fn collection_embed_still_listing_shows_an_estimate() {
+        let info = PlaylistInfo {
+            name: "Mix".to_string(),
+            track_count: Some(300),
+            still_listing: true,
+            ..PlaylistInfo::default()
+        };
+        let first = track(TrackSource::YouTube, None);
+
+        let embed = collection_embed(
+            &info,
+            "",
+            &TrackSource::YouTube,
+            &GuildSettings::default(),
+            &first,
+            true,
+            &[],
+        );
+        assert_eq!(
+            description(&embed),
+            "Added **Mix** with `~300` tracks to the queue.\n\
+             Now starting [**Song** - Artist](https://example.com/song) — fetching *Mix*, \
+             `1` tracks so far…"
+        );
+    }
+}
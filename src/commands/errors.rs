@@ -0,0 +1,38 @@
+use std::sync::atomic::Ordering;
+
+use crate::services::error::MusicError;
+use crate::{Context, Error};
+
+/// Replies to a failed command. Mistakes on the user's side only go to them,
+/// while anything pointing at the bot or Discord stays public so everyone
+/// can see why playback broke.
+pub async fn reply_error(ctx: Context<'_>, error: &Error) {
+    let user_error = error
+        .downcast_ref::<MusicError>()
+        .is_some_and(MusicError::is_user_error);
+    let content = format!("❌ {error}");
+
+    if !user_error {
+        tracing::error!("Command /{} failed: {error}", ctx.command().qualified_name);
+        let _ = ctx.say(content).await;
+        return;
+    }
+
+    tracing::info!("Command /{} rejected: {error}", ctx.command().qualified_name);
+
+    // The first reply after a public defer inherits its visibility, so drop
+    // the "thinking" placeholder to keep the error private
+    if let poise::Context::Application(app) = ctx {
+        if app.has_sent_initial_response.load(Ordering::SeqCst) {
+            let _ = app.interaction.delete_response(ctx.http()).await;
+        }
+    }
+
+    let _ = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(content)
+                .ephemeral(true),
+        )
+        .await;
+}
@@ -0,0 +1,161 @@
+use futures::StreamExt;
+use poise::serenity_prelude::{Colour, CreateEmbed};
+
+use crate::services::error::MusicError;
+use crate::services::queue_service::QueueService;
+use crate::{Context, Error};
+
+const EXPORT_COLOR: Colour = Colour::new(0x1DB954);
+/// Spotify searches run at once while matching YouTube tracks.
+const CONCURRENT_LOOKUPS: usize = 4;
+/// Unmatched tracks named in the summary.
+const MAX_LISTED_SKIPS: usize = 10;
+
+/// Save the queue somewhere else
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("spotify", "connect", "unlink"),
+    subcommand_required
+)]
+pub async fn export(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Save the playing and queued tracks as a playlist on your Spotify account
+#[poise::command(slash_command, guild_only)]
+async fn spotify(
+    ctx: Context<'_>,
+    #[description = "Name for the new playlist"]
+    #[max_length = 100]
+    name: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
+    let accounts = data
+        .spotify_accounts
+        .as_ref()
+        .ok_or(MusicError::SpotifyExportDisabled)?;
+    let user = ctx.author().id;
+
+    if !accounts.is_linked(user).await {
+        let url = accounts.authorize_url(user).await?;
+        let embed = CreateEmbed::new()
+            .title("Link your Spotify account")
+            .description(format!(
+                "1. [Approve access on Spotify]({url}).\n\
+                 2. You'll land on a page that may not load. Copy its full address.\n\
+                 3. Run `/export connect` with that address, then `/export spotify` again."
+            ))
+            .colour(EXPORT_COLOR);
+        ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+            .await?;
+        return Ok(());
+    }
+
+    let tracks: Vec<_> = QueueService::current(&data.guild_queues, guild_id)
+        .await
+        .into_iter()
+        .chain(QueueService::list(&data.guild_queues, guild_id).await)
+        .collect();
+    if tracks.is_empty() {
+        return Err(MusicError::EmptyQueue.into());
+    }
+    ctx.defer_ephemeral().await?;
+
+    // YouTube tracks are matched by a Spotify search on title and artist
+    let lookups: Vec<_> = tracks
+        .iter()
+        .map(|track| data.music_service.spotify_track_id(track))
+        .collect();
+    let matches: Vec<_> = futures::stream::iter(lookups)
+        .buffered(CONCURRENT_LOOKUPS)
+        .collect()
+        .await;
+    let mut ids = Vec::new();
+    let mut skipped = Vec::new();
+    for (track, id) in tracks.iter().zip(matches) {
+        match id {
+            Some(id) => ids.push(id),
+            None => skipped.push(track),
+        }
+    }
+    if ids.is_empty() {
+        ctx.say("None of the queued tracks could be found on Spotify, so no playlist was made.")
+            .await?;
+        return Ok(());
+    }
+
+    let guild_name = ctx.guild().map(|g| g.name.clone()).unwrap_or_default();
+    let description = format!("Exported from {guild_name} on Discord");
+    let url = accounts.create_playlist(user, &name, &description, &ids).await?;
+    tracing::info!(
+        "Exported {} of {} tracks in guild {guild_id} to Spotify for {user}",
+        ids.len(),
+        tracks.len()
+    );
+
+    let mut embed = CreateEmbed::new()
+        .title("Playlist created")
+        .description(format!(
+            "[**{name}**]({url}) on your Spotify account, with `{}` of `{}` tracks.",
+            ids.len(),
+            tracks.len()
+        ))
+        .colour(EXPORT_COLOR);
+    if !skipped.is_empty() {
+        let mut list = skipped
+            .iter()
+            .take(MAX_LISTED_SKIPS)
+            .map(|t| format!("**{}** - {}", t.title, t.artist))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if skipped.len() > MAX_LISTED_SKIPS {
+            list.push_str(&format!("\n…and {} more", skipped.len() - MAX_LISTED_SKIPS));
+        }
+        // Embed field values are capped at 1024 characters
+        if list.chars().count() > 1000 {
+            list = format!("{}…", list.chars().take(1000).collect::<String>());
+        }
+        embed = embed.field("Not found on Spotify", list, false);
+    }
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Finish linking your Spotify account
+#[poise::command(slash_command, guild_only)]
+async fn connect(
+    ctx: Context<'_>,
+    #[description = "The full address Spotify sent you to after approving"] address: String,
+) -> Result<(), Error> {
+    let accounts = ctx
+        .data()
+        .spotify_accounts
+        .as_ref()
+        .ok_or(MusicError::SpotifyExportDisabled)?;
+    ctx.defer_ephemeral().await?;
+    accounts.finish(ctx.author().id, &address).await?;
+
+    ctx.say("Spotify account linked. Run `/export spotify` to save the queue.")
+        .await?;
+    Ok(())
+}
+
+/// Forget your linked Spotify account
+#[poise::command(slash_command, guild_only)]
+async fn unlink(ctx: Context<'_>) -> Result<(), Error> {
+    let accounts = ctx
+        .data()
+        .spotify_accounts
+        .as_ref()
+        .ok_or(MusicError::SpotifyExportDisabled)?;
+    let content = if accounts.unlink(ctx.author().id).await {
+        "Spotify account unlinked."
+    } else {
+        "No Spotify account was linked."
+    };
+    ctx.send(poise::CreateReply::default().content(content).ephemeral(true))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use poise::serenity_prelude::{Colour, CreateEmbed, CreateEmbedFooter};
+
+use crate::commands::embeds::numbered_line;
+use crate::commands::permissions::is_dj;
+use crate::domain::history::PlayedTrack;
+use crate::domain::track::format_secs;
+use crate::services::error::MusicError;
+use crate::services::history_service::HistoryService;
+use crate::{Context, Error};
+
+const HISTORY_COLOR: Colour = Colour::new(0x99AAB5);
+/// Entries listed by `/history show`, newest first.
+const HISTORY_SHOWN: usize = 15;
+/// Room for entries under Discord's 4096 character description limit.
+const DESCRIPTION_BUDGET: usize = 4000;
+
+/// "`3.` [YT] [**Song** - Artist](url) - `3:07`", then when it started and
+/// how long it played.
+fn history_line(position: usize, entry: &PlayedTrack) -> String {
+    let started = format!("<t:{}:R>", entry.started_at.timestamp());
+    let played = match entry.finished_at {
+        Some(finished) => {
+            let secs = (finished - entry.started_at).num_seconds().max(0) as u64;
+            format!("played `{}`", format_secs(secs))
+        }
+        None => "playing now".to_string(),
+    };
+    format!("{}\nStarted {started} · {played}", numbered_line(position, &entry.track))
+}
+
+/// Show or clear the tracks played this session
+#[poise::command(slash_command, guild_only, subcommands("show", "clear"), subcommand_required)]
+pub async fn history(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show the most recently played tracks
+#[poise::command(slash_command, guild_only)]
+async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let history = HistoryService::get(&ctx.data().play_histories, guild_id).await;
+
+    let description = if history.is_empty() {
+        "Nothing has played this session yet.".to_string()
+    } else {
+        let mut lines = Vec::new();
+        let mut chars = 0;
+        for (i, entry) in history.iter().rev().take(HISTORY_SHOWN).enumerate() {
+            let line = history_line(i + 1, entry);
+            chars += line.chars().count() + 1;
+            if chars > DESCRIPTION_BUDGET {
+                break;
+            }
+            lines.push(line);
+        }
+        if history.len() > lines.len() {
+            lines.push(format!("…and {} earlier", history.len() - lines.len()));
+        }
+        lines.join("\n")
+    };
+
+    let word = if history.len() == 1 { "track" } else { "tracks" };
+    let embed = CreateEmbed::new()
+        .title("History")
+        .description(description)
+        .colour(HISTORY_COLOR)
+        .footer(CreateEmbedFooter::new(format!("{} {word} this session", history.len())));
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Forget the tracks played so far, without touching the queue (DJ only)
+#[poise::command(slash_command, guild_only)]
+async fn clear(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    if !is_dj(ctx).await {
+        return Err(MusicError::NotDj.into());
+    }
+    HistoryService::clear(&ctx.data().play_histories, guild_id).await;
+    tracing::info!("History cleared in guild {guild_id} by {}", ctx.author().id);
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("History cleared. The queue is unchanged.")
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
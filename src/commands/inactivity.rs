@@ -0,0 +1,154 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use poise::serenity_prelude::{Cache, ChannelId, GuildId, Http};
+use songbird::tracks::PlayMode;
+use tokio::sync::Notify;
+
+use crate::commands::standby::{is_on_standby, listeners};
+use crate::commands::cleanup::{cleanup_session, DisconnectReason};
+use crate::services::settings_service::SettingsService;
+use crate::Data;
+
+const INACTIVITY_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+/// Used instead while a queue waits on standby for listeners.
+const STANDBY_TIMEOUT: Duration = Duration::from_secs(2 * 60 * 60);
+const CHECK_INTERVAL: Duration = Duration::from_secs(30);
+/// Idle reasons that mean nobody is listening, rather than nothing playing.
+const ALONE: &str = "alone in the voice channel";
+const ALL_DEAFENED: &str = "everyone in the voice channel is deafened";
+
+/// Spawns a background task that auto-disconnects the bot after 15 minutes
+/// of inactivity (nothing playing or alone in the voice channel), or two hours
+/// while the queue is on standby.
+///
+/// The voice channel is read from the live `Call` and the notice channel from
+/// `Data::text_channels` on every check, so moving the bot with `/join` keeps
+/// the monitor pointed at the right place.
+///
+/// Returns a `Notify` handle — notify it to cancel the task early (e.g. on `/stop`).
+pub fn spawn_inactivity_monitor(
+    manager: Arc<songbird::Songbird>,
+    guild_id: GuildId,
+    session: u64,
+    http: Arc<Http>,
+    cache: Arc<Cache>,
+    data: Data,
+) -> Arc<Notify> {
+    let cancel = Arc::new(Notify::new());
+    let cancel_clone = cancel.clone();
+
+    tokio::spawn(async move {
+        let mut idle_elapsed = Duration::ZERO;
+        let mut last_reason = "";
+
+        loop {
+            tokio::select! {
+                _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                _ = cancel_clone.notified() => {
+                    return;
+                }
+            }
+
+            match idle_reason(&manager, guild_id, &cache, &data).await {
+                Some(reason) => {
+                    if idle_elapsed.is_zero() {
+                        tracing::debug!("Guild {guild_id} went idle: {reason}");
+                    }
+                    idle_elapsed += CHECK_INTERVAL;
+                    last_reason = reason;
+                }
+                None => idle_elapsed = Duration::ZERO,
+            }
+
+            let timeout = if is_on_standby(&data, guild_id).await {
+                STANDBY_TIMEOUT
+            } else {
+                INACTIVITY_TIMEOUT
+            };
+            if idle_elapsed >= timeout {
+                tracing::info!("Disconnecting from guild {guild_id} after inactivity: {last_reason}");
+                let reason = if matches!(last_reason, ALONE | ALL_DEAFENED) {
+                    DisconnectReason::EveryoneLeft(timeout)
+                } else {
+                    DisconnectReason::Inactivity(timeout)
+                };
+
+                // Claim the cleanup before leaving, so the disconnect handler
+                // that leaving triggers finds nothing left to do. It posts
+                // the notice too.
+                if !cleanup_session(guild_id, session, &data, &http, reason).await {
+                    return;
+                }
+
+                if let Some(handler_lock) = manager.get(guild_id) {
+                    let handler = handler_lock.lock().await;
+                    handler.queue().stop();
+                }
+                let _ = manager.leave(guild_id).await;
+
+                return;
+            }
+        }
+    });
+
+    cancel
+}
+
+/// Returns why the session counts as idle, or `None` if it's active. A
+/// session is idle when nothing is playing or paused (whatever is still
+/// pending in either queue), or when the bot is alone in its channel, which
+/// can include everyone there being deafened.
+async fn idle_reason(
+    manager: &Arc<songbird::Songbird>,
+    guild_id: GuildId,
+    cache: &Arc<Cache>,
+    data: &Data,
+) -> Option<&'static str> {
+    // Check what the current track is doing, and find the channel we're in
+    let Some(handler_lock) = manager.get(guild_id) else {
+        return Some("no voice call");
+    };
+    let (current, voice_channel_id) = {
+        let handler = handler_lock.lock().await;
+        let channel = handler
+            .current_channel()
+            .map(|id| ChannelId::new(id.0.get()));
+        (handler.queue().current(), channel)
+    };
+
+    let Some(current) = current else {
+        return Some("nothing playing");
+    };
+    let playing = current
+        .get_info()
+        .await
+        .is_ok_and(|info| matches!(info.playing, PlayMode::Play | PlayMode::Pause));
+    if !playing {
+        return Some("current track is not playing");
+    }
+
+    let Some(voice_channel_id) = voice_channel_id else {
+        return Some("not in a voice channel");
+    };
+
+    // Check if bot is alone in the voice channel
+    if let Some(guild) = cache.guild(guild_id) {
+        let members_in_channel = guild
+            .voice_states
+            .values()
+            .filter(|vs| vs.channel_id == Some(voice_channel_id))
+            .count();
+
+        if members_in_channel <= 1 {
+            return Some(ALONE);
+        }
+    }
+
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    if settings.count_deafened_as_absent && listeners(cache, guild_id, voice_channel_id, true) == 0 {
+        return Some(ALL_DEAFENED);
+    }
+
+    None
+}
@@ -0,0 +1,57 @@
+use crate::commands::permissions::is_dj;
+use crate::commands::play::{
+    author_voice_channel, bot_voice_channel, ensure_voice_connection, summon,
+};
+use crate::services::error::MusicError;
+use crate::{Context, Error};
+
+/// Summon the bot to your voice channel
+#[poise::command(slash_command, guild_only)]
+pub async fn join(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let voice_channel_id = author_voice_channel(ctx)?;
+
+    ctx.defer().await?;
+
+    let data = ctx.data();
+    let text_channel_id = ctx.channel_id();
+
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    let active = data.inactivity_handles.read().await.contains_key(&guild_id);
+    if let (true, Some(handler_lock)) = (active, manager.get(guild_id)) {
+        let current_channel = bot_voice_channel(&manager, guild_id).await;
+
+        if current_channel != Some(voice_channel_id) {
+            // Moving while playing pulls the music away from other listeners
+            let playing = !handler_lock.lock().await.queue().is_empty();
+            if playing && !is_dj(ctx).await {
+                return Err(MusicError::NotDj.into());
+            }
+        }
+
+        summon(&manager, data, guild_id, voice_channel_id, text_channel_id).await?;
+
+        if current_channel == Some(voice_channel_id) {
+            ctx.say(format!("Already in <#{voice_channel_id}>.")).await?;
+        } else {
+            ctx.say(format!("Moved to <#{voice_channel_id}>.")).await?;
+        }
+        return Ok(());
+    }
+
+    ensure_voice_connection(
+        ctx.serenity_context(),
+        &manager,
+        data,
+        guild_id,
+        voice_channel_id,
+        text_channel_id,
+    )
+    .await?;
+
+    ctx.say(format!("Joined <#{voice_channel_id}>.")).await?;
+    Ok(())
+}
@@ -1,38 +1,61 @@
+use std::time::UNIX_EPOCH;
+
 use poise::serenity_prelude::{Colour, CreateEmbed, CreateEmbedFooter};
 
-use crate::commands::play::{linked_title, source_info};
-use crate::domain::track::TrackSource;
+use crate::commands::embeds::{numbered_line, now_playing_embed, requester_footer, themed_colour};
 use crate::services::error::MusicError;
+use crate::services::playback_service::PlaybackService;
 use crate::services::queue_service::QueueService;
+use crate::services::settings_service::SettingsService;
 use crate::{Context, Error};
 
 const QUEUE_COLOR: Colour = Colour::new(0x5865F2);
+/// Tracks shown by the recent view when no count is given.
+const DEFAULT_RECENT: usize = 10;
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum ListView {
+    #[name = "Queue order"]
+    Queue,
+    #[name = "Recently added"]
+    Recent,
+}
 
 /// Show the current music queue
 #[poise::command(slash_command, guild_only)]
-pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
+pub async fn list(
+    ctx: Context<'_>,
+    #[description = "Show the queue in order, or the most recently added tracks"]
+    view: Option<ListView>,
+    #[description = "How many recently added tracks to show"]
+    #[min = 1]
+    #[max = 25]
+    added: Option<usize>,
+) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
     let data = ctx.data();
 
+    if matches!(view, Some(ListView::Recent)) || added.is_some() {
+        return list_recent(ctx, added.unwrap_or(DEFAULT_RECENT)).await;
+    }
+
     let current = QueueService::current(&data.guild_queues, guild_id).await;
     let upcoming = QueueService::list(&data.guild_queues, guild_id).await;
 
     let Some(current) = current else {
-        return Err(MusicError::EmptyQueue.into());
+        return Err(PlaybackService::idle_error(data.voice.as_ref(), guild_id).into());
     };
 
-    let (_, color, _) = source_info(&current.source);
-    let duration = current.duration.as_deref().unwrap_or("--:--");
-
     // Now playing embed
-    let mut now_playing = CreateEmbed::new()
-        .title("Now playing")
-        .description(format!("{} - `{}`", linked_title(&current), duration))
-        .colour(color);
-
-    if let Some(url) = &current.thumbnail_url {
-        now_playing = now_playing.thumbnail(url);
-    }
+    let footer = match current.requested_by {
+        Some(requester) => {
+            let serenity_ctx = ctx.serenity_context();
+            requester_footer(&serenity_ctx.http, &serenity_ctx.cache, guild_id, requester).await
+        }
+        None => None,
+    };
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let now_playing = now_playing_embed(&current, footer, &settings);
 
     let mut reply = poise::CreateReply::default().embed(now_playing);
 
@@ -42,18 +65,8 @@ pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
         let mut desc = String::new();
 
         for (i, track) in upcoming.iter().take(MAX_DISPLAY).enumerate() {
-            let d = track.duration.as_deref().unwrap_or("--:--");
-            let icon = match track.source {
-                TrackSource::Spotify => "[SP]",
-                TrackSource::YouTube => "[YT]",
-            };
-            desc.push_str(&format!(
-                "`{}.` {} {} - `{}`\n",
-                i + 1,
-                icon,
-                linked_title(track),
-                d
-            ));
+            desc.push_str(&numbered_line(i + 1, track));
+            desc.push('\n');
         }
 
         let remaining = upcoming.len().saturating_sub(MAX_DISPLAY);
@@ -66,7 +79,7 @@ pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
         let queue_embed = CreateEmbed::new()
             .title("Up next")
             .description(desc)
-            .colour(QUEUE_COLOR)
+            .colour(themed_colour(&settings, QUEUE_COLOR))
             .footer(CreateEmbedFooter::new(footer_text));
 
         reply = reply.embed(queue_embed);
@@ -75,3 +88,41 @@ pub async fn list(ctx: Context<'_>) -> Result<(), Error> {
     ctx.send(reply).await?;
     Ok(())
 }
+
+/// Lists the newest additions to the queue, wherever they ended up in it.
+async fn list_recent(ctx: Context<'_>, n: usize) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
+
+    let recent = QueueService::recent(&data.guild_queues, guild_id, n).await;
+    if recent.is_empty() {
+        return Err(PlaybackService::idle_error(data.voice.as_ref(), guild_id).into());
+    }
+
+    let mut desc = String::new();
+    for (position, track) in &recent {
+        let requester = match track.requested_by {
+            Some(requester) => requester.to_string(),
+            None => "unknown".to_string(),
+        };
+        let added = track
+            .enqueued_at
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| format!("<t:{}:R>", d.as_secs()))
+            .unwrap_or_else(|| "unknown".to_string());
+        desc.push_str(&format!(
+            "{} · {requester} · {added}\n",
+            numbered_line(*position, track)
+        ));
+    }
+
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let embed = CreateEmbed::new()
+        .title("Recently added")
+        .description(desc)
+        .colour(themed_colour(&settings, QUEUE_COLOR))
+        .footer(CreateEmbedFooter::new("Numbers are queue positions"));
+
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use poise::serenity_prelude::GuildId;
+use songbird::events::{Event, EventContext, EventHandler, TrackEvent};
+use songbird::tracks::TrackHandle;
+
+use crate::infrastructure::audio::ResolvedMatch;
+use crate::services::queue_service::QueueService;
+use crate::services::settings_service::SettingsService;
+use crate::Data;
+
+/// Records the loudness yt-dlp reported once a track's input has loaded,
+/// and evens out its volume when the guild has that turned on. Tracks
+/// without a reading keep the guild's volume as it is.
+struct LoudnessReady {
+    guild_id: GuildId,
+    data: Data,
+    loaded: ResolvedMatch,
+}
+
+#[async_trait]
+impl EventHandler for LoudnessReady {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track([(_, handle), ..]) = ctx else {
+            return None;
+        };
+        let loudness = self.loaded.get()?.loudness?;
+        let queues = &self.data.guild_queues;
+        let track =
+            QueueService::set_loudness(queues, self.guild_id, handle.uuid(), loudness).await?;
+        let settings = SettingsService::get(&self.data.guild_settings, self.guild_id).await;
+        if settings.normalize_loudness {
+            let _ = handle.set_volume(settings.track_volume(&track));
+        }
+        None
+    }
+}
+
+/// Registers [`LoudnessReady`] on a freshly queued track.
+pub(crate) fn watch_loudness(
+    handle: &TrackHandle,
+    guild_id: GuildId,
+    data: &Data,
+    loaded: ResolvedMatch,
+) {
+    let _ = handle.add_event(
+        Event::Track(TrackEvent::Playable),
+        LoudnessReady {
+            guild_id,
+            data: data.clone(),
+            loaded,
+        },
+    );
+}
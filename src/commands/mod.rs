@@ -1,6 +1,38 @@
+pub mod blocklist;
+pub mod board;
+pub mod channel_status;
+pub mod clean;
+pub mod cleanup;
+pub mod debug;
+pub mod embeds;
+pub mod errors;
+pub mod export;
+pub mod history;
+pub mod inactivity;
+pub mod join;
 pub mod list;
+pub mod loudness;
+pub mod move_user;
+pub mod my_settings;
 pub mod next;
 pub mod now_playing;
+pub mod onboarding;
+pub mod permissions;
 pub mod play;
+pub mod prune;
+pub mod queue;
+pub mod registration;
+pub mod reset;
+pub mod resolve;
+pub mod schedule;
+pub mod seek;
+pub mod server_mute;
+pub mod session;
+pub mod settings;
 pub mod skip;
+pub mod stale_input;
+pub mod standby;
 pub mod stop;
+pub mod top;
+pub mod vote;
+pub mod watchdog;
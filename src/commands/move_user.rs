@@ -0,0 +1,69 @@
+use poise::serenity_prelude::{CreateAllowedMentions, User};
+
+use crate::commands::permissions::is_dj;
+use crate::commands::play::sync_playback_order;
+use crate::domain::track::{Requester, Track};
+use crate::services::error::MusicError;
+use crate::services::queue_service::QueueService;
+use crate::{Context, Error};
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum MoveTarget {
+    #[name = "Front (play next)"]
+    Front,
+    #[name = "Back"]
+    Back,
+    #[name = "Position"]
+    Position,
+}
+
+/// Move all of one member's queued tracks together, keeping their order (DJ only)
+#[poise::command(slash_command, guild_only)]
+pub async fn moveuser(
+    ctx: Context<'_>,
+    #[description = "Whose tracks to move"] user: User,
+    #[description = "Where to put them"] to: MoveTarget,
+    #[description = "Queue position for the first track when moving to a position (1 = up next)"]
+    #[min = 1]
+    position: Option<usize>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    if !is_dj(ctx).await {
+        return Err(MusicError::NotDj.into());
+    }
+    let data = ctx.data();
+
+    // Indexes count the queue without the moved tracks, so "back" is
+    // anything past its end
+    let index = match to {
+        MoveTarget::Front => 0,
+        MoveTarget::Back => usize::MAX,
+        MoveTarget::Position => position.ok_or(MusicError::MissingPosition)? - 1,
+    };
+    let requester = Requester::User(user.id);
+    let theirs = |track: &Track| track.requested_by == Some(requester);
+    let (count, first) = QueueService::move_where(&data.guild_queues, guild_id, theirs, index)
+        .await
+        .ok_or(MusicError::NothingQueuedBy(user.id))?;
+
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+    if let Some(handler_lock) = manager.get(guild_id) {
+        sync_playback_order(&handler_lock, data, guild_id).await;
+    }
+    tracing::info!("Moved {count} of {}'s tracks to #{} in guild {guild_id}", user.id, first + 1);
+
+    let word = if count == 1 { "track" } else { "tracks" };
+    ctx.send(
+        poise::CreateReply::default()
+            .content(format!(
+                "Moved {count} {word} from <@{}> — the first is now #{} in the queue.",
+                user.id,
+                first + 1
+            ))
+            .allowed_mentions(CreateAllowedMentions::new()),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use poise::serenity_prelude::{Colour, CreateEmbed};
+
+use crate::domain::preferences::{Confirmations, UserPreferences};
+use crate::domain::settings::SearchPreference;
+use crate::services::preferences_service::PreferencesService;
+use crate::{Context, Error};
+
+const PREFERENCES_COLOR: Colour = Colour::new(0x5865F2);
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum SourceChoice {
+    #[name = "Auto (server default)"]
+    Auto,
+    #[name = "YouTube"]
+    YouTube,
+    #[name = "Spotify"]
+    Spotify,
+}
+
+impl From<SourceChoice> for Option<SearchPreference> {
+    fn from(choice: SourceChoice) -> Self {
+        match choice {
+            SourceChoice::Auto => None,
+            SourceChoice::YouTube => Some(SearchPreference::YouTube),
+            SourceChoice::Spotify => Some(SearchPreference::Spotify),
+        }
+    }
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum ConfirmationChoice {
+    #[name = "Public"]
+    Public,
+    #[name = "Only me"]
+    Ephemeral,
+}
+
+impl From<ConfirmationChoice> for Confirmations {
+    fn from(choice: ConfirmationChoice) -> Self {
+        match choice {
+            ConfirmationChoice::Public => Confirmations::Public,
+            ConfirmationChoice::Ephemeral => Confirmations::Ephemeral,
+        }
+    }
+}
+
+fn preferences_embed(preferences: &UserPreferences) -> CreateEmbed {
+    let source = match preferences.search_preference {
+        None => "server default",
+        Some(SearchPreference::YouTube) => "YouTube",
+        Some(SearchPreference::Spotify) => "Spotify",
+        Some(SearchPreference::Fastest) => "fastest",
+    };
+    let confirmations = match preferences.confirmations.unwrap_or_default() {
+        Confirmations::Public => "public",
+        Confirmations::Ephemeral => "only you",
+    };
+    CreateEmbed::new()
+        .title("Your settings")
+        .field("Search provider", source, true)
+        .field("/play confirmations", confirmations, true)
+        .colour(PREFERENCES_COLOR)
+}
+
+/// Set how /play searches and replies for you, in every server
+#[poise::command(slash_command)]
+pub async fn mysettings(
+    ctx: Context<'_>,
+    #[description = "Which provider your text searches use"] source: Option<SourceChoice>,
+    #[description = "Whether /play confirmations are shown to everyone or only you"]
+    confirmations: Option<ConfirmationChoice>,
+) -> Result<(), Error> {
+    let preferences =
+        PreferencesService::update(&ctx.data().user_preferences, ctx.author().id, |p| {
+            if let Some(source) = source {
+                p.search_preference = source.into();
+            }
+            if let Some(confirmations) = confirmations {
+                p.confirmations = Some(confirmations.into());
+            }
+        })
+        .await;
+    if let Some(store) = &ctx.data().preferences_store {
+        PreferencesService::save(&ctx.data().user_preferences, store).await;
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(preferences_embed(&preferences))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,34 +1,56 @@
+use crate::commands::embeds::{requester_footer, up_next_embed};
+use crate::commands::play::estimate_wait;
+use crate::commands::skip::skip_current;
 use crate::services::error::MusicError;
+use crate::services::playback_service::PlaybackService;
 use crate::services::queue_service::QueueService;
+use crate::services::settings_service::SettingsService;
 use crate::{Context, Error};
 
-/// Skip the current track
+/// Show the next track in the queue
 #[poise::command(slash_command, guild_only)]
-pub async fn next(ctx: Context<'_>) -> Result<(), Error> {
+pub async fn next(
+    ctx: Context<'_>,
+    #[description = "Skip the current track and play the next one now"] play_now: Option<bool>,
+) -> Result<(), Error> {
+    if play_now.unwrap_or(false) {
+        return skip_current(ctx).await;
+    }
+
     let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
+
+    let current = QueueService::current(&data.guild_queues, guild_id).await;
+    let Some(next_track) = QueueService::peek(&data.guild_queues, guild_id).await else {
+        return Err(match current {
+            Some(_) => MusicError::NothingUpNext,
+            None => PlaybackService::idle_error(data.voice.as_ref(), guild_id),
+        }
+        .into());
+    };
 
     let manager = songbird::get(ctx.serenity_context())
         .await
         .expect("Songbird not registered");
 
-    // Capture the currently playing track BEFORE songbird skip
-    let skipped = QueueService::skip(&ctx.data().guild_queues, guild_id).await;
+    // The next track starts once whatever is playing now runs out
+    let starts_in = match manager.get(guild_id) {
+        Some(handler_lock) => estimate_wait(&handler_lock, data, guild_id, 0).await,
+        None => None,
+    };
 
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let handler = handler_lock.lock().await;
-        let queue = handler.queue();
-        if queue.is_empty() {
-            return Err(MusicError::EmptyQueue.into());
+    let footer = match next_track.requested_by {
+        Some(requester) => {
+            let serenity_ctx = ctx.serenity_context();
+            requester_footer(&serenity_ctx.http, &serenity_ctx.cache, guild_id, requester).await
         }
-        let _ = queue.skip();
-    } else {
-        return Err(MusicError::EmptyQueue.into());
-    }
-
-    match skipped {
-        Some(track) => ctx.say(format!("Skipped: **{}**", track)).await?,
-        None => ctx.say("Skipped current track.").await?,
+        None => None,
     };
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
 
+    ctx.send(
+        poise::CreateReply::default().embed(up_next_embed(&next_track, footer, &settings, starts_in)),
+    )
+    .await?;
     Ok(())
 }
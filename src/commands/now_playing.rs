@@ -1,13 +1,22 @@
-use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use poise::serenity_prelude::{
     self as serenity, ButtonStyle, ComponentInteraction, CreateActionRow, CreateButton,
-    CreateInteractionResponse, CreateInteractionResponseMessage, GuildId,
+    CreateInteractionResponse, CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage, EditInteractionResponse, GuildId, MessageId,
 };
-use songbird::tracks::PlayMode;
-
-use crate::services::cleanup::cleanup_guild;
+use songbird::tracks::{PlayMode, TrackHandle};
+use tokio::sync::Mutex;
+
+use crate::commands::permissions::check_session_lock;
+use crate::commands::server_mute::refresh_now_playing;
+use crate::commands::skip::skip_playing;
+use crate::commands::standby;
+use crate::commands::stale_input::{note_paused, resume_playback};
+use crate::commands::cleanup::{cleanup_guild, persist_now_playing, DisconnectReason};
+use crate::domain::track::Track;
+use crate::services::error::MusicError;
+use crate::services::playback_service::PlaybackService;
 use crate::services::queue_service::QueueService;
 use crate::Data;
 
@@ -65,154 +74,236 @@ fn parse_custom_id(custom_id: &str) -> Option<(&str, GuildId)> {
     Some((action, GuildId::new(guild_id)))
 }
 
+/// Defer the button press if handling runs this long, staying inside
+/// Discord's 3 second window to acknowledge an interaction.
+const INTERACTION_DEADLINE: Duration = Duration::from_millis(2500);
+
+/// Runs in its own task (see `main`), so a slow seek or a contended call lock
+/// doesn't hold up the gateway event loop.
 pub async fn handle_now_playing_interaction(
-    ctx: &serenity::Context,
-    component: &ComponentInteraction,
-    data: &Data,
+    ctx: serenity::Context,
+    component: ComponentInteraction,
+    data: Data,
 ) {
-    let Some((action, guild_id)) = parse_custom_id(&component.data.custom_id) else {
+    let reply = ButtonReply::new(component);
+    let Some((action, guild_id)) = parse_custom_id(&reply.component.data.custom_id) else {
         return;
     };
 
-    let manager = songbird::get(ctx).await.expect("Songbird not registered");
+    let user_id = reply.component.user.id;
+    let member = reply.component.member.as_ref();
+    if let Err(e) = check_session_lock(&data, &ctx.cache, guild_id, user_id, member).await {
+        reply.ephemeral(&ctx, &e.to_string()).await;
+        return;
+    }
 
-    match action {
-        "pause" => handle_pause(ctx, component, &manager, guild_id, data).await,
-        "skip" => handle_skip(ctx, component, &manager, guild_id, data).await,
-        "stop" => handle_stop(ctx, component, &manager, guild_id, data).await,
-        "seekback" => handle_seek(ctx, component, &manager, guild_id, false).await,
-        "seekfwd" => handle_seek(ctx, component, &manager, guild_id, true).await,
-        "repeat" => handle_repeat(ctx, component, &manager, guild_id, data).await,
-        _ => {}
+    let handling = async {
+        match action {
+            "pause" => handle_pause(&ctx, &reply, guild_id, &data).await,
+            "skip" => handle_skip(&ctx, &reply, guild_id, &data).await,
+            "stop" => handle_stop(&ctx, &reply, guild_id, &data).await,
+            "seekback" => handle_seek(&ctx, &reply, guild_id, &data, false).await,
+            "seekfwd" => handle_seek(&ctx, &reply, guild_id, &data, true).await,
+            "repeat" => handle_repeat(&ctx, &reply, guild_id, &data).await,
+            _ => {}
+        }
+    };
+    tokio::pin!(handling);
+
+    tokio::select! {
+        _ = &mut handling => return,
+        _ = tokio::time::sleep(INTERACTION_DEADLINE) => reply.defer(&ctx).await,
     }
+    handling.await;
 }
 
-async fn handle_pause(
-    ctx: &serenity::Context,
-    component: &ComponentInteraction,
-    manager: &Arc<songbird::Songbird>,
-    guild_id: GuildId,
-    data: &Data,
-) {
-    let Some(handler_lock) = manager.get(guild_id) else {
-        send_ephemeral(ctx, component, "Not currently playing.").await;
-        return;
-    };
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ReplyState {
+    Pending,
+    Deferred,
+    Responded,
+}
 
-    let handler = handler_lock.lock().await;
-    let Some(current) = handler.queue().current() else {
-        send_ephemeral(ctx, component, "No track is currently playing.").await;
-        return;
-    };
+/// Responds to a button press, switching to the edit/followup endpoints once
+/// the interaction has been deferred or answered.
+struct ButtonReply {
+    component: ComponentInteraction,
+    state: Mutex<ReplyState>,
+}
 
-    let info = match current.get_info().await {
-        Ok(info) => info,
-        Err(_) => {
-            send_ephemeral(ctx, component, "Could not get track info.").await;
-            return;
+impl ButtonReply {
+    fn new(component: ComponentInteraction) -> Self {
+        Self {
+            component,
+            state: Mutex::new(ReplyState::Pending),
         }
-    };
+    }
 
-    let now_paused = match info.playing {
-        PlayMode::Play => {
-            let _ = current.pause();
-            true
+    /// Acknowledges the press without changing the message, unless a real
+    /// response already went out.
+    async fn defer(&self, ctx: &serenity::Context) {
+        let mut state = self.state.lock().await;
+        if *state != ReplyState::Pending {
+            return;
         }
-        _ => {
-            let _ = current.play();
-            false
+        match self
+            .component
+            .create_response(&ctx.http, CreateInteractionResponse::Acknowledge)
+            .await
+        {
+            Ok(()) => *state = ReplyState::Deferred,
+            Err(e) => tracing::warn!("Failed to defer component interaction: {e}"),
         }
-    };
+    }
 
-    let repeating = {
-        let states = data.repeat_states.read().await;
-        states.get(&guild_id).copied().unwrap_or(false)
-    };
+    async fn ephemeral(&self, ctx: &serenity::Context, content: &str) {
+        let mut state = self.state.lock().await;
+        let result = if *state == ReplyState::Pending {
+            let response = CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            );
+            self.component.create_response(&ctx.http, response).await
+        } else {
+            let followup = CreateInteractionResponseFollowup::new()
+                .content(content)
+                .ephemeral(true);
+            self.component.create_followup(&ctx.http, followup).await.map(|_| ())
+        };
+
+        match result {
+            Ok(()) => *state = ReplyState::Responded,
+            Err(e) => tracing::warn!("Failed to respond to component interaction: {e}"),
+        }
+    }
 
-    // Update the message with toggled button
-    let components = build_now_playing_components(guild_id, now_paused, repeating);
+    /// Swaps the buttons on the Now Playing message.
+    async fn update_components(&self, ctx: &serenity::Context, components: Vec<CreateActionRow>) {
+        let mut state = self.state.lock().await;
+        let result = if *state == ReplyState::Pending {
+            let response = CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new().components(components),
+            );
+            self.component.create_response(&ctx.http, response).await
+        } else {
+            let edit = EditInteractionResponse::new().components(components);
+            self.component.edit_response(&ctx.http, edit).await.map(|_| ())
+        };
 
-    let response = CreateInteractionResponse::UpdateMessage(
-        CreateInteractionResponseMessage::new().components(components),
-    );
+        match result {
+            Ok(()) => *state = ReplyState::Responded,
+            Err(e) => tracing::warn!("Failed to update Now Playing buttons: {e}"),
+        }
+    }
+}
 
-    if let Err(e) = component.create_response(&ctx.http, response).await {
-        tracing::warn!("Failed to respond to pause interaction: {e}");
+/// How long a control waits for the next track to start during a track
+/// change, and how often it looks.
+const TRACK_CHANGE_WAIT: Duration = Duration::from_millis(1500);
+const TRACK_CHANGE_POLL: Duration = Duration::from_millis(100);
+
+/// The playing track. Between one track ending and the next starting (or a
+/// `/play` queueing its first track) songbird briefly has none while tracks
+/// are still pending, so this waits a moment for it. Fails with
+/// `SwitchingTracks` if none turns up in time, or `EmptyQueue` when there
+/// really is nothing to play.
+pub(crate) async fn current_or_next(data: &Data, guild_id: GuildId) -> Result<TrackHandle, MusicError> {
+    let deadline = Instant::now() + TRACK_CHANGE_WAIT;
+    loop {
+        if let Some(current) = data.voice.current(guild_id).await {
+            return Ok(current);
+        }
+        if QueueService::pending_len(&data.guild_queues, guild_id).await == 0 {
+            return Err(MusicError::EmptyQueue);
+        }
+        if Instant::now() >= deadline {
+            return Err(MusicError::SwitchingTracks);
+        }
+        tokio::time::sleep(TRACK_CHANGE_POLL).await;
     }
 }
 
-async fn handle_skip(
+async fn handle_pause(
     ctx: &serenity::Context,
-    component: &ComponentInteraction,
-    manager: &Arc<songbird::Songbird>,
+    reply: &ButtonReply,
     guild_id: GuildId,
     data: &Data,
 ) {
-    let Some(handler_lock) = manager.get(guild_id) else {
-        send_ephemeral(ctx, component, "Not currently playing.").await;
+    if !data.voice.is_connected(guild_id) {
+        reply.ephemeral(ctx, &MusicError::NotConnected.to_string()).await;
         return;
-    };
+    }
 
-    // Capture the currently playing track BEFORE skipping
-    let skipped = QueueService::skip(&data.guild_queues, guild_id).await;
+    let current = match current_or_next(data, guild_id).await {
+        Ok(current) => current,
+        Err(e) => {
+            reply.ephemeral(ctx, &e.to_string()).await;
+            return;
+        }
+    };
 
-    {
-        let handler = handler_lock.lock().await;
-        let queue = handler.queue();
-        if queue.is_empty() {
-            send_ephemeral(ctx, component, "Queue is empty.").await;
+    let resume = resume_playback(ctx, data, guild_id, &current);
+    let toggled = PlaybackService::toggle_pause(data.voice.as_ref(), guild_id, resume).await;
+    let now_paused = match toggled {
+        Ok(paused) => paused,
+        Err(e) => {
+            reply.ephemeral(ctx, &e.to_string()).await;
             return;
         }
-        let _ = queue.skip();
+    };
+    if now_paused {
+        note_paused(data, guild_id).await;
     }
+    // Resuming by hand ends a standby early
+    let left_standby = !now_paused && standby::cancel(data, guild_id).await;
 
-    let msg = match skipped {
-        Some(track) => format!("Skipped: **{track}**"),
-        None => "Skipped current track.".to_string(),
+    let repeating = {
+        let states = data.repeat_states.read().await;
+        states.get(&guild_id).copied().unwrap_or(false)
     };
 
-    send_ephemeral(ctx, component, &msg).await;
-}
+    // Update the message with toggled button
+    let components = build_now_playing_components(guild_id, now_paused, repeating);
 
-async fn handle_stop(
-    ctx: &serenity::Context,
-    component: &ComponentInteraction,
-    manager: &Arc<songbird::Songbird>,
-    guild_id: GuildId,
-    data: &Data,
-) {
-    cleanup_guild(
-        guild_id,
-        &data.guild_queues,
-        &data.enqueue_cancels,
-        &data.inactivity_handles,
-        &data.now_playing_messages,
-        &ctx.http,
-        &data.repeat_states,
-    )
-    .await;
-
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let handler = handler_lock.lock().await;
-        handler.queue().stop();
+    reply.update_components(ctx, components).await;
+    if left_standby {
+        let manager = songbird::get(ctx).await.expect("Songbird not registered");
+        refresh_now_playing(&manager, &ctx.http, &ctx.cache, data, guild_id).await;
     }
+}
+
+async fn handle_skip(ctx: &serenity::Context, reply: &ButtonReply, guild_id: GuildId, data: &Data) {
+    let msg = match skip_playing(ctx, data, guild_id).await {
+        Ok(msg) => msg,
+        Err(e) => e.to_string(),
+    };
+    reply.ephemeral(ctx, &msg).await;
+}
 
-    let _ = manager.leave(guild_id).await;
+async fn handle_stop(ctx: &serenity::Context, reply: &ButtonReply, guild_id: GuildId, data: &Data) {
+    let reason = DisconnectReason::Stopped(reply.component.user.id);
+    cleanup_guild(guild_id, data, &ctx.http, reason).await;
+    // A failed leave still leaves nothing playing
+    let stopped = PlaybackService::stop(data.voice.as_ref(), guild_id).await;
+    if let Err(e @ MusicError::NotConnected) = stopped {
+        reply.ephemeral(ctx, &e.to_string()).await;
+        return;
+    }
 
-    send_ephemeral(ctx, component, "Stopped playback and left the voice channel.").await;
+    reply.ephemeral(ctx, "Stopped playback and left the voice channel.").await;
 }
 
 async fn handle_repeat(
     ctx: &serenity::Context,
-    component: &ComponentInteraction,
-    manager: &Arc<songbird::Songbird>,
+    reply: &ButtonReply,
     guild_id: GuildId,
     data: &Data,
 ) {
-    let Some(handler_lock) = manager.get(guild_id) else {
-        send_ephemeral(ctx, component, "Not currently playing.").await;
+    if !data.voice.is_connected(guild_id) {
+        reply.ephemeral(ctx, &MusicError::NotConnected.to_string()).await;
         return;
-    };
+    }
 
     // Toggle repeat state
     let now_repeating = {
@@ -223,8 +314,7 @@ async fn handle_repeat(
     };
 
     // Enable/disable loop on the current songbird track
-    let handler = handler_lock.lock().await;
-    if let Some(current) = handler.queue().current() {
+    if let Some(current) = data.voice.current(guild_id).await {
         if now_repeating {
             let _ = current.enable_loop();
         } else {
@@ -233,84 +323,119 @@ async fn handle_repeat(
     }
 
     // Get current pause state to rebuild components correctly
-    let paused = if let Some(current) = handler.queue().current() {
-        current
-            .get_info()
-            .await
-            .map(|info| !matches!(info.playing, PlayMode::Play))
-            .unwrap_or(false)
-    } else {
-        false
-    };
+    let paused = data
+        .voice
+        .current_track_info(guild_id)
+        .await
+        .is_some_and(|info| !matches!(info.playing, PlayMode::Play));
 
     let components = build_now_playing_components(guild_id, paused, now_repeating);
 
-    let response = CreateInteractionResponse::UpdateMessage(
-        CreateInteractionResponseMessage::new().components(components),
-    );
-
-    if let Err(e) = component.create_response(&ctx.http, response).await {
-        tracing::warn!("Failed to respond to repeat interaction: {e}");
-    }
+    reply.update_components(ctx, components).await;
 }
 
+/// How far the seek buttons jump.
+const SEEK_STEP: Duration = Duration::from_secs(15);
+/// Forward seeks stop this far short of a known end, so the track still
+/// finishes on its own.
+const SEEK_END_MARGIN: Duration = Duration::from_secs(1);
+
 async fn handle_seek(
     ctx: &serenity::Context,
-    component: &ComponentInteraction,
-    manager: &Arc<songbird::Songbird>,
+    reply: &ButtonReply,
     guild_id: GuildId,
+    data: &Data,
     forward: bool,
 ) {
-    let Some(handler_lock) = manager.get(guild_id) else {
-        send_ephemeral(ctx, component, "Not currently playing.").await;
+    if !data.voice.is_connected(guild_id) {
+        reply.ephemeral(ctx, &MusicError::NotConnected.to_string()).await;
         return;
-    };
+    }
 
-    let handler = handler_lock.lock().await;
-    let Some(current) = handler.queue().current() else {
-        send_ephemeral(ctx, component, "No track is currently playing.").await;
-        return;
+    let current = match current_or_next(data, guild_id).await {
+        Ok(current) => current,
+        Err(e) => {
+            reply.ephemeral(ctx, &e.to_string()).await;
+            return;
+        }
     };
 
     let info = match current.get_info().await {
         Ok(info) => info,
         Err(_) => {
-            send_ephemeral(ctx, component, "Could not get track info.").await;
+            reply.ephemeral(ctx, "Could not get track info.").await;
             return;
         }
     };
 
     let position = info.position;
     let new_position = if forward {
-        position + Duration::from_secs(15)
+        let wanted = position + SEEK_STEP;
+        let track = QueueService::find_by_handle(&data.guild_queues, guild_id, current.uuid()).await;
+        match track.as_ref().and_then(media_end) {
+            Some(end) => {
+                let last = end.saturating_sub(SEEK_END_MARGIN);
+                if position >= last {
+                    reply.ephemeral(ctx, "Already at the end of the track.").await;
+                    return;
+                }
+                wanted.min(last)
+            }
+            None => wanted,
+        }
     } else {
-        position.saturating_sub(Duration::from_secs(15))
+        position.saturating_sub(SEEK_STEP)
     };
 
-    let _ = current.seek(new_position);
+    // Without a known length a forward seek can still land past the end
+    let new_position = match current.seek_async(new_position).await {
+        Ok(landed) => landed,
+        Err(e) => {
+            tracing::warn!("Seek button failed in guild {guild_id}: {e}");
+            reply.ephemeral(ctx, "Couldn't seek — near the end of the track?").await;
+            return;
+        }
+    };
 
     let direction = if forward { "forward" } else { "backward" };
+    let moved = new_position.abs_diff(position).as_secs();
     let secs = new_position.as_secs();
     let msg = format!(
-        "Seeked {direction} 15s → `{}:{:02}`",
+        "Seeked {direction} {moved}s → `{}:{:02}`",
         secs / 60,
         secs % 60
     );
-    send_ephemeral(ctx, component, &msg).await;
+    reply.ephemeral(ctx, &msg).await;
 }
 
-async fn send_ephemeral(
-    ctx: &serenity::Context,
-    component: &ComponentInteraction,
-    content: &str,
-) {
-    let response = CreateInteractionResponse::Message(
-        CreateInteractionResponseMessage::new()
-            .content(content)
-            .ephemeral(true),
-    );
+/// Where the track's audio ends in the underlying media, when its length is
+/// known. Spotify tracks get the matched upload's length once it resolves.
+fn media_end(track: &Track) -> Option<Duration> {
+    let start = track.span.map_or(Duration::ZERO, |span| span.start);
+    track.span.and_then(|span| span.end).or_else(|| track.duration.map(|length| start + length))
+}
 
-    if let Err(e) = component.create_response(&ctx.http, response).await {
-        tracing::warn!("Failed to respond to component interaction: {e}");
+/// Forgets a "Now Playing" message when someone deletes it by hand, so we
+/// don't keep trying to unpin or delete a message that's gone. The next track
+/// posts a fresh one.
+pub async fn handle_message_delete(guild_id: Option<GuildId>, message_id: MessageId, data: &Data) {
+    let Some(guild_id) = guild_id else {
+        return;
+    };
+
+    let removed = {
+        let mut messages = data.now_playing_messages.write().await;
+        let Some(cards) = messages.get_mut(&guild_id) else {
+            return;
+        };
+        let removed = cards.remove_message(message_id);
+        if cards.is_empty() {
+            messages.remove(&guild_id);
+        }
+        removed
+    };
+    if removed {
+        tracing::debug!("Now Playing message deleted externally in guild {guild_id}");
+        persist_now_playing(data).await;
     }
 }
@@ -0,0 +1,80 @@
+use poise::serenity_prelude::{
+    self as serenity, ChannelType, Colour, CreateEmbed, CreateMessage, Guild, GuildChannel,
+    Permissions,
+};
+
+use crate::Data;
+
+const WELCOME_COLOR: Colour = Colour::new(0x5865F2);
+
+/// Picks where to post the welcome message: the system channel if we can
+/// write there, otherwise the first text channel we can.
+fn welcome_channel(guild: &Guild, bot_id: serenity::UserId) -> Option<&GuildChannel> {
+    let member = guild.members.get(&bot_id)?;
+    let needed = Permissions::SEND_MESSAGES | Permissions::EMBED_LINKS;
+    let writable =
+        |channel: &GuildChannel| guild.user_permissions_in(channel, member).contains(needed);
+
+    let system = guild
+        .system_channel_id
+        .and_then(|id| guild.channels.get(&id))
+        .filter(|channel| writable(channel));
+
+    system.or_else(|| {
+        let mut text_channels: Vec<_> = guild
+            .channels
+            .values()
+            .filter(|channel| channel.kind == ChannelType::Text && writable(channel))
+            .collect();
+        text_channels.sort_by_key(|channel| channel.position);
+        text_channels.into_iter().next()
+    })
+}
+
+fn welcome_embed() -> CreateEmbed {
+    CreateEmbed::new()
+        .title("Thanks for adding me!")
+        .description(
+            "Join a voice channel and use `/play search` with a song name or `/play url` with a YouTube/Spotify link to get started.",
+        )
+        .field(
+            "Commands",
+            "`/play` · `/skip` · `/next` · `/list` · `/stop` · `/join`",
+            false,
+        )
+        .field(
+            "Permissions",
+            "Connect and Speak in voice channels, plus Send Messages and Embed Links where you use me.",
+            false,
+        )
+        .field(
+            "Setup",
+            "Admins can change embeds, queue behaviour, and more with `/settings`.",
+            false,
+        )
+        .colour(WELCOME_COLOR)
+}
+
+/// Posts a welcome message when the bot is added to a server. `is_new` is
+/// only `Some(true)` for real joins, not the guilds replayed at startup.
+pub async fn handle_guild_create(
+    ctx: &serenity::Context,
+    guild: &Guild,
+    is_new: Option<bool>,
+    data: &Data,
+) {
+    if is_new != Some(true) || !data.onboarding_messages {
+        return;
+    }
+
+    let bot_id = ctx.cache.current_user().id;
+    let Some(channel) = welcome_channel(guild, bot_id) else {
+        tracing::info!("No writable channel for the welcome message in guild {}", guild.id);
+        return;
+    };
+
+    let msg = CreateMessage::new().embed(welcome_embed());
+    if let Err(e) = channel.id.send_message(&ctx.http, msg).await {
+        tracing::warn!("Failed to send welcome message in guild {}: {e}", guild.id);
+    }
+}
@@ -0,0 +1,86 @@
+use poise::serenity_prelude::{Cache, GuildId, Member, Permissions, UserId};
+
+use crate::services::error::MusicError;
+use crate::{Context, Data};
+
+/// Members holding a role with this name (case-insensitive) count as DJs.
+const DJ_ROLE_NAME: &str = "DJ";
+
+/// Returns true if the member may control playback for everyone: server
+/// owners, admins, members with Manage Server / Manage Channels, or anyone
+/// holding the DJ role.
+pub fn is_dj_member(cache: &Cache, guild_id: GuildId, member: &Member) -> bool {
+    let elevated =
+        Permissions::ADMINISTRATOR | Permissions::MANAGE_GUILD | Permissions::MANAGE_CHANNELS;
+
+    // Interaction members carry their resolved permissions
+    if member.permissions.is_some_and(|p| p.intersects(elevated)) {
+        return true;
+    }
+
+    let Some(guild) = cache.guild(guild_id) else {
+        return false;
+    };
+
+    if guild.member_permissions(member).intersects(elevated) {
+        return true;
+    }
+
+    member.roles.iter().any(|role_id| {
+        guild
+            .roles
+            .get(role_id)
+            .is_some_and(|role| role.name.eq_ignore_ascii_case(DJ_ROLE_NAME))
+    })
+}
+
+/// DJ check for the author of a command.
+pub async fn is_dj(ctx: Context<'_>) -> bool {
+    let Some(guild_id) = ctx.guild_id() else {
+        return false;
+    };
+    let Some(member) = ctx.author_member().await else {
+        return false;
+    };
+    is_dj_member(ctx.cache(), guild_id, &member)
+}
+
+/// Returns true for server owners, admins and members with Manage Server,
+/// who can always control playback, even during a locked session.
+pub fn is_admin_member(cache: &Cache, guild_id: GuildId, member: &Member) -> bool {
+    let elevated = Permissions::ADMINISTRATOR | Permissions::MANAGE_GUILD;
+    if member.permissions.is_some_and(|p| p.intersects(elevated)) {
+        return true;
+    }
+    cache
+        .guild(guild_id)
+        .is_some_and(|guild| guild.member_permissions(member).intersects(elevated))
+}
+
+/// Fails with `SessionLocked` while the guild's session is locked to someone
+/// other than `user_id`, unless `member` is an admin. Shared by the playback
+/// commands and the Now Playing buttons.
+pub async fn check_session_lock(
+    data: &Data,
+    cache: &Cache,
+    guild_id: GuildId,
+    user_id: UserId,
+    member: Option<&Member>,
+) -> Result<(), MusicError> {
+    let Some(host) = data.session_locks.read().await.get(&guild_id).copied() else {
+        return Ok(());
+    };
+    if host == user_id || member.is_some_and(|m| is_admin_member(cache, guild_id, m)) {
+        return Ok(());
+    }
+    Err(MusicError::SessionLocked(host))
+}
+
+/// Session lock check for the author of a command.
+pub async fn ensure_unlocked(ctx: Context<'_>) -> Result<(), MusicError> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(());
+    };
+    let member = ctx.author_member().await;
+    check_session_lock(ctx.data(), ctx.cache(), guild_id, ctx.author().id, member.as_deref()).await
+}
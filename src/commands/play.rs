@@ -1,141 +1,396 @@
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
 
 use async_trait::async_trait;
 use poise::serenity_prelude::{
-    AutocompleteChoice, ChannelId, Colour, CreateEmbed, CreateEmbedAuthor, CreateMessage, GuildId,
-    Http,
+    self as serenity, AutocompleteChoice, Cache, ChannelId, CreateActionRow, CreateAllowedMentions,
+    CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateMessage, EditMessage, GuildId, Http, Permissions,
 };
-use songbird::events::{Event, EventContext, EventHandler, TrackEvent};
+use rand::seq::SliceRandom;
 use songbird::Call;
-use tokio::sync::Mutex;
-
-use crate::domain::track::{Track, TrackSource};
-use crate::infrastructure::audio::AudioSource;
-use crate::infrastructure::inactivity::spawn_inactivity_monitor;
-use crate::services::cleanup::cleanup_guild;
+use songbird::driver::Bitrate;
+use songbird::events::context_data::DisconnectReason as VoiceDisconnect;
+use songbird::events::{Event, EventContext, EventHandler, TrackEvent};
+use songbird::model::CloseCode;
+use songbird::tracks::{LoopState, PlayMode, TrackHandle};
+use tokio::sync::{Mutex, mpsc};
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+use crate::commands::board::{board_content, note_rendered, spawn_board_updater};
+use crate::commands::channel_status::show_track;
+use crate::commands::cleanup::{
+    DisconnectReason, cleanup_session, drop_failed_track, resume_held_enqueues,
+    retire_now_playing_message, start_session,
+};
+use crate::commands::embeds::{
+    NEUTRAL_COLOR, QueuePlacement, card_content, collection_embed, enqueue_embed, group_digits,
+    linked_title, links_embed, note_collection_limit, now_playing_embed, plain_now_playing,
+    requester_footer, source_info, source_tag,
+};
+use crate::commands::inactivity::spawn_inactivity_monitor;
+use crate::commands::loudness::watch_loudness;
+use crate::commands::permissions::{ensure_unlocked, is_dj};
+use crate::commands::settings::SearchChoice;
+use crate::commands::stale_input::{note_paused, watch_input};
+use crate::commands::standby::{self, STANDBY_NOTICE};
+use crate::commands::watchdog::spawn_playback_watchdog;
+use crate::domain::chapters::parse_chapters;
+use crate::domain::now_playing::NowPlayingCards;
+use crate::domain::playlist::{PageCursor, PlaylistInfo, PlaylistTracks};
+use crate::domain::preferences::{Confirmations, PlayPreferences};
+use crate::domain::settings::{
+    FollowRequester, GuildSettings, NowPlayingMode, SearchPreference, SingleTrackOrder,
+};
+use crate::domain::track::{Requester, Track, TrackSource, TrackSpan, format_secs};
+use crate::infrastructure::audio::{AudioSource, ResolvedMatch};
+use crate::infrastructure::enqueue_store::{PendingCollection, PendingPages};
+use crate::infrastructure::youtube::{PlaylistError, canonicalize_youtube_url};
+use crate::services::enqueue_lock::{Ticket, release_cancel_flag, track_cancel_flag};
 use crate::services::error::MusicError;
-use crate::services::music_service::{MusicService, SpotifyUrl};
-use crate::services::queue_service::{GuildQueues, QueueService};
-use crate::{Context, EnqueueCancels, Error, InactivityHandles, JoinLocks, NowPlayingMessages, RepeatStates};
-
-pub const SPOTIFY_ICON: &str = "https://upload.wikimedia.org/wikipedia/commons/thumb/1/19/Spotify_logo_without_text.svg/168px-Spotify_logo_without_text.svg.png";
-pub const YOUTUBE_ICON: &str = "https://www.gstatic.com/images/branding/product/2x/youtube_64dp.png";
-
-const SPOTIFY_COLOR: Colour = Colour::new(0x1DB954);
-const YOUTUBE_COLOR: Colour = Colour::new(0xFF0000);
-
-pub fn source_info(source: &TrackSource) -> (&'static str, Colour, &'static str) {
-    match source {
-        TrackSource::Spotify => (SPOTIFY_ICON, SPOTIFY_COLOR, "Spotify"),
-        TrackSource::YouTube => (YOUTUBE_ICON, YOUTUBE_COLOR, "YouTube"),
+use crate::services::history_service::HistoryService;
+use crate::services::music_service::{MusicService, SpotifyUrl, autocomplete_choices};
+use crate::services::now_playing_service::NowPlayingService;
+use crate::services::playback_service::PlaybackService;
+use crate::services::preferences_service::PreferencesService;
+use crate::services::queue_service::QueueService;
+use crate::services::settings_service::SettingsService;
+use crate::services::stats_service::StatsService;
+use crate::{Context, Data, Error};
+
+/// Minimum gap between "failed to send" warnings for the same channel.
+const SEND_FAILURE_LOG_INTERVAL: Duration = Duration::from_secs(600);
+
+static SEND_FAILURES: LazyLock<std::sync::Mutex<HashMap<ChannelId, Instant>>> =
+    LazyLock::new(Default::default);
+
+/// Rate-limits Now Playing send-failure logs so an unwritable channel
+/// doesn't produce a warning on every track change.
+fn should_log_send_failure(channel_id: ChannelId) -> bool {
+    let mut failures = SEND_FAILURES.lock().unwrap_or_else(|e| e.into_inner());
+    let now = Instant::now();
+    match failures.get(&channel_id) {
+        Some(last) if now.duration_since(*last) < SEND_FAILURE_LOG_INTERVAL => false,
+        _ => {
+            failures.insert(channel_id, now);
+            true
+        }
     }
 }
 
-pub fn linked_title(track: &Track) -> String {
-    if track.url.is_empty() {
-        format!("**{}** - {}", track.title, track.artist)
-    } else {
-        format!("[**{}** - {}]({})", track.title, track.artist, track.url)
-    }
+struct NowPlayingNotifier {
+    http: Arc<Http>,
+    cache: Arc<Cache>,
+    guild_id: GuildId,
+    requester: Option<Requester>,
+    /// Where the search input records its YouTube match, for Spotify tracks.
+    resolved: Option<ResolvedMatch>,
+    data: Data,
 }
 
-fn enqueue_embed(track: &Track) -> CreateEmbed {
-    let (icon, color, source_name) = source_info(&track.source);
-    let duration = track.duration.as_deref().unwrap_or("--:--");
+/// How long the Now Playing card waits for a Spotify track's YouTube match.
+const RESOLVE_WAIT: Duration = Duration::from_secs(5);
 
-    CreateEmbed::new()
-        .author(CreateEmbedAuthor::new(source_name).icon_url(icon))
-        .description(format!(
-            "Added {} - `{}`  to the queue.",
-            linked_title(track), duration
-        ))
-        .colour(color)
-}
+#[async_trait]
+impl EventHandler for NowPlayingNotifier {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        // Advance the domain queue: pop next track into `current`
+        let mut track = QueueService::advance(&self.data.guild_queues, self.guild_id).await?;
+        HistoryService::started(
+            &self.data.play_histories,
+            self.guild_id,
+            track.clone(),
+            self.data.history_limit,
+        )
+        .await;
 
-pub fn now_playing_embed(track: &Track, requester: &str) -> CreateEmbed {
-    let (_, color, _) = source_info(&track.source);
-    let duration = track.duration.as_deref().unwrap_or("--:--");
+        // Play fires while a lazy input may still be loading, so give yt-dlp
+        // a moment to pick the upload before rendering the card
+        if let (Some(resolved), EventContext::Track([(_, handle), ..])) = (&self.resolved, ctx) {
+            let _ = tokio::time::timeout(RESOLVE_WAIT, handle.make_playable_async()).await;
+            if let Some((url, found)) = resolved
+                .get()
+                .and_then(|found| Some((found.url.clone()?, found)))
+            {
+                // The upload's length is what playback and seeking go by
+                track.resolved_url = Some(url.clone());
+                if found.duration.is_some() {
+                    track.duration = found.duration;
+                }
+                // A skip during the wait means this card is already stale
+                if !QueueService::set_resolved(
+                    &self.data.guild_queues,
+                    self.guild_id,
+                    handle.uuid(),
+                    url,
+                    found.duration,
+                )
+                .await
+                {
+                    return None;
+                }
+            }
+        }
 
-    let mut embed = CreateEmbed::new()
-        .title("Now playing")
-        .description(format!(
-            "{} - `{}`\n\nRequested by {}",
-            linked_title(track), duration, requester
-        ))
-        .colour(color);
+        // If repeat is enabled, enable looping on the new track via songbird
+        let repeating = {
+            let states = self.data.repeat_states.read().await;
+            states.get(&self.guild_id).copied().unwrap_or(false)
+        };
 
-    if let Some(url) = &track.thumbnail_url {
-        embed = embed.thumbnail(url);
-    }
+        let settings = SettingsService::get(&self.data.guild_settings, self.guild_id).await;
+        let standby = standby::is_on_standby(&self.data, self.guild_id).await;
+
+        let handle = match ctx {
+            EventContext::Track([(_, handle), ..]) => Some(*handle),
+            _ => None,
+        };
+
+        // One swap at a time per guild, so rapid track changes can't leave
+        // two cards behind
+        let replace = |previous: NowPlayingCards| async move {
+            // Post into whichever channel the session is currently bound to,
+            // and any extra Now Playing channels
+            let channel_id = self
+                .data
+                .text_channels
+                .read()
+                .await
+                .get(&self.guild_id)
+                .copied();
+            if settings.now_playing_status {
+                let (http, cache) = (&self.http, &self.cache);
+                show_track(http, cache, &self.data, self.guild_id, channel_id, &track).await;
+            }
+
+            let (embed, components) = match settings.now_playing_mode {
+                NowPlayingMode::Card => {
+                    let footer = match self.requester {
+                        Some(requester) => {
+                            let (http, cache) = (&self.http, &self.cache);
+                            requester_footer(http, cache, self.guild_id, requester).await
+                        }
+                        None => None,
+                    };
+                    let components = super::now_playing::build_now_playing_components(
+                        self.guild_id,
+                        standby,
+                        repeating,
+                    );
+                    (now_playing_embed(&track, footer, &settings), components)
+                }
+                NowPlayingMode::Board => {
+                    board_content(&self.data, self.guild_id, &track, handle, &settings).await
+                }
+            };
+
+            let plain = match settings.plain_text_announcements {
+                true => {
+                    let (http, cache) = (&self.http, &self.cache);
+                    Some(plain_now_playing(http, cache, self.guild_id, &track).await)
+                }
+                false => None,
+            };
+            // The board shows standby in its embed; a card needs it in the text
+            let notice = (standby && settings.now_playing_mode == NowPlayingMode::Card)
+                .then_some(STANDBY_NOTICE);
+            let content = card_content(notice, plain.as_deref());
+
+            // A board stays put and is edited in each channel that already has
+            // one, as long as the session hasn't moved away from it
+            let mut cards = NowPlayingCards::default();
+            if settings.now_playing_mode == NowPlayingMode::Board {
+                for target in settings.now_playing_targets(channel_id) {
+                    let Some(msg_id) = previous.get(target) else {
+                        continue;
+                    };
+                    let edit = EditMessage::new()
+                        .content(content.clone())
+                        .embed(embed.clone())
+                        .components(components.clone())
+                        .allowed_mentions(CreateAllowedMentions::new());
+                    if target.edit_message(&self.http, msg_id, edit).await.is_ok() {
+                        cards.insert(target, msg_id);
+                    }
+                }
+            }
+
+            // Delete the previous "Now Playing" messages that weren't kept
+            for (ch, msg_id) in previous.iter() {
+                if cards.get(ch) != Some(msg_id) {
+                    let unpin = settings.pin_now_playing;
+                    retire_now_playing_message(&self.http, ch, msg_id, unpin).await;
+                }
+            }
 
-    embed
+            for target in settings.now_playing_targets(channel_id) {
+                if cards.get(target).is_some() {
+                    continue;
+                }
+                // Titles are arbitrary text, so the plain announcement can't
+                // ping
+                let message = CreateMessage::new()
+                    .content(content.clone())
+                    .embed(embed.clone())
+                    .components(components.clone())
+                    .allowed_mentions(CreateAllowedMentions::new());
+                // A channel we can't post in is logged and skipped, the others
+                // still get their card
+                match target.send_message(&self.http, message).await {
+                    Ok(msg) => {
+                        // Pinning fails when the channel already has 50 pins or
+                        // we lack Manage Messages; the card just stays unpinned
+                        // then.
+                        if settings.pin_now_playing {
+                            if let Err(e) = msg.pin(&self.http).await {
+                                tracing::warn!("Failed to pin Now Playing message: {e}");
+                            }
+                        }
+                        cards.insert(target, msg.id);
+                    }
+                    Err(e) => {
+                        if should_log_send_failure(target) {
+                            let problem = "Failed to send Now Playing message";
+                            tracing::warn!("{problem} in {target}: {e}");
+                        }
+                    }
+                }
+            }
+            if settings.now_playing_mode == NowPlayingMode::Board {
+                // So the next refresh can tell whether anything moved since
+                for (_, msg_id) in cards.iter() {
+                    note_rendered(&self.data, self.guild_id, msg_id, &embed, &components).await;
+                }
+            }
+            cards
+        };
+        NowPlayingService::swap(
+            &self.data.now_playing_locks,
+            &self.data.now_playing_messages,
+            self.data.now_playing_store.as_deref(),
+            self.guild_id,
+            replace,
+        )
+        .await;
+        None
+    }
 }
 
-fn collection_embed(name: &str, url: &str, count: usize, source: &TrackSource) -> CreateEmbed {
-    let (icon, color, source_name) = source_info(source);
-    let linked_name = if url.is_empty() {
-        format!("**{name}**")
-    } else {
-        format!("[**{name}**]({url})")
-    };
+/// Seeks a track to `position` once it has loaded. Chapter entries also
+/// register it for when they loop, to jump back to their chapter's start.
+pub(crate) struct SeekTo {
+    pub(crate) position: Duration,
+}
 
-    CreateEmbed::new()
-        .author(CreateEmbedAuthor::new(source_name).icon_url(icon))
-        .description(format!(
-            "Added {linked_name} with `{count}` tracks to the queue."
-        ))
-        .colour(color)
+#[async_trait]
+impl EventHandler for SeekTo {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            for (_, handle) in *tracks {
+                let _ = handle.seek(self.position);
+            }
+        }
+        None
+    }
 }
 
-struct NowPlayingNotifier {
-    http: Arc<Http>,
-    channel_id: ChannelId,
-    guild_id: GuildId,
-    requester: String,
-    now_playing_messages: NowPlayingMessages,
-    guild_queues: GuildQueues,
-    repeat_states: RepeatStates,
+/// Ends a chapter entry when playback reaches the next chapter, or jumps
+/// back to its start when the track is on repeat.
+struct ChapterEnd {
+    start: Duration,
+    end: Duration,
 }
 
 #[async_trait]
-impl EventHandler for NowPlayingNotifier {
-    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
-        // Advance the domain queue: pop next track into `current`
-        let track = QueueService::advance(&self.guild_queues, self.guild_id).await;
-        let Some(track) = track else {
+impl EventHandler for ChapterEnd {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(tracks) = ctx else {
             return None;
         };
+        for (state, handle) in *tracks {
+            if state.position < self.end {
+                continue;
+            }
+            let repeating = !matches!(state.loops, LoopState::Finite(0));
+            if repeating {
+                let _ = handle.seek(self.start);
+            } else {
+                let _ = handle.stop();
+                return Some(Event::Cancel);
+            }
+        }
+        None
+    }
+}
 
-        // If repeat is enabled, enable looping on the new track via songbird
-        let repeating = {
-            let states = self.repeat_states.read().await;
-            states.get(&self.guild_id).copied().unwrap_or(false)
-        };
+/// How often a chapter entry checks whether it has reached its end.
+const CHAPTER_END_CHECK: Duration = Duration::from_millis(500);
 
-        // Delete the previous "Now Playing" message
-        if let Some((ch, msg_id)) = self
-            .now_playing_messages
-            .write()
-            .await
-            .remove(&self.guild_id)
-        {
-            let _ = ch.delete_message(&self.http, msg_id).await;
-        }
+/// Drops a track from the domain queue once songbird gives up on it, so
+/// `/list` and `/next` don't show tracks that will never play.
+struct TrackErrorCleanup {
+    http: Arc<Http>,
+    guild_id: GuildId,
+    handle_id: Uuid,
+    data: Data,
+}
 
-        let embed = now_playing_embed(&track, &self.requester);
-        let components =
-            super::now_playing::build_now_playing_components(self.guild_id, false, repeating);
-        let message = CreateMessage::new().embed(embed).components(components);
-        match self.channel_id.send_message(&self.http, message).await {
-            Ok(msg) => {
-                self.now_playing_messages
-                    .write()
-                    .await
-                    .insert(self.guild_id, (self.channel_id, msg.id));
+#[async_trait]
+impl EventHandler for TrackErrorCleanup {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let error = match ctx {
+            EventContext::Track(tracks) => {
+                tracks.iter().find_map(|(state, _)| match &state.playing {
+                    PlayMode::Errored(e) => Some(e.to_string()),
+                    _ => None,
+                })
             }
-            Err(e) => {
-                tracing::warn!("Failed to send Now Playing message: {e}");
+            _ => None,
+        };
+        drop_failed_track(
+            &self.http,
+            &self.data,
+            self.guild_id,
+            self.handle_id,
+            "Couldn't play",
+            error.unwrap_or_else(|| "unknown error".to_string()),
+        )
+        .await;
+        None
+    }
+}
+
+/// Feeds a finished track into the guild's `/top` stats and closes its
+/// history entry.
+struct PlayStats {
+    guild_id: GuildId,
+    track: Track,
+    data: Data,
+}
+
+#[async_trait]
+impl EventHandler for PlayStats {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        if let EventContext::Track(tracks) = ctx {
+            for (state, handle) in *tracks {
+                let counted = StatsService::record(
+                    &self.data.guild_stats,
+                    self.guild_id,
+                    &self.track,
+                    state.play_time,
+                )
+                .await;
+                // Tracks dropped unplayed by a /stop or /clean end too, and
+                // don't need saving
+                if counted {
+                    super::top::persist_stats(&self.data);
+                }
+                HistoryService::finished(&self.data.play_histories, self.guild_id, handle.uuid())
+                    .await;
             }
         }
         None
@@ -144,522 +399,2647 @@ impl EventHandler for NowPlayingNotifier {
 
 struct DisconnectCleanup {
     guild_id: GuildId,
+    session: u64,
     http: Arc<Http>,
-    guild_queues: GuildQueues,
-    enqueue_cancels: EnqueueCancels,
-    inactivity_handles: InactivityHandles,
-    now_playing_messages: NowPlayingMessages,
-    repeat_states: RepeatStates,
+    data: Data,
 }
 
 #[async_trait]
 impl EventHandler for DisconnectCleanup {
-    async fn act(&self, _ctx: &EventContext<'_>) -> Option<Event> {
-        tracing::info!("Bot disconnected from guild {}, cleaning up", self.guild_id);
-        cleanup_guild(
-            self.guild_id,
-            &self.guild_queues,
-            &self.enqueue_cancels,
-            &self.inactivity_handles,
-            &self.now_playing_messages,
-            &self.http,
-            &self.repeat_states,
-        )
-        .await;
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        // A disconnect without a reason is songbird switching channels (e.g.
+        // `/join` moving the bot), so the session carries on.
+        let reason = match ctx {
+            EventContext::DriverDisconnect(info) => match info.reason {
+                None => return None,
+                // Discord closes the connection with 4014 when the bot is
+                // disconnected from its channel or the channel is deleted
+                Some(VoiceDisconnect::WsClosed(Some(CloseCode::Disconnected))) => {
+                    DisconnectReason::Kicked
+                }
+                Some(_) => DisconnectReason::VoiceError,
+            },
+            _ => DisconnectReason::VoiceError,
+        };
+
+        if cleanup_session(self.guild_id, self.session, &self.data, &self.http, reason).await {
+            tracing::info!("Bot disconnected from guild {}, cleaned up", self.guild_id);
+        }
         None
     }
 }
 
-async fn enqueue_track(
+/// How long before a track ends songbird starts loading the next one.
+const PRELOAD_LEAD: Duration = Duration::from_secs(5);
+
+/// The point in `track`'s media at which songbird should load the next
+/// track, or `None` to wait until it ends when its length isn't known.
+fn preload_time(track: &Track) -> Option<Duration> {
+    track
+        .media_end()
+        .map(|end| end.saturating_sub(PRELOAD_LEAD))
+}
+
+/// Builds the songbird input for `track`, which should carry its requester,
+/// and adds it to the end of the call's queue with the bot's track events.
+/// With `announce`, starting it
+/// advances the domain queue and posts Now Playing; a fresh input swapped in
+/// for the current track leaves both alone.
+pub(crate) async fn queue_input(
     track: &Track,
     search_query: &str,
-    http_client: &reqwest::Client,
     handler_lock: &Arc<Mutex<Call>>,
-    serenity_http: &Arc<Http>,
-    channel_id: ChannelId,
-    requester: &str,
-    guild_queues: &GuildQueues,
+    serenity_ctx: &serenity::Context,
     guild_id: GuildId,
-    now_playing_messages: &NowPlayingMessages,
-    repeat_states: &RepeatStates,
-) {
-    let input = if search_query.is_empty() {
-        AudioSource::from_url(http_client.clone(), &track.url)
+    data: &Data,
+    announce: bool,
+) -> TrackHandle {
+    let loaded = ResolvedMatch::default();
+    let (input, resolved) = if search_query.is_empty() {
+        let input = AudioSource::from_url(
+            data.http_client.clone(),
+            &track.url,
+            &data.ytdlp_format,
+            loaded.clone(),
+        );
+        (input, None)
     } else {
-        AudioSource::from_search(http_client.clone(), search_query)
+        let input = AudioSource::from_search(
+            data.http_client.clone(),
+            search_query,
+            &data.ytdlp_format,
+            loaded.clone(),
+        );
+        (input, Some(loaded.clone()))
     };
 
-    {
-        let mut handler = handler_lock.lock().await;
-        let track_handle = handler.enqueue_input(input).await;
+    let volume = SettingsService::get(&data.guild_settings, guild_id)
+        .await
+        .track_volume(track);
+    let standby = standby::is_on_standby(data, guild_id).await;
+    // `enqueue_input` would ask yt-dlp for the length while holding the call
+    // lock, stalling every button and command on the call until it answers
+    let queued = songbird::tracks::Track::from(input).volume(volume);
+    let (track_handle, position) = data
+        .voice
+        .enqueue(handler_lock, queued, preload_time(track))
+        .await;
+    // Songbird starts the first track right away; on standby it's held
+    // before any audio goes out
+    if standby && position == 0 {
+        let _ = track_handle.pause();
+        note_paused(data, guild_id).await;
+    }
+    if announce {
         let _ = track_handle.add_event(
             Event::Track(TrackEvent::Play),
             NowPlayingNotifier {
-                http: serenity_http.clone(),
-                channel_id,
+                http: serenity_ctx.http.clone(),
+                cache: serenity_ctx.cache.clone(),
                 guild_id,
-                requester: requester.to_string(),
-                now_playing_messages: now_playing_messages.clone(),
-                guild_queues: guild_queues.clone(),
-                repeat_states: repeat_states.clone(),
+                requester: track.requested_by,
+                resolved,
+                data: data.clone(),
             },
         );
     }
+    watch_input(&track_handle, guild_id, data);
+    watch_loudness(&track_handle, guild_id, data, loaded);
+    let _ = track_handle.add_event(
+        Event::Track(TrackEvent::Error),
+        TrackErrorCleanup {
+            http: serenity_ctx.http.clone(),
+            guild_id,
+            handle_id: track_handle.uuid(),
+            data: data.clone(),
+        },
+    );
+    let _ = track_handle.add_event(
+        Event::Track(TrackEvent::End),
+        PlayStats {
+            guild_id,
+            track: track.clone(),
+            data: data.clone(),
+        },
+    );
+    if let Some(span) = track.span {
+        if !span.start.is_zero() {
+            let chapter_start = || SeekTo {
+                position: span.start,
+            };
+            let _ = track_handle.add_event(Event::Track(TrackEvent::Playable), chapter_start());
+            let _ = track_handle.add_event(Event::Track(TrackEvent::Loop), chapter_start());
+        }
+        if let Some(end) = span.end {
+            let _ = track_handle.add_event(
+                Event::Periodic(CHAPTER_END_CHECK, None),
+                ChapterEnd {
+                    start: span.start,
+                    end,
+                },
+            );
+        }
+    }
+    track_handle
+}
+
+async fn enqueue_track(
+    track: &Track,
+    search_query: &str,
+    handler_lock: &Arc<Mutex<Call>>,
+    serenity_ctx: &serenity::Context,
+    requester: Requester,
+    guild_id: GuildId,
+    data: &Data,
+) -> Uuid {
+    let mut track = Track {
+        requested_by: Some(requester),
+        ..track.clone()
+    };
+    let handle_id = queue_input(
+        &track,
+        search_query,
+        handler_lock,
+        serenity_ctx,
+        guild_id,
+        data,
+        true,
+    )
+    .await
+    .uuid();
+    track.handle_id = Some(handle_id);
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let pending =
+        QueueService::add_track(&data.guild_queues, guild_id, track, settings.fair_queue).await;
+
+    if settings.fair_queue {
+        sync_playback_order(handler_lock, data, guild_id).await;
+    }
+    if settings.queue_nearly_full(pending) {
+        warn_queue_filling(&serenity_ctx.http, data, guild_id, pending, &settings).await;
+    }
 
-    QueueService::add_track(guild_queues, guild_id, track.clone()).await;
+    handle_id
 }
 
-async fn enqueue_collection_tracks(
-    tracks: Vec<Track>,
-    http_client: reqwest::Client,
-    handler_lock: Arc<Mutex<Call>>,
-    serenity_http: Arc<Http>,
-    channel_id: ChannelId,
-    requester: String,
-    guild_queues: GuildQueues,
+/// Tells the music channel once per session that the queue is closing in on
+/// its limit.
+async fn warn_queue_filling(
+    http: &Http,
+    data: &Data,
     guild_id: GuildId,
-    enqueue_mutex: Arc<Mutex<()>>,
-    cancel_flag: Arc<AtomicBool>,
-    now_playing_messages: NowPlayingMessages,
-    repeat_states: RepeatStates,
+    pending: usize,
+    settings: &GuildSettings,
 ) {
-    // Acquire per-guild lock so collections are enqueued sequentially
-    let _guard = enqueue_mutex.lock_owned().await;
+    let (Some(limit), Some(session)) = (
+        settings.queue_limit,
+        data.sessions.read().await.get(&guild_id).copied(),
+    ) else {
+        return;
+    };
+    if data.queue_warnings.write().await.insert(guild_id, session) == Some(session) {
+        return;
+    }
 
-    for track in &tracks {
-        if cancel_flag.load(Ordering::Relaxed) {
-            tracing::info!("Background enqueue cancelled for guild {guild_id}");
-            return;
-        }
+    let Some(channel_id) = data.text_channels.read().await.get(&guild_id).copied() else {
+        return;
+    };
+    let msg = CreateMessage::new().content(format!(
+        "Queue is at {pending}/{limit} tracks. Once it's full, new tracks won't be added until some have played."
+    ));
+    let _ = channel_id.send_message(http, msg).await;
+}
 
-        let search_query = match track.source {
-            TrackSource::Spotify => MusicService::spotify_to_youtube_query(track),
-            TrackSource::YouTube => String::new(),
-        };
+/// Errors when the guild's queue has no room for another track.
+async fn ensure_queue_room(
+    data: &Data,
+    guild_id: GuildId,
+    settings: &GuildSettings,
+) -> Result<(), MusicError> {
+    let Some(limit) = settings.queue_limit else {
+        return Ok(());
+    };
+    if QueueService::pending_len(&data.guild_queues, guild_id).await >= limit {
+        return Err(MusicError::QueueFull(limit));
+    }
+    Ok(())
+}
 
-        enqueue_track(
-            track,
-            &search_query,
-            &http_client,
-            &handler_lock,
-            &serenity_http,
-            channel_id,
-            &requester,
-            &guild_queues,
-            guild_id,
-            &now_playing_messages,
-            &repeat_states,
-        )
-        .await;
+/// Adds a note to a collection's embed when it won't fit in the queue.
+/// `rest` is how many fetched tracks are left for the background task.
+async fn note_queue_overflow(
+    embed: CreateEmbed,
+    data: &Data,
+    guild_id: GuildId,
+    settings: &GuildSettings,
+    info: &PlaylistInfo,
+    rest: usize,
+) -> CreateEmbed {
+    overflow_field(
+        embed,
+        queue_overflow(data, guild_id, settings, info, rest).await,
+    )
+}
+
+/// The queue limit and about how many of a collection's tracks won't fit
+/// under it, or `None` when they all should.
+async fn queue_overflow(
+    data: &Data,
+    guild_id: GuildId,
+    settings: &GuildSettings,
+    info: &PlaylistInfo,
+    rest: usize,
+) -> Option<(usize, usize)> {
+    let limit = settings.queue_limit?;
+    let pending = QueueService::pending_len(&data.guild_queues, guild_id).await;
+    let incoming = info.expected_tracks(rest + 1) - 1;
+    let overflow = (pending + incoming).saturating_sub(limit);
+    (overflow > 0).then_some((limit, overflow))
+}
+
+fn overflow_field(embed: CreateEmbed, overflow: Option<(usize, usize)>) -> CreateEmbed {
+    let Some((limit, overflow)) = overflow else {
+        return embed;
+    };
+    embed.field(
+        "Queue limit",
+        format!("The queue holds {limit} tracks, so about {overflow} of these won't be added."),
+        false,
+    )
+}
+
+async fn queue_placement(
+    handler_lock: &Arc<Mutex<Call>>,
+    data: &Data,
+    guild_id: GuildId,
+    handle_id: Uuid,
+    was_idle: bool,
+) -> QueuePlacement {
+    if was_idle {
+        return QueuePlacement::StartingNow;
     }
 
-    tracing::info!(
-        "Background enqueue complete: {} tracks for guild {}",
-        tracks.len(),
-        guild_id
-    );
+    let pending = QueueService::list(&data.guild_queues, guild_id).await;
+    let index = pending
+        .iter()
+        .position(|t| t.handle_id == Some(handle_id))
+        .unwrap_or(pending.len().saturating_sub(1));
+
+    QueuePlacement::Queued {
+        position: index + 1,
+        current: QueueService::current(&data.guild_queues, guild_id)
+            .await
+            .map(|t| t.title),
+        wait: estimate_wait(handler_lock, data, guild_id, index).await,
+    }
 }
 
-async fn ensure_voice_connection(
-    manager: &Arc<songbird::Songbird>,
+/// Estimates how long until the pending track at `index` starts: what's left
+/// of the current track plus every pending track ahead of it. `None` when any
+/// of those lengths is unknown.
+pub(crate) async fn estimate_wait(
+    handler_lock: &Arc<Mutex<Call>>,
+    data: &Data,
     guild_id: GuildId,
-    voice_channel_id: ChannelId,
-    join_locks: &JoinLocks,
-    inactivity_handles: &InactivityHandles,
-) -> Result<Arc<Mutex<Call>>, MusicError> {
-    // Fast path: already connected AND has active session
-    if inactivity_handles.read().await.contains_key(&guild_id) {
-        if let Some(handler) = manager.get(guild_id) {
-            return Ok(handler);
+    index: usize,
+) -> Option<Duration> {
+    let current = QueueService::current(&data.guild_queues, guild_id).await?;
+    let handle = handler_lock.lock().await.queue().current()?;
+    let position = handle.get_info().await.ok()?.position;
+
+    let mut wait = current.duration?.saturating_sub(current.elapsed(position));
+    for track in QueueService::list(&data.guild_queues, guild_id)
+        .await
+        .iter()
+        .take(index)
+    {
+        wait += track.duration?;
+    }
+    Some(wait)
+}
+
+/// Drops the given tracks from songbird's queue and stops them. The track at
+/// the front (the one playing) is never touched.
+pub(crate) async fn remove_from_playback(handler_lock: &Arc<Mutex<Call>>, handle_ids: &[Uuid]) {
+    let handler = handler_lock.lock().await;
+    handler.queue().modify_queue(|queue| {
+        let mut index = 1;
+        while index < queue.len() {
+            if handle_ids.contains(&queue[index].uuid()) {
+                if let Some(queued) = queue.remove(index) {
+                    let _ = queued.stop();
+                }
+            } else {
+                index += 1;
+            }
+        }
+    });
+}
+
+/// Sets every queued track's volume from the guild's per-source offsets and
+/// loudness setting, for when they change mid-session.
+pub(crate) async fn apply_volume_offsets(
+    handler_lock: &Arc<Mutex<Call>>,
+    data: &Data,
+    guild_id: GuildId,
+    settings: &GuildSettings,
+) {
+    let current = QueueService::current(&data.guild_queues, guild_id).await;
+    let pending = QueueService::list(&data.guild_queues, guild_id).await;
+    let handler = handler_lock.lock().await;
+    for handle in handler.queue().current_queue() {
+        let track = current
+            .iter()
+            .chain(&pending)
+            .find(|t| t.handle_id == Some(handle.uuid()));
+        if let Some(track) = track {
+            let _ = handle.set_volume(settings.track_volume(track));
         }
     }
+}
 
-    // Remove stale handler if present (e.g. after /stop)
-    let _ = manager.leave(guild_id).await;
+/// Reorders songbird's pending tracks to match the domain queue, so that
+/// `/list` keeps showing the real playback order after the domain queue is
+/// rearranged. The track at the front (the one playing) is never moved.
+pub(crate) async fn sync_playback_order(
+    handler_lock: &Arc<Mutex<Call>>,
+    data: &Data,
+    guild_id: GuildId,
+) {
+    let order = QueueService::pending_handle_ids(&data.guild_queues, guild_id).await;
+    let handler = handler_lock.lock().await;
+    handler.queue().modify_queue(|queue| {
+        let Some(playing) = queue.pop_front() else {
+            return;
+        };
+        queue.make_contiguous().sort_by_key(|queued| {
+            let id = queued.uuid();
+            order.iter().position(|o| *o == id).unwrap_or(usize::MAX)
+        });
+        queue.push_front(playing);
+    });
+}
 
-    // Slow path: acquire per-guild lock to prevent concurrent joins
-    let lock = {
-        let mut locks = join_locks.write().await;
-        locks
-            .entry(guild_id)
-            .or_insert_with(|| Arc::new(Mutex::new(())))
-            .clone()
-    };
-    let _guard = lock.lock().await;
+/// The part of a collection still to be listed after `/play` replied about
+/// its first page.
+pub(crate) struct RemainingPages {
+    cursor: PageCursor,
+    /// Entries already read, which count toward the item cap.
+    read: usize,
+    /// Shuffle the rest once it's all listed.
+    shuffle: bool,
+    /// Album name to fill in on Spotify album tracks.
+    album: Option<String>,
+    /// Collection name for the completion notice.
+    name: String,
+    /// Tracks the server's collection limit still allows, if it has one.
+    budget: Option<usize>,
+    /// The `/play` reply to keep up to date while listing.
+    reply: Option<ListingReply>,
+}
 
-    // Double-check after acquiring lock
-    if inactivity_handles.read().await.contains_key(&guild_id) {
-        if let Some(handler) = manager.get(guild_id) {
-            return Ok(handler);
+impl RemainingPages {
+    /// Takes the cursor off a first page that didn't list everything.
+    fn after(
+        fetched: &mut PlaylistTracks,
+        name: &str,
+        shuffle: bool,
+        album: Option<String>,
+    ) -> Option<Self> {
+        let cursor = fetched.next.take()?;
+        Some(Self {
+            cursor,
+            read: fetched.read(),
+            shuffle,
+            album,
+            name: name.to_string(),
+            budget: None,
+            reply: None,
+        })
+    }
+
+    /// Picks listing back up where a restart cut it short.
+    pub(crate) fn resumed(pages: PendingPages, name: String) -> Self {
+        Self {
+            cursor: pages.cursor,
+            read: pages.read,
+            shuffle: pages.shuffle,
+            album: pages.album,
+            name,
+            budget: pages.budget,
+            reply: None,
         }
     }
 
-    manager
-        .join(guild_id, voice_channel_id)
-        .await
-        .map_err(|e| MusicError::JoinError(e.to_string()))
+    /// The pages still to be listed, for the record of what's left.
+    fn pending(&self) -> PendingPages {
+        PendingPages {
+            cursor: self.cursor.clone(),
+            read: self.read,
+            shuffle: self.shuffle,
+            album: self.album.clone(),
+            budget: self.budget,
+        }
+    }
+
+    fn report_to(self, reply: Option<ListingReply>) -> Self {
+        Self { reply, ..self }
+    }
+
+    /// Stops listing once `budget` more tracks are read, or drops the rest
+    /// entirely when there's no room left.
+    fn limit(mut self, budget: usize) -> Option<Self> {
+        (budget > 0).then(|| {
+            self.budget = Some(budget);
+            self
+        })
+    }
+}
+
+/// Tracks the listing task hands the enqueuer.
+struct ListedPage {
+    tracks: Vec<Track>,
+    /// Where listing picks up after these tracks, `None` once nothing is
+    /// left to list.
+    next: Option<PendingPages>,
+}
+
+/// Shortest gap between two edits of a listing's `/play` reply.
+const LISTING_EDIT_INTERVAL: Duration = Duration::from_secs(2);
+
+impl RemainingPages {
+    /// Lists the rest of the collection, sending each page's tracks to the
+    /// enqueuer as it arrives, or a shuffled collection's all at once at the
+    /// end, and keeps the `/play` reply showing how far it got. Stops early
+    /// when cancelled or the enqueuer hangs up.
+    async fn list(
+        mut self,
+        data: Data,
+        http: Arc<Http>,
+        cancel: Arc<AtomicBool>,
+        pages: mpsc::UnboundedSender<ListedPage>,
+    ) -> RemainingOutcome {
+        let music = data.music_service.clone();
+        let mut outcome = RemainingOutcome::default();
+        let mut cursor = Some(self.cursor.clone());
+        let mut read = self.read;
+        let mut held = Vec::new();
+        let mut last_edit = Instant::now();
+
+        while let Some(at) = cursor.take() {
+            if cancel.load(Ordering::Relaxed) {
+                return outcome;
+            }
+            let Ok(mut page) = music.fetch_page(&at, read).await else {
+                outcome.incomplete = true;
+                break;
+            };
+
+            read += page.read();
+            outcome.skipped += page.skipped;
+            outcome.truncated |= page.truncated;
+            outcome.incomplete |= !page.complete;
+            cursor = page.next.take();
+
+            if let Some(album) = &self.album {
+                for track in &mut page.tracks {
+                    track.album = Some(album.clone());
+                }
+            }
+            if let Some(reply) = &mut self.reply {
+                reply.info.skipped += page.skipped;
+            }
+            if self.shuffle {
+                if let Some(reply) = &mut self.reply {
+                    reply.rest.extend(page.tracks.iter().cloned());
+                }
+                held.append(&mut page.tracks);
+            } else {
+                if let Some(budget) = &mut self.budget {
+                    let listed = page.tracks.len();
+                    page.tracks.truncate(*budget);
+                    *budget -= page.tracks.len();
+                    outcome.capped |=
+                        page.tracks.len() < listed || (*budget == 0 && cursor.is_some());
+                }
+                if let Some(reply) = &mut self.reply {
+                    reply.rest.extend(page.tracks.iter().cloned());
+                }
+                let next = cursor
+                    .clone()
+                    .filter(|_| self.budget != Some(0))
+                    .map(|cursor| PendingPages {
+                        cursor,
+                        read,
+                        shuffle: false,
+                        album: self.album.clone(),
+                        budget: self.budget,
+                    });
+                let listed = ListedPage {
+                    tracks: page.tracks,
+                    next,
+                };
+                if pages.send(listed).is_err() {
+                    return outcome;
+                }
+                if self.budget == Some(0) {
+                    break;
+                }
+            }
+
+            if let Some(reply) = &self.reply {
+                if cursor.is_some() && last_edit.elapsed() >= LISTING_EDIT_INTERVAL {
+                    reply.show(&http).await;
+                    last_edit = Instant::now();
+                }
+            }
+        }
+
+        if self.shuffle {
+            held.shuffle(&mut rand::rng());
+            if let Some(budget) = self.budget {
+                outcome.capped |= held.len() > budget;
+                held.truncate(budget);
+            }
+            if let Some(reply) = &mut self.reply {
+                reply.rest = held.clone();
+            }
+            let listed = ListedPage {
+                tracks: held,
+                next: None,
+            };
+            if pages.send(listed).is_err() {
+                return outcome;
+            }
+        }
+
+        if let Some(mut reply) = self.reply {
+            reply.finish(&outcome, music.max_playlist_items);
+            reply.show(&http).await;
+        }
+        outcome
+    }
+}
+
+/// The `/play` reply about a collection whose listing carries on in the
+/// background, edited as pages arrive and once it's done.
+pub(crate) struct ListingReply {
+    interaction: serenity::CommandInteraction,
+    info: PlaylistInfo,
+    url: String,
+    source: TrackSource,
+    settings: GuildSettings,
+    first: Track,
+    started: bool,
+    /// Tracks listed after `first`.
+    rest: Vec<Track>,
+    /// The queue limit note from the first reply, kept as it was since the
+    /// queue fills up while listing.
+    overflow: Option<(usize, usize)>,
+}
+
+impl ListingReply {
+    /// Records the outcome on the reply's collection info, so the embed
+    /// shows the final count and anything that went wrong.
+    fn finish(&mut self, outcome: &RemainingOutcome, max_items: usize) {
+        let info = &mut self.info;
+        info.still_listing = false;
+        info.incomplete |= outcome.incomplete;
+        // The first reply could only guess these from the provider's count
+        info.truncated_at = outcome.truncated.then_some(max_items);
+        info.capped_at = self.settings.max_collection_size.filter(|_| outcome.capped);
+    }
+
+    async fn show(&self, http: &Http) {
+        let embed = collection_embed(
+            &self.info,
+            &self.url,
+            &self.source,
+            &self.settings,
+            &self.first,
+            self.started,
+            &self.rest,
+        );
+        let embed = overflow_field(embed, self.overflow);
+        let edit = serenity::EditInteractionResponse::new().embed(embed);
+        if let Err(e) = self.interaction.edit_response(http, edit).await {
+            tracing::debug!(
+                "Couldn't update the /play reply for {}: {e}",
+                self.info.name
+            );
+        }
+    }
+}
+
+/// Applies the server's per-`/play` collection limit to a collection's first
+/// tracks and the pages still to be listed, noting it on `info`. Strict mode
+/// rejects an oversized collection outright.
+fn apply_collection_limit(
+    info: &mut PlaylistInfo,
+    tracks: &mut Vec<Track>,
+    remaining: Option<RemainingPages>,
+    settings: &GuildSettings,
+    force: bool,
+) -> Result<Option<RemainingPages>, MusicError> {
+    let Some(limit) = settings.max_collection_size.filter(|_| !force) else {
+        return Ok(remaining);
+    };
+    let expected = info.expected_tracks(tracks.len());
+    if expected > limit {
+        if settings.strict_collection_size {
+            return Err(MusicError::CollectionTooLarge(expected, limit));
+        }
+        tracks.truncate(limit);
+        info.capped_at = Some(limit);
+    }
+    Ok(remaining.and_then(|remaining| remaining.limit(limit - tracks.len())))
+}
+
+/// How listing the rest of a collection went.
+#[derive(Default)]
+struct RemainingOutcome {
+    skipped: usize,
+    truncated: bool,
+    incomplete: bool,
+    /// Stopped at the server's per-`/play` collection limit.
+    capped: bool,
+}
+
+/// A collection being queued in the background after `/play` replied.
+struct BackgroundEnqueue {
+    handler_lock: Arc<Mutex<Call>>,
+    serenity_ctx: serenity::Context,
+    requester: Requester,
+    guild_id: GuildId,
+    ticket: Ticket,
+    data: Data,
+    added: usize,
+    blocked: usize,
+    /// Tracks that didn't fit in the queue.
+    left_out: usize,
+    /// Name of a collection listed page by page, or of the lone track
+    /// queued behind collections.
+    name: Option<String>,
+    /// Where listing picks up after the tracks being queued, while pages
+    /// are left to list.
+    pages: Option<PendingPages>,
+}
+
+/// How many tracks a background enqueue adds between records of what's left.
+const RECORD_EVERY: usize = 25;
+
+impl BackgroundEnqueue {
+    /// Records `rest` and the unlisted pages as what's left to add, so a
+    /// restart can resume it. Called under the ticket's lock, after its
+    /// cancel check, so it can't land after `cleanup_guild` has forgotten
+    /// the guild's enqueues.
+    async fn record(&self, rest: &[Track]) {
+        let Some(store) = &self.data.enqueue_store else {
+            return;
+        };
+        let Some(channel_id) = self
+            .data
+            .text_channels
+            .read()
+            .await
+            .get(&self.guild_id)
+            .copied()
+        else {
+            return;
+        };
+        let pending = PendingCollection {
+            guild_id: self.guild_id,
+            channel_id,
+            requester: self.requester.user(),
+            name: self.name.clone(),
+            tracks: rest.to_vec(),
+            pages: self.pages.clone(),
+        };
+        store.record(self.ticket.number(), pending).await;
+    }
+
+    /// Queues `tracks` in order. Returns false when the enqueue should stop
+    /// because it was cancelled or the queue is full.
+    async fn queue(&mut self, tracks: &[Track], settings: &GuildSettings) -> bool {
+        for (i, track) in tracks.iter().enumerate() {
+            // The lock is only held for this track, so other queue edits can
+            // run between tracks. `cleanup_guild` waits for it before
+            // clearing, so a stop can't slip in halfway through an iteration.
+            let _guard = self.ticket.lock().await;
+            if self.ticket.is_cancelled() {
+                return false;
+            }
+            if i % RECORD_EVERY == 0 {
+                self.record(&tracks[i..]).await;
+            }
+
+            if settings.blocked_by(track).is_some() {
+                self.blocked += 1;
+                continue;
+            }
+            if ensure_queue_room(&self.data, self.guild_id, settings)
+                .await
+                .is_err()
+            {
+                self.left_out += tracks.len() - i;
+                return false;
+            }
+
+            let search_query = match track.source {
+                TrackSource::Spotify => {
+                    MusicService::spotify_to_youtube_query(track, settings.prefer_topic)
+                }
+                TrackSource::YouTube => String::new(),
+            };
+
+            enqueue_track(
+                track,
+                &search_query,
+                &self.handler_lock,
+                &self.serenity_ctx,
+                self.requester,
+                self.guild_id,
+                &self.data,
+            )
+            .await;
+            self.added += 1;
+        }
+        true
+    }
+
+    /// Queues the pages the listing task sends until it's done, then
+    /// returns how the listing went.
+    async fn queue_listed(
+        &mut self,
+        mut pages: mpsc::UnboundedReceiver<ListedPage>,
+        listing: JoinHandle<RemainingOutcome>,
+        settings: &GuildSettings,
+    ) -> RemainingOutcome {
+        while let Some(page) = pages.recv().await {
+            // Pages still waiting in the channel are listed again on resume
+            self.pages = page.next;
+            if !self.queue(&page.tracks, settings).await {
+                break;
+            }
+        }
+        // Stops the listing if it's still going
+        drop(pages);
+        listing.await.unwrap_or_default()
+    }
+
+    async fn run(mut self, tracks: Vec<Track>, remaining: Option<RemainingPages>) {
+        // Listing starts now, so the rest of a big playlist is ready by the
+        // time this collection's turn comes
+        let paged = remaining.as_ref().map(|remaining| remaining.name.clone());
+        self.name = match (&paged, tracks.as_slice()) {
+            (Some(name), _) => Some(name.clone()),
+            // A lone track queued behind collections is recorded by its own
+            // name rather than as a nameless collection
+            (None, [track]) => Some(format!("{} - {}", track.title, track.artist)),
+            (None, _) => None,
+        };
+        self.pages = remaining.as_ref().map(RemainingPages::pending);
+        let listing = remaining.map(|remaining| {
+            let http = self.serenity_ctx.http.clone();
+            let cancel = self.ticket.cancel_flag().clone();
+            let (send, pages) = mpsc::unbounded_channel();
+            let task = tokio::spawn(remaining.list(self.data.clone(), http, cancel, send));
+            (pages, task)
+        });
+
+        // Recorded before waiting, so collections still in line are reported
+        // too
+        {
+            let _guard = self.ticket.lock().await;
+            if !self.ticket.is_cancelled() {
+                self.record(&tracks).await;
+            }
+        }
+
+        // Collections are enqueued one after another, in the order they were
+        // requested
+        self.ticket.wait_turn().await;
+
+        let settings = SettingsService::get(&self.data.guild_settings, self.guild_id).await;
+        let mut outcome = None;
+        if self.queue(&tracks, &settings).await {
+            if let Some((pages, task)) = listing {
+                outcome = Some(self.queue_listed(pages, task, &settings).await);
+            }
+        }
+
+        let guild_id = self.guild_id;
+        if self.ticket.is_cancelled() {
+            // `cleanup_guild` forgot the record along with the guild's others
+            tracing::info!("Background enqueue cancelled for guild {guild_id}");
+            return;
+        }
+        if let Some(store) = &self.data.enqueue_store {
+            store.forget(guild_id, self.ticket.number()).await;
+        }
+
+        // Finished on our own, so drop our flag rather than leave it for cleanup
+        if let Some(flags) = self.data.enqueue_cancels.write().await.get_mut(&guild_id) {
+            release_cancel_flag(flags, self.ticket.cancel_flag());
+        }
+
+        tracing::info!(
+            "Background enqueue complete: {} tracks for guild {} ({} blocked, {} over the limit)",
+            self.added,
+            guild_id,
+            self.blocked,
+            self.left_out
+        );
+
+        let Some(channel_id) = self.data.text_channels.read().await.get(&guild_id).copied() else {
+            return;
+        };
+        let http = &self.serenity_ctx.http;
+        if let (Some(name), Some(outcome)) = (&paged, outcome) {
+            let mut notice = format!(
+                "Finished adding **{name}**: {} more {} queued.",
+                self.added,
+                if self.added == 1 { "track" } else { "tracks" }
+            );
+            if outcome.skipped > 0 {
+                let word = if outcome.skipped == 1 {
+                    "item was"
+                } else {
+                    "items were"
+                };
+                notice.push_str(&format!(" {} unplayable {word} skipped.", outcome.skipped));
+            }
+            if outcome.truncated {
+                notice.push_str(&format!(
+                    " Stopped at the {}-track limit.",
+                    group_digits(self.data.music_service.max_playlist_items)
+                ));
+            }
+            if let Some(limit) = settings.max_collection_size.filter(|_| outcome.capped) {
+                notice.push_str(&format!(
+                    " Stopped at this server's limit of {limit} tracks per /play."
+                ));
+            }
+            if outcome.incomplete {
+                notice.push_str(
+                    " Listing the rest failed partway through, so some tracks are missing.",
+                );
+            }
+            let _ = channel_id
+                .send_message(http, CreateMessage::new().content(notice))
+                .await;
+        }
+        if self.blocked > 0 {
+            let word = if self.blocked == 1 {
+                "track was"
+            } else {
+                "tracks were"
+            };
+            let msg = CreateMessage::new().content(format!(
+                "{} {word} skipped because of the server blocklist.",
+                self.blocked
+            ));
+            let _ = channel_id.send_message(http, msg).await;
+        }
+        if let Some(limit) = settings.queue_limit.filter(|_| self.left_out > 0) {
+            let content = if paged.is_some() {
+                format!(
+                    "The queue is full at {limit} tracks, so the rest of the collection wasn't added."
+                )
+            } else {
+                let word = if self.left_out == 1 {
+                    "track wasn't"
+                } else {
+                    "tracks weren't"
+                };
+                format!(
+                    "The queue is full at {limit} tracks, so the last {} {word} added.",
+                    self.left_out
+                )
+            };
+            let _ = channel_id
+                .send_message(http, CreateMessage::new().content(content))
+                .await;
+        }
+    }
+}
+
+/// Returns the voice channel the command author is currently in.
+pub(crate) fn author_voice_channel(ctx: Context<'_>) -> Result<ChannelId, MusicError> {
+    let guild = ctx.guild().ok_or(MusicError::NotInGuild)?;
+    guild
+        .voice_states
+        .get(&ctx.author().id)
+        .and_then(|vs| vs.channel_id)
+        .ok_or(MusicError::NotInVoiceChannel)
+}
+
+/// Returns the voice channel the bot is connected to in this guild, if any.
+pub(crate) async fn bot_voice_channel(
+    manager: &Arc<songbird::Songbird>,
+    guild_id: GuildId,
+) -> Option<ChannelId> {
+    let handler_lock = manager.get(guild_id)?;
+    let handler = handler_lock.lock().await;
+    handler
+        .current_channel()
+        .map(|id| ChannelId::new(id.0.get()))
+}
+
+/// How long the "move to your channel?" prompt waits for an answer.
+const FOLLOW_PROMPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Settles a `/play` from a different voice channel than the bot's according
+/// to the guild's `follow_requester` setting. Returns `false` when the
+/// requester turned down the move and the command should stop there.
+pub(crate) async fn follow_requester(
+    ctx: Context<'_>,
+    manager: &Arc<songbird::Songbird>,
+    guild_id: GuildId,
+    voice_channel_id: ChannelId,
+    text_channel_id: ChannelId,
+) -> Result<bool, Error> {
+    let data = ctx.data();
+    if !data.inactivity_handles.read().await.contains_key(&guild_id) {
+        return Ok(true);
+    }
+    let Some(handler_lock) = manager.get(guild_id) else {
+        return Ok(true);
+    };
+
+    let (busy, bot_channel) = {
+        let handler = handler_lock.lock().await;
+        let channel = handler
+            .current_channel()
+            .map(|id| ChannelId::new(id.0.get()));
+        (!handler.queue().is_empty(), channel)
+    };
+    let Some(bot_channel) = bot_channel.filter(|channel| *channel != voice_channel_id) else {
+        return Ok(true);
+    };
+
+    let follow = SettingsService::get(&data.guild_settings, guild_id)
+        .await
+        .follow_requester;
+    let move_now = match follow {
+        FollowRequester::Never => false,
+        FollowRequester::WhenIdle => !busy,
+        // Moving while playing pulls the music away from other listeners
+        FollowRequester::Ask if busy && !is_dj(ctx).await => {
+            return Err(MusicError::InUseElsewhere(bot_channel).into());
+        }
+        FollowRequester::Ask => {
+            if !confirm_move(ctx, bot_channel, voice_channel_id).await? {
+                return Ok(false);
+            }
+            true
+        }
+    };
+
+    if !move_now {
+        return Err(if is_dj(ctx).await {
+            MusicError::InUseElsewhereDj(bot_channel)
+        } else {
+            MusicError::InUseElsewhere(bot_channel)
+        }
+        .into());
+    }
+
+    summon(manager, data, guild_id, voice_channel_id, text_channel_id).await?;
+    Ok(true)
+}
+
+/// Asks the requester whether the bot should leave `bot_channel` for theirs.
+async fn confirm_move(
+    ctx: Context<'_>,
+    bot_channel: ChannelId,
+    voice_channel_id: ChannelId,
+) -> Result<bool, Error> {
+    let prefix = format!("follow_{}", ctx.id());
+    let buttons = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{prefix}_yes"))
+            .label("Move")
+            .style(serenity::ButtonStyle::Primary),
+        CreateButton::new(format!("{prefix}_no"))
+            .label("Stay")
+            .style(serenity::ButtonStyle::Secondary),
+    ])];
+    let prompt = ctx
+        .send(
+            poise::CreateReply::default()
+                .content(format!(
+                    "I'm in <#{bot_channel}>. Move to your channel <#{voice_channel_id}>?"
+                ))
+                .components(buttons),
+        )
+        .await?;
+
+    let filter_prefix = prefix.clone();
+    let Some(press) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter(move |press| press.data.custom_id.starts_with(&filter_prefix))
+        .timeout(FOLLOW_PROMPT_TIMEOUT)
+        .await
+    else {
+        prompt
+            .edit(
+                ctx,
+                poise::CreateReply::default()
+                    .content(format!("No answer, staying in <#{bot_channel}>."))
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(false);
+    };
+
+    let accepted = press.data.custom_id.ends_with("_yes");
+    let content = if accepted {
+        format!("Moving to <#{voice_channel_id}>.")
+    } else {
+        format!("Staying in <#{bot_channel}>.")
+    };
+    let response = CreateInteractionResponse::UpdateMessage(
+        CreateInteractionResponseMessage::new()
+            .content(content)
+            .components(Vec::new()),
+    );
+    press.create_response(ctx, response).await?;
+    Ok(accepted)
+}
+
+/// How long a voice join may take before it's given up. Discord's voice
+/// servers occasionally never answer, which would otherwise hang `/play`.
+const JOIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where a join was headed, for telling regional voice trouble apart: the
+/// channel's voice region, the voice endpoint Discord handed out if it got
+/// that far, and the gateway shard.
+async fn join_diagnostics(
+    serenity_ctx: &serenity::Context,
+    manager: &songbird::Songbird,
+    guild_id: GuildId,
+    voice_channel_id: ChannelId,
+) -> String {
+    let region = serenity_ctx
+        .cache
+        .guild(guild_id)
+        .and_then(|guild| guild.channels.get(&voice_channel_id)?.rtc_region.clone())
+        .unwrap_or_else(|| "automatic".to_string());
+    let endpoint = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock
+            .lock()
+            .await
+            .current_connection()
+            .map(|info| info.endpoint.clone()),
+        None => None,
+    };
+    format!(
+        "region {region}, voice endpoint {}, shard {}",
+        endpoint.as_deref().unwrap_or("not received"),
+        serenity_ctx.shard_id.0
+    )
+}
+
+/// Connects to `voice_channel_id` unless a session is already running, and
+/// sets up a fresh session while still holding the per-guild join lock, so
+/// concurrent `/play`s can't both tear down and re-register the session.
+pub(crate) async fn ensure_voice_connection(
+    serenity_ctx: &serenity::Context,
+    manager: &Arc<songbird::Songbird>,
+    data: &Data,
+    guild_id: GuildId,
+    voice_channel_id: ChannelId,
+    text_channel_id: ChannelId,
+) -> Result<Arc<Mutex<Call>>, MusicError> {
+    // A running inactivity monitor means the session is already set up
+    let live = || async { data.inactivity_handles.read().await.contains_key(&guild_id) };
+    let join = async {
+        // Remove stale handler if present (e.g. after /stop)
+        let _ = manager.leave(guild_id).await;
+
+        let joined =
+            tokio::time::timeout(JOIN_TIMEOUT, data.voice.join(guild_id, voice_channel_id)).await;
+        match joined {
+            Ok(result) => result,
+            Err(_) => {
+                let diagnostics =
+                    join_diagnostics(serenity_ctx, manager, guild_id, voice_channel_id).await;
+                tracing::warn!(
+                    "Timed out joining {voice_channel_id} in guild {guild_id} after {}s ({diagnostics})",
+                    JOIN_TIMEOUT.as_secs()
+                );
+                // Drop the half-set-up Call so the next /play starts clean
+                // instead of taking the fast path into it
+                if let Err(e) = manager.remove(guild_id).await {
+                    tracing::debug!("Failed to remove stalled voice call in guild {guild_id}: {e}");
+                }
+                Err(MusicError::JoinError(
+                    "timed out connecting to the voice server".to_string(),
+                ))
+            }
+        }
+    };
+    let setup = |handler_lock: Arc<Mutex<Call>>| async move {
+        setup_fresh_join(
+            data,
+            &handler_lock,
+            manager,
+            guild_id,
+            text_channel_id,
+            &serenity_ctx.http,
+            &serenity_ctx.cache,
+        )
+        .await;
+        resume_held_enqueues(serenity_ctx, data, &handler_lock, guild_id).await;
+    };
+
+    PlaybackService::connect(
+        data.voice.as_ref(),
+        &data.join_locks,
+        guild_id,
+        live,
+        join,
+        setup,
+    )
+    .await
+}
+
+/// Moves an active session to another voice channel. The songbird `Call` (and
+/// with it the track queue) is kept, only the voice connection is switched.
+pub(crate) async fn move_voice_connection(
+    manager: &Arc<songbird::Songbird>,
+    guild_id: GuildId,
+    voice_channel_id: ChannelId,
+) -> Result<(), MusicError> {
+    manager
+        .join(guild_id, voice_channel_id)
+        .await
+        .map(|_| ())
+        .map_err(|e| MusicError::JoinError(e.to_string()))
+}
+
+/// Brings an active session to `voice_channel_id` if it isn't there already.
+/// Now Playing updates follow whoever summoned the bot last, and the
+/// inactivity monitor picks the new channel up from the `Call` on its next
+/// check.
+pub(crate) async fn summon(
+    manager: &Arc<songbird::Songbird>,
+    data: &Data,
+    guild_id: GuildId,
+    voice_channel_id: ChannelId,
+    text_channel_id: ChannelId,
+) -> Result<(), MusicError> {
+    if bot_voice_channel(manager, guild_id).await != Some(voice_channel_id) {
+        move_voice_connection(manager, guild_id, voice_channel_id).await?;
+    }
+
+    data.text_channels
+        .write()
+        .await
+        .insert(guild_id, text_channel_id);
+    Ok(())
+}
+
+/// Leaves headroom under Discord's 3 second autocomplete deadline.
+const AUTOCOMPLETE_TIMEOUT: Duration = Duration::from_millis(2500);
+
+static AUTOCOMPLETE_CACHE_HITS: AtomicU64 = AtomicU64::new(0);
+static AUTOCOMPLETE_TIMEOUTS: AtomicU64 = AtomicU64::new(0);
+
+/// `(cached, timed out)` autocomplete searches across all guilds, for
+/// `/debug`.
+pub fn autocomplete_counts() -> (u64, u64) {
+    (
+        AUTOCOMPLETE_CACHE_HITS.load(Ordering::Relaxed),
+        AUTOCOMPLETE_TIMEOUTS.load(Ordering::Relaxed),
+    )
+}
+
+fn to_autocomplete(choices: Vec<(String, String)>) -> Vec<AutocompleteChoice> {
+    choices
+        .into_iter()
+        .map(|(name, value)| AutocompleteChoice::new(name, value))
+        .collect()
+}
+
+async fn autocomplete_query(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    let partial = partial.trim();
+
+    if partial.len() < 3 || partial.starts_with("http://") || partial.starts_with("https://") {
+        return Vec::new();
+    }
+
+    let settings = match ctx.guild_id() {
+        Some(guild_id) => SettingsService::get(&ctx.data().guild_settings, guild_id).await,
+        None => GuildSettings::default(),
+    };
+    let user_preferences =
+        PreferencesService::get(&ctx.data().user_preferences, ctx.author().id).await;
+    let preferences = PlayPreferences::resolve(None, &user_preferences, &settings);
+    let merged = settings.merged_autocomplete && !preferences.search_picked;
+    let prefer_topic = settings.prefer_topic;
+    // Everything besides the query that shapes the results
+    let scope = if merged {
+        format!("merged/{prefer_topic}")
+    } else {
+        format!("{:?}/{prefer_topic}", preferences.search)
+    };
+
+    let music_service = ctx.data().music_service.clone();
+    if let Some(choices) = music_service.autocomplete_cache.get(&scope, partial) {
+        AUTOCOMPLETE_CACHE_HITS.fetch_add(1, Ordering::Relaxed);
+        return to_autocomplete(choices);
+    }
+
+    let query = partial.to_string();
+    let search = {
+        let (music_service, scope) = (music_service.clone(), scope.clone());
+        async move {
+            let limit = music_service.search_limit;
+            let results = if merged {
+                music_service
+                    .search_merged(&query, limit, prefer_topic)
+                    .await
+            } else {
+                let preference = preferences.search;
+                music_service
+                    .search_with_preference(&query, limit, preference, prefer_topic)
+                    .await
+            };
+            let choices = autocomplete_choices(results);
+            music_service
+                .autocomplete_cache
+                .insert(&scope, &query, choices.clone());
+            choices
+        }
+    };
+
+    // Discord drops autocomplete responses after 3 seconds, so a slow
+    // search is left to finish in the background and fill the cache for the
+    // next keystroke, while this one offers the raw query and whatever an
+    // earlier keystroke found.
+    match tokio::time::timeout(AUTOCOMPLETE_TIMEOUT, tokio::spawn(search)).await {
+        Ok(Ok(choices)) => to_autocomplete(choices),
+        Ok(Err(e)) => {
+            tracing::warn!("Autocomplete search failed for {partial:?}: {e}");
+            Vec::new()
+        }
+        Err(_) => {
+            AUTOCOMPLETE_TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+            tracing::debug!("Autocomplete search timed out for {partial:?}");
+            let value: String = partial.chars().take(100).collect();
+            let mut choices = vec![(value.clone(), value)];
+            if let Some(earlier) = music_service
+                .autocomplete_cache
+                .longest_prefix(&scope, partial)
+            {
+                choices.extend(earlier.into_iter().take(24));
+            }
+            to_autocomplete(choices)
+        }
+    }
+}
+
+/// Per-invocation tweaks from the `/play` subcommands.
+#[derive(Debug, Default)]
+struct PlayOptions {
+    /// 1-based spot in the pending queue for a single track.
+    position: Option<usize>,
+    /// Shuffle a playlist or album before queueing it.
+    shuffle: bool,
+    /// Overrides the guild's search provider.
+    source: Option<SearchPreference>,
+    /// Let the requester pick from the search results.
+    choose: bool,
+    /// Queue a YouTube video's chapters as separate entries.
+    chapters: bool,
+    /// Skip the server's collection size limit; DJs only.
+    force: bool,
+    /// Hold the first track paused until someone else joins.
+    standby: bool,
+}
+
+/// Play a song from YouTube or Spotify
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("play_url", "play_search", "play_any"),
+    subcommand_required
+)]
+pub async fn play(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Play a YouTube or Spotify link
+#[poise::command(slash_command, guild_only, rename = "url")]
+async fn play_url(
+    ctx: Context<'_>,
+    #[description = "YouTube video/playlist or Spotify track/album/playlist link, or several separated by spaces"]
+    link: String,
+    #[description = "Queue position for a single track (1 = up next)"]
+    #[min = 1]
+    position: Option<usize>,
+    #[description = "Shuffle a playlist or album before queueing it"] shuffle: Option<bool>,
+    #[description = "Queue each chapter of a YouTube video as its own entry"] chapters: Option<
+        bool,
+    >,
+    #[description = "Ignore the server's limit on tracks per /play (DJs only)"] force: Option<bool>,
+    #[description = "Queue now, but hold playback until someone else joins the channel"]
+    standby: Option<bool>,
+) -> Result<(), Error> {
+    let link = link.trim();
+    if !MusicService::is_supported_link(link) {
+        return Err(MusicError::UnsupportedLink.into());
+    }
+    let force = force.unwrap_or(false);
+    if force && !is_dj(ctx).await {
+        return Err(MusicError::NotDj.into());
+    }
+
+    let options = PlayOptions {
+        position,
+        shuffle: shuffle.unwrap_or(false),
+        chapters: chapters.unwrap_or(false),
+        force,
+        standby: standby.unwrap_or(false),
+        ..Default::default()
+    };
+    play_or_standby(ctx, link, options).await
+}
+
+/// Search YouTube or Spotify for a song
+#[poise::command(slash_command, guild_only, rename = "search")]
+async fn play_search(
+    ctx: Context<'_>,
+    #[description = "Song name, artist, or anything else to search for"]
+    #[autocomplete = "autocomplete_query"]
+    query: String,
+    #[description = "Which provider to search instead of the server default"] source: Option<
+        SearchChoice,
+    >,
+    #[description = "Pick from the top results instead of taking the first"] choose: Option<bool>,
+    #[description = "Queue now, but hold playback until someone else joins the channel"]
+    standby: Option<bool>,
+) -> Result<(), Error> {
+    let options = PlayOptions {
+        source: source.map(Into::into),
+        choose: choose.unwrap_or(false),
+        standby: standby.unwrap_or(false),
+        ..Default::default()
+    };
+    play_or_standby(ctx, &query, options).await
+}
+
+/// Play a link or search query, whichever it looks like
+#[poise::command(slash_command, guild_only, rename = "query")]
+async fn play_any(
+    ctx: Context<'_>,
+    #[description = "YouTube/Spotify URL or search query"]
+    #[autocomplete = "autocomplete_query"]
+    query: String,
+    #[description = "Queue now, but hold playback until someone else joins the channel"]
+    standby: Option<bool>,
+) -> Result<(), Error> {
+    let options = PlayOptions {
+        standby: standby.unwrap_or(false),
+        ..Default::default()
+    };
+    play_or_standby(ctx, &query, options).await
+}
+
+/// Runs [`play_query`], first putting the guild on standby when asked so the
+/// track it starts waits for listeners.
+async fn play_or_standby(ctx: Context<'_>, query: &str, options: PlayOptions) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    ensure_unlocked(ctx).await?;
+    if !options.standby {
+        return play_query(ctx, query, options).await;
+    }
+    let voice_channel_id = author_voice_channel(ctx)?;
+    let standby = standby::begin(ctx, guild_id, voice_channel_id).await;
+
+    let result = play_query(ctx, query, options).await;
+    if !standby {
+        return result;
+    }
+    // Nothing started, so there's nothing to hold
+    if result.is_err() || ctx.data().voice.current(guild_id).await.is_none() {
+        standby::cancel(ctx.data(), guild_id).await;
+        return result;
+    }
+    ctx.send(poise::CreateReply::default().content(format!(
+        "On standby: the queue starts once someone else joins <#{voice_channel_id}>."
+    )))
+    .await?;
+    Ok(())
+}
+
+/// Interrupt the current track to play something right away (DJ only)
+#[poise::command(slash_command, guild_only)]
+pub async fn playnow(
+    ctx: Context<'_>,
+    #[description = "YouTube/Spotify link to a single track, or a search query"]
+    #[autocomplete = "autocomplete_query"]
+    query: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    ensure_unlocked(ctx).await?;
+    if !is_dj(ctx).await {
+        return Err(MusicError::NotDj.into());
+    }
+    let query = query.trim();
+    let collection = MusicService::is_youtube_playlist_url(query)
+        || matches!(
+            MusicService::parse_spotify_url(query),
+            Some(SpotifyUrl::Playlist(_) | SpotifyUrl::Album(_))
+        );
+    if collection {
+        return Err(MusicError::NotSingleTrack.into());
+    }
+
+    let voice_channel_id = author_voice_channel(ctx)?;
+    ctx.defer().await?;
+
+    let data = ctx.data();
+    let text_channel_id = ctx.channel_id();
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    if !follow_requester(ctx, &manager, guild_id, voice_channel_id, text_channel_id).await? {
+        return Ok(());
+    }
+
+    let (join_result, resolved) = tokio::join!(
+        ensure_voice_connection(
+            ctx.serenity_context(),
+            &manager,
+            data,
+            guild_id,
+            voice_channel_id,
+            text_channel_id,
+        ),
+        data.music_service
+            .resolve_tracks(query, settings.search_preference, settings.prefer_topic),
+    );
+    let handler_lock = join_result?;
+    let mut tracks = resolved?;
+    // A short link can still turn out to be a playlist
+    if tracks.len() > 1 {
+        return Err(MusicError::NotSingleTrack.into());
+    }
+    let track = tracks.remove(0);
+
+    if let Some(rule) = settings.blocked_by(&track) {
+        return Err(MusicError::Blocked(rule.to_string()).into());
+    }
+    ensure_queue_room(data, guild_id, &settings).await?;
+
+    let interrupted = QueueService::current(&data.guild_queues, guild_id).await;
+    let playing = data.voice.current(guild_id).await;
+
+    let search_query = match track.source {
+        TrackSource::Spotify => {
+            MusicService::spotify_to_youtube_query(&track, settings.prefer_topic)
+        }
+        TrackSource::YouTube => String::new(),
+    };
+    let handle_id = enqueue_track(
+        &track,
+        &search_query,
+        &handler_lock,
+        ctx.serenity_context(),
+        Requester::User(ctx.author().id),
+        guild_id,
+        data,
+    )
+    .await;
+
+    let (Some(interrupted), Some(playing)) = (interrupted, playing) else {
+        // Nothing to interrupt, so it starts like any /play
+        let placement = queue_placement(&handler_lock, data, guild_id, handle_id, true).await;
+        ctx.send(poise::CreateReply::default().embed(enqueue_embed(&track, &settings, &placement)))
+            .await?;
+        return Ok(());
+    };
+    move_to_position(&handler_lock, data, guild_id, handle_id, 1).await;
+
+    // Queue the rest of the interrupted track right behind the new one
+    let position = playing
+        .get_info()
+        .await
+        .map(|info| info.position)
+        .unwrap_or_default();
+    let rest = resume_point(&interrupted, position);
+    let rest_query = match rest.source {
+        TrackSource::Spotify => {
+            MusicService::spotify_to_youtube_query(&rest, settings.prefer_topic)
+        }
+        TrackSource::YouTube => String::new(),
+    };
+    let rest_requester = rest
+        .requested_by
+        .unwrap_or(Requester::User(ctx.author().id));
+    let rest_id = enqueue_track(
+        &rest,
+        &rest_query,
+        &handler_lock,
+        ctx.serenity_context(),
+        rest_requester,
+        guild_id,
+        data,
+    )
+    .await;
+    move_to_position(&handler_lock, data, guild_id, rest_id, 2).await;
+
+    QueueService::skip(&data.guild_queues, guild_id, Some(playing.uuid())).await;
+    data.voice.skip(guild_id).await?;
+
+    let (_, color, _) = source_info(&track.source, &settings);
+    let embed = CreateEmbed::new()
+        .description(format!(
+            "Interrupting **{}** to play {}; **{}** will resume after from `{}`.",
+            interrupted.title,
+            linked_title(&track),
+            interrupted.title,
+            format_secs(position.as_secs())
+        ))
+        .colour(color);
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
 }
 
-async fn autocomplete_query(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
-    let partial = partial.trim();
-
-    if partial.len() < 3 || partial.starts_with("http://") || partial.starts_with("https://") {
-        return Vec::new();
+/// The rest of `track` from `position` in its media, as its own queue entry
+/// that seeks there when it loads.
+fn resume_point(track: &Track, position: Duration) -> Track {
+    let start = track.span.map_or(Duration::ZERO, |span| span.start);
+    let end = track
+        .span
+        .and_then(|span| span.end)
+        .or_else(|| track.duration.map(|duration| start + duration));
+    Track {
+        duration: end.map(|end| end.saturating_sub(position)),
+        span: Some(TrackSpan {
+            start: position,
+            end: track.span.and_then(|span| span.end),
+        }),
+        handle_id: None,
+        resolved_url: None,
+        loudness: None,
+        ..track.clone()
     }
+}
 
-    let results = ctx.data().music_service.search(partial, 5).await;
+/// How long to spend expanding a Spotify short link.
+const SHORT_LINK_TIMEOUT: Duration = Duration::from_secs(3);
+/// Most links queued from a single `/play`.
+const MAX_LINKS: usize = 10;
+
+/// Splits a query holding several pasted links into its parts, or `None`
+/// for a single link or a search.
+fn split_links(query: &str) -> Option<Vec<&str>> {
+    let parts: Vec<&str> = query.split_whitespace().collect();
+    let links = parts
+        .iter()
+        .filter(|part| MusicService::is_supported_link(part))
+        .count();
+    (links > 1).then_some(parts)
+}
 
-    results
-        .into_iter()
-        .take(25)
-        .map(|track| {
-            let name = format!("{}", track);
-            let name = if name.len() > 100 {
-                format!("{}...", &name.chars().take(97).collect::<String>())
-            } else {
-                name
-            };
-            AutocompleteChoice::new(name, track.url)
-        })
-        .collect()
+/// Resolves pasted links in order. Returns their tracks, how many links
+/// resolved, and a line for each part that didn't.
+async fn resolve_links(
+    data: &Data,
+    parts: &[&str],
+    settings: &GuildSettings,
+) -> (Vec<Track>, usize, Vec<String>) {
+    let lookups = parts.iter().take(MAX_LINKS).map(|part| async move {
+        if !MusicService::is_supported_link(part) {
+            return Err("not a YouTube or Spotify link".to_string());
+        }
+        data.music_service
+            .resolve_tracks(part, settings.search_preference, settings.prefer_topic)
+            .await
+            .map_err(|e| e.to_string())
+    });
+    let results = futures::future::join_all(lookups).await;
+
+    let mut tracks = Vec::new();
+    let mut resolved = 0;
+    let mut rejects = Vec::new();
+    for (part, result) in parts.iter().zip(results) {
+        match result {
+            Ok(found) => {
+                resolved += 1;
+                tracks.extend(found);
+            }
+            Err(reason) => rejects.push(format!("`{part}` — {reason}")),
+        }
+    }
+    for part in parts.iter().skip(MAX_LINKS) {
+        rejects.push(format!(
+            "`{part}` — only {MAX_LINKS} links are taken at once"
+        ));
+    }
+    (tracks, resolved, rejects)
 }
 
-/// Play a song from YouTube or Spotify
-#[poise::command(slash_command, guild_only)]
-pub async fn play(
-    ctx: Context<'_>,
-    #[description = "YouTube/Spotify URL or search query"]
-    #[autocomplete = "autocomplete_query"]
-    query: String,
-) -> Result<(), Error> {
+/// Resolves `query` and queues it. Callers check the guild's lock first.
+async fn play_query(ctx: Context<'_>, query: &str, options: PlayOptions) -> Result<(), Error> {
     let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let voice_channel_id = author_voice_channel(ctx)?;
 
-    let voice_channel_id = {
-        let guild = ctx.guild().ok_or(MusicError::NotInGuild)?;
-        guild
-            .voice_states
-            .get(&ctx.author().id)
-            .and_then(|vs| vs.channel_id)
-            .ok_or(MusicError::NotInVoiceChannel)?
-    };
+    let data = ctx.data();
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let user_preferences = PreferencesService::get(&data.user_preferences, ctx.author().id).await;
+    let preferences = PlayPreferences::resolve(options.source, &user_preferences, &settings);
+    match preferences.confirmations {
+        Confirmations::Public => ctx.defer().await?,
+        Confirmations::Ephemeral => ctx.defer_ephemeral().await?,
+    }
 
-    ctx.defer().await?;
+    // Mobile shares hand out spotify.link URLs, expand them to the real one
+    let resolved;
+    let query = if MusicService::is_spotify_short_link(query.trim()) {
+        resolved = tokio::time::timeout(
+            SHORT_LINK_TIMEOUT,
+            data.music_service.resolve_spotify_short_link(query.trim()),
+        )
+        .await
+        .ok()
+        .flatten()
+        .ok_or(MusicError::ShortLinkUnresolved)?;
+        resolved.as_str()
+    } else {
+        query
+    };
 
-    let data = ctx.data();
-    let http = &data.http_client;
-    let serenity_http = ctx.serenity_context().http.clone();
+    // A video opened from inside a playlist carries both ids. Ask which was
+    // meant, unless the options only make sense for a single video
+    let video_url;
+    let query = match MusicService::video_in_playlist(query) {
+        Some((video_id, playlist_id)) => {
+            let single_only = options.chapters || options.position.is_some();
+            if !single_only && choose_whole_playlist(ctx, &playlist_id).await? {
+                query
+            } else {
+                video_url = canonicalize_youtube_url(&video_id);
+                video_url.as_str()
+            }
+        }
+        None => query,
+    };
     let text_channel_id = ctx.channel_id();
-    let requester = format!("<@{}>", ctx.author().id);
+    let requester = Requester::User(ctx.author().id);
 
     let manager = songbird::get(ctx.serenity_context())
         .await
         .expect("Songbird not registered");
 
-    let join_fut = ensure_voice_connection(&manager, guild_id, voice_channel_id, &data.join_locks, &data.inactivity_handles);
+    if !follow_requester(ctx, &manager, guild_id, voice_channel_id, text_channel_id).await? {
+        return Ok(());
+    }
 
-    if MusicService::is_youtube_playlist_url(&query) {
-        // YouTube playlist — parallelize join + metadata fetch
-        let playlist_id = MusicService::extract_youtube_playlist_id(&query)
-            .ok_or(MusicError::NoResults)?;
+    let join_fut = ensure_voice_connection(
+        ctx.serenity_context(),
+        &manager,
+        data,
+        guild_id,
+        voice_channel_id,
+        text_channel_id,
+    );
+
+    if let Some(parts) = split_links(query) {
+        // Several links pasted at once — resolve them all while joining and
+        // queue them as one batch, in the order given
+        let (join_result, (mut tracks, resolved, rejects)) =
+            tokio::join!(join_fut, resolve_links(data, &parts, &settings));
+        let handler_lock = join_result?;
 
-        let ((tracks, name), join_result) = tokio::join!(
+        if tracks.is_empty() {
+            let embed = links_embed(&settings, None, resolved, &rejects);
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+            return Ok(());
+        }
+        let mut info = PlaylistInfo::default();
+        apply_collection_limit(&mut info, &mut tracks, None, &settings, options.force)?;
+        if options.shuffle {
+            tracks.shuffle(&mut rand::rng());
+        }
+
+        let total = tracks.len();
+        let (first, started, rest) = enqueue_collection_head(
+            tracks,
+            &handler_lock,
+            ctx.serenity_context(),
+            requester,
+            guild_id,
+            data,
+        )
+        .await?;
+
+        let embed = links_embed(
+            &settings,
+            Some((&first, started, total)),
+            resolved,
+            &rejects,
+        );
+        let embed = note_collection_limit(embed, info.capped_at);
+        let embed = note_queue_overflow(embed, data, guild_id, &settings, &info, rest.len()).await;
+        ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+        spawn_background_enqueue(
+            data,
+            rest,
+            None,
+            handler_lock,
+            ctx.serenity_context(),
+            requester,
+            guild_id,
+        )
+        .await;
+    } else if MusicService::is_youtube_playlist_url(query) {
+        // YouTube playlist — parallelize join + metadata fetch
+        let playlist_id =
+            MusicService::extract_youtube_playlist_id(query).ok_or(MusicError::NoResults)?;
+
+        // Only the first page is listed before replying; big playlists can
+        // take longer to list than the interaction stays valid
+        let cursor = PageCursor::YouTubePlaylist {
+            id: playlist_id.clone(),
+            token: None,
+        };
+        let ((first_page, info), join_result) = tokio::join!(
             async {
                 tokio::join!(
-                    data.music_service.youtube.get_playlist_tracks(&playlist_id),
-                    data.music_service.youtube.get_playlist_name(&playlist_id),
+                    data.music_service.fetch_pages(&cursor, 0, false),
+                    data.music_service.youtube.get_playlist_info(&playlist_id),
                 )
             },
             join_fut,
         );
         let handler_lock = join_result?;
 
-        if tracks.is_empty() {
+        let mut fetched = first_page.map_err(|e| match e {
+            PlaylistError::Unavailable => MusicError::PlaylistUnavailable,
+            PlaylistError::Api(_) => MusicError::NoResults,
+        })?;
+        if fetched.tracks.is_empty() {
             return Err(MusicError::NoResults.into());
         }
 
-        // Fresh join setup
-        setup_fresh_join(
-            &data, &handler_lock, &manager, guild_id, voice_channel_id,
-            text_channel_id, &serenity_http, ctx,
-        ).await;
-
-        let name = name.unwrap_or_else(|| "Playlist".to_string());
+        let mut info = info.unwrap_or_else(|| PlaylistInfo {
+            name: "Playlist".to_string(),
+            ..Default::default()
+        });
+        info.note_fetch(&fetched, data.music_service.max_playlist_items);
+        let remaining = RemainingPages::after(&mut fetched, &info.name, options.shuffle, None);
+        let mut tracks = fetched.tracks;
+        let remaining =
+            apply_collection_limit(&mut info, &mut tracks, remaining, &settings, options.force)?;
         let url = format!("https://www.youtube.com/playlist?list={playlist_id}");
-        let count = tracks.len();
 
-        ctx.send(
-            poise::CreateReply::default()
-                .embed(collection_embed(&name, &url, count, &TrackSource::YouTube)),
+        if options.shuffle {
+            tracks.shuffle(&mut rand::rng());
+        }
+
+        let (first, started, rest) = enqueue_collection_head(
+            tracks,
+            &handler_lock,
+            ctx.serenity_context(),
+            requester,
+            guild_id,
+            data,
         )
         .await?;
 
+        let overflow = queue_overflow(data, guild_id, &settings, &info, rest.len()).await;
+        let embed = collection_embed(
+            &info,
+            &url,
+            &TrackSource::YouTube,
+            &settings,
+            &first,
+            started,
+            &rest,
+        );
+        let embed = overflow_field(embed, overflow);
+        ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+        // The reply counts up as the rest of the playlist is listed
+        let remaining = remaining.map(|remaining| {
+            let reply = match ctx {
+                poise::Context::Application(app) => Some(ListingReply {
+                    interaction: app.interaction.clone(),
+                    info,
+                    url,
+                    source: TrackSource::YouTube,
+                    settings: settings.clone(),
+                    first,
+                    started,
+                    rest: rest.clone(),
+                    overflow,
+                }),
+                poise::Context::Prefix(_) => None,
+            };
+            remaining.report_to(reply)
+        });
         spawn_background_enqueue(
-            data, tracks, http, handler_lock, serenity_http,
-            text_channel_id, requester, guild_id,
-        ).await;
-    } else if MusicService::is_youtube_url(&query) {
+            data,
+            rest,
+            remaining,
+            handler_lock,
+            ctx.serenity_context(),
+            requester,
+            guild_id,
+        )
+        .await;
+    } else if let Some(video_id) =
+        MusicService::extract_youtube_video_id(query).filter(|_| options.chapters)
+    {
+        // YouTube video split into chapters — parallelize join + video lookup
+        let (join_result, details) = tokio::join!(
+            join_fut,
+            data.music_service.youtube.playable_video(&video_id),
+        );
+        let details = details.map_err(MusicError::VideoUnavailable)?;
+        let (video, description) = details.ok_or(MusicError::NoResults)?;
+        let handler_lock = join_result?;
+
+        let mut tracks = chapter_tracks(&video, &description);
+        if tracks.is_empty() {
+            // No chapter markers, so it's just the one video
+            enqueue_single(ctx, &video, "", &handler_lock, &settings, options.position).await?;
+        } else {
+            if options.shuffle {
+                tracks.shuffle(&mut rand::rng());
+            }
+            let info = PlaylistInfo {
+                name: video.title.clone(),
+                owner: Some(video.artist.clone()),
+                image: video.thumbnail_url.clone(),
+                track_count: Some(tracks.len()),
+                incomplete: false,
+                truncated_at: None,
+                skipped: 0,
+                still_listing: false,
+                capped_at: None,
+            };
+
+            let (first, started, rest) = enqueue_collection_head(
+                tracks,
+                &handler_lock,
+                ctx.serenity_context(),
+                requester,
+                guild_id,
+                data,
+            )
+            .await?;
+
+            let embed = collection_embed(
+                &info,
+                &video.url,
+                &TrackSource::YouTube,
+                &settings,
+                &first,
+                started,
+                &rest,
+            );
+            let embed =
+                note_queue_overflow(embed, data, guild_id, &settings, &info, rest.len()).await;
+            ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
+            spawn_background_enqueue(
+                data,
+                rest,
+                None,
+                handler_lock,
+                ctx.serenity_context(),
+                requester,
+                guild_id,
+            )
+            .await;
+        }
+    } else if MusicService::is_youtube_url(query) {
         // YouTube single URL — parallelize join + video lookup
-        let video_id = MusicService::extract_youtube_video_id(&query);
+        let video_id = MusicService::extract_youtube_video_id(query);
         let resolve_fut = async {
-            if let Some(vid) = video_id {
-                data.music_service
-                    .youtube
-                    .get_video(&vid)
-                    .await
-                    .unwrap_or(Track {
-                        title: query.clone(),
-                        artist: String::from("YouTube"),
-                        url: query.clone(),
-                        source: TrackSource::YouTube,
-                        duration: None,
-                        thumbnail_url: None,
-                    })
-            } else {
-                Track {
-                    title: query.clone(),
+            // A failed lookup says nothing about the video, so let yt-dlp try
+            // it; one the API says can't play is turned away now
+            let (url, video) = match video_id {
+                Some(vid) => {
+                    let video = data.music_service.youtube.playable_video(&vid).await;
+                    let video = video.map_err(MusicError::VideoUnavailable)?;
+                    (canonicalize_youtube_url(&vid), video)
+                }
+                None => (query.to_string(), None),
+            };
+            Ok::<_, MusicError>(match video {
+                Some((track, _)) => track,
+                None => Track {
+                    title: query.to_string(),
                     artist: String::from("YouTube"),
-                    url: query.clone(),
+                    album: None,
+                    url,
                     source: TrackSource::YouTube,
                     duration: None,
                     thumbnail_url: None,
-                }
-            }
+                    thumbnail_small: None,
+                    thumbnail_large: None,
+                    requested_by: None,
+                    handle_id: None,
+                    enqueued_at: None,
+                    resolved_url: None,
+                    loudness: None,
+                    span: None,
+                    chapters: Vec::new(),
+                },
+            })
         };
 
         let (join_result, track) = tokio::join!(join_fut, resolve_fut);
+        let track = track?;
         let handler_lock = join_result?;
 
-        setup_fresh_join(
-            &data, &handler_lock, &manager, guild_id, voice_channel_id,
-            text_channel_id, &serenity_http, ctx,
-        ).await;
-
-        enqueue_track(
-            &track, "", http, &handler_lock, &serenity_http,
-            text_channel_id, &requester, &data.guild_queues, guild_id,
-            &data.now_playing_messages,
-            &data.repeat_states,
-        )
-        .await;
-
-        ctx.send(poise::CreateReply::default().embed(enqueue_embed(&track)))
-            .await?;
-    } else if let Some(spotify_url) = MusicService::parse_spotify_url(&query) {
+        enqueue_single(ctx, &track, "", &handler_lock, &settings, options.position).await?;
+    } else if let Some(spotify_url) = MusicService::parse_spotify_url(query) {
         match spotify_url {
             SpotifyUrl::Track(id) => {
-                let (join_result, track_opt) = tokio::join!(
-                    join_fut,
-                    data.music_service.spotify.get_track(&id),
-                );
+                let (join_result, track_opt) =
+                    tokio::join!(join_fut, data.music_service.spotify.get_track(&id),);
                 let handler_lock = join_result?;
                 let track = track_opt.ok_or(MusicError::NoResults)?;
 
-                setup_fresh_join(
-                    &data, &handler_lock, &manager, guild_id, voice_channel_id,
-                    text_channel_id, &serenity_http, ctx,
-                ).await;
-
-                let search_query = MusicService::spotify_to_youtube_query(&track);
-                enqueue_track(
-                    &track, &search_query, http, &handler_lock, &serenity_http,
-                    text_channel_id, &requester, &data.guild_queues, guild_id,
-                    &data.now_playing_messages, &data.repeat_states,
+                let search_query =
+                    MusicService::spotify_to_youtube_query(&track, settings.prefer_topic);
+                enqueue_single(
+                    ctx,
+                    &track,
+                    &search_query,
+                    &handler_lock,
+                    &settings,
+                    options.position,
                 )
-                .await;
-
-                ctx.send(poise::CreateReply::default().embed(enqueue_embed(&track)))
-                    .await?;
+                .await?;
             }
             SpotifyUrl::Playlist(id) => {
-                let ((tracks, name), join_result) = tokio::join!(
+                let cursor = PageCursor::SpotifyPlaylist {
+                    id: id.clone(),
+                    offset: 0,
+                };
+                let ((first_page, info), join_result) = tokio::join!(
                     async {
                         tokio::join!(
-                            data.music_service.spotify.get_playlist_tracks(&id),
-                            data.music_service.spotify.get_playlist_name(&id),
+                            data.music_service.fetch_pages(&cursor, 0, false),
+                            data.music_service.spotify.get_playlist_info(&id),
                         )
                     },
                     join_fut,
                 );
+                // Spotify pages never fail outright, they come back empty
+                let mut fetched = first_page.unwrap_or_default();
                 let handler_lock = join_result?;
 
-                if tracks.is_empty() {
-                    return Err(MusicError::NoResults.into());
+                if fetched.tracks.is_empty() {
+                    // The playlist exists but nothing in it is available in our market
+                    return Err(if info.is_some() {
+                        MusicError::NoPlayableTracks
+                    } else {
+                        MusicError::NoResults
+                    }
+                    .into());
                 }
 
-                setup_fresh_join(
-                    &data, &handler_lock, &manager, guild_id, voice_channel_id,
-                    text_channel_id, &serenity_http, ctx,
-                ).await;
-
-                let name = name.unwrap_or_else(|| "Playlist".to_string());
+                let mut info = info.unwrap_or_else(|| PlaylistInfo {
+                    name: "Playlist".to_string(),
+                    ..Default::default()
+                });
+                info.note_fetch(&fetched, data.music_service.max_playlist_items);
+                let remaining =
+                    RemainingPages::after(&mut fetched, &info.name, options.shuffle, None);
                 let url = format!("https://open.spotify.com/playlist/{id}");
-                let count = tracks.len();
+                let mut tracks = fetched.tracks;
+                let remaining = apply_collection_limit(
+                    &mut info,
+                    &mut tracks,
+                    remaining,
+                    &settings,
+                    options.force,
+                )?;
+                if options.shuffle {
+                    tracks.shuffle(&mut rand::rng());
+                }
 
-                ctx.send(
-                    poise::CreateReply::default()
-                        .embed(collection_embed(&name, &url, count, &TrackSource::Spotify)),
+                let (first, started, rest) = enqueue_collection_head(
+                    tracks,
+                    &handler_lock,
+                    ctx.serenity_context(),
+                    requester,
+                    guild_id,
+                    data,
                 )
                 .await?;
 
+                let embed = collection_embed(
+                    &info,
+                    &url,
+                    &TrackSource::Spotify,
+                    &settings,
+                    &first,
+                    started,
+                    &rest,
+                );
+                let embed =
+                    note_queue_overflow(embed, data, guild_id, &settings, &info, rest.len()).await;
+                ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
                 spawn_background_enqueue(
-                    data, tracks, http, handler_lock, serenity_http,
-                    text_channel_id, requester, guild_id,
-                ).await;
+                    data,
+                    rest,
+                    remaining,
+                    handler_lock,
+                    ctx.serenity_context(),
+                    requester,
+                    guild_id,
+                )
+                .await;
             }
             SpotifyUrl::Album(id) => {
-                let ((tracks, name), join_result) = tokio::join!(
+                let cursor = PageCursor::SpotifyAlbum {
+                    id: id.clone(),
+                    offset: 0,
+                };
+                let ((first_page, info), join_result) = tokio::join!(
                     async {
                         tokio::join!(
-                            data.music_service.spotify.get_album_tracks(&id),
-                            data.music_service.spotify.get_album_name(&id),
+                            data.music_service.fetch_pages(&cursor, 0, false),
+                            data.music_service.spotify.get_album_info(&id),
                         )
                     },
                     join_fut,
                 );
+                let mut fetched = first_page.unwrap_or_default();
                 let handler_lock = join_result?;
 
-                if tracks.is_empty() {
-                    return Err(MusicError::NoResults.into());
+                if fetched.tracks.is_empty() {
+                    // The playlist exists but nothing in it is available in our market
+                    return Err(if info.is_some() {
+                        MusicError::NoPlayableTracks
+                    } else {
+                        MusicError::NoResults
+                    }
+                    .into());
                 }
 
-                setup_fresh_join(
-                    &data, &handler_lock, &manager, guild_id, voice_channel_id,
-                    text_channel_id, &serenity_http, ctx,
-                ).await;
-
-                let name = name.unwrap_or_else(|| "Album".to_string());
+                let album = info.as_ref().map(|info| info.name.clone());
+                let mut info = info.unwrap_or_else(|| PlaylistInfo {
+                    name: "Album".to_string(),
+                    ..Default::default()
+                });
+                info.note_fetch(&fetched, data.music_service.max_playlist_items);
+                let remaining =
+                    RemainingPages::after(&mut fetched, &info.name, options.shuffle, album.clone());
+                let mut tracks: Vec<Track> = fetched
+                    .tracks
+                    .into_iter()
+                    .map(|track| Track {
+                        album: album.clone(),
+                        ..track
+                    })
+                    .collect();
+                let remaining = apply_collection_limit(
+                    &mut info,
+                    &mut tracks,
+                    remaining,
+                    &settings,
+                    options.force,
+                )?;
+                if options.shuffle {
+                    tracks.shuffle(&mut rand::rng());
+                }
                 let url = format!("https://open.spotify.com/album/{id}");
-                let count = tracks.len();
 
-                ctx.send(
-                    poise::CreateReply::default()
-                        .embed(collection_embed(&name, &url, count, &TrackSource::Spotify)),
+                let (first, started, rest) = enqueue_collection_head(
+                    tracks,
+                    &handler_lock,
+                    ctx.serenity_context(),
+                    requester,
+                    guild_id,
+                    data,
                 )
                 .await?;
 
+                let embed = collection_embed(
+                    &info,
+                    &url,
+                    &TrackSource::Spotify,
+                    &settings,
+                    &first,
+                    started,
+                    &rest,
+                );
+                let embed =
+                    note_queue_overflow(embed, data, guild_id, &settings, &info, rest.len()).await;
+                ctx.send(poise::CreateReply::default().embed(embed)).await?;
+
                 spawn_background_enqueue(
-                    data, tracks, http, handler_lock, serenity_http,
-                    text_channel_id, requester, guild_id,
-                ).await;
+                    data,
+                    rest,
+                    remaining,
+                    handler_lock,
+                    ctx.serenity_context(),
+                    requester,
+                    guild_id,
+                )
+                .await;
             }
         }
     } else {
         // Search query — parallelize join + search
-        let (join_result, results) = tokio::join!(
-            join_fut,
-            data.music_service.search(&query, 5),
-        );
+        // The picker shows both providers side by side unless one was asked for
+        let music_service = &data.music_service;
+        let limit = music_service.search_limit;
+        let search = async {
+            if options.choose && !preferences.search_picked {
+                music_service
+                    .search_merged(query, limit, settings.prefer_topic)
+                    .await
+            } else {
+                let preference = preferences.search;
+                music_service
+                    .search_with_preference(query, limit, preference, settings.prefer_topic)
+                    .await
+            }
+        };
+        let (join_result, results) = tokio::join!(join_fut, search);
         let handler_lock = join_result?;
 
         if results.is_empty() {
             return Err(MusicError::NoResults.into());
         }
 
-        setup_fresh_join(
-            &data, &handler_lock, &manager, guild_id, voice_channel_id,
-            text_channel_id, &serenity_http, ctx,
-        ).await;
+        let track = if options.choose && results.len() > 1 {
+            match choose_result(ctx, results).await? {
+                Some(track) => track,
+                None => return Ok(()),
+            }
+        } else {
+            results.into_iter().next().unwrap()
+        };
 
-        let track = results.into_iter().next().unwrap();
         let search_query = match track.source {
             TrackSource::YouTube => String::new(),
-            TrackSource::Spotify => MusicService::spotify_to_youtube_query(&track),
+            TrackSource::Spotify => {
+                MusicService::spotify_to_youtube_query(&track, settings.prefer_topic)
+            }
         };
+        enqueue_single(
+            ctx,
+            &track,
+            &search_query,
+            &handler_lock,
+            &settings,
+            options.position,
+        )
+        .await?;
+    }
 
-        enqueue_track(
-            &track, &search_query, http, &handler_lock, &serenity_http,
-            text_channel_id, &requester, &data.guild_queues, guild_id,
-            &data.now_playing_messages,
-            &data.repeat_states,
+    // Interaction replies work without channel permissions, but the Now
+    // Playing updates don't, so let the user know why they won't show up.
+    let bound_here = data.text_channels.read().await.get(&guild_id) == Some(&text_channel_id);
+    if bound_here && !can_post_updates(ctx) {
+        ctx.send(
+            poise::CreateReply::default()
+                .content(
+                    "I can't post Now Playing updates in this channel — I need the Send Messages and Embed Links permissions here.",
+                )
+                .ephemeral(true),
         )
-        .await;
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Queues a single track and replies with where it landed in the queue.
+async fn enqueue_single(
+    ctx: Context<'_>,
+    track: &Track,
+    search_query: &str,
+    handler_lock: &Arc<Mutex<Call>>,
+    settings: &GuildSettings,
+    position: Option<usize>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
 
-        ctx.send(poise::CreateReply::default().embed(enqueue_embed(&track)))
+    if let Some(rule) = settings.blocked_by(track) {
+        return Err(MusicError::Blocked(rule.to_string()).into());
+    }
+    ensure_queue_room(data, guild_id, settings).await?;
+
+    // Takes a place in the same line as the collections, so it lands after them
+    let in_line = settings.single_tracks_during_collection == SingleTrackOrder::AppendAfter;
+    if in_line && position.is_none() && collection_in_progress(data, guild_id).await {
+        let requester = Requester::User(ctx.author().id);
+        let serenity_ctx = ctx.serenity_context();
+        let tracks = vec![track.clone()];
+        spawn_background_enqueue(
+            data,
+            tracks,
+            None,
+            handler_lock.clone(),
+            serenity_ctx,
+            requester,
+            guild_id,
+        )
+        .await;
+        let placement = QueuePlacement::AfterCollections;
+        ctx.send(poise::CreateReply::default().embed(enqueue_embed(track, settings, &placement)))
             .await?;
+        return Ok(());
+    }
+
+    let was_idle = handler_lock.lock().await.queue().is_empty();
+    let handle_id = enqueue_track(
+        track,
+        search_query,
+        handler_lock,
+        ctx.serenity_context(),
+        Requester::User(ctx.author().id),
+        guild_id,
+        data,
+    )
+    .await;
+    if let (false, Some(position)) = (was_idle, position) {
+        move_to_position(handler_lock, data, guild_id, handle_id, position).await;
     }
 
+    let placement = queue_placement(handler_lock, data, guild_id, handle_id, was_idle).await;
+    ctx.send(poise::CreateReply::default().embed(enqueue_embed(track, settings, &placement)))
+        .await?;
     Ok(())
 }
 
+/// Whether a collection is still being added to the guild's queue in the
+/// background.
+async fn collection_in_progress(data: &Data, guild_id: GuildId) -> bool {
+    data.enqueue_cancels
+        .read()
+        .await
+        .get(&guild_id)
+        .is_some_and(|flags| flags.iter().any(|flag| !flag.load(Ordering::Relaxed)))
+}
+
+/// One queue entry per chapter in the video's description, all pointing at
+/// the same video. Empty when the description has no chapter markers.
+fn chapter_tracks(video: &Track, description: &str) -> Vec<Track> {
+    parse_chapters(description, video.duration)
+        .into_iter()
+        .map(|chapter| Track {
+            title: format!("{} — {}", video.title, chapter.title),
+            duration: chapter.end.map(|end| end - chapter.start),
+            span: Some(TrackSpan {
+                start: chapter.start,
+                end: chapter.end,
+            }),
+            chapters: Vec::new(),
+            ..video.clone()
+        })
+        .collect()
+}
+
+/// Queues `query` to play next, as `/play url position:1` would.
+pub(crate) async fn play_next(ctx: Context<'_>, query: &str) -> Result<(), Error> {
+    // A DJ may have locked the queue while the vote was open
+    ensure_unlocked(ctx).await?;
+    let options = PlayOptions {
+        position: Some(1),
+        ..Default::default()
+    };
+    play_query(ctx, query, options).await
+}
+
+/// Moves a freshly queued track to the 1-based `position` in the pending
+/// queue and reorders songbird's queue to match.
+async fn move_to_position(
+    handler_lock: &Arc<Mutex<Call>>,
+    data: &Data,
+    guild_id: GuildId,
+    handle_id: Uuid,
+    position: usize,
+) {
+    let index = position.saturating_sub(1);
+    if QueueService::move_track(&data.guild_queues, guild_id, handle_id, index).await {
+        sync_playback_order(handler_lock, data, guild_id).await;
+    }
+}
+
+/// How long the video-or-playlist prompt waits before settling on the video.
+const PLAYLIST_CHOICE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Asks whether a `watch?v=…&list=…` link meant the one video or the whole
+/// playlist. Returns `true` for the playlist; no answer means the video.
+async fn choose_whole_playlist(ctx: Context<'_>, playlist_id: &str) -> Result<bool, Error> {
+    let info = ctx
+        .data()
+        .music_service
+        .youtube
+        .get_playlist_info(playlist_id)
+        .await;
+    let playlist_label = match info.and_then(|info| info.track_count) {
+        Some(1) => "Queue the whole playlist (1 track)".to_string(),
+        Some(count) => format!("Queue the whole playlist ({} tracks)", group_digits(count)),
+        None => "Queue the whole playlist".to_string(),
+    };
+
+    let prefix = format!("plchoice_{}", ctx.id());
+    let buttons = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{prefix}_video"))
+            .label("Play this video")
+            .style(serenity::ButtonStyle::Primary),
+        CreateButton::new(format!("{prefix}_playlist"))
+            .label(playlist_label)
+            .style(serenity::ButtonStyle::Secondary),
+    ])];
+    let prompt = ctx
+        .send(
+            poise::CreateReply::default()
+                .content("That link points at a video inside a playlist. Which did you mean?")
+                .components(buttons),
+        )
+        .await?;
+
+    let filter_prefix = prefix.clone();
+    let Some(press) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter(move |press| press.data.custom_id.starts_with(&filter_prefix))
+        .timeout(PLAYLIST_CHOICE_TIMEOUT)
+        .await
+    else {
+        prompt
+            .edit(
+                ctx,
+                poise::CreateReply::default()
+                    .content("No answer, playing just the video.")
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(false);
+    };
+
+    let whole = press.data.custom_id.ends_with("_playlist");
+    let content = if whole {
+        "Queueing the whole playlist."
+    } else {
+        "Playing just the video."
+    };
+    let response = CreateInteractionResponse::UpdateMessage(
+        CreateInteractionResponseMessage::new()
+            .content(content)
+            .components(Vec::new()),
+    );
+    press.create_response(ctx, response).await?;
+    Ok(whole)
+}
+
+/// How long the search result picker waits for a choice.
+const CHOOSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Shows the search results as numbered buttons and returns the one the
+/// requester picks, or `None` if they don't pick in time.
+async fn choose_result(ctx: Context<'_>, results: Vec<Track>) -> Result<Option<Track>, Error> {
+    let prefix = format!("choose_{}", ctx.id());
+    let lines = results
+        .iter()
+        .enumerate()
+        .map(|(i, track)| {
+            let duration = track
+                .formatted_duration()
+                .map(|d| format!(" `{d}`"))
+                .unwrap_or_default();
+            let tag = source_tag(&track.source);
+            format!(
+                "`{}.` {tag} **{}** - {}{duration}",
+                i + 1,
+                track.title,
+                track.artist
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let buttons = (0..results.len())
+        .map(|i| {
+            CreateButton::new(format!("{prefix}_{i}"))
+                .label((i + 1).to_string())
+                .style(serenity::ButtonStyle::Secondary)
+        })
+        .collect();
+
+    let picker = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(
+                    CreateEmbed::new()
+                        .title("Pick a track")
+                        .description(lines)
+                        .colour(NEUTRAL_COLOR),
+                )
+                .components(vec![CreateActionRow::Buttons(buttons)]),
+        )
+        .await?;
+
+    let filter_prefix = prefix.clone();
+    let Some(press) = serenity::ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter(move |press| press.data.custom_id.starts_with(&filter_prefix))
+        .timeout(CHOOSE_TIMEOUT)
+        .await
+    else {
+        picker
+            .edit(
+                ctx,
+                poise::CreateReply::default()
+                    .content("No track picked.")
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(None);
+    };
+
+    let Some(track) = press
+        .data
+        .custom_id
+        .rsplit('_')
+        .next()
+        .and_then(|i| i.parse::<usize>().ok())
+        .and_then(|i| results.into_iter().nth(i))
+    else {
+        return Ok(None);
+    };
+
+    let response = CreateInteractionResponse::UpdateMessage(
+        CreateInteractionResponseMessage::new()
+            .content(format!("Picked **{}**.", track.title))
+            .embeds(Vec::new())
+            .components(Vec::new()),
+    );
+    press.create_response(ctx, response).await?;
+    Ok(Some(track))
+}
+
+/// Whether the bot can send embeds in the channel the command was run in,
+/// going by the permissions Discord attaches to the interaction.
+fn can_post_updates(ctx: Context<'_>) -> bool {
+    let needed = Permissions::SEND_MESSAGES | Permissions::EMBED_LINKS;
+    match ctx {
+        poise::Context::Application(app) => app
+            .interaction
+            .app_permissions
+            .is_none_or(|p| p.contains(needed)),
+        poise::Context::Prefix(_) => true,
+    }
+}
+
 async fn setup_fresh_join(
-    data: &crate::Data,
+    data: &Data,
     handler_lock: &Arc<Mutex<Call>>,
     manager: &Arc<songbird::Songbird>,
     guild_id: GuildId,
-    voice_channel_id: ChannelId,
     text_channel_id: ChannelId,
     serenity_http: &Arc<Http>,
-    ctx: Context<'_>,
+    cache: &Arc<Cache>,
 ) {
     let mut handles = data.inactivity_handles.write().await;
-    if !handles.contains_key(&guild_id) {
+    if let Entry::Vacant(entry) = handles.entry(guild_id) {
+        let session = start_session(data, guild_id).await;
         {
             let handler = handler_lock.lock().await;
             handler.queue().stop();
         }
         {
             let mut handler = handler_lock.lock().await;
+            if let Some(bps) = data.voice_bitrate {
+                handler.set_bitrate(Bitrate::BitsPerSecond(bps));
+            }
             handler.add_global_event(
                 Event::Core(songbird::CoreEvent::DriverDisconnect),
                 DisconnectCleanup {
                     guild_id,
+                    session,
                     http: serenity_http.clone(),
-                    guild_queues: data.guild_queues.clone(),
-                    enqueue_cancels: data.enqueue_cancels.clone(),
-                    inactivity_handles: data.inactivity_handles.clone(),
-                    now_playing_messages: data.now_playing_messages.clone(),
-                    repeat_states: data.repeat_states.clone(),
+                    data: data.clone(),
                 },
             );
         }
-        handles.insert(
+        data.text_channels
+            .write()
+            .await
+            .insert(guild_id, text_channel_id);
+        spawn_board_updater(
+            manager.clone(),
             guild_id,
-            spawn_inactivity_monitor(
-                manager.clone(),
-                guild_id,
-                voice_channel_id,
-                text_channel_id,
-                serenity_http.clone(),
-                ctx.serenity_context().cache.clone(),
-                data.guild_queues.clone(),
-                data.inactivity_handles.clone(),
-                data.enqueue_cancels.clone(),
-                data.now_playing_messages.clone(),
-                data.repeat_states.clone(),
-            ),
+            session,
+            serenity_http.clone(),
+            data.clone(),
+        );
+        spawn_playback_watchdog(
+            manager.clone(),
+            guild_id,
+            session,
+            serenity_http.clone(),
+            data.clone(),
         );
+        entry.insert(spawn_inactivity_monitor(
+            manager.clone(),
+            guild_id,
+            session,
+            serenity_http.clone(),
+            cache.clone(),
+            data.clone(),
+        ));
     }
 }
 
-async fn spawn_background_enqueue(
-    data: &crate::Data,
+/// Enqueues the first playable track of a collection inline so playback
+/// doesn't wait behind the per-guild enqueue lock. Returns that track, whether
+/// it started playing straight away, and the tracks left for the background
+/// task (blocked ones stay in so they're counted there).
+pub(crate) async fn enqueue_collection_head(
+    mut tracks: Vec<Track>,
+    handler_lock: &Arc<Mutex<Call>>,
+    serenity_ctx: &serenity::Context,
+    requester: Requester,
+    guild_id: GuildId,
+    data: &Data,
+) -> Result<(Track, bool, Vec<Track>), MusicError> {
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let Some(index) = tracks.iter().position(|t| settings.blocked_by(t).is_none()) else {
+        let rule = tracks
+            .first()
+            .and_then(|t| settings.blocked_by(t))
+            .map(ToString::to_string)
+            .unwrap_or_default();
+        return Err(MusicError::Blocked(rule));
+    };
+    ensure_queue_room(data, guild_id, &settings).await?;
+    let first = tracks.remove(index);
+
+    let idle = handler_lock.lock().await.queue().is_empty();
+    let search_query = match first.source {
+        TrackSource::Spotify => {
+            MusicService::spotify_to_youtube_query(&first, settings.prefer_topic)
+        }
+        TrackSource::YouTube => String::new(),
+    };
+    enqueue_track(
+        &first,
+        &search_query,
+        handler_lock,
+        serenity_ctx,
+        requester,
+        guild_id,
+        data,
+    )
+    .await;
+
+    Ok((first, idle, tracks))
+}
+
+/// Queues the rest of a collection in a background task, then lists and
+/// queues any `remaining` pages.
+pub(crate) async fn spawn_background_enqueue(
+    data: &Data,
     tracks: Vec<Track>,
-    http: &reqwest::Client,
+    remaining: Option<RemainingPages>,
     handler_lock: Arc<Mutex<Call>>,
-    serenity_http: Arc<Http>,
-    text_channel_id: ChannelId,
-    requester: String,
+    serenity_ctx: &serenity::Context,
+    requester: Requester,
     guild_id: GuildId,
 ) {
-    let enqueue_mutex = {
+    let cancel_flag = Arc::new(AtomicBool::new(false));
+    {
+        let mut cancels = data.enqueue_cancels.write().await;
+        track_cancel_flag(cancels.entry(guild_id).or_default(), cancel_flag.clone());
+    }
+    let ticket = {
         let mut locks = data.enqueue_locks.write().await;
-        locks.entry(guild_id).or_insert_with(|| Arc::new(Mutex::new(()))).clone()
+        locks.entry(guild_id).or_default().take_ticket(cancel_flag)
     };
-    let cancel_flag = Arc::new(AtomicBool::new(false));
-    data.enqueue_cancels.write().await.entry(guild_id).or_default().push(cancel_flag.clone());
 
-    tokio::spawn(enqueue_collection_tracks(
-        tracks,
-        http.clone(),
+    let job = BackgroundEnqueue {
         handler_lock,
-        serenity_http,
-        text_channel_id,
+        serenity_ctx: serenity_ctx.clone(),
         requester,
-        data.guild_queues.clone(),
         guild_id,
-        enqueue_mutex,
-        cancel_flag,
-        data.now_playing_messages.clone(),
-        data.repeat_states.clone(),
-    ));
+        ticket,
+        data: data.clone(),
+        added: 0,
+        blocked: 0,
+        left_out: 0,
+        name: None,
+        pages: None,
+    };
+    tokio::spawn(job.run(tracks, remaining));
 }
@@ -0,0 +1,239 @@
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use poise::serenity_prelude::{
+    self as serenity, Colour, ComponentInteractionDataKind, CreateActionRow, CreateButton,
+    CreateEmbed, CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption,
+};
+
+use crate::commands::permissions::is_dj;
+use crate::commands::play::remove_from_playback;
+use crate::domain::track::Track;
+use crate::services::error::MusicError;
+use crate::services::queue_service::QueueService;
+use crate::{Context, Error};
+
+const PRUNE_COLOR: Colour = Colour::new(0xED4245);
+/// Tracks per page, the most a select menu can offer.
+const PAGE_SIZE: usize = 25;
+/// How long the menu keeps responding after the last press.
+const PRUNE_TIMEOUT: Duration = Duration::from_secs(120);
+/// Discord's limit on a select option's label and description.
+const OPTION_MAX_CHARS: usize = 100;
+
+fn clip(text: String) -> String {
+    if text.chars().count() <= OPTION_MAX_CHARS {
+        return text;
+    }
+    format!("{}...", text.chars().take(OPTION_MAX_CHARS - 3).collect::<String>())
+}
+
+fn prune_embed(tracks: &[Track], picked: &BTreeSet<usize>, page: usize, pages: usize) -> CreateEmbed {
+    let description = if picked.is_empty() {
+        "Tick the tracks to remove, then press **Remove**. Picks on other pages are kept."
+            .to_string()
+    } else {
+        let mut lines = picked
+            .iter()
+            .map(|&i| format!("`{}.` **{}** - {}", i + 1, tracks[i].title, tracks[i].artist))
+            .collect::<Vec<_>>()
+            .join("\n");
+        // Stay well inside the embed description limit
+        if lines.chars().count() > 3500 {
+            lines = format!("{}\n…", lines.chars().take(3500).collect::<String>());
+        }
+        format!("Press **Remove** to take these out of the queue:\n{lines}")
+    };
+
+    CreateEmbed::new()
+        .title("Prune the queue")
+        .description(description)
+        .colour(PRUNE_COLOR)
+        .footer(CreateEmbedFooter::new(format!(
+            "Page {}/{pages} · {} pending tracks · {} picked",
+            page + 1,
+            tracks.len(),
+            picked.len()
+        )))
+}
+
+fn prune_components(
+    prefix: &str,
+    tracks: &[Track],
+    picked: &BTreeSet<usize>,
+    page: usize,
+    pages: usize,
+) -> Vec<CreateActionRow> {
+    let start = page * PAGE_SIZE;
+    let options: Vec<_> = tracks
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(PAGE_SIZE)
+        .map(|(i, track)| {
+            let duration = track.formatted_duration().unwrap_or_else(|| "--:--".to_string());
+            CreateSelectMenuOption::new(clip(format!("{}. {}", i + 1, track.title)), i.to_string())
+                .description(clip(format!("{} · {duration}", track.artist)))
+                .default_selection(picked.contains(&i))
+        })
+        .collect();
+    let count = options.len() as u8;
+
+    let menu = CreateSelectMenu::new(format!("{prefix}_pick"), CreateSelectMenuKind::String { options })
+        .placeholder("Pick tracks to remove")
+        .min_values(0)
+        .max_values(count);
+
+    let mut buttons = Vec::new();
+    if pages > 1 {
+        buttons.push(
+            CreateButton::new(format!("{prefix}_prev"))
+                .label("Previous")
+                .style(serenity::ButtonStyle::Secondary)
+                .disabled(page == 0),
+        );
+        buttons.push(
+            CreateButton::new(format!("{prefix}_next"))
+                .label("Next")
+                .style(serenity::ButtonStyle::Secondary)
+                .disabled(page + 1 >= pages),
+        );
+    }
+    buttons.push(
+        CreateButton::new(format!("{prefix}_confirm"))
+            .label(format!("Remove {}", picked.len()))
+            .style(serenity::ButtonStyle::Danger)
+            .disabled(picked.is_empty()),
+    );
+    buttons.push(
+        CreateButton::new(format!("{prefix}_cancel"))
+            .label("Cancel")
+            .style(serenity::ButtonStyle::Secondary),
+    );
+
+    vec![CreateActionRow::SelectMenu(menu), CreateActionRow::Buttons(buttons)]
+}
+
+fn closed(embed: CreateEmbed) -> CreateInteractionResponse {
+    CreateInteractionResponse::UpdateMessage(
+        CreateInteractionResponseMessage::new()
+            .embed(embed)
+            .components(Vec::new()),
+    )
+}
+
+/// Pick several pending tracks and remove them at once (DJ only)
+#[poise::command(slash_command, guild_only)]
+pub async fn prune(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    if !is_dj(ctx).await {
+        return Err(MusicError::NotDj.into());
+    }
+    let data = ctx.data();
+
+    let tracks = QueueService::list(&data.guild_queues, guild_id).await;
+    if tracks.is_empty() {
+        return Err(MusicError::EmptyQueue.into());
+    }
+
+    let pages = tracks.len().div_ceil(PAGE_SIZE);
+    let mut page = 0;
+    let mut picked = BTreeSet::new();
+
+    let prefix = format!("prune_{}", ctx.id());
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(prune_embed(&tracks, &picked, page, pages))
+                .components(prune_components(&prefix, &tracks, &picked, page, pages))
+                .ephemeral(true),
+        )
+        .await?;
+
+    // Picks refer to the snapshot taken above until they're confirmed
+    loop {
+        let filter_prefix = prefix.clone();
+        let Some(press) = serenity::ComponentInteractionCollector::new(ctx)
+            .author_id(ctx.author().id)
+            .filter(move |press| press.data.custom_id.starts_with(&filter_prefix))
+            .timeout(PRUNE_TIMEOUT)
+            .await
+        else {
+            let embed = CreateEmbed::new()
+                .title("Prune the queue")
+                .description("Timed out, nothing was removed.")
+                .colour(PRUNE_COLOR);
+            reply
+                .edit(ctx, poise::CreateReply::default().embed(embed).components(Vec::new()))
+                .await?;
+            break;
+        };
+
+        let custom_id = press.data.custom_id.as_str();
+        if custom_id.ends_with("_cancel") {
+            let embed = CreateEmbed::new()
+                .title("Prune cancelled")
+                .description("Nothing was removed.")
+                .colour(PRUNE_COLOR);
+            press.create_response(ctx, closed(embed)).await?;
+            break;
+        }
+
+        if custom_id.ends_with("_confirm") {
+            let picks: Vec<(usize, String)> =
+                picked.iter().map(|&i| (i, tracks[i].url.clone())).collect();
+            let (removed, missing) =
+                QueueService::remove_picked(&data.guild_queues, guild_id, &picks).await;
+
+            let manager = songbird::get(ctx.serenity_context())
+                .await
+                .expect("Songbird not registered");
+            if let Some(handler_lock) = manager.get(guild_id) {
+                let handle_ids: Vec<_> = removed.iter().filter_map(|t| t.handle_id).collect();
+                remove_from_playback(&handler_lock, &handle_ids).await;
+            }
+
+            let mut description = match removed.len() {
+                1 => "Removed 1 track.".to_string(),
+                n => format!("Removed {n} tracks."),
+            };
+            if missing > 0 {
+                description.push_str(&format!(
+                    "\n{missing} of the picked tracks had already left the queue."
+                ));
+            }
+            let embed = CreateEmbed::new()
+                .title("Queue pruned")
+                .description(description)
+                .colour(PRUNE_COLOR);
+            press.create_response(ctx, closed(embed)).await?;
+            break;
+        }
+
+        if let ComponentInteractionDataKind::StringSelect { values } = &press.data.kind {
+            // The menu only shows this page, so its picks replace this page's
+            let start = page * PAGE_SIZE;
+            picked.retain(|i| !(start..start + PAGE_SIZE).contains(i));
+            picked.extend(
+                values
+                    .iter()
+                    .filter_map(|value| value.parse::<usize>().ok())
+                    .filter(|&i| i < tracks.len()),
+            );
+        } else if custom_id.ends_with("_next") {
+            page = (page + 1).min(pages - 1);
+        } else if custom_id.ends_with("_prev") {
+            page = page.saturating_sub(1);
+        }
+
+        let response = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .embed(prune_embed(&tracks, &picked, page, pages))
+                .components(prune_components(&prefix, &tracks, &picked, page, pages)),
+        );
+        press.create_response(ctx, response).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use poise::serenity_prelude::{
+    self as serenity, Colour, CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter,
+    CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+
+use crate::commands::permissions::is_dj;
+use crate::domain::track::{Requester, Track};
+use crate::services::error::MusicError;
+use crate::services::queue_service::QueueService;
+use crate::{Context, Error};
+
+const AUDIT_COLOR: Colour = Colour::new(0x5865F2);
+const PAGE_SIZE: usize = 10;
+/// How long the page buttons keep responding.
+const PAGE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Inspect the music queue
+#[poise::command(slash_command, guild_only, subcommands("audit"), subcommand_required)]
+pub async fn queue(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// "@alice: 23, @bob: 4", most tracks first.
+fn requester_counts(tracks: &[Track]) -> String {
+    let mut counts: Vec<(Option<Requester>, usize)> = Vec::new();
+    for track in tracks {
+        match counts.iter_mut().find(|(user, _)| *user == track.requested_by) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((track.requested_by, 1)),
+        }
+    }
+    counts.sort_by(|a, b| b.1.cmp(&a.1));
+
+    counts
+        .iter()
+        .map(|(user, count)| match user {
+            Some(requester) => format!("{requester}: {count}"),
+            None => format!("unknown: {count}"),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn audit_line(position: usize, track: &Track) -> String {
+    let source = track.source.display_name();
+    let duration = track.formatted_duration().unwrap_or_else(|| "--:--".to_string());
+    let requester = match track.requested_by {
+        Some(requester) => requester.to_string(),
+        None => "unknown".to_string(),
+    };
+    let added = track
+        .enqueued_at
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| format!("<t:{}:R>", d.as_secs()))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    format!(
+        "`{position}.` **{}** - {}\n{source} · `{duration}` · {requester} · added {added}",
+        track.title, track.artist
+    )
+}
+
+fn audit_embed(tracks: &[Track], summary: &str, page: usize, pages: usize) -> CreateEmbed {
+    let start = page * PAGE_SIZE;
+    let lines = tracks
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(PAGE_SIZE)
+        .map(|(i, track)| audit_line(i + 1, track))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    CreateEmbed::new()
+        .title("Queue audit")
+        .description(format!("{summary}\n\n{lines}"))
+        .colour(AUDIT_COLOR)
+        .footer(CreateEmbedFooter::new(format!(
+            "Page {}/{pages} · {} pending tracks",
+            page + 1,
+            tracks.len()
+        )))
+}
+
+fn page_buttons(prefix: &str, page: usize, pages: usize) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{prefix}_prev"))
+            .label("Previous")
+            .style(serenity::ButtonStyle::Secondary)
+            .disabled(page == 0),
+        CreateButton::new(format!("{prefix}_next"))
+            .label("Next")
+            .style(serenity::ButtonStyle::Secondary)
+            .disabled(page + 1 >= pages),
+    ])]
+}
+
+/// List every pending track with who queued it and when (DJ only)
+#[poise::command(slash_command, guild_only)]
+async fn audit(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    if !is_dj(ctx).await {
+        return Err(MusicError::NotDj.into());
+    }
+
+    let tracks = QueueService::list(&ctx.data().guild_queues, guild_id).await;
+    if tracks.is_empty() {
+        return Err(MusicError::EmptyQueue.into());
+    }
+
+    let summary = requester_counts(&tracks);
+    let pages = tracks.len().div_ceil(PAGE_SIZE);
+    let mut page = 0;
+
+    let prefix = format!("audit_{}", ctx.id());
+    let mut reply = poise::CreateReply::default()
+        .embed(audit_embed(&tracks, &summary, page, pages))
+        .ephemeral(true);
+    if pages > 1 {
+        reply = reply.components(page_buttons(&prefix, page, pages));
+    }
+    ctx.send(reply).await?;
+
+    if pages == 1 {
+        return Ok(());
+    }
+
+    // Page through the snapshot taken above until the buttons time out
+    loop {
+        let filter_prefix = prefix.clone();
+        let Some(press) = serenity::ComponentInteractionCollector::new(ctx)
+            .author_id(ctx.author().id)
+            .filter(move |press| press.data.custom_id.starts_with(&filter_prefix))
+            .timeout(PAGE_TIMEOUT)
+            .await
+        else {
+            break;
+        };
+
+        if press.data.custom_id.ends_with("_next") {
+            page = (page + 1).min(pages - 1);
+        } else {
+            page = page.saturating_sub(1);
+        }
+
+        let response = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .embed(audit_embed(&tracks, &summary, page, pages))
+                .components(page_buttons(&prefix, page, pages)),
+        );
+        press.create_response(ctx, response).await?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,57 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::Path;
+
+use poise::serenity_prelude::{self as serenity, ApplicationId, CreateCommand, GuildId};
+
+use crate::{Data, Error};
+
+/// Fingerprint of the command set as Discord receives it, tied to the
+/// application so two bots sharing a file can't mistake each other's.
+fn command_hash(application_id: ApplicationId, commands: &[CreateCommand]) -> String {
+    let mut hasher = DefaultHasher::new();
+    serde_json::to_vec(commands).unwrap_or_default().hash(&mut hasher);
+    format!("{application_id}:{:016x}", hasher.finish())
+}
+
+/// Registers the slash commands with Discord. With `dev_guild` they go to
+/// that guild only, where changes show up at once. Otherwise they're
+/// registered globally, skipped when the set matches the fingerprint in
+/// `hash_file` from the last registration, since each global registration
+/// takes up to an hour to spread and counts against a daily limit.
+pub async fn register_commands(
+    ctx: &serenity::Context,
+    application_id: ApplicationId,
+    commands: &[poise::Command<Data, Error>],
+    dev_guild: Option<GuildId>,
+    hash_file: Option<&Path>,
+) -> Result<(), serenity::Error> {
+    if let Some(guild_id) = dev_guild {
+        poise::builtins::register_in_guild(ctx, commands, guild_id).await?;
+        tracing::info!(
+            "Registered {} commands in dev guild {guild_id}, skipping global registration",
+            commands.len()
+        );
+        return Ok(());
+    }
+
+    let created = poise::builtins::create_application_commands(commands);
+    let count = created.len();
+    let hash = command_hash(application_id, &created);
+    let previous = match hash_file {
+        Some(path) => tokio::fs::read_to_string(path).await.ok(),
+        None => None,
+    };
+    if previous.as_deref().map(str::trim) == Some(hash.as_str()) {
+        tracing::info!("Global commands unchanged, skipped re-registering {count} commands");
+        return Ok(());
+    }
+
+    serenity::Command::set_global_commands(ctx, created).await?;
+    tracing::info!("Registered {count} commands globally");
+    if let Some(path) = hash_file {
+        if let Err(e) = tokio::fs::write(path, &hash).await {
+            tracing::warn!("Failed to record the command set in {}: {e}", path.display());
+        }
+    }
+    Ok(())
+}
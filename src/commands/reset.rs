@@ -0,0 +1,90 @@
+use poise::serenity_prelude::{Colour, CreateEmbed};
+
+use crate::commands::cleanup::{cleanup_guild, DisconnectReason};
+use crate::services::error::MusicError;
+use crate::services::queue_service::QueueService;
+use crate::{Context, Error};
+
+const RESET_COLOR: Colour = Colour::new(0xFEE75C);
+
+/// Tear down all of the bot's state for this server
+#[poise::command(
+    slash_command,
+    guild_only,
+    default_member_permissions = "ADMINISTRATOR",
+    required_permissions = "ADMINISTRATOR"
+)]
+pub async fn reset(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    ctx.defer_ephemeral().await?;
+    let data = ctx.data();
+
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+
+    // Take stock before tearing anything down so the reply can report it
+    let had_call = manager.get(guild_id).is_some();
+    let tracks = QueueService::list(&data.guild_queues, guild_id).await.len()
+        + usize::from(QueueService::current(&data.guild_queues, guild_id).await.is_some());
+    let enqueues = data
+        .enqueue_cancels
+        .read()
+        .await
+        .get(&guild_id)
+        .map_or(0, Vec::len);
+    let had_monitor = data.inactivity_handles.read().await.contains_key(&guild_id);
+    let had_np_message = data.now_playing_messages.read().await.contains_key(&guild_id);
+
+    let reason = DisconnectReason::Reset(ctx.author().id);
+    cleanup_guild(guild_id, data, &ctx.serenity_context().http, reason).await;
+
+    // Drop the Call outright rather than leaving, in case it's wedged
+    if had_call {
+        if let Err(e) = manager.remove(guild_id).await {
+            tracing::warn!("Failed to remove voice call for guild {guild_id}: {e}");
+        }
+    }
+
+    data.join_locks.write().await.remove(&guild_id);
+    data.enqueue_locks.write().await.remove(&guild_id);
+    data.now_playing_locks.write().await.remove(&guild_id);
+    data.guild_queues.write().await.remove(&guild_id);
+
+    let mut lines = Vec::new();
+    if had_call {
+        lines.push("Dropped the voice connection".to_string());
+    }
+    if tracks > 0 {
+        lines.push(format!("Cleared `{tracks}` queued tracks"));
+    }
+    if enqueues > 0 {
+        lines.push(format!("Cancelled `{enqueues}` background enqueues"));
+    }
+    if had_monitor {
+        lines.push("Stopped the inactivity monitor".to_string());
+    }
+    if had_np_message {
+        lines.push("Deleted the Now Playing message".to_string());
+    }
+
+    let description = if lines.is_empty() {
+        "Nothing was active. Locks and cached state were cleared anyway.".to_string()
+    } else {
+        let mut description = lines
+            .iter()
+            .map(|line| format!("• {line}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        description.push_str("\n\nLocks and cached state were cleared too. Settings are kept.");
+        description
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Reset complete")
+        .description(description)
+        .colour(RESET_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use poise::serenity_prelude::{Colour, CreateEmbed, CreateEmbedFooter};
+
+use crate::domain::track::{format_secs, Track, TrackSource};
+use crate::services::error::MusicError;
+use crate::services::music_service::{MusicService, SpotifyUrl};
+use crate::services::settings_service::SettingsService;
+use crate::{Context, Error};
+
+const RESOLVE_COLOR: Colour = Colour::new(0x5865F2);
+/// YouTube results shown for a Spotify track's search.
+const CANDIDATES: u32 = 3;
+/// Longest title and channel name shown per result, so three results fit
+/// in an embed field's 1024 characters.
+const TITLE_MAX_CHARS: usize = 60;
+const CHANNEL_MAX_CHARS: usize = 40;
+
+fn clip(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    format!("{}...", text.chars().take(max - 3).collect::<String>())
+}
+
+/// "+0:04" or "-1:10": how much longer or shorter `candidate` is.
+fn length_delta(candidate: Duration, expected: Duration) -> String {
+    if candidate >= expected {
+        format!("+{}", format_secs((candidate - expected).as_secs()))
+    } else {
+        format!("-{}", format_secs((expected - candidate).as_secs()))
+    }
+}
+
+/// "`1.` [Title](url) — Channel · `3:07` (+0:02) · channel matches the
+/// artist", comparing a search result against the Spotify track.
+fn candidate_line(position: usize, candidate: &Track, wanted: &Track) -> String {
+    let mut line = format!(
+        "`{position}.` [{}]({}) — {}",
+        clip(&candidate.title, TITLE_MAX_CHARS).replace(['[', ']'], ""),
+        candidate.url,
+        clip(&candidate.artist, CHANNEL_MAX_CHARS)
+    );
+    if let Some(length) = candidate.duration {
+        line.push_str(&format!(" · `{}`", format_secs(length.as_secs())));
+        if let Some(expected) = wanted.duration {
+            line.push_str(&format!(" ({})", length_delta(length, expected)));
+        }
+    }
+    let channel = candidate.artist.to_lowercase();
+    if channel.ends_with("- topic") {
+        line.push_str(" · Topic upload");
+    }
+    if channel.contains(&wanted.artist.to_lowercase()) {
+        line.push_str(" · channel matches the artist");
+    }
+    if position == 1 {
+        line.push_str(" · **plays**");
+    }
+    line
+}
+
+/// Show how a link or search would resolve, without queueing anything
+#[poise::command(slash_command, guild_only)]
+pub async fn resolve(
+    ctx: Context<'_>,
+    #[description = "Spotify or YouTube link to a single track, or a search query"]
+    query: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let query = query.trim();
+    let collection = MusicService::is_youtube_playlist_url(query)
+        || matches!(
+            MusicService::parse_spotify_url(query),
+            Some(SpotifyUrl::Playlist(_) | SpotifyUrl::Album(_))
+        );
+    if collection {
+        return Err(MusicError::NotSingleTrack.into());
+    }
+    ctx.defer_ephemeral().await?;
+
+    let data = ctx.data();
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let mut tracks = data
+        .music_service
+        .resolve_tracks(query, settings.search_preference, settings.prefer_topic)
+        .await?;
+    // A short link can still turn out to be a playlist
+    if tracks.len() > 1 {
+        return Err(MusicError::NotSingleTrack.into());
+    }
+    let track = tracks.remove(0);
+
+    let length = track
+        .formatted_duration()
+        .map(|d| format!(" · `{d}`"))
+        .unwrap_or_default();
+    let source = track.source.display_name();
+    let mut embed = CreateEmbed::new()
+        .title("Resolution")
+        .field(
+            "Source",
+            format!("{source}: [**{}** - {}]({}){length}", track.title, track.artist, track.url),
+            false,
+        )
+        .colour(RESOLVE_COLOR);
+
+    embed = match track.source {
+        TrackSource::YouTube => embed.field(
+            "Playback",
+            "Plays this YouTube video directly, there's nothing to match.",
+            false,
+        ),
+        TrackSource::Spotify => {
+            let search = MusicService::spotify_to_youtube_query(&track, settings.prefer_topic);
+            let candidates = data.music_service.youtube_candidates(&search, CANDIDATES).await;
+            let lines = if candidates.is_empty() {
+                "YouTube returned nothing for this search.".to_string()
+            } else {
+                candidates
+                    .iter()
+                    .enumerate()
+                    .map(|(i, candidate)| candidate_line(i + 1, candidate, &track))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            embed
+                .field("YouTube search", format!("`{search}`"), false)
+                .field("Top results", lines, false)
+                .footer(CreateEmbedFooter::new(
+                    "yt-dlp plays the first result of this search; nothing else scores them. \
+                     Its order can differ slightly from YouTube's API.",
+                ))
+        }
+    };
+
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,292 @@
+use std::time::Duration;
+
+use chrono::{Local, Utc};
+use poise::serenity_prelude::{
+    self as serenity, Colour, CreateEmbed, CreateMessage, GuildChannel, GuildId,
+};
+
+use crate::commands::embeds::linked_title;
+use crate::commands::play::{
+    bot_voice_channel, enqueue_collection_head, ensure_voice_connection, spawn_background_enqueue,
+};
+use crate::domain::schedule::{parse_time, ScheduledJob};
+use crate::domain::track::Requester;
+use crate::services::error::MusicError;
+use crate::services::schedule_service::ScheduleService;
+use crate::services::settings_service::SettingsService;
+use crate::{Context, Data, Error};
+
+const SCHEDULE_COLOR: Colour = Colour::new(0x57F287);
+/// Pending jobs a guild can have at once.
+const MAX_SCHEDULED: usize = 25;
+/// How often the scheduler looks for due jobs.
+const SCHEDULE_CHECK: Duration = Duration::from_secs(15);
+/// How late a job may start, e.g. after a restart, before it counts as
+/// missed instead.
+const MISSED_AFTER: chrono::TimeDelta = chrono::TimeDelta::minutes(10);
+
+/// `<t:…:F>` renders in each reader's own timezone.
+fn discord_time(job: &ScheduledJob) -> String {
+    format!("<t:{}:F>", job.at.timestamp())
+}
+
+/// Writes the pending jobs to the store, if there is one. Call it after
+/// changing `scheduled_jobs`.
+async fn persist_schedules(data: &Data) {
+    if let Some(store) = &data.schedule_store {
+        ScheduleService::save(&data.scheduled_jobs, store).await;
+    }
+}
+
+/// Start playing something in a voice channel at a set time
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("add", "list", "remove"),
+    subcommand_required,
+    default_member_permissions = "MANAGE_GUILD"
+)]
+pub async fn schedule(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Schedule a track, playlist, or search to start playing
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn add(
+    ctx: Context<'_>,
+    #[description = "When to start, as HH:MM or a date like 2026-10-23T20:00 (bot's local time)"]
+    time: String,
+    #[description = "Voice channel to play in"]
+    #[channel_types("Voice", "Stage")]
+    channel: GuildChannel,
+    #[description = "URL or search query"] query: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
+
+    let at = parse_time(&time, Local::now()).ok_or_else(|| MusicError::InvalidTime(time.clone()))?;
+    if at <= Utc::now() {
+        return Err(MusicError::TimeInPast.into());
+    }
+    if ScheduleService::list(&data.scheduled_jobs, guild_id).await.len() >= MAX_SCHEDULED {
+        return Err(MusicError::TooManySchedules(MAX_SCHEDULED).into());
+    }
+
+    let job = ScheduleService::add(
+        &data.scheduled_jobs,
+        guild_id,
+        ScheduledJob {
+            id: 0,
+            at,
+            voice_channel: channel.id,
+            text_channel: ctx.channel_id(),
+            query: query.trim().to_string(),
+            created_by: ctx.author().id,
+        },
+    )
+    .await;
+    persist_schedules(data).await;
+
+    let embed = CreateEmbed::new()
+        .title("Scheduled")
+        .description(format!(
+            "`{}` will start in <#{}> at {}.\nUse `/schedule remove id:{}` to cancel it.",
+            job.query,
+            job.voice_channel,
+            discord_time(&job),
+            job.id
+        ))
+        .colour(SCHEDULE_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Show this server's scheduled plays
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn list(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let jobs = ScheduleService::list(&ctx.data().scheduled_jobs, guild_id).await;
+
+    let description = if jobs.is_empty() {
+        "Nothing scheduled.".to_string()
+    } else {
+        jobs.iter()
+            .map(|job| {
+                format!(
+                    "`{}` {} in <#{}> — `{}` (by <@{}>)",
+                    job.id,
+                    discord_time(job),
+                    job.voice_channel,
+                    job.query,
+                    job.created_by
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let embed = CreateEmbed::new()
+        .title("Scheduled plays")
+        .description(description)
+        .colour(SCHEDULE_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+/// Cancel a scheduled play
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn remove(
+    ctx: Context<'_>,
+    #[description = "Id shown by /schedule list"] id: u32,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let job = ScheduleService::remove(&ctx.data().scheduled_jobs, guild_id, id)
+        .await
+        .ok_or(MusicError::ScheduleNotFound(id))?;
+    persist_schedules(ctx.data()).await;
+
+    let embed = CreateEmbed::new()
+        .title("Schedule cancelled")
+        .description(format!("`{}` won't play at {}.", job.query, discord_time(&job)))
+        .colour(SCHEDULE_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
+
+async fn announce(http: &serenity::Http, job: &ScheduledJob, content: String) {
+    let msg = CreateMessage::new().content(content);
+    if let Err(e) = job.text_channel.send_message(http, msg).await {
+        tracing::debug!("Failed to post schedule notice in {}: {e}", job.text_channel);
+    }
+}
+
+/// Joins the job's voice channel and queues its query like `/play` would,
+/// unless the bot is already busy in another channel.
+async fn run_job(
+    serenity_ctx: &serenity::Context,
+    data: &Data,
+    guild_id: GuildId,
+    job: &ScheduledJob,
+) -> Result<(), MusicError> {
+    let manager = songbird::get(serenity_ctx)
+        .await
+        .expect("Songbird not registered");
+
+    let in_session = data.inactivity_handles.read().await.contains_key(&guild_id);
+    let current = bot_voice_channel(&manager, guild_id).await;
+    if let Some(current) = current.filter(|c| in_session && *c != job.voice_channel) {
+        tracing::info!(
+            "Skipping scheduled job {} in guild {guild_id}: busy in channel {current}",
+            job.id
+        );
+        announce(
+            &serenity_ctx.http,
+            job,
+            format!(
+                "Skipped the scheduled play of `{}` in <#{}> — I was already playing in <#{current}>.",
+                job.query, job.voice_channel
+            ),
+        )
+        .await;
+        return Ok(());
+    }
+
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let tracks = data
+        .music_service
+        .resolve_tracks(&job.query, settings.search_preference, settings.prefer_topic)
+        .await?;
+
+    let handler_lock = ensure_voice_connection(
+        serenity_ctx,
+        &manager,
+        data,
+        guild_id,
+        job.voice_channel,
+        job.text_channel,
+    )
+    .await?;
+    let (first, _, rest) = enqueue_collection_head(
+        tracks,
+        &handler_lock,
+        serenity_ctx,
+        Requester::Scheduler,
+        guild_id,
+        data,
+    )
+    .await?;
+
+    let more = match rest.len() {
+        0 => String::new(),
+        1 => " and 1 more track".to_string(),
+        n => format!(" and {n} more tracks"),
+    };
+    announce(
+        &serenity_ctx.http,
+        job,
+        format!(
+            "Scheduled play started in <#{}>: {}{more}",
+            job.voice_channel,
+            linked_title(&first)
+        ),
+    )
+    .await;
+
+    if !rest.is_empty() {
+        spawn_background_enqueue(
+            data,
+            rest,
+            None,
+            handler_lock,
+            serenity_ctx,
+            Requester::Scheduler,
+            guild_id,
+        )
+        .await;
+    }
+    Ok(())
+}
+
+/// Starts due jobs for the lifetime of the bot.
+pub fn spawn_scheduler(serenity_ctx: serenity::Context, data: Data) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(SCHEDULE_CHECK);
+        loop {
+            interval.tick().await;
+            let now = Utc::now();
+            let due = ScheduleService::take_due(&data.scheduled_jobs, now).await;
+            if !due.is_empty() {
+                persist_schedules(&data).await;
+            }
+            for (guild_id, job) in due {
+                // Only happens when the bot was down at the time
+                if now - job.at > MISSED_AFTER {
+                    tracing::info!("Dropping scheduled job {} in guild {guild_id}: missed", job.id);
+                    let notice = format!(
+                        "Missed the scheduled play of `{}` at {} — I was offline then.",
+                        job.query,
+                        discord_time(&job)
+                    );
+                    announce(&serenity_ctx.http, &job, notice).await;
+                    continue;
+                }
+                let serenity_ctx = serenity_ctx.clone();
+                let data = data.clone();
+                // Each job resolves and joins on its own so a slow lookup
+                // doesn't hold up the others
+                tokio::spawn(async move {
+                    if let Err(e) = run_job(&serenity_ctx, &data, guild_id, &job).await {
+                        tracing::warn!(
+                            "Scheduled play of `{}` failed in guild {guild_id}: {e}",
+                            job.query
+                        );
+                        let notice = format!("The scheduled play of `{}` couldn't start: {e}", job.query);
+                        announce(&serenity_ctx.http, &job, notice).await;
+                    }
+                });
+            }
+        }
+    });
+}
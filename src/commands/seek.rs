@@ -0,0 +1,139 @@
+use std::time::Duration;
+
+use poise::serenity_prelude::AutocompleteChoice;
+
+use crate::commands::now_playing::current_or_next;
+use crate::commands::permissions::ensure_unlocked;
+use crate::domain::chapters::parse_timestamp;
+use crate::domain::track::{format_secs, Track};
+use crate::services::error::MusicError;
+use crate::services::queue_service::QueueService;
+use crate::{Context, Error};
+
+/// Relative jumps offered alongside the chapters, as `(label, value)`.
+const QUICK_PICKS: [(&str, &str); 3] = [
+    ("+30 seconds", "+30"),
+    ("+60 seconds", "+60"),
+    ("-30 seconds", "-30"),
+];
+/// Discord's limit on an autocomplete choice's name, in characters.
+const CHOICE_MAX_CHARS: usize = 100;
+
+/// Where `/seek` was asked to go, relative to the start of the track.
+enum SeekTarget {
+    At(Duration),
+    Forward(Duration),
+    Back(Duration),
+}
+
+impl SeekTarget {
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        // Autocomplete labels like "0:00 (start)" may be typed out in full
+        let value = value.split_whitespace().next()?;
+        if let Some(rest) = value.strip_prefix('+') {
+            parse_timestamp(rest).map(SeekTarget::Forward)
+        } else if let Some(rest) = value.strip_prefix('-') {
+            parse_timestamp(rest).map(SeekTarget::Back)
+        } else {
+            parse_timestamp(value).map(SeekTarget::At)
+        }
+    }
+
+    fn resolve(&self, elapsed: Duration) -> Duration {
+        match *self {
+            SeekTarget::At(at) => at,
+            SeekTarget::Forward(by) => elapsed + by,
+            SeekTarget::Back(by) => elapsed.saturating_sub(by),
+        }
+    }
+}
+
+/// Where the track starts in the underlying media.
+fn span_start(track: &Track) -> Duration {
+    track.span.map_or(Duration::ZERO, |span| span.start)
+}
+
+/// The track's chapters that fall inside what it plays, with their offsets
+/// from the start of the track.
+fn chapter_offsets(track: &Track) -> impl Iterator<Item = (Duration, &str)> {
+    let start = span_start(track);
+    let end = track.span.and_then(|span| span.end);
+    track
+        .chapters
+        .iter()
+        .filter(move |c| c.start >= start && end.is_none_or(|end| c.start < end))
+        .map(move |c| (c.start - start, c.title.as_str()))
+}
+
+/// `(name, value)` choices for the position argument: the start, the track's
+/// chapters, then the relative quick picks.
+fn position_choices(track: &Track) -> Vec<(String, String)> {
+    let mut choices = vec![("0:00 (start)".to_string(), "0:00".to_string())];
+    choices.extend(chapter_offsets(track).map(|(offset, title)| {
+        let at = format_secs(offset.as_secs());
+        let name: String = format!("{at} {title}").chars().take(CHOICE_MAX_CHARS).collect();
+        (name, at)
+    }));
+    choices.extend(QUICK_PICKS.iter().map(|(name, value)| (name.to_string(), value.to_string())));
+    choices
+}
+
+async fn autocomplete_position(ctx: Context<'_>, partial: &str) -> Vec<AutocompleteChoice> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Vec::new();
+    };
+    let Some(track) = QueueService::current(&ctx.data().guild_queues, guild_id).await else {
+        return Vec::new();
+    };
+
+    let partial = partial.trim().to_lowercase();
+    position_choices(&track)
+        .into_iter()
+        .filter(|(name, _)| name.to_lowercase().contains(&partial))
+        .take(25)
+        .map(|(name, value)| AutocompleteChoice::new(name, value))
+        .collect()
+}
+
+/// Jump to a point in the current track
+#[poise::command(slash_command, guild_only)]
+pub async fn seek(
+    ctx: Context<'_>,
+    #[description = "A time like 1:23, a chapter, or +30 / -30 seconds"]
+    #[autocomplete = "autocomplete_position"]
+    position: String,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    ensure_unlocked(ctx).await?;
+    let target =
+        SeekTarget::parse(&position).ok_or_else(|| MusicError::InvalidPosition(position.clone()))?;
+
+    let data = ctx.data();
+    if !data.voice.is_connected(guild_id) {
+        return Err(MusicError::NotConnected.into());
+    }
+    let current = current_or_next(data, guild_id).await?;
+    let track = QueueService::find_by_handle(&data.guild_queues, guild_id, current.uuid())
+        .await
+        .ok_or(MusicError::EmptyQueue)?;
+
+    let position = current.get_info().await.map(|info| info.position).unwrap_or_default();
+    let offset = target.resolve(track.elapsed(position));
+    if let Some(length) = track.duration.filter(|length| offset >= *length) {
+        return Err(MusicError::SeekPastEnd(format_secs(length.as_secs())).into());
+    }
+
+    if let Err(e) = current.seek_async(span_start(&track) + offset).await {
+        tracing::warn!("Seek failed in guild {guild_id}: {e}");
+        return Err(MusicError::SeekFailed.into());
+    }
+
+    let at = format_secs(offset.as_secs());
+    let msg = match chapter_offsets(&track).find(|(start, _)| *start == offset) {
+        Some((_, chapter)) => format!("Jumped to **{chapter}** (`{at}`) in **{}**.", track.title),
+        None => format!("Jumped to `{at}` in **{}**.", track.title),
+    };
+    ctx.say(msg).await?;
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use poise::serenity_prelude::{
+    self as serenity, Cache, ChannelId, CreateAllowedMentions, CreateMessage, EditMessage, GuildId,
+    Http, MessageId, VoiceState,
+};
+use songbird::tracks::PlayMode;
+use tokio::sync::RwLock;
+
+use crate::commands::board::refresh_board;
+use crate::commands::embeds::{card_content, plain_now_playing};
+use crate::commands::now_playing::build_now_playing_components;
+use crate::commands::stale_input::{note_paused, resume_playback};
+use crate::commands::standby::{is_on_standby, STANDBY_NOTICE};
+use crate::domain::settings::NowPlayingMode;
+use crate::commands::cleanup::now_playing_lock;
+use crate::services::queue_service::QueueService;
+use crate::services::settings_service::SettingsService;
+use crate::Data;
+
+/// Guilds where the bot is server-muted.
+pub type ServerMutes = Arc<RwLock<HashMap<GuildId, MutePause>>>;
+
+/// What the bot did when it was server-muted, so unmuting can undo it.
+#[derive(Debug, Clone, Copy)]
+pub struct MutePause {
+    /// Playback was running and got paused, so unmuting resumes it. A track
+    /// someone had already paused stays paused.
+    resume: bool,
+    /// The "I've been muted" notice, edited once the mute is lifted.
+    notice: Option<(ChannelId, MessageId)>,
+}
+
+/// Pauses playback while a moderator has the bot server-muted, since nobody
+/// can hear it, and resumes once they unmute it. Only the server mute counts:
+/// the bot never mutes itself, and being suppressed on a stage is up to the
+/// stage's moderators.
+pub async fn handle_voice_state_update(ctx: &serenity::Context, new: &VoiceState, data: &Data) {
+    let Some(guild_id) = new.guild_id else {
+        return;
+    };
+    if new.user_id != ctx.cache.current_user().id {
+        return;
+    }
+
+    let muted = new.mute && new.channel_id.is_some();
+    let was_muted = data.server_mutes.read().await.contains_key(&guild_id);
+    if muted == was_muted {
+        return;
+    }
+
+    let manager = songbird::get(ctx).await.expect("Songbird not registered");
+    if muted {
+        pause_for_mute(ctx, &manager, guild_id, data).await;
+        return;
+    }
+
+    let pause = data.server_mutes.write().await.remove(&guild_id);
+    // Leaving the channel also clears the mute; there's nothing to resume
+    if let (Some(pause), Some(_)) = (pause, new.channel_id) {
+        resume_after_mute(ctx, &manager, guild_id, data, pause).await;
+    }
+}
+
+async fn pause_for_mute(
+    ctx: &serenity::Context,
+    manager: &songbird::Songbird,
+    guild_id: GuildId,
+    data: &Data,
+) {
+    let current = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.queue().current(),
+        None => None,
+    };
+    let mut resume = false;
+    if let Some(current) = &current {
+        let playing = current
+            .get_info()
+            .await
+            .is_ok_and(|info| info.playing == PlayMode::Play);
+        resume = playing && current.pause().is_ok();
+    }
+    if resume {
+        note_paused(data, guild_id).await;
+    }
+
+    // Recorded before the notice goes out so a quick unmute still finds it
+    data.server_mutes
+        .write()
+        .await
+        .insert(guild_id, MutePause { resume, notice: None });
+    tracing::info!("Server-muted in guild {guild_id} (paused playback: {resume})");
+
+    let text_channel = data.text_channels.read().await.get(&guild_id).copied();
+    if let Some(channel_id) = text_channel {
+        let content = if resume {
+            "I've been muted — playback paused. It'll pick up again once I'm unmuted."
+        } else {
+            "I've been muted, so nobody can hear me until I'm unmuted."
+        };
+        let msg = CreateMessage::new().content(content);
+        if let Ok(msg) = channel_id.send_message(&ctx.http, msg).await {
+            if let Some(pause) = data.server_mutes.write().await.get_mut(&guild_id) {
+                pause.notice = Some((channel_id, msg.id));
+            }
+        }
+    }
+
+    refresh_now_playing(manager, &ctx.http, &ctx.cache, data, guild_id).await;
+}
+
+async fn resume_after_mute(
+    ctx: &serenity::Context,
+    manager: &songbird::Songbird,
+    guild_id: GuildId,
+    data: &Data,
+    pause: MutePause,
+) {
+    if pause.resume {
+        let current = match manager.get(guild_id) {
+            Some(handler_lock) => handler_lock.lock().await.queue().current(),
+            None => None,
+        };
+        if let Some(current) = current {
+            resume_playback(ctx, data, guild_id, &current).await;
+        }
+    }
+    tracing::info!("Unmuted in guild {guild_id} (resumed playback: {})", pause.resume);
+
+    if let Some((channel_id, message_id)) = pause.notice {
+        let content = if pause.resume {
+            "I've been unmuted — playback resumed."
+        } else {
+            "I've been unmuted."
+        };
+        let edit = EditMessage::new().content(content);
+        if let Err(e) = channel_id.edit_message(&ctx.http, message_id, edit).await {
+            tracing::debug!("Failed to update mute notice in guild {guild_id}: {e}");
+        }
+    }
+
+    refresh_now_playing(manager, &ctx.http, &ctx.cache, data, guild_id).await;
+}
+
+/// Brings the Now Playing buttons, or the whole session board, in line with
+/// the new pause and standby state.
+pub(crate) async fn refresh_now_playing(
+    manager: &songbird::Songbird,
+    http: &Http,
+    cache: &Cache,
+    data: &Data,
+    guild_id: GuildId,
+) {
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    if settings.now_playing_mode == NowPlayingMode::Board {
+        refresh_board(manager, http, data, guild_id).await;
+        return;
+    }
+
+    let np_lock = now_playing_lock(data, guild_id).await;
+    let _guard = np_lock.lock().await;
+    let Some(cards) = data.now_playing_messages.read().await.get(&guild_id).copied() else {
+        return;
+    };
+
+    let current = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.queue().current(),
+        None => None,
+    };
+    let paused = match &current {
+        Some(current) => current
+            .get_info()
+            .await
+            .is_ok_and(|info| info.playing == PlayMode::Pause),
+        None => false,
+    };
+    let repeating = data
+        .repeat_states
+        .read()
+        .await
+        .get(&guild_id)
+        .copied()
+        .unwrap_or(false);
+
+    let notice = is_on_standby(data, guild_id).await.then_some(STANDBY_NOTICE);
+    let plain = match QueueService::current(&data.guild_queues, guild_id).await {
+        Some(track) if settings.plain_text_announcements => {
+            Some(plain_now_playing(http, cache, guild_id, &track).await)
+        }
+        _ => None,
+    };
+    let content = card_content(notice, plain.as_deref());
+    let components = build_now_playing_components(guild_id, paused, repeating);
+    for (channel_id, message_id) in cards.iter() {
+        let edit = EditMessage::new()
+            .content(content.clone())
+            .components(components.clone())
+            .allowed_mentions(CreateAllowedMentions::new());
+        if let Err(e) = channel_id.edit_message(http, message_id, edit).await {
+            tracing::debug!("Failed to update Now Playing buttons in {channel_id}: {e}");
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use poise::serenity_prelude::{self as serenity, Colour, CreateEmbed, CreateMessage, VoiceState};
+
+use crate::commands::permissions::{check_session_lock, is_dj};
+use crate::commands::play::{author_voice_channel, bot_voice_channel};
+use crate::services::error::MusicError;
+use crate::{Context, Data, Error};
+
+const SESSION_COLOR: Colour = Colour::new(0xFEE75C);
+
+/// Lock or unlock the queue for a listening party
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("lock", "unlock"),
+    subcommand_required
+)]
+pub async fn session(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Only let you and admins play, skip and use the Now Playing buttons (DJ only)
+#[poise::command(slash_command, guild_only)]
+async fn lock(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    if !is_dj(ctx).await {
+        return Err(MusicError::NotDj.into());
+    }
+    let data = ctx.data();
+    if !data.voice.is_connected(guild_id) {
+        return Err(MusicError::NoSession.into());
+    }
+    // The lock goes with the host when they leave, so they have to be here
+    author_voice_channel(ctx)?;
+
+    // Another DJ can't take over someone else's lock, but an admin can
+    let member = ctx.author_member().await;
+    check_session_lock(data, ctx.cache(), guild_id, ctx.author().id, member.as_deref()).await?;
+    data.session_locks.write().await.insert(guild_id, ctx.author().id);
+    tracing::info!("Session in guild {guild_id} locked to {}", ctx.author().id);
+
+    let embed = CreateEmbed::new()
+        .title("Session locked")
+        .description(format!(
+            "Only <@{}> and admins can play, skip or use the Now Playing buttons until \
+             `/session unlock` or they leave the voice channel.",
+            ctx.author().id
+        ))
+        .colour(SESSION_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Open the queue to everyone again (host or admins)
+#[poise::command(slash_command, guild_only)]
+async fn unlock(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
+
+    let member = ctx.author_member().await;
+    check_session_lock(data, ctx.cache(), guild_id, ctx.author().id, member.as_deref()).await?;
+    if data.session_locks.write().await.remove(&guild_id).is_none() {
+        ctx.send(
+            poise::CreateReply::default()
+                .content("The queue isn't locked.")
+                .ephemeral(true),
+        )
+        .await?;
+        return Ok(());
+    }
+    tracing::info!("Session in guild {guild_id} unlocked by {}", ctx.author().id);
+
+    let embed = CreateEmbed::new()
+        .title("Session unlocked")
+        .description("Everyone can play and skip again.")
+        .colour(SESSION_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed)).await?;
+    Ok(())
+}
+
+/// Releases the lock when its host leaves the bot's voice channel.
+pub async fn handle_voice_state_update(ctx: &serenity::Context, new: &VoiceState, data: &Data) {
+    let Some(guild_id) = new.guild_id else {
+        return;
+    };
+    let host = data.session_locks.read().await.get(&guild_id).copied();
+    if host != Some(new.user_id) {
+        return;
+    }
+
+    let manager = songbird::get(ctx).await.expect("Songbird not registered");
+    if new.channel_id.is_some() && new.channel_id == bot_voice_channel(&manager, guild_id).await {
+        return;
+    }
+
+    // Only release the lock this update was about, not one taken since
+    let released = {
+        let mut locks = data.session_locks.write().await;
+        let still_host = locks.get(&guild_id) == Some(&new.user_id);
+        still_host && locks.remove(&guild_id).is_some()
+    };
+    if !released {
+        return;
+    }
+    tracing::info!("Session in guild {guild_id} unlocked: host {} left", new.user_id);
+
+    let text_channel = data.text_channels.read().await.get(&guild_id).copied();
+    if let Some(channel_id) = text_channel {
+        let msg = CreateMessage::new().content(format!(
+            "<@{}> left the voice channel, so the queue is open to everyone again.",
+            new.user_id
+        ));
+        let _ = channel_id.send_message(&ctx.http, msg).await;
+    }
+}
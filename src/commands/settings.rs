@@ -0,0 +1,596 @@
+use poise::serenity_prelude::{Colour, CreateEmbed, GuildChannel, GuildId};
+
+use crate::commands::play::{apply_volume_offsets, sync_playback_order};
+use crate::domain::settings::{
+    format_offset, ArtworkStyle, FollowRequester, GuildSettings, NowPlayingMode, SearchPreference,
+    SingleTrackOrder, MAX_NOW_PLAYING_CHANNELS,
+};
+use crate::services::error::MusicError;
+use crate::services::queue_service::QueueService;
+use crate::services::settings_service::SettingsService;
+use crate::{Context, Data, Error};
+
+const SETTINGS_COLOR: Colour = Colour::new(0x5865F2);
+
+/// Applies `f` to the guild's settings, writes them to the store if there
+/// is one, and returns the updated copy. Every settings change goes through
+/// here so none is lost on a restart.
+pub(crate) async fn update_settings(
+    data: &Data,
+    guild_id: GuildId,
+    f: impl FnOnce(&mut GuildSettings),
+) -> GuildSettings {
+    let settings = SettingsService::update(&data.guild_settings, guild_id, f).await;
+    if let Some(store) = &data.settings_store {
+        SettingsService::save(&data.guild_settings, store).await;
+    }
+    settings
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value { "on" } else { "off" }
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+pub(crate) enum SearchChoice {
+    #[name = "Fastest"]
+    Fastest,
+    #[name = "YouTube"]
+    YouTube,
+    #[name = "Spotify"]
+    Spotify,
+}
+
+impl From<SearchChoice> for SearchPreference {
+    fn from(choice: SearchChoice) -> Self {
+        match choice {
+            SearchChoice::Fastest => SearchPreference::Fastest,
+            SearchChoice::YouTube => SearchPreference::YouTube,
+            SearchChoice::Spotify => SearchPreference::Spotify,
+        }
+    }
+}
+
+fn search_label(preference: SearchPreference) -> &'static str {
+    match preference {
+        SearchPreference::Fastest => "fastest",
+        SearchPreference::YouTube => "YouTube",
+        SearchPreference::Spotify => "Spotify",
+    }
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum FollowChoice {
+    #[name = "Ask"]
+    Ask,
+    #[name = "Never"]
+    Never,
+    #[name = "When idle"]
+    WhenIdle,
+}
+
+impl From<FollowChoice> for FollowRequester {
+    fn from(choice: FollowChoice) -> Self {
+        match choice {
+            FollowChoice::Ask => FollowRequester::Ask,
+            FollowChoice::Never => FollowRequester::Never,
+            FollowChoice::WhenIdle => FollowRequester::WhenIdle,
+        }
+    }
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum NowPlayingChoice {
+    #[name = "Card per track"]
+    Card,
+    #[name = "Session board"]
+    Board,
+}
+
+impl From<NowPlayingChoice> for NowPlayingMode {
+    fn from(choice: NowPlayingChoice) -> Self {
+        match choice {
+            NowPlayingChoice::Card => NowPlayingMode::Card,
+            NowPlayingChoice::Board => NowPlayingMode::Board,
+        }
+    }
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum ArtworkChoice {
+    #[name = "Thumbnail"]
+    Thumbnail,
+    #[name = "Small thumbnail"]
+    SmallThumbnail,
+    #[name = "Large banner"]
+    Banner,
+}
+
+impl From<ArtworkChoice> for ArtworkStyle {
+    fn from(choice: ArtworkChoice) -> Self {
+        match choice {
+            ArtworkChoice::Thumbnail => ArtworkStyle::Thumbnail,
+            ArtworkChoice::SmallThumbnail => ArtworkStyle::SmallThumbnail,
+            ArtworkChoice::Banner => ArtworkStyle::Banner,
+        }
+    }
+}
+
+fn artwork_label(style: ArtworkStyle) -> &'static str {
+    match style {
+        ArtworkStyle::Thumbnail => "thumbnail",
+        ArtworkStyle::SmallThumbnail => "small thumbnail",
+        ArtworkStyle::Banner => "large banner",
+    }
+}
+
+#[derive(Debug, poise::ChoiceParameter)]
+enum SingleTrackChoice {
+    #[name = "Jump ahead"]
+    JumpAhead,
+    #[name = "Append after"]
+    AppendAfter,
+}
+
+impl From<SingleTrackChoice> for SingleTrackOrder {
+    fn from(choice: SingleTrackChoice) -> Self {
+        match choice {
+            SingleTrackChoice::JumpAhead => SingleTrackOrder::JumpAhead,
+            SingleTrackChoice::AppendAfter => SingleTrackOrder::AppendAfter,
+        }
+    }
+}
+
+fn single_track_label(order: SingleTrackOrder) -> &'static str {
+    match order {
+        SingleTrackOrder::JumpAhead => "jump ahead",
+        SingleTrackOrder::AppendAfter => "append after",
+    }
+}
+
+fn now_playing_label(mode: NowPlayingMode) -> &'static str {
+    match mode {
+        NowPlayingMode::Card => "card per track",
+        NowPlayingMode::Board => "session board",
+    }
+}
+
+fn follow_label(follow: FollowRequester) -> &'static str {
+    match follow {
+        FollowRequester::Ask => "ask",
+        FollowRequester::Never => "never",
+        FollowRequester::WhenIdle => "when idle",
+    }
+}
+
+fn settings_embed(settings: &GuildSettings) -> CreateEmbed {
+    let colour = match settings.embed_color_override {
+        Some(hex) => format!("`#{hex:06X}`"),
+        None => "default".to_string(),
+    };
+    let queue_limit = match settings.queue_limit {
+        Some(limit) => format!("`{limit}` tracks"),
+        None => "none".to_string(),
+    };
+    let inactivity = match (&settings.inactivity_message, settings.inactivity_notice) {
+        (_, false) => "off".to_string(),
+        (None, true) => "default message".to_string(),
+        (Some(message), true) => format!("\"{message}\""),
+    };
+    let announce = if settings.now_playing_channels.is_empty() {
+        "session channel only".to_string()
+    } else {
+        let channels: Vec<String> =
+            settings.now_playing_channels.iter().map(|ch| format!("<#{ch}>")).collect();
+        format!("session channel, {}", channels.join(", "))
+    };
+    let collection_limit = match settings.max_collection_size {
+        Some(limit) if settings.strict_collection_size => format!("`{limit}` tracks (strict)"),
+        Some(limit) => format!("`{limit}` tracks"),
+        None => "none".to_string(),
+    };
+
+    CreateEmbed::new()
+        .title("Server settings")
+        .field("Embed colour", colour, true)
+        .field("Source colours", on_off(settings.use_source_colors), true)
+        .field("Compact embeds", on_off(settings.compact_embeds), true)
+        .field("Now Playing", now_playing_label(settings.now_playing_mode), true)
+        .field("Pin Now Playing", on_off(settings.pin_now_playing), true)
+        .field("Artwork", artwork_label(settings.artwork), true)
+        .field("Plain text announcements", on_off(settings.plain_text_announcements), true)
+        .field("Channel status", on_off(settings.now_playing_status), true)
+        .field("Now Playing channels", announce, false)
+        .field("Fair queue", on_off(settings.fair_queue), true)
+        .field("Auto clean", on_off(settings.auto_clean), true)
+        .field("Queue limit", queue_limit, true)
+        .field("Collection limit", collection_limit, true)
+        .field(
+            "Single tracks during collections",
+            single_track_label(settings.single_tracks_during_collection),
+            true,
+        )
+        .field("Search provider", search_label(settings.search_preference), true)
+        .field("Topic uploads", on_off(settings.prefer_topic), true)
+        .field("Merged autocomplete", on_off(settings.merged_autocomplete), true)
+        .field("Follow requester", follow_label(settings.follow_requester), true)
+        .field(
+            "Volume offsets",
+            format!(
+                "YouTube {} · Spotify {}",
+                format_offset(settings.volume_offset_youtube),
+                format_offset(settings.volume_offset_spotify)
+            ),
+            true,
+        )
+        .field("Loudness evening", on_off(settings.normalize_loudness), true)
+        .field("Deafened count as away", on_off(settings.count_deafened_as_absent), true)
+        .field("Inactivity notice", inactivity, false)
+        .colour(SETTINGS_COLOR)
+}
+
+/// View or change the bot's settings for this server
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands(
+        "show",
+        "embeds",
+        "announce",
+        "queue",
+        "search",
+        "collections",
+        "volume",
+        "inactivity"
+    ),
+    subcommand_required,
+    default_member_permissions = "MANAGE_GUILD"
+)]
+pub async fn settings(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show the current settings
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let settings = SettingsService::get(&ctx.data().guild_settings, guild_id).await;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .embed(settings_embed(&settings))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Customize how the bot's embeds look
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn embeds(
+    ctx: Context<'_>,
+    #[description = "Hex colour for all embeds (e.g. #1DB954), or \"default\" to reset"]
+    color: Option<String>,
+    #[description = "Colour embeds by track source when no colour is set"]
+    source_colors: Option<bool>,
+    #[description = "Hide thumbnails and the source row"]
+    compact: Option<bool>,
+    #[description = "Pin the Now Playing message (needs Manage Messages)"]
+    pin_now_playing: Option<bool>,
+    #[description = "Post a card per track, or keep one board message updated"]
+    now_playing: Option<NowPlayingChoice>,
+    #[description = "Show Now Playing artwork as a thumbnail or a large banner"]
+    artwork: Option<ArtworkChoice>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    let color_override = match color.as_deref() {
+        None => None,
+        Some("default") => Some(None),
+        Some(value) => Some(Some(
+            SettingsService::parse_hex_colour(value)
+                .ok_or_else(|| MusicError::InvalidColour(value.to_string()))?,
+        )),
+    };
+
+    let settings = update_settings(ctx.data(), guild_id, |s| {
+        if let Some(color_override) = color_override {
+            s.embed_color_override = color_override;
+        }
+        if let Some(source_colors) = source_colors {
+            s.use_source_colors = source_colors;
+        }
+        if let Some(compact) = compact {
+            s.compact_embeds = compact;
+        }
+        if let Some(pin_now_playing) = pin_now_playing {
+            s.pin_now_playing = pin_now_playing;
+        }
+        if let Some(now_playing) = now_playing {
+            s.now_playing_mode = now_playing.into();
+        }
+        if let Some(artwork) = artwork {
+            s.artwork = artwork.into();
+        }
+    })
+    .await;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("Embed settings updated.")
+            .embed(settings_embed(&settings))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Choose where and how Now Playing is announced
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn announce(
+    ctx: Context<'_>,
+    #[description = "Channel that also gets the Now Playing card"]
+    #[channel_types("Text")]
+    channel1: Option<GuildChannel>,
+    #[description = "Another channel for the card"]
+    #[channel_types("Text")]
+    channel2: Option<GuildChannel>,
+    #[description = "Another channel for the card"]
+    #[channel_types("Text")]
+    channel3: Option<GuildChannel>,
+    #[description = "Only post in the session's channel again"] clear: Option<bool>,
+    #[description = "Also announce each track as plain text, which screen readers handle better"]
+    plain_text: Option<bool>,
+    #[description = "Show the track as the voice channel status; a topic fallback updates every 5 min at most"]
+    status: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    let mut channels = Vec::new();
+    for channel in [channel1, channel2, channel3].into_iter().flatten() {
+        if !channels.contains(&channel.id) {
+            channels.push(channel.id);
+        }
+    }
+    channels.truncate(MAX_NOW_PLAYING_CHANNELS);
+
+    let settings = update_settings(ctx.data(), guild_id, |s| {
+        if clear == Some(true) {
+            s.now_playing_channels.clear();
+        }
+        if !channels.is_empty() {
+            s.now_playing_channels = channels;
+        }
+        if let Some(plain_text) = plain_text {
+            s.plain_text_announcements = plain_text;
+        }
+        if let Some(status) = status {
+            s.now_playing_status = status;
+        }
+    })
+    .await;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("Now Playing announcements updated. They take effect from the next track.")
+            .embed(settings_embed(&settings))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Change how the queue is ordered and how long it can get
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn queue(
+    ctx: Context<'_>,
+    #[description = "Rotate through requesters instead of first come, first served"]
+    fair: Option<bool>,
+    #[description = "Drop a user's queued tracks when they leave the voice channel"]
+    auto_clean: Option<bool>,
+    #[description = "Which provider answers text searches"]
+    search: Option<SearchChoice>,
+    #[description = "Prefer official \"Artist - Topic\" uploads when searching YouTube"]
+    topic_uploads: Option<bool>,
+    #[description = "Move to the requester's voice channel when /play comes from another one"]
+    follow_requester: Option<FollowChoice>,
+    #[description = "Most tracks that can wait in the queue, 0 for no limit"]
+    #[max = 10000]
+    limit: Option<usize>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
+
+    let was_fair = SettingsService::get(&data.guild_settings, guild_id).await.fair_queue;
+    let settings = update_settings(data, guild_id, |s| {
+        if let Some(fair) = fair {
+            s.fair_queue = fair;
+        }
+        if let Some(auto_clean) = auto_clean {
+            s.auto_clean = auto_clean;
+        }
+        if let Some(search) = search {
+            s.search_preference = search.into();
+        }
+        if let Some(topic_uploads) = topic_uploads {
+            s.prefer_topic = topic_uploads;
+        }
+        if let Some(follow_requester) = follow_requester {
+            s.follow_requester = follow_requester.into();
+        }
+        if let Some(limit) = limit {
+            s.queue_limit = (limit > 0).then_some(limit);
+        }
+    })
+    .await;
+
+    // Rebalance what's already queued when fair play is switched on
+    if settings.fair_queue && !was_fair {
+        QueueService::interleave(&data.guild_queues, guild_id).await;
+
+        let manager = songbird::get(ctx.serenity_context())
+            .await
+            .expect("Songbird not registered");
+        if let Some(handler_lock) = manager.get(guild_id) {
+            sync_playback_order(&handler_lock, data, guild_id).await;
+        }
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("Queue settings updated.")
+            .embed(settings_embed(&settings))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Change how /play suggests tracks while typing
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn search(
+    ctx: Context<'_>,
+    #[description = "Suggest results from YouTube and Spotify together, which is slower"]
+    merged_autocomplete: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    let settings = update_settings(ctx.data(), guild_id, |s| {
+        if let Some(merged_autocomplete) = merged_autocomplete {
+            s.merged_autocomplete = merged_autocomplete;
+        }
+    })
+    .await;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("Search settings updated.")
+            .embed(settings_embed(&settings))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Limit how much one /play of a playlist, album or several links can add
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn collections(
+    ctx: Context<'_>,
+    #[description = "Most tracks one /play can add, 0 for no limit"]
+    #[max = 10000]
+    limit: Option<usize>,
+    #[description = "Reject collections over the limit instead of queueing their first tracks"]
+    strict: Option<bool>,
+    #[description = "Whether a single track played while a collection is being added goes before the rest of it"]
+    single_tracks: Option<SingleTrackChoice>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    let settings = update_settings(ctx.data(), guild_id, |s| {
+        if let Some(limit) = limit {
+            s.max_collection_size = (limit > 0).then_some(limit);
+        }
+        if let Some(strict) = strict {
+            s.strict_collection_size = strict;
+        }
+        if let Some(single_tracks) = single_tracks {
+            s.single_tracks_during_collection = single_tracks.into();
+        }
+    })
+    .await;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("Collection settings updated.")
+            .embed(settings_embed(&settings))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Make tracks from one source play louder or quieter, or even out loudness
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn volume(
+    ctx: Context<'_>,
+    #[description = "Percent to turn YouTube links up or down, e.g. -10"]
+    #[min = -90]
+    #[max = 100]
+    youtube: Option<i32>,
+    #[description = "Percent to turn Spotify tracks up or down, e.g. 20"]
+    #[min = -90]
+    #[max = 100]
+    spotify: Option<i32>,
+    #[description = "Turn tracks up or down towards a common loudness, when it's known"]
+    normalize: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
+
+    let settings = update_settings(data, guild_id, |s| {
+        if let Some(youtube) = youtube {
+            s.volume_offset_youtube = youtube;
+        }
+        if let Some(spotify) = spotify {
+            s.volume_offset_spotify = spotify;
+        }
+        if let Some(normalize) = normalize {
+            s.normalize_loudness = normalize;
+        }
+    })
+    .await;
+
+    let manager = songbird::get(ctx.serenity_context())
+        .await
+        .expect("Songbird not registered");
+    if let Some(handler_lock) = manager.get(guild_id) {
+        apply_volume_offsets(&handler_lock, data, guild_id, &settings).await;
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("Volume settings updated.")
+            .embed(settings_embed(&settings))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
+
+/// Change the notice posted when the bot leaves after sitting idle, and what counts as idle
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn inactivity(
+    ctx: Context<'_>,
+    #[description = "Notice text, {timeout} becomes the idle time; \"default\" to reset"]
+    #[max_length = 500]
+    message: Option<String>,
+    #[description = "Post the notice at all"] notify: Option<bool>,
+    #[description = "Count deafened members as not listening, for standby and the idle timeout"]
+    deafened_absent: Option<bool>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+
+    let settings = update_settings(ctx.data(), guild_id, |s| {
+        if let Some(message) = message {
+            let message = message.trim();
+            let reset = message.is_empty() || message.eq_ignore_ascii_case("default");
+            s.inactivity_message = (!reset).then(|| message.to_string());
+        }
+        if let Some(notify) = notify {
+            s.inactivity_notice = notify;
+        }
+        if let Some(deafened_absent) = deafened_absent {
+            s.count_deafened_as_absent = deafened_absent;
+        }
+    })
+    .await;
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("Inactivity settings updated.")
+            .embed(settings_embed(&settings))
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
@@ -1,34 +1,55 @@
-use crate::services::error::MusicError;
-use crate::services::queue_service::QueueService;
-use crate::{Context, Error};
+use poise::serenity_prelude::{self as serenity, GuildId};
 
-/// Skip the current track
-#[poise::command(slash_command, guild_only)]
-pub async fn skip(ctx: Context<'_>) -> Result<(), Error> {
-    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+use crate::commands::permissions::ensure_unlocked;
+use crate::commands::stale_input::refresh_pending;
+use crate::services::error::MusicError;
+use crate::services::playback_service::PlaybackService;
+use crate::{Context, Data, Error};
 
-    let manager = songbird::get(ctx.serenity_context())
-        .await
-        .expect("Songbird not registered");
+/// Skips the track songbird is playing and describes it, e.g. "Skipped
+/// **A** → now playing **B**". Shared by `/skip` and the Now Playing button.
+pub(crate) async fn skip_playing(
+    serenity_ctx: &serenity::Context,
+    data: &Data,
+    guild_id: GuildId,
+) -> Result<String, MusicError> {
+    let manager = songbird::get(serenity_ctx).await.expect("Songbird not registered");
+    let handler_lock = manager.get(guild_id).ok_or(MusicError::NotConnected)?;
 
-    // Capture the currently playing track BEFORE songbird skip
-    let skipped = QueueService::skip(&ctx.data().guild_queues, guild_id).await;
+    // Whatever is next may have loaded before a long pause
+    refresh_pending(serenity_ctx, &handler_lock, data, guild_id).await;
 
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let handler = handler_lock.lock().await;
-        let queue = handler.queue();
-        if queue.is_empty() {
-            return Err(MusicError::EmptyQueue.into());
-        }
-        let _ = queue.skip();
-    } else {
-        return Err(MusicError::EmptyQueue.into());
+    let skip = PlaybackService::skip(data.voice.as_ref(), &data.guild_queues, guild_id).await?;
+    let mut msg = match skip.skipped {
+        Some(track) => format!("Skipped **{track}**"),
+        None => "Skipped the current track".to_string(),
+    };
+    match skip.next {
+        Some(track) => msg.push_str(&format!(" → now playing **{track}**")),
+        None if skip.queued > 1 => msg.push_str(" → playing the next track"),
+        None => msg.push_str(", nothing else is queued"),
+    }
+    let waiting = skip.queued.saturating_sub(2);
+    if waiting > 0 {
+        let word = if waiting == 1 { "track" } else { "tracks" };
+        msg.push_str(&format!(" (`{waiting}` more {word} queued)"));
     }
+    msg.push('.');
+    Ok(msg)
+}
 
-    match skipped {
-        Some(track) => ctx.say(format!("Skipped: **{}**", track)).await?,
-        None => ctx.say("Skipped current track.").await?,
-    };
+/// Skips the current track and reports it. Shared by `/skip` and `/next play_now`.
+pub(crate) async fn skip_current(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    ensure_unlocked(ctx).await?;
 
+    let msg = skip_playing(ctx.serenity_context(), ctx.data(), guild_id).await?;
+    ctx.say(msg).await?;
     Ok(())
 }
+
+/// Skip the current track
+#[poise::command(slash_command, guild_only)]
+pub async fn skip(ctx: Context<'_>) -> Result<(), Error> {
+    skip_current(ctx).await
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use poise::serenity_prelude::{self as serenity, GuildId};
+use songbird::events::{Event, EventContext, EventHandler, TrackEvent};
+use songbird::tracks::TrackHandle;
+use songbird::Call;
+use tokio::sync::{Mutex, RwLock};
+use uuid::Uuid;
+
+use crate::commands::play::{queue_input, remove_from_playback, sync_playback_order, SeekTo};
+use crate::domain::track::{Track, TrackSource};
+use crate::services::history_service::HistoryService;
+use crate::services::music_service::MusicService;
+use crate::services::queue_service::QueueService;
+use crate::services::settings_service::SettingsService;
+use crate::Data;
+
+/// How long a loaded input is trusted. yt-dlp hands songbird a googlevideo
+/// URL that expires after a few hours, so an input older than this is
+/// rebuilt before it plays.
+const STALE_INPUT_AGE: Duration = Duration::from_secs(2 * 60 * 60);
+/// Resuming after a pause at least this long checks the current track's
+/// input too.
+const LONG_PAUSE: Duration = Duration::from_secs(30 * 60);
+
+/// Ages of each guild's songbird inputs.
+pub type InputClocks = Arc<RwLock<HashMap<GuildId, InputClock>>>;
+
+/// When a guild's songbird inputs loaded, and since when playback has been
+/// paused.
+#[derive(Debug, Default)]
+pub struct InputClock {
+    loaded: HashMap<Uuid, Instant>,
+    paused_since: Option<Instant>,
+}
+
+impl InputClock {
+    fn is_stale(&self, handle_id: Uuid) -> bool {
+        self.loaded
+            .get(&handle_id)
+            .is_some_and(|loaded| loaded.elapsed() >= STALE_INPUT_AGE)
+    }
+}
+
+/// Notes when a track's input has loaded, and forgets it once the track is
+/// done.
+pub(crate) struct InputReady {
+    pub(crate) guild_id: GuildId,
+    pub(crate) data: Data,
+}
+
+#[async_trait]
+impl EventHandler for InputReady {
+    async fn act(&self, ctx: &EventContext<'_>) -> Option<Event> {
+        let EventContext::Track(tracks) = ctx else {
+            return None;
+        };
+        let mut clocks = self.data.input_clocks.write().await;
+        let clock = clocks.entry(self.guild_id).or_default();
+        for (state, handle) in *tracks {
+            if state.playing.is_done() {
+                clock.loaded.remove(&handle.uuid());
+            } else {
+                clock.loaded.insert(handle.uuid(), Instant::now());
+            }
+        }
+        None
+    }
+}
+
+/// Registers [`InputReady`] on a freshly queued track.
+pub(crate) fn watch_input(handle: &TrackHandle, guild_id: GuildId, data: &Data) {
+    for event in [TrackEvent::Playable, TrackEvent::End, TrackEvent::Error] {
+        let _ = handle.add_event(
+            Event::Track(event),
+            InputReady {
+                guild_id,
+                data: data.clone(),
+            },
+        );
+    }
+}
+
+/// Remembers when the guild's playback was paused.
+pub(crate) async fn note_paused(data: &Data, guild_id: GuildId) {
+    let mut clocks = data.input_clocks.write().await;
+    clocks.entry(guild_id).or_default().paused_since = Some(Instant::now());
+}
+
+fn search_query(track: &Track, prefer_topic: bool) -> String {
+    match track.source {
+        TrackSource::Spotify => MusicService::spotify_to_youtube_query(track, prefer_topic),
+        TrackSource::YouTube => String::new(),
+    }
+}
+
+/// Rebuilds pending inputs that loaded too long ago to still stream, so the
+/// next track doesn't die the moment it starts. Songbird only loads a
+/// pending track a few seconds before it's due, so this catches the one that
+/// loaded right before a long pause.
+pub(crate) async fn refresh_pending(
+    serenity_ctx: &serenity::Context,
+    handler_lock: &Arc<Mutex<Call>>,
+    data: &Data,
+    guild_id: GuildId,
+) {
+    let queued: Vec<Uuid> = {
+        let handler = handler_lock.lock().await;
+        handler.queue().current_queue().iter().map(TrackHandle::uuid).collect()
+    };
+    let stale: Vec<Uuid> = {
+        let mut clocks = data.input_clocks.write().await;
+        let Some(clock) = clocks.get_mut(&guild_id) else {
+            return;
+        };
+        clock.loaded.retain(|id, _| queued.contains(id));
+        queued
+            .iter()
+            .skip(1)
+            .copied()
+            .filter(|id| clock.is_stale(*id))
+            .collect()
+    };
+    if stale.is_empty() {
+        return;
+    }
+
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let pending = QueueService::list(&data.guild_queues, guild_id).await;
+    let mut refreshed = Vec::new();
+    for old in stale {
+        let Some(track) = pending.iter().find(|t| t.handle_id == Some(old)) else {
+            continue;
+        };
+        let query = search_query(track, settings.prefer_topic);
+        let fresh =
+            queue_input(track, &query, handler_lock, serenity_ctx, guild_id, data, true).await;
+        if QueueService::replace_handle(&data.guild_queues, guild_id, old, fresh.uuid()).await {
+            refreshed.push(old);
+        } else {
+            // Left the queue while the new input was being built
+            remove_from_playback(handler_lock, &[fresh.uuid()]).await;
+        }
+    }
+    if refreshed.is_empty() {
+        return;
+    }
+
+    tracing::info!("Rebuilt {} stale input(s) in guild {guild_id}", refreshed.len());
+    remove_from_playback(handler_lock, &refreshed).await;
+    sync_playback_order(handler_lock, data, guild_id).await;
+}
+
+/// Resumes the guild's paused track. When it was paused long enough that its
+/// stream has likely expired, it's first swapped for a fresh input that picks
+/// up at the same position.
+pub(crate) async fn resume_playback(
+    serenity_ctx: &serenity::Context,
+    data: &Data,
+    guild_id: GuildId,
+    current: &TrackHandle,
+) {
+    let paused_since = data
+        .input_clocks
+        .write()
+        .await
+        .get_mut(&guild_id)
+        .and_then(|clock| clock.paused_since.take());
+
+    let manager = songbird::get(serenity_ctx).await.expect("Songbird not registered");
+    let Some(handler_lock) = manager.get(guild_id) else {
+        let _ = current.play();
+        return;
+    };
+    refresh_pending(serenity_ctx, &handler_lock, data, guild_id).await;
+
+    let long_pause = paused_since.is_some_and(|at| at.elapsed() >= LONG_PAUSE);
+    let stale = long_pause
+        && data
+            .input_clocks
+            .read()
+            .await
+            .get(&guild_id)
+            .is_some_and(|clock| clock.is_stale(current.uuid()));
+    if !stale || !swap_current(serenity_ctx, &handler_lock, data, guild_id, current).await {
+        let _ = current.play();
+    }
+}
+
+/// Replaces the playing track's input with a fresh one at the same position.
+/// The domain queue's current track stays, so no new Now Playing is posted.
+async fn swap_current(
+    serenity_ctx: &serenity::Context,
+    handler_lock: &Arc<Mutex<Call>>,
+    data: &Data,
+    guild_id: GuildId,
+    current: &TrackHandle,
+) -> bool {
+    let Some(track) = QueueService::current(&data.guild_queues, guild_id)
+        .await
+        .filter(|t| t.handle_id == Some(current.uuid()))
+    else {
+        return false;
+    };
+    let position = current.get_info().await.map(|info| info.position).unwrap_or_default();
+
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    let query = search_query(&track, settings.prefer_topic);
+    let fresh = queue_input(&track, &query, handler_lock, serenity_ctx, guild_id, data, false).await;
+    let _ = fresh.add_event(Event::Track(TrackEvent::Playable), SeekTo { position });
+    let repeating = data.repeat_states.read().await.get(&guild_id).copied().unwrap_or(false);
+    if repeating {
+        let _ = fresh.enable_loop();
+    }
+
+    if !QueueService::replace_handle(&data.guild_queues, guild_id, current.uuid(), fresh.uuid()).await {
+        remove_from_playback(handler_lock, &[fresh.uuid()]).await;
+        return false;
+    }
+    HistoryService::replace_handle(&data.play_histories, guild_id, current.uuid(), fresh.uuid())
+        .await;
+
+    // Take the stale input out of the queue before stopping it, so songbird
+    // doesn't treat it as finished and start whatever is next
+    let handler = handler_lock.lock().await;
+    let old = handler.queue().modify_queue(|queue| {
+        let index = queue.iter().position(|q| q.uuid() == fresh.uuid())?;
+        let queued = queue.remove(index)?;
+        queue.insert(1, queued);
+        queue.pop_front()
+    });
+    let Some(old) = old else {
+        return false;
+    };
+    let _ = old.stop();
+    let _ = fresh.play();
+    tracing::info!("Rebuilt the stale input of the current track in guild {guild_id}");
+    true
+}
@@ -0,0 +1,120 @@
+use poise::serenity_prelude::{self as serenity, Cache, ChannelId, CreateMessage, GuildId, VoiceState};
+
+use crate::commands::play::bot_voice_channel;
+use crate::commands::server_mute::refresh_now_playing;
+use crate::commands::stale_input::resume_playback;
+use crate::services::settings_service::SettingsService;
+use crate::{Context, Data};
+
+/// Shown on Now Playing while the first track waits for listeners.
+pub const STANDBY_NOTICE: &str = "⏸ Waiting for listeners";
+
+/// People other than bots in `channel_id`. With `skip_deafened`, those who
+/// are self- or server-deafened don't count either.
+pub(crate) fn listeners(
+    cache: &Cache,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    skip_deafened: bool,
+) -> usize {
+    let Some(guild) = cache.guild(guild_id) else {
+        return 0;
+    };
+    guild
+        .voice_states
+        .values()
+        .filter(|vs| vs.channel_id == Some(channel_id))
+        .filter(|vs| !(skip_deafened && (vs.self_deaf || vs.deaf)))
+        .filter(|vs| {
+            let bot = match &vs.member {
+                Some(member) => member.user.bot,
+                None => guild.members.get(&vs.user_id).is_some_and(|m| m.user.bot),
+            };
+            !bot
+        })
+        .count()
+}
+
+/// Puts the guild on standby ahead of a `/play` into `voice_channel_id`, so
+/// the first track it starts is held paused until a second person joins.
+/// Returns whether standby began: there's no point when something is already
+/// playing or someone else is there to listen.
+pub(crate) async fn begin(ctx: Context<'_>, guild_id: GuildId, voice_channel_id: ChannelId) -> bool {
+    let data = ctx.data();
+    if data.voice.current(guild_id).await.is_some() {
+        return false;
+    }
+    let settings = SettingsService::get(&data.guild_settings, guild_id).await;
+    if listeners(ctx.cache(), guild_id, voice_channel_id, settings.count_deafened_as_absent) > 1 {
+        return false;
+    }
+    data.standbys.write().await.insert(guild_id, ctx.author().id);
+    tracing::info!("Guild {guild_id} on standby for {}", ctx.author().id);
+    true
+}
+
+/// Whether the guild is holding playback for listeners.
+pub(crate) async fn is_on_standby(data: &Data, guild_id: GuildId) -> bool {
+    data.standbys.read().await.contains_key(&guild_id)
+}
+
+/// Drops the guild's standby without resuming anything, e.g. when the `/play`
+/// that started it queued nothing or someone resumed by hand. Returns whether
+/// it was on standby.
+pub(crate) async fn cancel(data: &Data, guild_id: GuildId) -> bool {
+    data.standbys.write().await.remove(&guild_id).is_some()
+}
+
+/// Starts the held track once a second person joins the bot's channel, or
+/// undeafens there when deafened members count as away.
+pub async fn handle_voice_state_update(
+    ctx: &serenity::Context,
+    old: Option<&VoiceState>,
+    new: &VoiceState,
+    data: &Data,
+) {
+    let Some(guild_id) = new.guild_id else {
+        return;
+    };
+    if new.member.as_ref().is_some_and(|m| m.user.bot) || !is_on_standby(data, guild_id).await {
+        return;
+    }
+
+    let manager = songbird::get(ctx).await.expect("Songbird not registered");
+    let Some(bot_channel) = bot_voice_channel(&manager, guild_id).await else {
+        return;
+    };
+    let skip_deafened =
+        SettingsService::get(&data.guild_settings, guild_id).await.count_deafened_as_absent;
+    if new.channel_id != Some(bot_channel)
+        || listeners(&ctx.cache, guild_id, bot_channel, skip_deafened) < 2
+    {
+        return;
+    }
+
+    let Some(host) = data.standbys.write().await.remove(&guild_id) else {
+        return;
+    };
+    let current = match manager.get(guild_id) {
+        Some(handler_lock) => handler_lock.lock().await.queue().current(),
+        None => None,
+    };
+    if let Some(current) = current {
+        resume_playback(ctx, data, guild_id, &current).await;
+    }
+    let arrived = if old.is_some_and(|old| old.channel_id == Some(bot_channel)) {
+        "undeafened"
+    } else {
+        "joined"
+    };
+    tracing::info!("Guild {guild_id} left standby for {host}: {} {arrived}", new.user_id);
+
+    let text_channel = data.text_channels.read().await.get(&guild_id).copied();
+    if let Some(channel_id) = text_channel {
+        let msg = CreateMessage::new()
+            .content(format!("<@{}> {arrived}, so the music is starting.", new.user_id));
+        let _ = channel_id.send_message(&ctx.http, msg).await;
+    }
+
+    refresh_now_playing(&manager, &ctx.http, &ctx.cache, data, guild_id).await;
+}
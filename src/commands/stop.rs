@@ -1,5 +1,6 @@
-use crate::services::cleanup::cleanup_guild;
+use crate::commands::cleanup::{cleanup_guild, DisconnectReason};
 use crate::services::error::MusicError;
+use crate::services::playback_service::PlaybackService;
 use crate::{Context, Error};
 
 /// Stop playback, clear the queue, and leave the voice channel
@@ -10,31 +11,9 @@ pub async fn stop(ctx: Context<'_>) -> Result<(), Error> {
     let data = ctx.data();
 
     // Cancel background enqueue tasks FIRST so they stop adding tracks
-    cleanup_guild(
-        guild_id,
-        &data.guild_queues,
-        &data.enqueue_cancels,
-        &data.inactivity_handles,
-        &data.now_playing_messages,
-        &ctx.serenity_context().http,
-        &data.repeat_states,
-    )
-    .await;
-
-    let manager = songbird::get(ctx.serenity_context())
-        .await
-        .expect("Songbird not registered");
-
-    // Stop the songbird queue (clears any last track that slipped through)
-    if let Some(handler_lock) = manager.get(guild_id) {
-        let handler = handler_lock.lock().await;
-        handler.queue().stop();
-    }
-
-    manager
-        .leave(guild_id)
-        .await
-        .map_err(|e| MusicError::JoinError(e.to_string()))?;
+    let reason = DisconnectReason::Stopped(ctx.author().id);
+    cleanup_guild(guild_id, data, &ctx.serenity_context().http, reason).await;
+    PlaybackService::stop(data.voice.as_ref(), guild_id).await?;
 
     ctx.say("Stopped playback and left the voice channel.").await?;
     Ok(())
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use poise::serenity_prelude::{Colour, CreateEmbed, CreateEmbedFooter};
+
+use crate::domain::stats::GuildStats;
+use crate::domain::track::format_secs;
+use crate::services::error::MusicError;
+use crate::services::stats_service::StatsService;
+use crate::{Context, Data, Error};
+
+const TOP_COLOR: Colour = Colour::new(0xEB459E);
+const TOP_ENTRIES: usize = 10;
+
+/// How often changed play stats are written to the store. Plays are
+/// recorded far more often than that, e.g. on every track a `/stop` drops.
+const STATS_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Marks the play stats for saving, if there's a store. Call it after
+/// changing `guild_stats`.
+pub(crate) fn persist_stats(data: &Data) {
+    if let Some(store) = &data.stats_store {
+        store.mark_changed();
+    }
+}
+
+/// Writes the play stats to the store at most every
+/// [`STATS_SAVE_INTERVAL`], when they changed, for the lifetime of the bot.
+/// A restart loses at most that much.
+pub fn spawn_stats_saver(data: Data) {
+    let Some(store) = data.stats_store.clone() else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(STATS_SAVE_INTERVAL);
+        loop {
+            interval.tick().await;
+            if store.take_changed() {
+                StatsService::save(&data.guild_stats, &store).await;
+            }
+        }
+    });
+}
+
+fn plays(count: u32) -> String {
+    if count == 1 {
+        "1 play".to_string()
+    } else {
+        format!("{count} plays")
+    }
+}
+
+fn top_embed(title: &str, lines: Vec<String>, stats: &GuildStats) -> CreateEmbed {
+    let description = if lines.is_empty() {
+        "No plays recorded yet.".to_string()
+    } else {
+        lines.join("\n")
+    };
+    let footer = format!(
+        "{} plays · {} listened",
+        stats.total_plays(),
+        format_secs(stats.listening.as_secs())
+    );
+
+    CreateEmbed::new()
+        .title(title)
+        .description(description)
+        .colour(TOP_COLOR)
+        .footer(CreateEmbedFooter::new(footer))
+}
+
+/// Show this server's most played tracks and most active requesters
+#[poise::command(
+    slash_command,
+    guild_only,
+    subcommands("tracks", "requesters", "reset"),
+    subcommand_required
+)]
+pub async fn top(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Show the most played tracks
+#[poise::command(slash_command, guild_only)]
+async fn tracks(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let stats = StatsService::get(&ctx.data().guild_stats, guild_id).await;
+
+    let lines = stats
+        .top_tracks(TOP_ENTRIES)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (url, track))| {
+            format!(
+                "`{}.` [**{}** - {}]({url}) · {}",
+                i + 1,
+                track.title,
+                track.artist,
+                plays(track.plays)
+            )
+        })
+        .collect();
+
+    ctx.send(poise::CreateReply::default().embed(top_embed("Top tracks", lines, &stats)))
+        .await?;
+    Ok(())
+}
+
+/// Show who requested the most played tracks
+#[poise::command(slash_command, guild_only)]
+async fn requesters(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let stats = StatsService::get(&ctx.data().guild_stats, guild_id).await;
+
+    let lines = stats
+        .top_requesters(TOP_ENTRIES)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (user_id, count))| format!("`{}.` <@{user_id}> · {}", i + 1, plays(count)))
+        .collect();
+
+    ctx.send(poise::CreateReply::default().embed(top_embed("Top requesters", lines, &stats)))
+        .await?;
+    Ok(())
+}
+
+/// Clear this server's play stats
+#[poise::command(slash_command, guild_only, required_permissions = "MANAGE_GUILD")]
+async fn reset(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    StatsService::reset(&ctx.data().guild_stats, guild_id).await;
+    persist_stats(ctx.data());
+
+    let embed = CreateEmbed::new()
+        .title("Stats reset")
+        .description("Play counts and listening time start over from now.")
+        .colour(TOP_COLOR);
+    ctx.send(poise::CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+    Ok(())
+}
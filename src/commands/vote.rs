@@ -0,0 +1,285 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::StreamExt;
+use poise::serenity_prelude::{
+    self as serenity, Colour, CreateActionRow, CreateButton, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, MessageId,
+};
+
+use crate::commands::embeds::linked_title;
+use crate::commands::permissions::{ensure_unlocked, is_dj};
+use crate::commands::play::{play_next, sync_playback_order};
+use crate::domain::track::Track;
+use crate::domain::vote::{VotePoll, MAX_VOTE_OPTIONS};
+use crate::services::error::MusicError;
+use crate::services::queue_service::QueueService;
+use crate::services::vote_service::VoteService;
+use crate::{Context, Error};
+
+const VOTE_COLOR: Colour = Colour::new(0xFEE75C);
+/// How long a poll takes votes.
+const VOTE_DURATION: Duration = Duration::from_secs(60);
+
+/// What one of a poll's options stands for.
+enum Choice {
+    /// A track already in the queue, moved up if it wins.
+    Queued(Box<Track>),
+    /// A link queued to play next if it wins.
+    Link(String),
+}
+
+impl Choice {
+    fn label(&self) -> String {
+        match self {
+            Choice::Queued(track) => linked_title(track),
+            Choice::Link(url) => format!("<{url}>"),
+        }
+    }
+}
+
+fn votes_label(count: usize) -> String {
+    match count {
+        1 => "1 vote".to_string(),
+        n => format!("{n} votes"),
+    }
+}
+
+/// The poll with its running tally. `status` replaces the closing time once
+/// it's over.
+fn poll_embed(choices: &[Choice], tally: &[usize], status: &str) -> CreateEmbed {
+    let lines: Vec<String> = choices
+        .iter()
+        .zip(tally)
+        .enumerate()
+        .map(|(i, (choice, &count))| {
+            format!("`{}.` {} — {}", i + 1, choice.label(), votes_label(count))
+        })
+        .collect();
+    CreateEmbed::new()
+        .title("What plays next?")
+        .description(format!("{}\n\n{status}", lines.join("\n")))
+        .colour(VOTE_COLOR)
+}
+
+fn vote_buttons(prefix: &str, options: usize) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(
+        (0..options)
+            .map(|i| {
+                CreateButton::new(format!("{prefix}_{i}"))
+                    .label((i + 1).to_string())
+                    .style(serenity::ButtonStyle::Primary)
+            })
+            .collect(),
+    )]
+}
+
+/// Let members vote on what plays next
+#[poise::command(slash_command, guild_only, subcommands("start", "cancel"), subcommand_required)]
+pub async fn vote(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Start a 60 second poll on the next queued tracks, or on up to 5 links
+#[poise::command(slash_command, guild_only)]
+async fn start(
+    ctx: Context<'_>,
+    #[description = "A link to vote on instead of the queue"] link1: Option<String>,
+    #[description = "A link to vote on instead of the queue"] link2: Option<String>,
+    #[description = "A link to vote on instead of the queue"] link3: Option<String>,
+    #[description = "A link to vote on instead of the queue"] link4: Option<String>,
+    #[description = "A link to vote on instead of the queue"] link5: Option<String>,
+) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    ensure_unlocked(ctx).await?;
+    let data = ctx.data();
+    if VoteService::open_in(&data.vote_polls, guild_id).await.is_some() {
+        return Err(MusicError::VoteInProgress.into());
+    }
+
+    let links: Vec<String> = [link1, link2, link3, link4, link5]
+        .into_iter()
+        .flatten()
+        .map(|link| link.trim().to_string())
+        .filter(|link| !link.is_empty())
+        .collect();
+    let choices: Vec<Choice> = if links.is_empty() {
+        QueueService::list(&data.guild_queues, guild_id)
+            .await
+            .into_iter()
+            .take(MAX_VOTE_OPTIONS)
+            .map(|track| Choice::Queued(Box::new(track)))
+            .collect()
+    } else {
+        links.into_iter().map(Choice::Link).collect()
+    };
+    if choices.len() < 2 {
+        return Err(MusicError::NotEnoughVoteOptions.into());
+    }
+
+    let closes_at = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        + VOTE_DURATION.as_secs();
+    let open_status = format!("Closes <t:{closes_at}:R>. You can change your vote until then.");
+    let prefix = format!("vote_{}", ctx.id());
+    let reply = ctx
+        .send(
+            poise::CreateReply::default()
+                .embed(poll_embed(&choices, &vec![0; choices.len()], &open_status))
+                .components(vote_buttons(&prefix, choices.len())),
+        )
+        .await?;
+    let message_id = reply.message().await?.id;
+
+    let poll = VotePoll::new(guild_id, ctx.author().id, choices.len());
+    let Some(cancelled) = VoteService::open(&data.vote_polls, message_id, poll).await else {
+        reply
+            .edit(
+                ctx,
+                poise::CreateReply::default()
+                    .content(MusicError::VoteInProgress.to_string())
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(());
+    };
+
+    collect_votes(ctx, message_id, &prefix, &choices, &open_status, &cancelled).await;
+
+    // Closing takes the poll out of the map; `None` means it was cancelled
+    let Some(poll) = VoteService::close(&data.vote_polls, message_id).await else {
+        reply
+            .edit(
+                ctx,
+                poise::CreateReply::default()
+                    .content("Poll cancelled.")
+                    .components(Vec::new()),
+            )
+            .await?;
+        return Ok(());
+    };
+
+    let tally = poll.tally();
+    reply
+        .edit(
+            ctx,
+            poise::CreateReply::default()
+                .embed(poll_embed(&choices, &tally, "Poll closed."))
+                .components(Vec::new()),
+        )
+        .await?;
+
+    let Some(winner) = poll.winner() else {
+        ctx.say("Nobody voted, so the queue stays as it is.").await?;
+        return Ok(());
+    };
+    let votes = votes_label(tally[winner]);
+    match &choices[winner] {
+        Choice::Queued(track) => {
+            let moved = match track.handle_id {
+                Some(handle_id) => {
+                    QueueService::move_track(&data.guild_queues, guild_id, handle_id, 0).await
+                }
+                None => false,
+            };
+            if !moved {
+                ctx.say(format!(
+                    "**{}** won with {votes}, but it's no longer in the queue.",
+                    track.title
+                ))
+                .await?;
+                return Ok(());
+            }
+            let manager = songbird::get(ctx.serenity_context())
+                .await
+                .expect("Songbird not registered");
+            if let Some(handler_lock) = manager.get(guild_id) {
+                sync_playback_order(&handler_lock, data, guild_id).await;
+            }
+            ctx.say(format!("**{}** won with {votes} and plays next.", track.title))
+                .await?;
+        }
+        Choice::Link(url) => {
+            ctx.say(format!("Option {} won with {votes} — queueing it next.", winner + 1))
+                .await?;
+            play_next(ctx, url).await?;
+        }
+    }
+    Ok(())
+}
+
+/// Takes votes on the poll until it times out or is cancelled, updating the
+/// tally on the message with each one.
+async fn collect_votes(
+    ctx: Context<'_>,
+    message_id: MessageId,
+    prefix: &str,
+    choices: &[Choice],
+    open_status: &str,
+    cancelled: &tokio::sync::Notify,
+) {
+    let data = ctx.data();
+    let filter_prefix = prefix.to_string();
+    let mut presses = serenity::ComponentInteractionCollector::new(ctx)
+        .message_id(message_id)
+        .filter(move |press| press.data.custom_id.starts_with(&filter_prefix))
+        .timeout(VOTE_DURATION)
+        .stream();
+
+    loop {
+        let press = tokio::select! {
+            press = presses.next() => press,
+            _ = cancelled.notified() => None,
+        };
+        let Some(press) = press else {
+            break;
+        };
+        let choice = press
+            .data
+            .custom_id
+            .rsplit('_')
+            .next()
+            .and_then(|index| index.parse().ok());
+        let Some(choice) = choice else {
+            continue;
+        };
+        let Some(tally) =
+            VoteService::vote(&data.vote_polls, message_id, press.user.id, choice).await
+        else {
+            // Cancelled while this press was on its way
+            break;
+        };
+        let response = CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .embed(poll_embed(choices, &tally, open_status)),
+        );
+        if let Err(e) = press.create_response(ctx, response).await {
+            tracing::warn!("Couldn't update vote tally in {message_id}: {e}");
+        }
+    }
+}
+
+/// Cancel the open poll (whoever started it, or a DJ)
+#[poise::command(slash_command, guild_only)]
+async fn cancel(ctx: Context<'_>) -> Result<(), Error> {
+    let guild_id = ctx.guild_id().ok_or(MusicError::NotInGuild)?;
+    let data = ctx.data();
+    let Some(started_by) = VoteService::open_in(&data.vote_polls, guild_id).await else {
+        return Err(MusicError::NoVoteOpen.into());
+    };
+    if started_by != ctx.author().id && !is_dj(ctx).await {
+        return Err(MusicError::NotDj.into());
+    }
+    if !VoteService::cancel(&data.vote_polls, guild_id).await {
+        return Err(MusicError::NoVoteOpen.into());
+    }
+
+    ctx.send(
+        poise::CreateReply::default()
+            .content("Poll cancelled. The queue is unchanged.")
+            .ephemeral(true),
+    )
+    .await?;
+    Ok(())
+}
@@ -0,0 +1,79 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use poise::serenity_prelude::{GuildId, Http};
+use songbird::tracks::{PlayMode, ReadyState};
+use uuid::Uuid;
+
+use crate::domain::track::format_secs;
+use crate::commands::cleanup::drop_failed_track;
+use crate::Data;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_secs(20);
+/// Samples in a row without progress before a track counts as stalled.
+const STALL_SAMPLES: u32 = 2;
+
+/// Spawns a background task that skips the current track when songbird says
+/// it's playing but its position stops moving, which happens when the
+/// upstream stream dies without an error. Paused or still-loading tracks
+/// aren't judged. The task ends with `session`, i.e. on `cleanup_guild`.
+pub fn spawn_playback_watchdog(
+    manager: Arc<songbird::Songbird>,
+    guild_id: GuildId,
+    session: u64,
+    http: Arc<Http>,
+    data: Data,
+) {
+    tokio::spawn(async move {
+        let mut last: Option<(Uuid, Duration)> = None;
+        let mut stalled_samples = 0;
+
+        loop {
+            tokio::time::sleep(SAMPLE_INTERVAL).await;
+            if data.sessions.read().await.get(&guild_id) != Some(&session) {
+                return;
+            }
+
+            let Some(handler_lock) = manager.get(guild_id) else {
+                return;
+            };
+            let Some(handle) = handler_lock.lock().await.queue().current() else {
+                last = None;
+                stalled_samples = 0;
+                continue;
+            };
+            let Ok(info) = handle.get_info().await else {
+                continue;
+            };
+
+            let sample = (handle.uuid(), info.position);
+            let judged = info.playing == PlayMode::Play && info.ready == ReadyState::Playable;
+            if judged && last == Some(sample) {
+                stalled_samples += 1;
+            } else {
+                stalled_samples = 0;
+            }
+            last = Some(sample);
+
+            if stalled_samples >= STALL_SAMPLES {
+                tracing::warn!(
+                    "Playback stalled in guild {guild_id} at {:?}, skipping",
+                    info.position
+                );
+                let error = format!("stalled at {}", format_secs(info.position.as_secs()));
+                drop_failed_track(
+                    &http,
+                    &data,
+                    guild_id,
+                    handle.uuid(),
+                    "Lost the audio stream for",
+                    error,
+                )
+                .await;
+                let _ = handle.stop();
+                last = None;
+                stalled_samples = 0;
+            }
+        }
+    });
+}
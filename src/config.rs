@@ -1,10 +1,106 @@
 use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use poise::serenity_prelude::GuildId;
+
+const DEFAULT_MAX_PLAYLIST_ITEMS: usize = 1000;
+const DEFAULT_NOW_PLAYING_FILE: &str = "now_playing.json";
+const DEFAULT_ENQUEUE_FILE: &str = "pending_enqueues.json";
+const DEFAULT_STATS_FILE: &str = "stats.json";
+const DEFAULT_SCHEDULE_FILE: &str = "schedules.json";
+const DEFAULT_PREFERENCES_FILE: &str = "preferences.json";
+const DEFAULT_SETTINGS_FILE: &str = "settings.json";
+const DEFAULT_SPOTIFY_TOKEN_FILE: &str = "spotify_tokens.json";
+const DEFAULT_SEARCH_RESULT_LIMIT: u32 = 5;
+/// Discord lists at most 25 autocomplete choices or select menu options.
+const MAX_SEARCH_RESULT_LIMIT: u32 = 25;
+const DEFAULT_SEARCH_TIMEOUT_MS: u64 = 5000;
+const DEFAULT_HISTORY_LIMIT: usize = 50;
+const DEFAULT_COMMANDS_HASH_FILE: &str = "commands.hash";
+/// Best audio up to 128 kbps, falling back to the best there is. Opus sent to
+/// Discord tops out well below what YouTube's best streams carry.
+const DEFAULT_YTDLP_FORMAT: &str = "bestaudio[abr<=128]/bestaudio";
+/// The Opus encoder's bitrate range, in bits per second.
+const MIN_VOICE_BITRATE: i32 = 500;
+const MAX_VOICE_BITRATE: i32 = 512_000;
 
 pub struct Config {
     pub discord_token: String,
     pub spotify_client_id: String,
     pub spotify_client_secret: String,
     pub youtube_api_key: String,
+    /// Two-letter country code passed to Spotify as the `market`. Some
+    /// editorial playlists come back empty without one.
+    pub spotify_market: Option<String>,
+    /// Two-letter country code the bot plays YouTube from
+    /// (`YOUTUBE_REGION`). Videos blocked there are turned away when queued;
+    /// unset skips the check.
+    pub youtube_region: Option<String>,
+    /// Post a welcome message in servers the bot is added to. Set
+    /// `ONBOARDING_MESSAGES=false` to turn it off.
+    pub onboarding_messages: bool,
+    /// Fixed number of gateway shards from `SHARD_COUNT`. Unset asks Discord
+    /// for the recommended count.
+    pub shard_count: Option<u32>,
+    /// Most tracks read from one playlist or album (`MAX_PLAYLIST_ITEMS`,
+    /// default 1000).
+    pub max_playlist_items: usize,
+    /// Where live Now Playing messages are recorded so they can be retired
+    /// after a crash (`NOW_PLAYING_FILE`, default `now_playing.json`). Set it
+    /// empty to turn this off.
+    pub now_playing_file: Option<PathBuf>,
+    /// Where the unqueued rest of each collection is recorded, so one a
+    /// restart cuts short is resumed in the next session (`ENQUEUE_FILE`,
+    /// default `pending_enqueues.json`). Set it empty to turn this off.
+    pub enqueue_file: Option<PathBuf>,
+    /// Where `/top` play stats are kept across restarts (`STATS_FILE`,
+    /// default `stats.json`). Set it empty to keep them in memory only.
+    pub stats_file: Option<PathBuf>,
+    /// Where pending `/schedule` jobs are kept across restarts
+    /// (`SCHEDULE_FILE`, default `schedules.json`). Set it empty to keep
+    /// them in memory only.
+    pub schedule_file: Option<PathBuf>,
+    /// Where each user's `/mysettings` choices are kept across restarts
+    /// (`PREFERENCES_FILE`, default `preferences.json`). Set it empty to
+    /// keep them in memory only.
+    pub preferences_file: Option<PathBuf>,
+    /// Where each server's `/settings` and blocklist are kept across
+    /// restarts (`SETTINGS_FILE`, default `settings.json`). Set it empty to
+    /// keep them in memory only.
+    pub settings_file: Option<PathBuf>,
+    /// Redirect URI registered on the Spotify app for `/export spotify`
+    /// (`SPOTIFY_REDIRECT_URI`). Unset turns exporting off.
+    pub spotify_redirect_uri: Option<String>,
+    /// Where accounts linked for `/export spotify` are kept across restarts
+    /// (`SPOTIFY_TOKEN_FILE`, default `spotify_tokens.json`). Set it empty to
+    /// keep them in memory only.
+    pub spotify_token_file: Option<PathBuf>,
+    /// Results asked of each provider for a text search, offered by `/play
+    /// search choose` and autocomplete (`SEARCH_RESULT_LIMIT`, default 5, at
+    /// most 25).
+    pub search_result_limit: u32,
+    /// How long one provider's search may take before its results are given
+    /// up on (`SEARCH_TIMEOUT_MS`, default 5000).
+    pub search_timeout: Duration,
+    /// Most played tracks remembered per guild for `/history`
+    /// (`HISTORY_LIMIT`, default 50).
+    pub history_limit: usize,
+    /// Register commands in this guild only (`DEV_GUILD_ID`), where changes
+    /// show up at once, instead of globally.
+    pub dev_guild_id: Option<GuildId>,
+    /// Where the fingerprint of the last global command registration is kept,
+    /// so an unchanged command set isn't registered again
+    /// (`COMMANDS_HASH_FILE`, default `commands.hash`). Set it empty to
+    /// register on every start.
+    pub commands_hash_file: Option<PathBuf>,
+    /// Format selector passed to yt-dlp as `-f` (`YTDLP_FORMAT`, default
+    /// `bestaudio[abr<=128]/bestaudio`).
+    pub ytdlp_format: String,
+    /// Opus bitrate in bits per second set on each voice connection
+    /// (`VOICE_BITRATE`, clamped to 500–512000). Unset keeps songbird's
+    /// default.
+    pub voice_bitrate: Option<i32>,
 }
 
 impl Config {
@@ -15,6 +111,106 @@ impl Config {
             spotify_client_secret: env::var("SPOTIFY_CLIENT_SECRET")
                 .expect("Missing SPOTIFY_CLIENT_SECRET"),
             youtube_api_key: env::var("YOUTUBE_API_KEY").expect("Missing YOUTUBE_API_KEY"),
+            spotify_market: env::var("SPOTIFY_MARKET").ok().filter(|m| !m.trim().is_empty()),
+            youtube_region: env::var("YOUTUBE_REGION")
+                .ok()
+                .map(|r| r.trim().to_ascii_uppercase())
+                .filter(|r| !r.is_empty()),
+            onboarding_messages: env::var("ONBOARDING_MESSAGES")
+                .map(|v| !matches!(v.trim().to_ascii_lowercase().as_str(), "false" | "0" | "no"))
+                .unwrap_or(true),
+            shard_count: env::var("SHARD_COUNT")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| v.trim().parse().expect("SHARD_COUNT must be a positive number"))
+                .filter(|&count| count > 0),
+            max_playlist_items: env::var("MAX_PLAYLIST_ITEMS")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|&max| max > 0)
+                .unwrap_or(DEFAULT_MAX_PLAYLIST_ITEMS),
+            now_playing_file: match env::var("NOW_PLAYING_FILE") {
+                Ok(path) if path.trim().is_empty() => None,
+                Ok(path) => Some(PathBuf::from(path.trim())),
+                Err(_) => Some(PathBuf::from(DEFAULT_NOW_PLAYING_FILE)),
+            },
+            enqueue_file: match env::var("ENQUEUE_FILE") {
+                Ok(path) if path.trim().is_empty() => None,
+                Ok(path) => Some(PathBuf::from(path.trim())),
+                Err(_) => Some(PathBuf::from(DEFAULT_ENQUEUE_FILE)),
+            },
+            stats_file: match env::var("STATS_FILE") {
+                Ok(path) if path.trim().is_empty() => None,
+                Ok(path) => Some(PathBuf::from(path.trim())),
+                Err(_) => Some(PathBuf::from(DEFAULT_STATS_FILE)),
+            },
+            schedule_file: match env::var("SCHEDULE_FILE") {
+                Ok(path) if path.trim().is_empty() => None,
+                Ok(path) => Some(PathBuf::from(path.trim())),
+                Err(_) => Some(PathBuf::from(DEFAULT_SCHEDULE_FILE)),
+            },
+            preferences_file: match env::var("PREFERENCES_FILE") {
+                Ok(path) if path.trim().is_empty() => None,
+                Ok(path) => Some(PathBuf::from(path.trim())),
+                Err(_) => Some(PathBuf::from(DEFAULT_PREFERENCES_FILE)),
+            },
+            settings_file: match env::var("SETTINGS_FILE") {
+                Ok(path) if path.trim().is_empty() => None,
+                Ok(path) => Some(PathBuf::from(path.trim())),
+                Err(_) => Some(PathBuf::from(DEFAULT_SETTINGS_FILE)),
+            },
+            spotify_redirect_uri: env::var("SPOTIFY_REDIRECT_URI")
+                .ok()
+                .map(|uri| uri.trim().to_string())
+                .filter(|uri| !uri.is_empty()),
+            spotify_token_file: match env::var("SPOTIFY_TOKEN_FILE") {
+                Ok(path) if path.trim().is_empty() => None,
+                Ok(path) => Some(PathBuf::from(path.trim())),
+                Err(_) => Some(PathBuf::from(DEFAULT_SPOTIFY_TOKEN_FILE)),
+            },
+            search_result_limit: env::var("SEARCH_RESULT_LIMIT")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|&limit| limit > 0)
+                .unwrap_or(DEFAULT_SEARCH_RESULT_LIMIT)
+                .min(MAX_SEARCH_RESULT_LIMIT),
+            search_timeout: Duration::from_millis(
+                env::var("SEARCH_TIMEOUT_MS")
+                    .ok()
+                    .and_then(|v| v.trim().parse().ok())
+                    .filter(|&ms| ms > 0)
+                    .unwrap_or(DEFAULT_SEARCH_TIMEOUT_MS),
+            ),
+            history_limit: env::var("HISTORY_LIMIT")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .filter(|&limit| limit > 0)
+                .unwrap_or(DEFAULT_HISTORY_LIMIT),
+            dev_guild_id: env::var("DEV_GUILD_ID")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| v.trim().parse().expect("DEV_GUILD_ID must be a server id"))
+                .filter(|&id| id > 0)
+                .map(GuildId::new),
+            commands_hash_file: match env::var("COMMANDS_HASH_FILE") {
+                Ok(path) if path.trim().is_empty() => None,
+                Ok(path) => Some(PathBuf::from(path.trim())),
+                Err(_) => Some(PathBuf::from(DEFAULT_COMMANDS_HASH_FILE)),
+            },
+            ytdlp_format: env::var("YTDLP_FORMAT")
+                .ok()
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| DEFAULT_YTDLP_FORMAT.to_string()),
+            voice_bitrate: env::var("VOICE_BITRATE")
+                .ok()
+                .filter(|v| !v.trim().is_empty())
+                .map(|v| {
+                    v.trim()
+                        .parse::<i32>()
+                        .expect("VOICE_BITRATE must be in bits per second, e.g. 96000")
+                })
+                .map(|bps| bps.clamp(MIN_VOICE_BITRATE, MAX_VOICE_BITRATE)),
         }
     }
 }
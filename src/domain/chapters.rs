@@ -0,0 +1,155 @@
+use std::sync::LazyLock;
+use std::time::Duration;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// "0:00 Intro", "1. 03:15 - Song", "[1:02:03] Song", "Song - 4:05"
+static LEADING_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[\s\-•*▶>]*(?:\d{1,3}[.)]\s*)?[\[(]?((?:\d{1,2}:)?\d{1,2}:\d{2})[\])]?\s*[-–—:|.]*\s*(.+?)\s*$")
+        .unwrap()
+});
+static TRAILING_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^[\s\-•*▶>]*(?:\d{1,3}[.)]\s*)?(.+?)\s*[-–—:|]*\s*[\[(]?((?:\d{1,2}:)?\d{1,2}:\d{2})[\])]?\s*$")
+        .unwrap()
+});
+
+/// A chapter of a longer video.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chapter {
+    pub title: String,
+    pub start: Duration,
+    /// `None` for the last chapter when the video's length is unknown.
+    pub end: Option<Duration>,
+}
+
+/// Reads `m:ss`, `h:mm:ss` or plain seconds.
+pub fn parse_timestamp(value: &str) -> Option<Duration> {
+    let secs = value
+        .split(':')
+        .try_fold(0u64, |acc, part| Some(acc * 60 + part.parse::<u64>().ok()?))?;
+    Some(Duration::from_secs(secs))
+}
+
+/// Picks chapter markers out of a video description. Lines need a timestamp
+/// at the start or the end; the markers have to be in increasing order and
+/// there have to be at least two, otherwise the video counts as having none.
+pub fn parse_chapters(description: &str, total: Option<Duration>) -> Vec<Chapter> {
+    let mut markers: Vec<(Duration, String)> = Vec::new();
+    for line in description.lines() {
+        let marker = LEADING_RE
+            .captures(line)
+            .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+            .or_else(|| {
+                TRAILING_RE
+                    .captures(line)
+                    .map(|caps| (caps[2].to_string(), caps[1].to_string()))
+            });
+        let Some((timestamp, title)) = marker else {
+            continue;
+        };
+        let Some(start) = parse_timestamp(&timestamp) else {
+            continue;
+        };
+        if title.is_empty() || total.is_some_and(|total| start >= total) {
+            continue;
+        }
+        if markers.last().is_some_and(|(previous, _)| start <= *previous) {
+            return Vec::new();
+        }
+        markers.push((start, title));
+    }
+
+    if markers.len() < 2 {
+        return Vec::new();
+    }
+
+    let ends: Vec<Option<Duration>> = markers
+        .iter()
+        .skip(1)
+        .map(|(start, _)| Some(*start))
+        .chain(std::iter::once(total))
+        .collect();
+    markers
+        .into_iter()
+        .zip(ends)
+        .map(|((start, title), end)| Chapter { title, start, end })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    fn summary(chapters: &[Chapter]) -> Vec<(&str, u64, Option<u64>)> {
+        chapters
+            .iter()
+            .map(|c| (c.title.as_str(), c.start.as_secs(), c.end.map(|end| end.as_secs())))
+            .collect()
+    }
+
+    #[test]
+    fn timestamps_read_as_seconds_minutes_or_hours() {
+        assert_eq!(parse_timestamp("45"), Some(secs(45)));
+        assert_eq!(parse_timestamp("3:15"), Some(secs(195)));
+        assert_eq!(parse_timestamp("1:02:03"), Some(secs(3723)));
+        assert_eq!(parse_timestamp("1:xx"), None);
+        assert_eq!(parse_timestamp(""), None);
+    }
+
+    #[test]
+    fn a_mix_description_splits_into_chapters_ending_where_the_next_starts() {
+        let description = "\
+Best of the year, thanks for listening!
+
+Tracklist:
+0:00 Intro
+1. 03:15 - First Song
+2) [7:40] Second Song
+▶ 12:05 | Third Song
+
+Follow me: https://example.com/me";
+        let chapters = parse_chapters(description, Some(secs(900)));
+        assert_eq!(
+            summary(&chapters),
+            [
+                ("Intro", 0, Some(195)),
+                ("First Song", 195, Some(460)),
+                ("Second Song", 460, Some(725)),
+                ("Third Song", 725, Some(900)),
+            ]
+        );
+    }
+
+    #[test]
+    fn timestamps_at_the_end_of_the_line_work_too() {
+        let description = "Intro - 0:00\nThe Song (1:30)\nOutro — 1:02:03";
+        let chapters = parse_chapters(description, None);
+        assert_eq!(
+            summary(&chapters),
+            [("Intro", 0, Some(90)), ("The Song", 90, Some(3723)), ("Outro", 3723, None)]
+        );
+    }
+
+    #[test]
+    fn markers_past_the_end_of_the_video_are_dropped() {
+        let description = "0:00 Intro\n1:00 Song\n9:00 Bonus from the full album";
+        let chapters = parse_chapters(description, Some(secs(300)));
+        assert_eq!(summary(&chapters), [("Intro", 0, Some(60)), ("Song", 60, Some(300))]);
+    }
+
+    #[test]
+    fn descriptions_without_a_real_chapter_list_have_none() {
+        // A single marker
+        assert!(parse_chapters("Skip to 1:30 for the drop", None).is_empty());
+        // Out of order, e.g. timestamps quoted in a comment section
+        assert!(parse_chapters("0:00 Intro\n5:00 Song\n2:00 Back", None).is_empty());
+        // No timestamps at all
+        assert!(parse_chapters("Just a song\nwith lyrics", None).is_empty());
+        assert!(parse_chapters("", None).is_empty());
+    }
+}
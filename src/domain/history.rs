@@ -0,0 +1,74 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use super::track::Track;
+
+/// A track the guild played this session.
+#[derive(Debug, Clone)]
+pub struct PlayedTrack {
+    pub track: Track,
+    pub started_at: DateTime<Utc>,
+    /// `None` while it's still playing.
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// The tracks a guild has played this session, oldest first. Kept apart from
+/// the queue, so it never counts towards the queue limit.
+#[derive(Debug, Clone, Default)]
+pub struct PlayHistory {
+    entries: VecDeque<PlayedTrack>,
+}
+
+impl PlayHistory {
+    /// Records `track` as started at `at`, closing the entry before it if it
+    /// was still open, and drops the oldest entries past `limit`.
+    pub fn start(&mut self, track: Track, at: DateTime<Utc>, limit: usize) {
+        if let Some(last) = self.entries.back_mut() {
+            last.finished_at.get_or_insert(at);
+        }
+        self.entries.push_back(PlayedTrack {
+            track,
+            started_at: at,
+            finished_at: None,
+        });
+        while self.entries.len() > limit {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Marks the entry played as songbird's `handle_id` finished at `at`,
+    /// unless it already was.
+    pub fn finish(&mut self, handle_id: Uuid, at: DateTime<Utc>) {
+        let entry = self
+            .entries
+            .iter_mut()
+            .rev()
+            .find(|e| e.track.handle_id == Some(handle_id));
+        if let Some(entry) = entry {
+            entry.finished_at.get_or_insert(at);
+        }
+    }
+
+    /// Follows a track whose input was swapped for a fresh one.
+    pub fn replace_handle(&mut self, old: Uuid, new: Uuid) {
+        for entry in &mut self.entries {
+            if entry.track.handle_id == Some(old) {
+                entry.track.handle_id = Some(new);
+            }
+        }
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &PlayedTrack> {
+        self.entries.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::VecDeque;
+
+use chrono::{DateTime, Utc};
+
+/// Incidents kept per guild; the oldest is dropped first.
+pub const MAX_INCIDENTS: usize = 20;
+
+/// A track that failed to play or lost its stream partway through.
+#[derive(Debug, Clone)]
+pub struct PlaybackIncident {
+    pub title: String,
+    pub url: String,
+    pub error: String,
+    pub at: DateTime<Utc>,
+}
+
+/// The guild's most recent playback incidents for the current session,
+/// oldest first.
+#[derive(Debug, Clone, Default)]
+pub struct IncidentLog {
+    incidents: VecDeque<PlaybackIncident>,
+    /// Every incident this session, including ones pushed out of the log.
+    total: usize,
+}
+
+impl IncidentLog {
+    pub fn push(&mut self, incident: PlaybackIncident) {
+        if self.incidents.len() >= MAX_INCIDENTS {
+            self.incidents.pop_front();
+        }
+        self.incidents.push_back(incident);
+        self.total += 1;
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &PlaybackIncident> {
+        self.incidents.iter()
+    }
+
+    pub fn total(&self) -> usize {
+        self.total
+    }
+}
@@ -1,2 +1,12 @@
+pub mod chapters;
+pub mod history;
+pub mod incident;
+pub mod now_playing;
+pub mod playlist;
+pub mod preferences;
 pub mod queue;
+pub mod schedule;
+pub mod settings;
+pub mod stats;
 pub mod track;
+pub mod vote;
@@ -0,0 +1,109 @@
+use poise::serenity_prelude::{ChannelId, MessageId};
+
+use super::settings::MAX_NOW_PLAYING_CHANNELS;
+
+/// Most cards one guild can have live: the session's channel plus the extra
+/// Now Playing channels.
+const MAX_CARDS: usize = MAX_NOW_PLAYING_CHANNELS + 1;
+
+/// A guild's live Now Playing messages, at most one per channel. Kept in a
+/// fixed array, so the usual single card doesn't allocate.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NowPlayingCards {
+    slots: [Option<(ChannelId, MessageId)>; MAX_CARDS],
+}
+
+impl NowPlayingCards {
+    /// Records `message_id` as the card in `channel_id`, replacing any
+    /// earlier one there. Returns `false` when every slot is taken.
+    pub fn insert(&mut self, channel_id: ChannelId, message_id: MessageId) -> bool {
+        let slot = match self.slots.iter().position(|s| s.is_some_and(|(ch, _)| ch == channel_id)) {
+            Some(index) => Some(index),
+            None => self.slots.iter().position(Option::is_none),
+        };
+        match slot {
+            Some(index) => {
+                self.slots[index] = Some((channel_id, message_id));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The card posted in `channel_id`.
+    pub fn get(&self, channel_id: ChannelId) -> Option<MessageId> {
+        self.iter().find(|(ch, _)| *ch == channel_id).map(|(_, id)| id)
+    }
+
+    /// Forgets the card with `message_id`, returning whether there was one.
+    pub fn remove_message(&mut self, message_id: MessageId) -> bool {
+        match self.slots.iter_mut().find(|s| s.is_some_and(|(_, id)| id == message_id)) {
+            Some(slot) => {
+                *slot = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ChannelId, MessageId)> + '_ {
+        self.slots.iter().flatten().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.iter().all(Option::is_none)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MUSIC: ChannelId = ChannelId::new(1);
+    const EXTRA: ChannelId = ChannelId::new(2);
+
+    #[test]
+    fn a_card_deleted_by_hand_is_forgotten_and_the_next_one_is_posted_fresh() {
+        let mut cards = NowPlayingCards::default();
+        assert!(cards.insert(MUSIC, MessageId::new(10)));
+
+        assert!(cards.remove_message(MessageId::new(10)));
+        assert!(cards.is_empty());
+        assert_eq!(cards.get(MUSIC), None);
+
+        // Deleting it again, or a message that was never a card, is a no-op
+        assert!(!cards.remove_message(MessageId::new(10)));
+        assert!(!cards.remove_message(MessageId::new(99)));
+
+        assert!(cards.insert(MUSIC, MessageId::new(11)));
+        assert_eq!(cards.get(MUSIC), Some(MessageId::new(11)));
+    }
+
+    #[test]
+    fn deleting_one_card_leaves_the_other_channels_alone() {
+        let mut cards = NowPlayingCards::default();
+        cards.insert(MUSIC, MessageId::new(10));
+        cards.insert(EXTRA, MessageId::new(20));
+
+        assert!(cards.remove_message(MessageId::new(20)));
+        assert_eq!(cards.get(MUSIC), Some(MessageId::new(10)));
+        assert_eq!(cards.get(EXTRA), None);
+    }
+
+    #[test]
+    fn inserting_replaces_the_card_in_the_same_channel() {
+        let mut cards = NowPlayingCards::default();
+        cards.insert(MUSIC, MessageId::new(10));
+        cards.insert(MUSIC, MessageId::new(11));
+        assert_eq!(cards.iter().collect::<Vec<_>>(), [(MUSIC, MessageId::new(11))]);
+    }
+
+    #[test]
+    fn inserting_fails_once_every_slot_is_taken() {
+        let mut cards = NowPlayingCards::default();
+        for channel in 1..=MAX_CARDS as u64 {
+            assert!(cards.insert(ChannelId::new(channel), MessageId::new(channel)));
+        }
+        assert!(!cards.insert(ChannelId::new(100), MessageId::new(100)));
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+
+use super::track::Track;
+
+/// Metadata for a playlist or album, shown in the collection embed.
+#[derive(Debug, Clone, Default)]
+pub struct PlaylistInfo {
+    pub name: String,
+    /// Playlist owner, YouTube channel, or album artists.
+    pub owner: Option<String>,
+    /// Cover image or playlist thumbnail URL.
+    pub image: Option<String>,
+    /// Track count reported by the provider, which can be higher than what
+    /// we could fetch (unavailable or local tracks).
+    pub track_count: Option<usize>,
+    /// Set when the provider errored partway through listing the tracks.
+    pub incomplete: bool,
+    /// The item cap that cut the listing short, if it was reached.
+    pub truncated_at: Option<usize>,
+    /// Entries known to be unplayable and left out.
+    pub skipped: usize,
+    /// Set when only the first page was listed and the rest is fetched in
+    /// the background.
+    pub still_listing: bool,
+    /// The server's per-`/play` limit, when it cut the collection short.
+    pub capped_at: Option<usize>,
+}
+
+impl PlaylistInfo {
+    /// Records how listing the tracks went. A listing that stopped at
+    /// `max_items` only counts as truncated if the provider's track count
+    /// doesn't say that was everything.
+    pub fn note_fetch(&mut self, fetched: &PlaylistTracks, max_items: usize) {
+        self.incomplete = !fetched.complete;
+        self.skipped = fetched.skipped;
+        self.still_listing = fetched.next.is_some();
+        let more_left = self.track_count.is_none_or(|count| count > max_items);
+        // With pages still to come, only the provider's count can tell
+        let will_truncate =
+            self.still_listing && self.track_count.is_some_and(|count| count > max_items);
+        self.truncated_at = (fetched.truncated && more_left || will_truncate).then_some(max_items);
+    }
+
+    /// How many tracks the collection should come to: `fetched` once it's
+    /// fully listed, or the provider's count up to the cap while it's still
+    /// being listed.
+    pub fn expected_tracks(&self, fetched: usize) -> usize {
+        let expected = if self.still_listing {
+            self.truncated_at
+                .or(self.track_count)
+                .map_or(fetched, |n| n.saturating_sub(self.skipped).max(fetched))
+        } else {
+            fetched
+        };
+        self.capped_at.map_or(expected, |cap| expected.min(cap))
+    }
+}
+
+/// Where listing a playlist or album picks up next.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum PageCursor {
+    SpotifyPlaylist { id: String, offset: usize },
+    SpotifyAlbum { id: String, offset: usize },
+    /// YouTube pages by token; `None` is the first page.
+    YouTubePlaylist { id: String, token: Option<String> },
+}
+
+/// Tracks fetched from a playlist or album.
+#[derive(Default)]
+pub struct PlaylistTracks {
+    pub tracks: Vec<Track>,
+    /// False when the provider errored after some tracks were fetched.
+    pub complete: bool,
+    /// Set when listing stopped at the item cap, with more possibly left.
+    pub truncated: bool,
+    /// Entries left out because they can't be played: local files,
+    /// unavailable tracks and podcast episodes.
+    pub skipped: usize,
+    /// Set when only some pages were fetched and more are left under the
+    /// item cap.
+    pub next: Option<PageCursor>,
+}
+
+impl PlaylistTracks {
+    /// Entries read from the provider, which is what the item cap counts.
+    pub fn read(&self) -> usize {
+        self.tracks.len() + self.skipped
+    }
+
+    /// Appends a later page of the same collection.
+    pub fn extend(&mut self, page: PlaylistTracks) {
+        self.tracks.extend(page.tracks);
+        self.skipped += page.skipped;
+        self.complete = page.complete;
+        self.truncated = page.truncated;
+        self.next = page.next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::track::test_track;
+
+    fn listed(tracks: usize, truncated: bool, more: bool) -> PlaylistTracks {
+        PlaylistTracks {
+            tracks: (0..tracks).map(|i| test_track(&i.to_string(), None)).collect(),
+            complete: true,
+            truncated,
+            next: more.then(|| PageCursor::YouTubePlaylist { id: "PL".into(), token: None }),
+            ..PlaylistTracks::default()
+        }
+    }
+
+    #[test]
+    fn a_big_playlist_still_listing_is_announced_as_truncated_at_the_cap() {
+        let mut info = PlaylistInfo { track_count: Some(10_000), ..PlaylistInfo::default() };
+        info.note_fetch(&listed(50, false, true), 1000);
+
+        assert_eq!(info.truncated_at, Some(1000));
+        assert!(info.still_listing);
+        assert_eq!(info.expected_tracks(50), 1000);
+    }
+
+    #[test]
+    fn stopping_at_the_cap_is_not_truncation_when_that_was_everything() {
+        let mut info = PlaylistInfo { track_count: Some(1000), ..PlaylistInfo::default() };
+        info.note_fetch(&listed(1000, true, false), 1000);
+        assert_eq!(info.truncated_at, None);
+
+        // Without a count to go by, a listing cut at the cap is truncated
+        let mut info = PlaylistInfo::default();
+        info.note_fetch(&listed(1000, true, false), 1000);
+        assert_eq!(info.truncated_at, Some(1000));
+        assert_eq!(info.expected_tracks(1000), 1000);
+    }
+
+    #[test]
+    fn the_servers_per_play_limit_caps_the_expected_count() {
+        let mut info = PlaylistInfo {
+            track_count: Some(300),
+            capped_at: Some(200),
+            ..PlaylistInfo::default()
+        };
+        info.note_fetch(&listed(50, false, true), 1000);
+        assert_eq!(info.expected_tracks(50), 200);
+    }
+}
@@ -0,0 +1,104 @@
+use serde::{Deserialize, Serialize};
+
+use crate::domain::settings::{GuildSettings, SearchPreference};
+
+/// How `/play` confirms what it queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Confirmations {
+    /// Replies everyone in the channel can see.
+    #[default]
+    Public,
+    /// Replies only the requester sees.
+    Ephemeral,
+}
+
+/// One user's own choices, kept across servers. `None` leaves a field to
+/// the server's settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UserPreferences {
+    pub search_preference: Option<SearchPreference>,
+    pub confirmations: Option<Confirmations>,
+}
+
+/// What `/play` goes by for one request, once the command's own options, the
+/// requester's preferences and the server's settings are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlayPreferences {
+    /// Provider for text searches.
+    pub search: SearchPreference,
+    /// The provider came from the command or the requester rather than the
+    /// server, so results from the other one aren't wanted.
+    pub search_picked: bool,
+    pub confirmations: Confirmations,
+}
+
+impl PlayPreferences {
+    /// The command's options win, then the requester's preferences, then the
+    /// server's settings, then the defaults. The server has no confirmation
+    /// setting, so those go straight from the requester to the default.
+    pub fn resolve(
+        requested: Option<SearchPreference>,
+        user: &UserPreferences,
+        guild: &GuildSettings,
+    ) -> Self {
+        let picked = requested.or(user.search_preference);
+        Self {
+            search: picked.unwrap_or(guild.search_preference),
+            search_picked: picked.is_some(),
+            confirmations: user.confirmations.unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn guild(search: SearchPreference) -> GuildSettings {
+        GuildSettings {
+            search_preference: search,
+            ..GuildSettings::default()
+        }
+    }
+
+    #[test]
+    fn the_command_wins_over_the_user_and_the_server() {
+        let user = UserPreferences {
+            search_preference: Some(SearchPreference::Spotify),
+            confirmations: None,
+        };
+        let resolved = PlayPreferences::resolve(
+            Some(SearchPreference::Fastest),
+            &user,
+            &guild(SearchPreference::YouTube),
+        );
+        assert_eq!(resolved.search, SearchPreference::Fastest);
+        assert!(resolved.search_picked);
+    }
+
+    #[test]
+    fn the_user_wins_over_the_server() {
+        let user = UserPreferences {
+            search_preference: Some(SearchPreference::Spotify),
+            confirmations: Some(Confirmations::Ephemeral),
+        };
+        let resolved = PlayPreferences::resolve(None, &user, &guild(SearchPreference::YouTube));
+        assert_eq!(resolved.search, SearchPreference::Spotify);
+        assert!(resolved.search_picked);
+        assert_eq!(resolved.confirmations, Confirmations::Ephemeral);
+    }
+
+    #[test]
+    fn the_server_setting_applies_when_nobody_picked() {
+        let user = UserPreferences::default();
+        let resolved = PlayPreferences::resolve(None, &user, &guild(SearchPreference::YouTube));
+        assert_eq!(
+            resolved,
+            PlayPreferences {
+                search: SearchPreference::YouTube,
+                search_picked: false,
+                confirmations: Confirmations::Public,
+            }
+        );
+    }
+}
@@ -1,7 +1,37 @@
 use std::collections::VecDeque;
 
-use super::track::Track;
+use uuid::Uuid;
 
+use super::track::{Requester, Track};
+
+/// Reorders pending tracks into a round-robin rotation by requester: each
+/// requester keeps their own order, and the queue cycles through requesters in
+/// the order they first appear. Tracks the bot queued itself take turns as
+/// one more requester, and so do tracks with no requester.
+pub fn interleave_by_requester(tracks: VecDeque<Track>) -> VecDeque<Track> {
+    let mut lanes: Vec<(Option<Requester>, VecDeque<Track>)> = Vec::new();
+    for track in tracks {
+        match lanes.iter_mut().find(|(user, _)| *user == track.requested_by) {
+            Some((_, lane)) => lane.push_back(track),
+            None => lanes.push((track.requested_by, VecDeque::from([track]))),
+        }
+    }
+
+    let mut interleaved = VecDeque::new();
+    while !lanes.is_empty() {
+        for (_, lane) in &mut lanes {
+            if let Some(track) = lane.pop_front() {
+                interleaved.push_back(track);
+            }
+        }
+        lanes.retain(|(_, lane)| !lane.is_empty());
+    }
+    interleaved
+}
+
+/// One guild's queue: the track playing and the ones waiting, in play order.
+/// Songbird keeps the matching playback queue, linked through
+/// [`Track::handle_id`].
 #[derive(Debug, Default)]
 pub struct MusicQueue {
     current: Option<Track>,
@@ -13,6 +43,17 @@ impl MusicQueue {
         self.tracks.push_back(track);
     }
 
+    /// Pushes a track and re-runs the fair-play rotation over the pending queue.
+    pub fn push_fair(&mut self, track: Track) {
+        self.tracks.push_back(track);
+        self.interleave();
+    }
+
+    /// Reorders the pending queue with [`interleave_by_requester`].
+    pub fn interleave(&mut self) {
+        self.tracks = interleave_by_requester(std::mem::take(&mut self.tracks));
+    }
+
     pub fn pop(&mut self) -> Option<Track> {
         self.tracks.pop_front()
     }
@@ -28,11 +69,128 @@ impl MusicQueue {
         self.current.as_ref()
     }
 
+    pub fn current_mut(&mut self) -> Option<&mut Track> {
+        self.current.as_mut()
+    }
+
+    /// The current or pending track queued as songbird's `handle_id`.
+    pub fn find_by_handle(&self, handle_id: Uuid) -> Option<&Track> {
+        self.current
+            .iter()
+            .chain(self.tracks.iter())
+            .find(|track| track.handle_id == Some(handle_id))
+    }
+
+    pub fn find_by_handle_mut(&mut self, handle_id: Uuid) -> Option<&mut Track> {
+        self.current
+            .iter_mut()
+            .chain(self.tracks.iter_mut())
+            .find(|track| track.handle_id == Some(handle_id))
+    }
+
     /// Takes the current track out (used by skip to return the skipped track).
     pub fn take_current(&mut self) -> Option<Track> {
         self.current.take()
     }
 
+    /// Removes every pending track matching `predicate`, returning them in
+    /// queue order.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&Track) -> bool) -> Vec<Track> {
+        let mut removed = Vec::new();
+        self.tracks.retain(|track| {
+            if predicate(track) {
+                removed.push(track.clone());
+                false
+            } else {
+                true
+            }
+        });
+        removed
+    }
+
+    /// Points the track queued as `old` at a new songbird handle, whether it's
+    /// pending or the one playing. Returns whether the track was found.
+    pub fn replace_handle(&mut self, old: Uuid, new: Uuid) -> bool {
+        let track = self
+            .current
+            .iter_mut()
+            .chain(self.tracks.iter_mut())
+            .find(|t| t.handle_id == Some(old));
+        match track {
+            Some(track) => {
+                track.handle_id = Some(new);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes pending tracks picked from an earlier listing, given as their
+    /// 0-based position and URL then. The queue may have moved since, so each
+    /// pick takes the unclaimed track with that URL nearest its old position.
+    /// Returns the removed tracks in queue order and how many picks matched
+    /// nothing.
+    pub fn remove_picked(&mut self, picks: &[(usize, String)]) -> (Vec<Track>, usize) {
+        let mut chosen: Vec<usize> = Vec::new();
+        let mut missing = 0;
+        for (position, url) in picks {
+            let found = (0..self.tracks.len())
+                .filter(|i| !chosen.contains(i) && self.tracks[*i].url == *url)
+                .min_by_key(|i| i.abs_diff(*position));
+            match found {
+                Some(index) => chosen.push(index),
+                None => missing += 1,
+            }
+        }
+
+        chosen.sort_unstable();
+        let mut removed: Vec<Track> = chosen
+            .iter()
+            .rev()
+            .filter_map(|&index| self.tracks.remove(index))
+            .collect();
+        removed.reverse();
+        (removed, missing)
+    }
+
+    /// Moves the first pending track matching `predicate` to `index`,
+    /// clamped to the end of the queue.
+    pub fn move_track(&mut self, predicate: impl FnMut(&Track) -> bool, index: usize) -> bool {
+        let Some(from) = self.tracks.iter().position(predicate) else {
+            return false;
+        };
+        let track = self.tracks.remove(from).expect("index from position");
+        self.insert_block(index, vec![track]);
+        true
+    }
+
+    /// Takes every pending track matching `predicate` out, keeping their
+    /// order, and puts them back together starting at `index` of what's
+    /// left, clamped to the end. Returns how many moved and the index the
+    /// first one landed at, or `None` if nothing matched.
+    pub fn move_where(
+        &mut self,
+        predicate: impl FnMut(&Track) -> bool,
+        index: usize,
+    ) -> Option<(usize, usize)> {
+        let moved = self.remove_where(predicate);
+        if moved.is_empty() {
+            return None;
+        }
+        let count = moved.len();
+        Some((count, self.insert_block(index, moved)))
+    }
+
+    /// Inserts `tracks` in order at `index`, clamped to the end of the
+    /// queue. Returns the index they start at.
+    fn insert_block(&mut self, index: usize, tracks: Vec<Track>) -> usize {
+        let index = index.min(self.tracks.len());
+        for (offset, track) in tracks.into_iter().enumerate() {
+            self.tracks.insert(index + offset, track);
+        }
+        index
+    }
+
     pub fn clear(&mut self) {
         self.current = None;
         self.tracks.clear();
@@ -50,3 +208,168 @@ impl MusicQueue {
         self.tracks.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use poise::serenity_prelude::UserId;
+
+    use super::*;
+    use crate::domain::track::test_track;
+
+    fn user(id: u64) -> Option<Requester> {
+        Some(Requester::User(UserId::new(id)))
+    }
+
+    fn urls(tracks: &VecDeque<Track>) -> Vec<&str> {
+        tracks.iter().map(|track| track.url.as_str()).collect()
+    }
+
+    #[test]
+    fn interleave_rotates_between_users_in_order_of_first_appearance() {
+        let tracks = VecDeque::from([
+            test_track("a1", user(1)),
+            test_track("a2", user(1)),
+            test_track("b1", user(2)),
+            test_track("c1", user(3)),
+            test_track("b2", user(2)),
+        ]);
+        let interleaved = interleave_by_requester(tracks);
+        assert_eq!(urls(&interleaved), ["a1", "b1", "c1", "a2", "b2"]);
+    }
+
+    #[test]
+    fn interleave_keeps_rotating_once_a_user_runs_out() {
+        let tracks = VecDeque::from([
+            test_track("a1", user(1)),
+            test_track("b1", user(2)),
+            test_track("b2", user(2)),
+            test_track("c1", user(3)),
+            test_track("c2", user(3)),
+            test_track("c3", user(3)),
+        ]);
+        let interleaved = interleave_by_requester(tracks);
+        assert_eq!(urls(&interleaved), ["a1", "b1", "c1", "b2", "c2", "c3"]);
+    }
+
+    #[test]
+    fn interleave_breaks_up_a_burst_from_one_user() {
+        let mut tracks: VecDeque<Track> =
+            (1..=5).map(|i| test_track(&format!("a{i}"), user(1))).collect();
+        tracks.push_back(test_track("b1", user(2)));
+        let interleaved = interleave_by_requester(tracks);
+        assert_eq!(urls(&interleaved), ["a1", "b1", "a2", "a3", "a4", "a5"]);
+    }
+
+    #[test]
+    fn interleave_gives_the_scheduler_and_unknown_requesters_their_own_turns() {
+        let tracks = VecDeque::from([
+            test_track("a1", user(1)),
+            test_track("a2", user(1)),
+            test_track("s1", Some(Requester::Scheduler)),
+            test_track("n1", None),
+        ]);
+        let interleaved = interleave_by_requester(tracks);
+        assert_eq!(urls(&interleaved), ["a1", "s1", "n1", "a2"]);
+    }
+
+    #[test]
+    fn interleave_leaves_an_empty_queue_empty() {
+        assert!(interleave_by_requester(VecDeque::new()).is_empty());
+    }
+
+    #[test]
+    fn push_fair_slots_a_new_user_in_behind_the_first_track() {
+        let mut queue = MusicQueue::default();
+        for i in 1..=3 {
+            queue.push_fair(test_track(&format!("a{i}"), user(1)));
+        }
+        queue.push_fair(test_track("b1", user(2)));
+        assert_eq!(urls(queue.list()), ["a1", "b1", "a2", "a3"]);
+    }
+
+    fn queue_of(names: &[&str]) -> MusicQueue {
+        let mut queue = MusicQueue::default();
+        for name in names {
+            queue.push(test_track(name, None));
+        }
+        queue
+    }
+
+    fn picks(picked: &[(usize, &str)]) -> Vec<(usize, String)> {
+        picked.iter().map(|&(i, url)| (i, url.to_string())).collect()
+    }
+
+    #[test]
+    fn remove_picked_finds_tracks_that_moved_since_the_listing() {
+        let mut queue = queue_of(&["a", "b", "c", "d"]);
+        // "x" landed at the front after the listing was shown
+        queue.insert_block(0, vec![test_track("x", None)]);
+
+        let (removed, missing) = queue.remove_picked(&picks(&[(3, "d"), (1, "b")]));
+        assert_eq!(urls(&removed.into()), ["b", "d"]);
+        assert_eq!(missing, 0);
+        assert_eq!(urls(queue.list()), ["x", "a", "c"]);
+    }
+
+    #[test]
+    fn remove_picked_takes_the_duplicate_nearest_each_pick() {
+        let mut queue = queue_of(&["a", "dup", "b", "c", "dup"]);
+
+        let (removed, _) = queue.remove_picked(&picks(&[(4, "dup")]));
+        assert_eq!(removed.len(), 1);
+        assert_eq!(urls(queue.list()), ["a", "dup", "b", "c"]);
+
+        // Two picks of the same URL claim different copies
+        let mut queue = queue_of(&["dup", "a", "dup"]);
+        let (removed, missing) = queue.remove_picked(&picks(&[(0, "dup"), (0, "dup")]));
+        assert_eq!((removed.len(), missing), (2, 0));
+        assert_eq!(urls(queue.list()), ["a"]);
+    }
+
+    #[test]
+    fn remove_picked_counts_tracks_that_are_gone() {
+        let mut queue = queue_of(&["a", "b"]);
+
+        let (removed, missing) = queue.remove_picked(&picks(&[(0, "a"), (1, "gone")]));
+        assert_eq!(urls(&removed.into()), ["a"]);
+        assert_eq!(missing, 1);
+        assert_eq!(urls(queue.list()), ["b"]);
+    }
+
+    #[test]
+    fn move_where_gathers_matches_in_order_at_the_index() {
+        let mut queue = queue_of(&["a", "x1", "b", "x2", "c"]);
+
+        let moved = queue.move_where(|t| t.url.starts_with('x'), 0);
+        assert_eq!(moved, Some((2, 0)));
+        assert_eq!(urls(queue.list()), ["x1", "x2", "a", "b", "c"]);
+    }
+
+    #[test]
+    fn move_where_counts_the_index_in_what_is_left_and_clamps_it() {
+        let mut queue = queue_of(&["x1", "a", "b", "x2"]);
+        assert_eq!(queue.move_where(|t| t.url.starts_with('x'), 1), Some((2, 1)));
+        assert_eq!(urls(queue.list()), ["a", "x1", "x2", "b"]);
+
+        assert_eq!(queue.move_where(|t| t.url == "a", 99), Some((1, 3)));
+        assert_eq!(urls(queue.list()), ["x1", "x2", "b", "a"]);
+    }
+
+    #[test]
+    fn move_where_without_a_match_leaves_the_queue_alone() {
+        let mut queue = queue_of(&["a", "b"]);
+        assert_eq!(queue.move_where(|t| t.url == "z", 0), None);
+        assert_eq!(urls(queue.list()), ["a", "b"]);
+    }
+
+    #[test]
+    fn insert_block_keeps_the_block_together() {
+        let mut queue = queue_of(&["a", "b"]);
+        let block = vec![test_track("x", None), test_track("y", None)];
+        assert_eq!(queue.insert_block(1, block), 1);
+        assert_eq!(urls(queue.list()), ["a", "x", "y", "b"]);
+
+        assert_eq!(queue.insert_block(10, vec![test_track("z", None)]), 4);
+        assert_eq!(urls(queue.list()), ["a", "x", "y", "b", "z"]);
+    }
+}
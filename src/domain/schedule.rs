@@ -0,0 +1,45 @@
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, Utc};
+use poise::serenity_prelude::{ChannelId, UserId};
+use serde::{Deserialize, Serialize};
+
+/// A query to start playing in a voice channel at a set time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledJob {
+    /// Per-guild id shown by `/schedule list`.
+    pub id: u32,
+    pub at: DateTime<Utc>,
+    pub voice_channel: ChannelId,
+    /// Where the job was scheduled from; start and skip notices go here.
+    pub text_channel: ChannelId,
+    pub query: String,
+    pub created_by: UserId,
+}
+
+/// Formats accepted for a date and time without an offset, read as the bot
+/// host's local time.
+const NAIVE_FORMATS: [&str; 3] = ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M"];
+
+/// Parses an RFC 3339 timestamp, a local date and time, or a bare `HH:MM`,
+/// which means the next time the clock shows it after `now`.
+pub fn parse_time(input: &str, now: DateTime<Local>) -> Option<DateTime<Utc>> {
+    let input = input.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(input) {
+        return Some(at.with_timezone(&Utc));
+    }
+
+    let naive = NAIVE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .or_else(|| {
+            let time = NaiveTime::parse_from_str(input, "%H:%M").ok()?;
+            let mut day = now.date_naive();
+            if time <= now.time() {
+                day = day.succ_opt()?;
+            }
+            Some(day.and_time(time))
+        })?;
+    naive
+        .and_local_timezone(Local)
+        .earliest()
+        .map(|at| at.with_timezone(&Utc))
+}
@@ -0,0 +1,418 @@
+use std::fmt;
+
+use poise::serenity_prelude::ChannelId;
+use serde::{Deserialize, Serialize};
+
+use super::track::{Track, TrackSource};
+
+/// Posted when the bot leaves after sitting idle. `{timeout}` becomes the
+/// idle time, e.g. "15 minutes".
+pub const DEFAULT_INACTIVITY_MESSAGE: &str = "Disconnected due to {timeout} of inactivity.";
+
+/// Most extra channels that also get the Now Playing card.
+pub const MAX_NOW_PLAYING_CHANNELS: usize = 3;
+
+/// Most rules one server's blocklist can hold.
+pub const MAX_BLOCKLIST_RULES: usize = 500;
+/// Longest value a blocklist rule accepts, in characters.
+const MAX_URL_CHARS: usize = 200;
+const MAX_CHANNEL_CHARS: usize = 100;
+const MAX_KEYWORD_CHARS: usize = 100;
+
+/// A blocklist entry. Matching is case-insensitive. Exported and imported as
+/// `{"kind": "keyword", "value": "nightcore"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "lowercase")]
+pub enum BlockRule {
+    /// Exact track/video URL.
+    Url(String),
+    /// YouTube channel (or Spotify artist) name.
+    Channel(String),
+    /// Whole word or phrase in the title.
+    Keyword(String),
+}
+
+impl BlockRule {
+    /// Trims the rule's value and checks its shape: URLs must be http(s)
+    /// links, and no value may be empty or too long. Returns why otherwise.
+    pub fn validated(self) -> Result<Self, String> {
+        match self {
+            BlockRule::Url(url) => {
+                let url = checked_value("URL", &url, MAX_URL_CHARS)?;
+                let is_link = (url.starts_with("https://") || url.starts_with("http://"))
+                    && !url.contains(char::is_whitespace);
+                if !is_link {
+                    return Err(format!("`{url}` isn't an http(s) link"));
+                }
+                Ok(BlockRule::Url(url))
+            }
+            BlockRule::Channel(channel) => {
+                checked_value("channel name", &channel, MAX_CHANNEL_CHARS).map(BlockRule::Channel)
+            }
+            BlockRule::Keyword(keyword) => {
+                checked_value("keyword", &keyword, MAX_KEYWORD_CHARS).map(BlockRule::Keyword)
+            }
+        }
+    }
+
+    pub fn matches(&self, track: &Track) -> bool {
+        match self {
+            BlockRule::Url(url) => track.url.trim().eq_ignore_ascii_case(url.trim()),
+            BlockRule::Channel(channel) => track.artist.trim().eq_ignore_ascii_case(channel.trim()),
+            BlockRule::Keyword(keyword) => contains_word(&track.title, keyword),
+        }
+    }
+}
+
+impl fmt::Display for BlockRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockRule::Url(url) => write!(f, "URL `{url}`"),
+            BlockRule::Channel(channel) => write!(f, "channel `{channel}`"),
+            BlockRule::Keyword(keyword) => write!(f, "keyword `{keyword}`"),
+        }
+    }
+}
+
+/// `value` trimmed, unless that leaves it empty or over `max` characters.
+fn checked_value(what: &str, value: &str, max: usize) -> Result<String, String> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Err(format!("the {what} is empty"));
+    }
+    if value.chars().count() > max {
+        return Err(format!("the {what} is longer than {max} characters"));
+    }
+    Ok(value.to_string())
+}
+
+/// Case-insensitive search for `needle` in `haystack` that only matches on
+/// word boundaries, so "ass" doesn't match "assassin".
+fn contains_word(haystack: &str, needle: &str) -> bool {
+    let haystack = haystack.to_lowercase();
+    let needle = needle.trim().to_lowercase();
+    if needle.is_empty() {
+        return false;
+    }
+
+    haystack.match_indices(&needle).any(|(start, _)| {
+        let end = start + needle.len();
+        let before = haystack[..start].chars().next_back();
+        let after = haystack[end..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+/// Which provider answers free-text `/play` searches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SearchPreference {
+    /// Race both providers and take whichever returns results first.
+    #[default]
+    Fastest,
+    /// Ask YouTube, falling back to Spotify when it finds nothing.
+    YouTube,
+    /// Ask Spotify, falling back to YouTube when it finds nothing.
+    Spotify,
+}
+
+/// What `/play` does when the bot is connected to a different voice channel
+/// than the requester.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum FollowRequester {
+    /// Offer to move, with a button prompt.
+    Ask,
+    /// Stay put and reject the request.
+    Never,
+    /// Move if nothing is playing, otherwise reject.
+    #[default]
+    WhenIdle,
+}
+
+/// Where a single `/play` track goes while a collection is still being
+/// added in the background.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SingleTrackOrder {
+    /// Straight into the queue, ahead of the collection's remaining tracks.
+    #[default]
+    JumpAhead,
+    /// In line behind the collections already being added.
+    AppendAfter,
+}
+
+/// How the bot shows what's playing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum NowPlayingMode {
+    /// Post a fresh card on every track change.
+    #[default]
+    Card,
+    /// Keep one message per session and edit it in place.
+    Board,
+}
+
+/// Where the Now Playing embed shows the track's artwork.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ArtworkStyle {
+    /// Right-aligned thumbnail.
+    #[default]
+    Thumbnail,
+    /// Right-aligned thumbnail from the smaller artwork, which isn't
+    /// letterboxed for YouTube videos.
+    SmallThumbnail,
+    /// Full-width image under the description, from the largest artwork.
+    Banner,
+}
+
+/// Per-guild preferences that admins can change with `/settings`. Fields
+/// missing from a stored copy take their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GuildSettings {
+    /// Colour used for every embed instead of the per-source colours.
+    pub embed_color_override: Option<u32>,
+    /// Colour embeds by track source (Spotify green, YouTube red).
+    pub use_source_colors: bool,
+    /// Drop thumbnails and the author row to save vertical space.
+    pub compact_embeds: bool,
+    /// Interleave pending tracks round-robin by requester instead of FIFO.
+    pub fair_queue: bool,
+    /// Run `/clean` automatically whenever someone leaves the bot's channel.
+    pub auto_clean: bool,
+    /// Most tracks that can be waiting in the queue; `None` is unlimited.
+    pub queue_limit: Option<usize>,
+    /// Most tracks a single `/play` of a playlist, album or batch of links
+    /// can add; `None` is unlimited.
+    pub max_collection_size: Option<usize>,
+    /// Reject collections over `max_collection_size` instead of queueing
+    /// their first tracks.
+    pub strict_collection_size: bool,
+    /// Where single tracks go while a collection is being added.
+    pub single_tracks_during_collection: SingleTrackOrder,
+    /// Tracks matching any of these rules can't be queued.
+    pub blocklist: Vec<BlockRule>,
+    /// Pin the "Now Playing" message in the music channel.
+    pub pin_now_playing: bool,
+    /// Per-track cards or a single session board.
+    pub now_playing_mode: NowPlayingMode,
+    /// How Now Playing shows the artwork.
+    pub artwork: ArtworkStyle,
+    /// Channels that get the Now Playing card as well as the one the session
+    /// is bound to, at most [`MAX_NOW_PLAYING_CHANNELS`].
+    pub now_playing_channels: Vec<ChannelId>,
+    /// Provider used for text searches.
+    pub search_preference: SearchPreference,
+    /// Autocomplete suggests from both providers, merged, instead of just
+    /// the search provider's results.
+    pub merged_autocomplete: bool,
+    /// Favour uploads from auto-generated "Artist - Topic" channels for
+    /// YouTube searches and Spotify lookups.
+    pub prefer_topic: bool,
+    /// Whether `/play` moves the bot to the requester's channel.
+    pub follow_requester: FollowRequester,
+    /// Percent YouTube links play louder (or quieter, when negative).
+    pub volume_offset_youtube: i32,
+    /// Percent Spotify tracks play louder (or quieter, when negative). They
+    /// usually resolve to quieter "Artist - Topic" uploads.
+    pub volume_offset_spotify: i32,
+    /// Turn each track up or down towards a common loudness when yt-dlp
+    /// reports one for it.
+    pub normalize_loudness: bool,
+    /// Post a notice when the bot leaves after sitting idle.
+    pub inactivity_notice: bool,
+    /// Text of that notice; `None` uses [`DEFAULT_INACTIVITY_MESSAGE`].
+    pub inactivity_message: Option<String>,
+    /// Treat deafened members as not listening, so a channel where everyone
+    /// is deafened counts as empty for standby and the inactivity timeout.
+    pub count_deafened_as_absent: bool,
+    /// Also announce each track as a plain line of text above the Now
+    /// Playing embed, which screen readers handle better.
+    pub plain_text_announcements: bool,
+    /// Show the playing track as the voice channel's status, or the music
+    /// channel's topic when the bot can't set the status.
+    pub now_playing_status: bool,
+}
+
+impl Default for GuildSettings {
+    fn default() -> Self {
+        Self {
+            embed_color_override: None,
+            use_source_colors: true,
+            compact_embeds: false,
+            fair_queue: false,
+            auto_clean: false,
+            queue_limit: None,
+            max_collection_size: None,
+            strict_collection_size: false,
+            single_tracks_during_collection: SingleTrackOrder::JumpAhead,
+            blocklist: Vec::new(),
+            pin_now_playing: false,
+            now_playing_mode: NowPlayingMode::Card,
+            artwork: ArtworkStyle::Thumbnail,
+            now_playing_channels: Vec::new(),
+            search_preference: SearchPreference::Fastest,
+            merged_autocomplete: false,
+            prefer_topic: false,
+            follow_requester: FollowRequester::WhenIdle,
+            volume_offset_youtube: 0,
+            volume_offset_spotify: 0,
+            normalize_loudness: false,
+            inactivity_notice: true,
+            inactivity_message: None,
+            count_deafened_as_absent: false,
+            plain_text_announcements: false,
+            now_playing_status: false,
+        }
+    }
+}
+
+impl GuildSettings {
+    /// Whether `pending` tracks have crossed 80% of the queue limit.
+    pub fn queue_nearly_full(&self, pending: usize) -> bool {
+        self.queue_limit.is_some_and(|limit| pending * 5 >= limit * 4)
+    }
+
+    /// Percent the volume is adjusted by for tracks from `source`.
+    pub fn volume_offset(&self, source: &TrackSource) -> i32 {
+        match source {
+            TrackSource::YouTube => self.volume_offset_youtube,
+            TrackSource::Spotify => self.volume_offset_spotify,
+        }
+    }
+
+    /// Every channel the Now Playing card goes to: the session's channel
+    /// first, then the extra ones.
+    pub fn now_playing_targets(
+        &self,
+        session: Option<ChannelId>,
+    ) -> impl Iterator<Item = ChannelId> + '_ {
+        let extra = self.now_playing_channels.iter().copied();
+        session.into_iter().chain(extra.filter(move |ch| Some(*ch) != session))
+    }
+
+    /// Songbird volume for tracks from `source`, 1.0 being unchanged.
+    pub fn volume_for(&self, source: &TrackSource) -> f32 {
+        (100 + self.volume_offset(source)) as f32 / 100.0
+    }
+
+    /// Songbird volume for `track`: its source's offset, evened out by its
+    /// loudness when that's turned on.
+    pub fn track_volume(&self, track: &Track) -> f32 {
+        let volume = self.volume_for(&track.source);
+        if self.normalize_loudness { volume * track.loudness_gain() } else { volume }
+    }
+
+    /// The notice to post after an idle disconnect, with `{timeout}` filled
+    /// in, or `None` when it's turned off.
+    pub fn inactivity_text(&self, timeout: &str) -> Option<String> {
+        if !self.inactivity_notice {
+            return None;
+        }
+        let message = self.inactivity_message.as_deref().unwrap_or(DEFAULT_INACTIVITY_MESSAGE);
+        Some(message.replace("{timeout}", timeout))
+    }
+
+    /// Returns the first blocklist rule the track matches, if any.
+    pub fn blocked_by(&self, track: &Track) -> Option<&BlockRule> {
+        self.blocklist.iter().find(|rule| rule.matches(track))
+    }
+}
+
+/// "+20%", "−10%" or "0%".
+pub fn format_offset(offset: i32) -> String {
+    match offset {
+        0 => "0%".to_string(),
+        o if o > 0 => format!("+{o}%"),
+        o => format!("−{}%", o.unsigned_abs()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::track::test_track;
+
+    #[test]
+    fn contains_word_matches_whole_words_only() {
+        assert!(contains_word("Song (Nightcore Remix)", "nightcore"));
+        assert!(contains_word("nightcore", "NIGHTCORE"));
+        assert!(!contains_word("Nightcored", "nightcore"));
+        assert!(!contains_word("Assassin's Creed", "ass"));
+    }
+
+    #[test]
+    fn contains_word_matches_phrases_and_punctuation_boundaries() {
+        assert!(contains_word("Live at Wembley - 1986", "live at wembley"));
+        assert!(contains_word("[sped up] Song", "sped up"));
+        assert!(!contains_word("Song", "  "));
+    }
+
+    #[test]
+    fn contains_word_handles_non_ascii_titles() {
+        assert!(contains_word("Café Tacvba — Eres", "café"));
+        assert!(!contains_word("Cafés", "café"));
+    }
+
+    #[test]
+    fn block_rules_match_case_insensitively() {
+        let mut track = test_track("https://youtu.be/abc", None);
+        track.title = "Song (Nightcore)".to_string();
+        track.artist = "Some Channel".to_string();
+
+        assert!(BlockRule::Url("HTTPS://youtu.be/abc ".to_string()).matches(&track));
+        assert!(BlockRule::Channel("some channel".to_string()).matches(&track));
+        assert!(BlockRule::Keyword("nightcore".to_string()).matches(&track));
+        assert!(!BlockRule::Keyword("night".to_string()).matches(&track));
+    }
+
+    #[test]
+    fn validated_rules_come_back_trimmed() {
+        let rule = BlockRule::Keyword("  sped up ".to_string()).validated();
+        assert_eq!(rule, Ok(BlockRule::Keyword("sped up".to_string())));
+        let rule = BlockRule::Url(" http://youtu.be/abc".to_string()).validated();
+        assert_eq!(rule, Ok(BlockRule::Url("http://youtu.be/abc".to_string())));
+    }
+
+    #[test]
+    fn validated_rejects_empty_long_and_non_link_values() {
+        let empty = BlockRule::Channel("   ".to_string()).validated();
+        assert_eq!(empty, Err("the channel name is empty".to_string()));
+
+        let long = BlockRule::Keyword("k".repeat(MAX_KEYWORD_CHARS + 1)).validated();
+        assert_eq!(long, Err("the keyword is longer than 100 characters".to_string()));
+        let longest = BlockRule::Keyword("k".repeat(MAX_KEYWORD_CHARS)).validated();
+        assert!(longest.is_ok());
+
+        let not_a_link = BlockRule::Url("youtu.be/abc".to_string()).validated();
+        assert_eq!(not_a_link, Err("`youtu.be/abc` isn't an http(s) link".to_string()));
+        assert!(BlockRule::Url("https://a b".to_string()).validated().is_err());
+        assert!(BlockRule::Url("ftp://example.com".to_string()).validated().is_err());
+    }
+
+    #[test]
+    fn the_queue_counts_as_nearly_full_from_80_percent() {
+        let settings = GuildSettings {
+            queue_limit: Some(100),
+            ..GuildSettings::default()
+        };
+        assert!(!settings.queue_nearly_full(0));
+        assert!(!settings.queue_nearly_full(79));
+        assert!(settings.queue_nearly_full(80));
+        assert!(settings.queue_nearly_full(100));
+        // Over the limit, e.g. after it was lowered
+        assert!(settings.queue_nearly_full(150));
+
+        // Rounds up for limits that don't divide evenly
+        let settings = GuildSettings {
+            queue_limit: Some(7),
+            ..GuildSettings::default()
+        };
+        assert!(!settings.queue_nearly_full(5));
+        assert!(settings.queue_nearly_full(6));
+    }
+
+    #[test]
+    fn without_a_queue_limit_the_queue_is_never_nearly_full() {
+        let settings = GuildSettings::default();
+        assert!(!settings.queue_nearly_full(0));
+        assert!(!settings.queue_nearly_full(10_000));
+    }
+}
@@ -0,0 +1,156 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use poise::serenity_prelude::UserId;
+use serde::{Deserialize, Serialize};
+
+use super::track::{Requester, Track};
+
+/// Distinct tracks remembered per guild; the least recently played is
+/// forgotten first.
+pub const MAX_TRACKED: usize = 1000;
+/// Listening time after which a track counts as played; shorter tracks need
+/// half their length.
+const COUNTED_PLAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackStats {
+    pub title: String,
+    pub artist: String,
+    pub plays: u32,
+    /// Value of `GuildStats::clock` when this track last finished.
+    last_played: u64,
+}
+
+/// Play counts and listening time a guild has built up since it started
+/// using the bot, or since `/top reset`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GuildStats {
+    /// Keyed by track URL.
+    tracks: HashMap<String, TrackStats>,
+    requesters: HashMap<UserId, u32>,
+    pub listening: Duration,
+    /// Bumped on every recorded play to order tracks by recency.
+    clock: u64,
+}
+
+impl GuildStats {
+    /// Adds `listened` to the guild's listening time, and counts a play of
+    /// `track` if it was heard long enough. Returns whether it counted.
+    pub fn record(&mut self, track: &Track, listened: Duration) -> bool {
+        self.listening += listened;
+        let needed = track.duration.map_or(COUNTED_PLAY, |d| (d / 2).min(COUNTED_PLAY));
+        if listened < needed {
+            return false;
+        }
+
+        self.clock += 1;
+        // Only people count as requesters
+        if let Some(user) = track.requested_by.and_then(Requester::user) {
+            *self.requesters.entry(user).or_default() += 1;
+        }
+
+        if !self.tracks.contains_key(&track.url) && self.tracks.len() >= MAX_TRACKED {
+            let stalest = self
+                .tracks
+                .iter()
+                .min_by_key(|(_, stats)| stats.last_played)
+                .map(|(url, _)| url.clone());
+            if let Some(url) = stalest {
+                self.tracks.remove(&url);
+            }
+        }
+
+        let stats = self.tracks.entry(track.url.clone()).or_insert_with(|| TrackStats {
+            title: track.title.clone(),
+            artist: track.artist.clone(),
+            plays: 0,
+            last_played: 0,
+        });
+        stats.plays += 1;
+        stats.last_played = self.clock;
+        true
+    }
+
+    /// The `n` most played tracks as `(url, stats)`, most played first.
+    pub fn top_tracks(&self, n: usize) -> Vec<(&str, &TrackStats)> {
+        let mut tracks: Vec<_> = self
+            .tracks
+            .iter()
+            .map(|(url, stats)| (url.as_str(), stats))
+            .collect();
+        tracks.sort_by(|a, b| {
+            b.1.plays
+                .cmp(&a.1.plays)
+                .then(b.1.last_played.cmp(&a.1.last_played))
+        });
+        tracks.truncate(n);
+        tracks
+    }
+
+    /// The `n` users whose requests were played most, most first.
+    pub fn top_requesters(&self, n: usize) -> Vec<(UserId, u32)> {
+        let mut requesters: Vec<_> = self
+            .requesters
+            .iter()
+            .map(|(user, plays)| (*user, *plays))
+            .collect();
+        requesters.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        requesters.truncate(n);
+        requesters
+    }
+
+    pub fn total_plays(&self) -> u64 {
+        self.clock
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::track::test_track;
+
+    const PLAYED: Duration = Duration::from_secs(60);
+
+    fn tracked(stats: &GuildStats, url: &str) -> bool {
+        stats.tracks.contains_key(url)
+    }
+
+    #[test]
+    fn a_full_table_forgets_the_least_recently_played_track() {
+        let mut stats = GuildStats::default();
+        for i in 0..MAX_TRACKED {
+            stats.record(&test_track(&format!("t{i}"), None), PLAYED);
+        }
+        // Playing the oldest again makes t1 the stalest
+        stats.record(&test_track("t0", None), PLAYED);
+
+        stats.record(&test_track("new", None), PLAYED);
+        assert_eq!(stats.tracks.len(), MAX_TRACKED);
+        assert!(tracked(&stats, "new"));
+        assert!(tracked(&stats, "t0"));
+        assert!(!tracked(&stats, "t1"));
+        assert!(tracked(&stats, "t2"));
+    }
+
+    #[test]
+    fn replaying_a_tracked_song_never_evicts() {
+        let mut stats = GuildStats::default();
+        for i in 0..MAX_TRACKED {
+            stats.record(&test_track(&format!("t{i}"), None), PLAYED);
+        }
+        stats.record(&test_track("t5", None), PLAYED);
+        assert_eq!(stats.tracks.len(), MAX_TRACKED);
+        assert!(tracked(&stats, "t0"));
+        assert_eq!(stats.tracks["t5"].plays, 2);
+    }
+
+    #[test]
+    fn short_listens_add_time_without_counting_a_play() {
+        let mut stats = GuildStats::default();
+        assert!(!stats.record(&test_track("a", None), Duration::from_secs(5)));
+        assert_eq!(stats.total_plays(), 0);
+        assert!(stats.top_tracks(10).is_empty());
+        assert_eq!(stats.listening, Duration::from_secs(5));
+    }
+}
@@ -1,11 +1,46 @@
 use std::fmt;
+use std::time::{Duration, SystemTime};
 
-#[derive(Debug, Clone)]
+use poise::serenity_prelude::{Colour, UserId};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::chapters::Chapter;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum TrackSource {
     YouTube,
     Spotify,
 }
 
+const SPOTIFY_ICON: &str = "https://upload.wikimedia.org/wikipedia/commons/thumb/1/19/Spotify_logo_without_text.svg/168px-Spotify_logo_without_text.svg.png";
+const YOUTUBE_ICON: &str = "https://www.gstatic.com/images/branding/product/2x/youtube_64dp.png";
+
+impl TrackSource {
+    /// Logo shown as the embed author icon.
+    pub fn icon_url(&self) -> &'static str {
+        match self {
+            TrackSource::Spotify => SPOTIFY_ICON,
+            TrackSource::YouTube => YOUTUBE_ICON,
+        }
+    }
+
+    /// Brand colour, before any guild embed settings are applied.
+    pub fn colour(&self) -> Colour {
+        match self {
+            TrackSource::Spotify => Colour::new(0x1DB954),
+            TrackSource::YouTube => Colour::new(0xFF0000),
+        }
+    }
+
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            TrackSource::Spotify => "Spotify",
+            TrackSource::YouTube => "YouTube",
+        }
+    }
+}
+
 impl fmt::Display for TrackSource {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -15,14 +50,149 @@ impl fmt::Display for TrackSource {
     }
 }
 
-#[derive(Debug, Clone)]
+/// Who or what queued a track.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Requester {
+    User(UserId),
+    /// Queued by a `/schedule` job when it came due.
+    Scheduler,
+    /// Queued by autoplay to keep music going once the queue ran out.
+    Autoplay,
+    /// Put back on start after a restart cut short the collection it was
+    /// queued with.
+    Restored,
+}
+
+impl Requester {
+    /// The person behind the request, if a person made it. Per-user limits
+    /// and counts go by this, so the bot's own tracks are exempt from them.
+    pub fn user(self) -> Option<UserId> {
+        match self {
+            Requester::User(user_id) => Some(user_id),
+            Requester::Scheduler | Requester::Autoplay | Requester::Restored => None,
+        }
+    }
+}
+
+impl fmt::Display for Requester {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Requester::User(user_id) => write!(f, "<@{user_id}>"),
+            Requester::Scheduler => write!(f, "Scheduler"),
+            Requester::Autoplay => write!(f, "Autoplay"),
+            Requester::Restored => write!(f, "Restored after a restart"),
+        }
+    }
+}
+
+/// The part of the underlying media a track plays, for chapters of a longer
+/// video.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct TrackSpan {
+    pub start: Duration,
+    /// `None` plays to the end of the media.
+    pub end: Option<Duration>,
+}
+
+/// A song or video as the bot knows it, from a YouTube or Spotify lookup.
+/// Spotify tracks play through a YouTube search; the rest play their `url`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Track {
     pub title: String,
     pub artist: String,
+    /// Album name, only known for Spotify tracks.
+    pub album: Option<String>,
     pub url: String,
     pub source: TrackSource,
-    pub duration: Option<String>,
+    pub duration: Option<Duration>,
     pub thumbnail_url: Option<String>,
+    /// Smaller artwork for a compact thumbnail (YouTube `medium`, Spotify
+    /// 300px).
+    pub thumbnail_small: Option<String>,
+    /// Largest artwork available, for a banner image (YouTube `maxres`,
+    /// Spotify 640px).
+    pub thumbnail_large: Option<String>,
+    /// Who queued the track; `None` until it's enqueued.
+    pub requested_by: Option<Requester>,
+    /// Id of the songbird track this entry was queued as, linking the domain
+    /// queue to the playback queue. Not kept on disk, since the playback
+    /// queue doesn't outlive the session.
+    #[serde(skip)]
+    pub handle_id: Option<Uuid>,
+    /// When the track was added to the guild queue.
+    pub enqueued_at: Option<SystemTime>,
+    /// The YouTube upload actually playing for a Spotify track, once yt-dlp
+    /// has picked one.
+    pub resolved_url: Option<String>,
+    /// Integrated loudness of the playing upload in LUFS, when yt-dlp
+    /// reported one.
+    pub loudness: Option<f64>,
+    /// Only play this part of the media; `duration` is the span's length.
+    pub span: Option<TrackSpan>,
+    /// Chapters in a YouTube video's description, at their positions in the
+    /// underlying media. Empty for other tracks and for chapter entries.
+    pub chapters: Vec<Chapter>,
+}
+
+/// Loudness tracks are evened out towards, in LUFS.
+const TARGET_LOUDNESS: f64 = -14.0;
+/// Most a track is turned up, in dB, so a quiet reading can't make it clip.
+const MAX_BOOST_DB: f64 = 6.0;
+/// Most a track is turned down, in dB.
+const MAX_CUT_DB: f64 = 12.0;
+
+impl Track {
+    /// Length formatted for display, e.g. `3:07` or `1:02:45`.
+    pub fn formatted_duration(&self) -> Option<String> {
+        self.duration.map(|d| format_secs(d.as_secs()))
+    }
+
+    /// Volume multiplier that brings the track to [`TARGET_LOUDNESS`], 1.0
+    /// when its loudness isn't known.
+    pub fn loudness_gain(&self) -> f32 {
+        match self.loudness {
+            Some(lufs) => {
+                let db = (TARGET_LOUDNESS - lufs).clamp(-MAX_CUT_DB, MAX_BOOST_DB);
+                10f64.powf(db / 20.0) as f32
+            }
+            None => 1.0,
+        }
+    }
+
+    /// Title and artist with case, punctuation and spacing dropped, so
+    /// different uploads of one song compare equal.
+    pub fn dedup_key(&self) -> String {
+        format!("{} {}", self.title, self.artist)
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    }
+
+    /// Where this track ends in the underlying media: a chapter's end, or its
+    /// start plus its length. `None` when that isn't known.
+    pub fn media_end(&self) -> Option<Duration> {
+        match self.span {
+            Some(span) => span.end.or_else(|| Some(span.start + self.duration?)),
+            None => self.duration,
+        }
+    }
+
+    /// How far into this track a playback position in the underlying media
+    /// is, accounting for a chapter's start.
+    pub fn elapsed(&self, position: Duration) -> Duration {
+        position.saturating_sub(self.span.map_or(Duration::ZERO, |span| span.start))
+    }
+}
+
+/// Formats seconds as `m:ss`, or `h:mm:ss` from an hour up.
+pub fn format_secs(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs / 60 % 60, secs % 60);
+    if hours > 0 {
+        format!("{hours}:{minutes:02}:{seconds:02}")
+    } else {
+        format!("{minutes}:{seconds:02}")
+    }
 }
 
 impl fmt::Display for Track {
@@ -30,3 +200,107 @@ impl fmt::Display for Track {
         write!(f, "{} {} - {}", self.source, self.title, self.artist)
     }
 }
+
+/// A YouTube track with only a title and URL, for tests.
+#[cfg(test)]
+pub(crate) fn test_track(url: &str, requested_by: Option<Requester>) -> Track {
+    Track {
+        title: url.to_string(),
+        artist: String::new(),
+        album: None,
+        url: url.to_string(),
+        source: TrackSource::YouTube,
+        duration: None,
+        thumbnail_url: None,
+        thumbnail_small: None,
+        thumbnail_large: None,
+        requested_by,
+        handle_id: None,
+        enqueued_at: None,
+        resolved_url: None,
+        loudness: None,
+        span: None,
+        chapters: Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_people_count_as_users() {
+        let user = Requester::User(UserId::new(7));
+        assert_eq!(user.user(), Some(UserId::new(7)));
+        assert_eq!(user.to_string(), "<@7>");
+
+        let bots = [Requester::Scheduler, Requester::Autoplay, Requester::Restored];
+        assert!(bots.iter().all(|requester| requester.user().is_none()));
+        let names = bots.map(|requester| requester.to_string());
+        assert_eq!(names, ["Scheduler", "Autoplay", "Restored after a restart"]);
+    }
+
+    #[test]
+    fn format_secs_pads_seconds_under_an_hour() {
+        assert_eq!(format_secs(0), "0:00");
+        assert_eq!(format_secs(7), "0:07");
+        assert_eq!(format_secs(187), "3:07");
+        assert_eq!(format_secs(3599), "59:59");
+    }
+
+    #[test]
+    fn format_secs_adds_hours_from_an_hour_up() {
+        assert_eq!(format_secs(3600), "1:00:00");
+        assert_eq!(format_secs(3765), "1:02:45");
+        assert_eq!(format_secs(36_000 + 61), "10:01:01");
+    }
+
+    fn gain_at(loudness: Option<f64>) -> f32 {
+        let mut track = test_track("t", None);
+        track.loudness = loudness;
+        track.loudness_gain()
+    }
+
+    fn close(a: f32, b: f32) -> bool {
+        (a - b).abs() < 1e-3
+    }
+
+    #[test]
+    fn loudness_gain_brings_tracks_to_the_target() {
+        assert_eq!(gain_at(None), 1.0);
+        assert!(close(gain_at(Some(TARGET_LOUDNESS)), 1.0));
+        // 6 dB too loud halves the amplitude, 6 dB too quiet doubles it
+        assert!(close(gain_at(Some(-8.0)), 0.501));
+        assert!(close(gain_at(Some(-20.0)), 1.995));
+    }
+
+    #[test]
+    fn loudness_gain_is_clamped_both_ways() {
+        // Boosts stop at 6 dB, cuts at 12 dB
+        assert!(close(gain_at(Some(-40.0)), gain_at(Some(-20.0))));
+        assert!(close(gain_at(Some(10.0)), 0.251));
+        assert!(close(gain_at(Some(-2.0)), gain_at(Some(10.0))));
+    }
+
+    #[test]
+    fn media_end_accounts_for_chapters() {
+        let mut track = test_track("t", None);
+        assert_eq!(track.media_end(), None);
+
+        track.duration = Some(Duration::from_secs(200));
+        assert_eq!(track.media_end(), Some(Duration::from_secs(200)));
+
+        // A chapter running to the end of the video
+        track.span = Some(TrackSpan {
+            start: Duration::from_secs(600),
+            end: None,
+        });
+        assert_eq!(track.media_end(), Some(Duration::from_secs(800)));
+
+        track.span = Some(TrackSpan {
+            start: Duration::from_secs(600),
+            end: Some(Duration::from_secs(750)),
+        });
+        assert_eq!(track.media_end(), Some(Duration::from_secs(750)));
+    }
+}
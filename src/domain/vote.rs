@@ -0,0 +1,58 @@
+use std::collections::HashMap;
+
+use poise::serenity_prelude::{GuildId, UserId};
+
+/// Most options one poll offers, one button each.
+pub const MAX_VOTE_OPTIONS: usize = 5;
+
+/// A `/vote` poll on what plays next. Each member has one vote, which they
+/// can change until the poll closes.
+#[derive(Debug, Clone)]
+pub struct VotePoll {
+    pub guild_id: GuildId,
+    pub started_by: UserId,
+    options: usize,
+    votes: HashMap<UserId, usize>,
+}
+
+impl VotePoll {
+    pub fn new(guild_id: GuildId, started_by: UserId, options: usize) -> Self {
+        Self {
+            guild_id,
+            started_by,
+            options,
+            votes: HashMap::new(),
+        }
+    }
+
+    /// Records `user`'s vote for the 0-based option `choice`, replacing
+    /// their earlier one. Returns `false` for an option the poll doesn't
+    /// have.
+    pub fn vote(&mut self, user: UserId, choice: usize) -> bool {
+        if choice >= self.options {
+            return false;
+        }
+        self.votes.insert(user, choice);
+        true
+    }
+
+    /// Votes for each option, in option order.
+    pub fn tally(&self) -> Vec<usize> {
+        let mut counts = vec![0; self.options];
+        for &choice in self.votes.values() {
+            counts[choice] += 1;
+        }
+        counts
+    }
+
+    /// The option with the most votes, ties going to the earlier one. `None`
+    /// when nobody voted.
+    pub fn winner(&self) -> Option<usize> {
+        self.tally()
+            .into_iter()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .max_by(|a, b| a.1.cmp(&b.1).then(b.0.cmp(&a.0)))
+            .map(|(index, _)| index)
+    }
+}
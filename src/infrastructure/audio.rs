@@ -1,22 +1,189 @@
+use std::collections::HashMap;
+use std::io::ErrorKind;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Client;
-use songbird::input::{Input, YoutubeDl};
+use serde::Deserialize;
+use songbird::input::{
+    AudioStream, AudioStreamError, AuxMetadata, Compose, HlsRequest, HttpRequest, Input,
+};
+use symphonia::core::io::MediaSource;
+use tokio::process::Command;
+
+const YTDLP: &str = "yt-dlp";
+
+/// The video yt-dlp settled on for an input.
+#[derive(Debug, Clone)]
+pub struct MediaMatch {
+    pub url: Option<String>,
+    /// The upload's length, which can differ from what Spotify reports.
+    pub duration: Option<Duration>,
+    /// Integrated loudness in LUFS, when the extractor reports it.
+    pub loudness: Option<f64>,
+}
+
+/// Set to the input's match once it has loaded.
+pub type ResolvedMatch = Arc<OnceLock<MediaMatch>>;
+
+/// The parts of yt-dlp's `-j` output an input needs.
+#[derive(Debug, Clone, Deserialize)]
+struct YtDlpInfo {
+    url: String,
+    protocol: Option<String>,
+    filesize: Option<u64>,
+    http_headers: Option<HashMap<String, String>>,
+    webpage_url: Option<String>,
+    title: Option<String>,
+    uploader: Option<String>,
+    channel: Option<String>,
+    thumbnail: Option<String>,
+    duration: Option<f64>,
+    loudness: Option<f64>,
+}
 
-fn best_audio_args() -> Vec<String> {
-    vec!["-f".to_string(), "bestaudio".to_string()]
+enum Query {
+    Url(String),
+    Search(String),
+}
+
+/// A yt-dlp input that runs the query itself rather than through songbird's
+/// `YoutubeDl`, which drops fields like loudness and overrides `-f`.
+struct YtDlp {
+    http: Client,
+    query: Query,
+    format: String,
+    info: Option<YtDlpInfo>,
+    resolved: ResolvedMatch,
+}
+
+impl YtDlp {
+    /// Asks yt-dlp about the first match. Always runs, since the stream URL
+    /// it hands back expires.
+    async fn query(&mut self) -> Result<YtDlpInfo, AudioStreamError> {
+        let target = match &self.query {
+            Query::Url(url) => url.clone(),
+            Query::Search(query) => format!("ytsearch1:{query}"),
+        };
+        let output = Command::new(YTDLP)
+            .args(["-j", "--no-playlist", "-f", &self.format, &target])
+            .output()
+            .await
+            .map_err(|e| {
+                AudioStreamError::Fail(if e.kind() == ErrorKind::NotFound {
+                    format!("could not find executable '{YTDLP}' on path").into()
+                } else {
+                    Box::new(e)
+                })
+            })?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            return Err(AudioStreamError::Fail(
+                format!("{YTDLP} failed: {}", stderr.trim()).into(),
+            ));
+        }
+
+        let line = output
+            .stdout
+            .split(|&b| b == b'\n')
+            .find(|line| !line.is_empty())
+            .ok_or_else(|| AudioStreamError::Fail(format!("no results for '{target}'").into()))?;
+        let info: YtDlpInfo =
+            serde_json::from_slice(line).map_err(|e| AudioStreamError::Fail(Box::new(e)))?;
+
+        let _ = self.resolved.set(MediaMatch {
+            url: info.webpage_url.clone(),
+            duration: info.duration.map(Duration::from_secs_f64),
+            loudness: info.loudness,
+        });
+        self.info = Some(info.clone());
+        Ok(info)
+    }
+}
+
+#[async_trait]
+impl Compose for YtDlp {
+    fn create(&mut self) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        Err(AudioStreamError::Unsupported)
+    }
+
+    async fn create_async(
+        &mut self,
+    ) -> Result<AudioStream<Box<dyn MediaSource>>, AudioStreamError> {
+        let info = self.query().await?;
+
+        let mut headers = HeaderMap::default();
+        if let Some(map) = info.http_headers {
+            headers.extend(map.iter().filter_map(|(k, v)| {
+                Some((HeaderName::from_bytes(k.as_bytes()).ok()?, HeaderValue::from_str(v).ok()?))
+            }));
+        }
+
+        match info.protocol.as_deref() {
+            Some("m3u8_native") => {
+                HlsRequest::new_with_headers(self.http.clone(), info.url, headers).create()
+            }
+            _ => {
+                let mut request = HttpRequest {
+                    client: self.http.clone(),
+                    request: info.url,
+                    headers,
+                    content_length: info.filesize,
+                };
+                request.create_async().await
+            }
+        }
+    }
+
+    fn should_create_async(&self) -> bool {
+        true
+    }
+
+    async fn aux_metadata(&mut self) -> Result<AuxMetadata, AudioStreamError> {
+        let info = match &self.info {
+            Some(info) => info.clone(),
+            None => self.query().await?,
+        };
+        Ok(AuxMetadata {
+            title: info.title,
+            artist: info.uploader,
+            channel: info.channel,
+            duration: info.duration.map(Duration::from_secs_f64),
+            source_url: info.webpage_url,
+            thumbnail: info.thumbnail,
+            ..AuxMetadata::default()
+        })
+    }
 }
 
 pub struct AudioSource;
 
 impl AudioSource {
-    pub fn from_url(http: Client, url: &str) -> Input {
-        YoutubeDl::new(http, url.to_string())
-            .user_args(best_audio_args())
-            .into()
+    /// Plays `url` through yt-dlp's `format` selector, recording what it
+    /// loaded into `resolved`.
+    pub fn from_url(http: Client, url: &str, format: &str, resolved: ResolvedMatch) -> Input {
+        Self::input(http, Query::Url(url.to_string()), format, resolved)
+    }
+
+    /// Searches YouTube for `query`, recording the match into `resolved`.
+    pub fn from_search(
+        http: Client,
+        query: &str,
+        format: &str,
+        resolved: ResolvedMatch,
+    ) -> Input {
+        Self::input(http, Query::Search(query.to_string()), format, resolved)
     }
 
-    pub fn from_search(http: Client, query: &str) -> Input {
-        YoutubeDl::new_search(http, query.to_string())
-            .user_args(best_audio_args())
-            .into()
+    fn input(http: Client, query: Query, format: &str, resolved: ResolvedMatch) -> Input {
+        Input::Lazy(Box::new(YtDlp {
+            http,
+            query,
+            format: format.to_string(),
+            info: None,
+            resolved,
+        }))
     }
 }
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use poise::serenity_prelude::{ChannelId, GuildId, UserId};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::domain::playlist::PageCursor;
+use crate::domain::track::Track;
+use crate::infrastructure::json_store::JsonStore;
+
+/// What a background enqueue still had to add to a guild's queue.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PendingCollection {
+    pub guild_id: GuildId,
+    /// The guild's music channel when the collection was queued.
+    pub channel_id: ChannelId,
+    /// `None` for collections a schedule queued.
+    pub requester: Option<UserId>,
+    /// Known for collections listed page by page, and for a lone track
+    /// queued behind collections, named after the track.
+    pub name: Option<String>,
+    /// Listed tracks not yet queued.
+    pub tracks: Vec<Track>,
+    /// Where listing picks up after `tracks`, when pages of the collection
+    /// were never listed.
+    pub pages: Option<PendingPages>,
+}
+
+/// The unlisted pages of a collection, as a page cursor and the listing's
+/// progress so far.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingPages {
+    pub cursor: PageCursor,
+    /// Entries already read, which count toward the item cap.
+    pub read: usize,
+    /// Shuffle the rest once it's all listed.
+    pub shuffle: bool,
+    /// Album name to fill in on Spotify album tracks.
+    pub album: Option<String>,
+    /// Tracks the server's collection limit still allows, if it has one.
+    pub budget: Option<usize>,
+}
+
+/// Keeps the unqueued part of each background enqueue on disk, so the ones a
+/// crash or restart cuts short can be resumed once their guild's session is
+/// back.
+pub struct EnqueueStore {
+    store: JsonStore<Vec<PendingCollection>>,
+    /// Keyed by guild and enqueue ticket. Held while writing, so the file
+    /// always matches the latest change.
+    pending: Mutex<HashMap<(GuildId, u64), PendingCollection>>,
+    /// Enqueues a restart cut short, waiting for their guild's next session.
+    /// Only kept in memory, so a further restart drops them.
+    held: Mutex<HashMap<GuildId, Vec<PendingCollection>>>,
+}
+
+impl EnqueueStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            store: JsonStore::new(path, "pending enqueues"),
+            pending: Mutex::new(HashMap::new()),
+            held: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records what's left of the enqueue holding `ticket`.
+    pub async fn record(&self, ticket: u64, collection: PendingCollection) {
+        let mut pending = self.pending.lock().await;
+        pending.insert((collection.guild_id, ticket), collection);
+        self.save(&pending).await;
+    }
+
+    /// Forgets the enqueue holding `ticket`, once it's done or cancelled.
+    pub async fn forget(&self, guild_id: GuildId, ticket: u64) {
+        let mut pending = self.pending.lock().await;
+        if pending.remove(&(guild_id, ticket)).is_some() {
+            self.save(&pending).await;
+        }
+    }
+
+    /// Forgets every enqueue in the guild, when its session ends.
+    pub async fn forget_guild(&self, guild_id: GuildId) {
+        let mut pending = self.pending.lock().await;
+        let before = pending.len();
+        pending.retain(|&(guild, _), _| guild != guild_id);
+        if pending.len() != before {
+            self.save(&pending).await;
+        }
+    }
+
+    /// Replaces the file with `pending`, or removes it once nothing is left.
+    /// At worst a failed write means a restart doesn't resume the enqueue.
+    async fn save(&self, pending: &HashMap<(GuildId, u64), PendingCollection>) {
+        if pending.is_empty() {
+            self.store.clear().await;
+            return;
+        }
+        // In ticket order, so a guild's collections resume in the order
+        // they were requested
+        let mut keyed: Vec<_> = pending.iter().collect();
+        keyed.sort_by_key(|(key, _)| **key);
+        let stored: Vec<PendingCollection> = keyed.into_iter().map(|(_, c)| c.clone()).collect();
+        self.store.save(&stored).await;
+    }
+
+    /// Returns the enqueues left unfinished before this start and clears the
+    /// store.
+    pub async fn take(&self) -> Vec<PendingCollection> {
+        let _guard = self.pending.lock().await;
+        self.store.take().await.unwrap_or_default()
+    }
+
+    /// Holds the enqueues [`take`](Self::take) returned until their guild's
+    /// session is back, dropping any with nothing left to add.
+    pub async fn hold(&self, unfinished: Vec<PendingCollection>) {
+        let mut held = self.held.lock().await;
+        for pending in unfinished {
+            if !pending.tracks.is_empty() || pending.pages.is_some() {
+                held.entry(pending.guild_id).or_default().push(pending);
+            }
+        }
+    }
+
+    /// Hands over the guild's held enqueues, in the order they were
+    /// requested, once a session starts there.
+    pub async fn release(&self, guild_id: GuildId) -> Vec<PendingCollection> {
+        self.held.lock().await.remove(&guild_id).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::track::test_track;
+
+    fn store() -> (EnqueueStore, PathBuf) {
+        let path = std::env::temp_dir().join(format!("enqueue-{}.json", uuid::Uuid::new_v4()));
+        (EnqueueStore::new(path.clone()), path)
+    }
+
+    fn collection(guild: u64, url: &str, pages: Option<PendingPages>) -> PendingCollection {
+        PendingCollection {
+            guild_id: GuildId::new(guild),
+            channel_id: ChannelId::new(10),
+            requester: Some(UserId::new(30)),
+            name: Some("Mix".to_string()),
+            tracks: vec![test_track(url, None)],
+            pages,
+        }
+    }
+
+    #[tokio::test]
+    async fn a_remainder_reads_back_with_its_unlisted_pages() {
+        let (store, path) = store();
+        let pages = PendingPages {
+            cursor: PageCursor::SpotifyPlaylist {
+                id: "list".to_string(),
+                offset: 200,
+            },
+            read: 200,
+            shuffle: false,
+            album: None,
+            budget: Some(50),
+        };
+        store.record(1, collection(1, "a", Some(pages))).await;
+
+        let taken = EnqueueStore::new(path.clone()).take().await;
+        assert!(!path.exists());
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].tracks[0].url, "a");
+        let pages = taken[0].pages.as_ref().unwrap();
+        assert!(matches!(
+            &pages.cursor,
+            PageCursor::SpotifyPlaylist { id, offset: 200 } if id == "list"
+        ));
+        assert_eq!((pages.read, pages.budget), (200, Some(50)));
+    }
+
+    #[tokio::test]
+    async fn remainders_come_back_in_the_order_they_were_requested() {
+        let (store, path) = store();
+        for ticket in [3, 1, 2] {
+            store.record(ticket, collection(1, &format!("t{ticket}"), None)).await;
+        }
+
+        let taken = EnqueueStore::new(path).take().await;
+        let urls: Vec<_> = taken.iter().map(|c| c.tracks[0].url.as_str()).collect();
+        assert_eq!(urls, ["t1", "t2", "t3"]);
+    }
+
+    #[tokio::test]
+    async fn forgetting_the_last_remainder_removes_the_file() {
+        let (store, path) = store();
+        store.record(1, collection(1, "a", None)).await;
+        assert!(path.exists());
+
+        store.forget(GuildId::new(1), 1).await;
+        assert!(!path.exists());
+        assert!(store.take().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn held_enqueues_wait_for_their_own_guild() {
+        let (store, _) = store();
+        let mut emptied = collection(3, "gone", None);
+        emptied.tracks.clear();
+        store
+            .hold(vec![
+                collection(1, "a", None),
+                collection(2, "b", None),
+                collection(1, "c", None),
+                emptied,
+            ])
+            .await;
+
+        // No session came back in guild 3, and its remainder had nothing
+        // left anyway
+        assert!(store.release(GuildId::new(3)).await.is_empty());
+        let urls = |held: Vec<PendingCollection>| -> Vec<String> {
+            held.into_iter().map(|c| c.tracks[0].url.clone()).collect()
+        };
+        assert_eq!(urls(store.release(GuildId::new(1)).await), ["a", "c"]);
+        assert!(store.release(GuildId::new(1)).await.is_empty());
+        assert_eq!(urls(store.release(GuildId::new(2)).await), ["b"]);
+    }
+}
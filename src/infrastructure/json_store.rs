@@ -0,0 +1,132 @@
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::Mutex;
+
+/// A value kept in a JSON file, for the stores that carry state across
+/// restarts. Failures are only logged, since none of that state is worth
+/// stopping the bot over.
+pub struct JsonStore<T> {
+    path: PathBuf,
+    /// What the file holds, for the logs, e.g. "play stats".
+    what: &'static str,
+    /// Serializes writes so an older snapshot can't land after a newer one.
+    writing: Mutex<()>,
+    value: PhantomData<fn() -> T>,
+}
+
+impl<T: Serialize + DeserializeOwned> JsonStore<T> {
+    pub fn new(path: PathBuf, what: &'static str) -> Self {
+        Self {
+            path,
+            what,
+            writing: Mutex::new(()),
+            value: PhantomData,
+        }
+    }
+
+    /// Replaces the file with `value`.
+    pub async fn save(&self, value: &T) {
+        let json = match serde_json::to_vec(value) {
+            Ok(json) => json,
+            Err(e) => {
+                tracing::warn!("Failed to encode {}: {e}", self.what);
+                return;
+            }
+        };
+
+        let _guard = self.writing.lock().await;
+        // Written aside and renamed over, so a crash mid-write can't leave
+        // half a file
+        let tmp = self.path.with_extension("tmp");
+        let written = match tokio::fs::write(&tmp, json).await {
+            Ok(()) => tokio::fs::rename(&tmp, &self.path).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            tracing::warn!("Failed to save {} to {}: {e}", self.what, self.path.display());
+        }
+    }
+
+    /// Returns the stored value, `None` when there's no file yet or it
+    /// can't be read.
+    pub async fn load(&self) -> Option<T> {
+        let _guard = self.writing.lock().await;
+        self.read().await
+    }
+
+    /// Returns the stored value like [`load`](Self::load) and removes the
+    /// file.
+    pub async fn take(&self) -> Option<T> {
+        let _guard = self.writing.lock().await;
+        let value = self.read().await;
+        self.remove().await;
+        value
+    }
+
+    /// Removes the file, for when nothing is left to keep.
+    pub async fn clear(&self) {
+        let _guard = self.writing.lock().await;
+        self.remove().await;
+    }
+
+    async fn read(&self) -> Option<T> {
+        let json = match tokio::fs::read(&self.path).await {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                let path = self.path.display();
+                tracing::warn!("Failed to read {} from {path}: {e}", self.what);
+                return None;
+            }
+        };
+        serde_json::from_slice(&json)
+            .inspect_err(|e| tracing::warn!("Ignoring unreadable {}: {e}", self.path.display()))
+            .ok()
+    }
+
+    async fn remove(&self) {
+        match tokio::fs::remove_file(&self.path).await {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => tracing::warn!("Failed to clear {}: {e}", self.path.display()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn store() -> JsonStore<HashMap<String, u32>> {
+        let path = std::env::temp_dir().join(format!("store-{}.json", uuid::Uuid::new_v4()));
+        JsonStore::new(path, "counts")
+    }
+
+    #[tokio::test]
+    async fn a_saved_value_reads_back_the_same_after_a_restart() {
+        let store = store();
+        let counts = HashMap::from([("a".to_string(), 1), ("b".to_string(), 2)]);
+
+        store.save(&counts).await;
+        let reopened = JsonStore::<HashMap<String, u32>>::new(store.path.clone(), "counts");
+        assert_eq!(reopened.load().await, Some(counts.clone()));
+        assert_eq!(reopened.take().await, Some(counts));
+        assert!(!store.path.exists());
+    }
+
+    #[tokio::test]
+    async fn a_missing_or_unreadable_file_reads_as_nothing() {
+        let store = store();
+        assert_eq!(store.load().await, None);
+
+        tokio::fs::write(&store.path, b"{not json").await.unwrap();
+        assert_eq!(store.load().await, None);
+        store.clear().await;
+        assert!(!store.path.exists());
+    }
+}
@@ -1,4 +1,12 @@
 pub mod audio;
-pub mod inactivity;
+pub mod enqueue_store;
+pub mod json_store;
+pub mod now_playing_store;
+pub mod preferences_store;
+pub mod schedule_store;
+pub mod settings_store;
 pub mod spotify;
+pub mod spotify_token_store;
+pub mod stats_store;
+pub mod voice;
 pub mod youtube;
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::now_playing::NowPlayingCards;
+use crate::infrastructure::json_store::JsonStore;
+
+#[derive(Serialize, Deserialize)]
+struct StoredMessage {
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    message_id: MessageId,
+}
+
+/// Keeps the live Now Playing messages on disk, so the ones a crash leaves
+/// behind can be retired on the next start.
+pub struct NowPlayingStore {
+    store: JsonStore<Vec<StoredMessage>>,
+}
+
+impl NowPlayingStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            store: JsonStore::new(path, "Now Playing messages"),
+        }
+    }
+
+    /// Replaces the stored messages with `messages`. At worst a failed write
+    /// leaves a few buttons behind after a crash.
+    pub async fn save(&self, messages: &HashMap<GuildId, NowPlayingCards>) {
+        let stored: Vec<StoredMessage> = messages
+            .iter()
+            .flat_map(|(&guild_id, cards)| {
+                cards.iter().map(move |(channel_id, message_id)| StoredMessage {
+                    guild_id,
+                    channel_id,
+                    message_id,
+                })
+            })
+            .collect();
+        self.store.save(&stored).await;
+    }
+
+    /// Returns the stored messages and clears the store.
+    pub async fn take(&self) -> Vec<(GuildId, ChannelId, MessageId)> {
+        let stored = self.store.take().await.unwrap_or_default();
+        stored
+            .into_iter()
+            .map(|m| (m.guild_id, m.channel_id, m.message_id))
+            .collect()
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use poise::serenity_prelude::UserId;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::preferences::UserPreferences;
+use crate::infrastructure::json_store::JsonStore;
+
+#[derive(Serialize, Deserialize)]
+struct StoredPreferences {
+    user_id: UserId,
+    preferences: UserPreferences,
+}
+
+/// Keeps each user's `/mysettings` choices on disk, so they survive
+/// restarts.
+pub struct PreferencesStore {
+    store: JsonStore<Vec<StoredPreferences>>,
+}
+
+impl PreferencesStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            store: JsonStore::new(path, "user preferences"),
+        }
+    }
+
+    /// Replaces the stored preferences with `preferences`. At worst a failed
+    /// write forgets the choices made since the last save.
+    pub async fn save(&self, preferences: &HashMap<UserId, UserPreferences>) {
+        let stored: Vec<StoredPreferences> = preferences
+            .iter()
+            .map(|(&user_id, preferences)| StoredPreferences {
+                user_id,
+                preferences: preferences.clone(),
+            })
+            .collect();
+        self.store.save(&stored).await;
+    }
+
+    /// Returns the stored preferences, empty if there are none yet.
+    pub async fn load(&self) -> HashMap<UserId, UserPreferences> {
+        let stored = self.store.load().await.unwrap_or_default();
+        stored.into_iter().map(|s| (s.user_id, s.preferences)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::preferences::Confirmations;
+    use crate::domain::settings::SearchPreference;
+
+    #[tokio::test]
+    async fn preferences_read_back_the_same_after_a_restart() {
+        let path = std::env::temp_dir().join(format!("prefs-{}.json", uuid::Uuid::new_v4()));
+        let (picky, unset) = (UserId::new(1), UserId::new(2));
+        let preferences = HashMap::from([
+            (
+                picky,
+                UserPreferences {
+                    search_preference: Some(SearchPreference::Spotify),
+                    confirmations: Some(Confirmations::Ephemeral),
+                },
+            ),
+            (unset, UserPreferences::default()),
+        ]);
+
+        PreferencesStore::new(path.clone()).save(&preferences).await;
+        let loaded = PreferencesStore::new(path.clone()).load().await;
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        assert_eq!(loaded[&picky].search_preference, Some(SearchPreference::Spotify));
+        assert_eq!(loaded[&picky].confirmations, Some(Confirmations::Ephemeral));
+        assert_eq!(loaded[&unset].search_preference, None);
+        assert_eq!(loaded[&unset].confirmations, None);
+    }
+}
@@ -0,0 +1,96 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use poise::serenity_prelude::GuildId;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::schedule::ScheduledJob;
+use crate::infrastructure::json_store::JsonStore;
+
+#[derive(Serialize, Deserialize)]
+struct StoredJob {
+    guild_id: GuildId,
+    job: ScheduledJob,
+}
+
+/// Keeps pending `/schedule` jobs on disk, so they still start after a
+/// restart.
+pub struct ScheduleStore {
+    store: JsonStore<Vec<StoredJob>>,
+}
+
+impl ScheduleStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            store: JsonStore::new(path, "scheduled jobs"),
+        }
+    }
+
+    /// Replaces the stored jobs with `jobs`. At worst a failed write forgets
+    /// the jobs changed since the last save.
+    pub async fn save(&self, jobs: &HashMap<GuildId, Vec<ScheduledJob>>) {
+        let stored: Vec<StoredJob> = jobs
+            .iter()
+            .flat_map(|(&guild_id, guild_jobs)| {
+                guild_jobs.iter().map(move |job| StoredJob {
+                    guild_id,
+                    job: job.clone(),
+                })
+            })
+            .collect();
+        self.store.save(&stored).await;
+    }
+
+    /// Returns the stored jobs, empty if there are none yet.
+    pub async fn load(&self) -> HashMap<GuildId, Vec<ScheduledJob>> {
+        let mut jobs: HashMap<GuildId, Vec<ScheduledJob>> = HashMap::new();
+        for StoredJob { guild_id, job } in self.store.load().await.unwrap_or_default() {
+            jobs.entry(guild_id).or_default().push(job);
+        }
+        jobs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+    use poise::serenity_prelude::{ChannelId, UserId};
+
+    use super::*;
+
+    fn job(id: u32, query: &str) -> ScheduledJob {
+        ScheduledJob {
+            id,
+            at: Utc.with_ymd_and_hms(2026, 10, 23, 18, 0, 0).unwrap(),
+            voice_channel: ChannelId::new(10),
+            text_channel: ChannelId::new(11),
+            query: query.to_string(),
+            created_by: UserId::new(12),
+        }
+    }
+
+    #[tokio::test]
+    async fn jobs_read_back_the_same_after_a_restart() {
+        let path = std::env::temp_dir().join(format!("schedules-{}.json", uuid::Uuid::new_v4()));
+        let (first, second) = (GuildId::new(1), GuildId::new(2));
+        let jobs = HashMap::from([
+            (first, vec![job(1, "friday party"), job(2, "lofi")]),
+            (second, vec![job(1, "https://youtu.be/dQw4w9WgXcQ")]),
+        ]);
+
+        ScheduleStore::new(path.clone()).save(&jobs).await;
+        let loaded = ScheduleStore::new(path.clone()).load().await;
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        let queries = |guild| -> Vec<(u32, String)> {
+            loaded[&guild].iter().map(|j| (j.id, j.query.clone())).collect()
+        };
+        assert_eq!(queries(first), [(1, "friday party".into()), (2, "lofi".into())]);
+        assert_eq!(queries(second), [(1, "https://youtu.be/dQw4w9WgXcQ".into())]);
+        let restored = &loaded[&first][0];
+        assert_eq!(restored.at, job(1, "").at);
+        assert_eq!(restored.voice_channel, ChannelId::new(10));
+        assert_eq!(restored.text_channel, ChannelId::new(11));
+        assert_eq!(restored.created_by, UserId::new(12));
+    }
+}
@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use poise::serenity_prelude::GuildId;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::settings::GuildSettings;
+use crate::infrastructure::json_store::JsonStore;
+
+#[derive(Serialize, Deserialize)]
+struct StoredSettings {
+    guild_id: GuildId,
+    settings: GuildSettings,
+}
+
+/// Keeps each guild's `/settings` and blocklist on disk, so they survive
+/// restarts.
+pub struct SettingsStore {
+    store: JsonStore<Vec<StoredSettings>>,
+}
+
+impl SettingsStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            store: JsonStore::new(path, "server settings"),
+        }
+    }
+
+    /// Replaces the stored settings with `settings`. At worst a failed write
+    /// forgets the changes made since the last save.
+    pub async fn save(&self, settings: &HashMap<GuildId, GuildSettings>) {
+        let stored: Vec<StoredSettings> = settings
+            .iter()
+            .map(|(&guild_id, settings)| StoredSettings {
+                guild_id,
+                settings: settings.clone(),
+            })
+            .collect();
+        self.store.save(&stored).await;
+    }
+
+    /// Returns the stored settings, empty if there are none yet.
+    pub async fn load(&self) -> HashMap<GuildId, GuildSettings> {
+        let stored = self.store.load().await.unwrap_or_default();
+        stored.into_iter().map(|s| (s.guild_id, s.settings)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::settings::{BlockRule, NowPlayingMode};
+
+    #[tokio::test]
+    async fn the_blocklist_and_settings_read_back_the_same_after_a_restart() {
+        let path = std::env::temp_dir().join(format!("settings-{}.json", uuid::Uuid::new_v4()));
+        let guild = GuildId::new(1);
+        let settings = GuildSettings {
+            queue_limit: Some(200),
+            now_playing_mode: NowPlayingMode::Board,
+            blocklist: vec![
+                BlockRule::Keyword("nightcore".to_string()),
+                BlockRule::Url("https://youtu.be/dQw4w9WgXcQ".to_string()),
+            ],
+            ..GuildSettings::default()
+        };
+
+        SettingsStore::new(path.clone()).save(&HashMap::from([(guild, settings.clone())])).await;
+        let loaded = SettingsStore::new(path.clone()).load().await;
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        let restored = &loaded[&guild];
+        assert_eq!(restored.blocklist, settings.blocklist);
+        assert_eq!(restored.queue_limit, Some(200));
+        assert_eq!(restored.now_playing_mode, NowPlayingMode::Board);
+    }
+
+    #[test]
+    fn settings_saved_before_a_field_existed_take_its_default() {
+        let settings: GuildSettings = serde_json::from_str(r#"{"fair_queue": true}"#).unwrap();
+        assert!(settings.fair_queue);
+        assert!(settings.use_source_colors);
+        assert!(settings.blocklist.is_empty());
+    }
+}
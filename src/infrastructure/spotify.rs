@@ -1,19 +1,62 @@
-use futures::stream::TryStreamExt;
-use rspotify::model::{AlbumId, PlayableItem, PlaylistId, SearchResult, SimplifiedTrack, TrackId};
-use rspotify::{ClientCredsSpotify, Credentials, prelude::*};
+use std::collections::HashMap;
 
+use poise::serenity_prelude::UserId;
+use rspotify::model::{
+    AlbumId, Country, Image, Market, PlayableId, PlayableItem, PlaylistId, SearchResult,
+    SimplifiedTrack, TrackId,
+};
+use rspotify::{AuthCodePkceSpotify, ClientCredsSpotify, Credentials, OAuth, prelude::*, scopes};
+use tokio::sync::Mutex;
+
+use crate::domain::playlist::{PageCursor, PlaylistInfo, PlaylistTracks};
 use crate::domain::track::{Track, TrackSource};
+use crate::infrastructure::spotify_token_store::SpotifyTokenStore;
+use crate::services::error::MusicError;
+
+/// Largest pages Spotify serves for playlist items and album tracks.
+const PLAYLIST_PAGE: usize = 100;
+const ALBUM_PAGE: usize = 50;
+
+/// Spotify album art comes in 640, 300 and 64 pixel squares.
+const SMALL_ART: u32 = 300;
+const LARGE_ART: u32 = 640;
+
+/// The album image closest to `width` pixels wide.
+fn album_art(images: &[Image], width: u32) -> Option<String> {
+    images
+        .iter()
+        .min_by_key(|img| img.width.unwrap_or(0).abs_diff(width))
+        .map(|img| img.url.clone())
+}
 
 pub struct SpotifyClient {
     client: ClientCredsSpotify,
+    market: Option<Market>,
+}
+
+/// Parses an ISO 3166-1 alpha-2 code like "US" into a Spotify market.
+fn parse_market(code: &str) -> Option<Market> {
+    let code = code.trim().to_ascii_uppercase();
+    serde_json::from_value::<Country>(serde_json::Value::String(code))
+        .ok()
+        .map(Market::Country)
 }
 
 impl SpotifyClient {
-    pub async fn new(client_id: &str, client_secret: &str) -> Self {
+    pub async fn new(client_id: &str, client_secret: &str, market: Option<&str>) -> Self {
         let creds = Credentials::new(client_id, client_secret);
         let client = ClientCredsSpotify::new(creds);
         client.request_token().await.expect("Failed to get Spotify token");
-        Self { client }
+
+        let market = market.and_then(|code| {
+            let parsed = parse_market(code);
+            if parsed.is_none() {
+                tracing::warn!("Ignoring unknown SPOTIFY_MARKET {code:?}");
+            }
+            parsed
+        });
+
+        Self { client, market }
     }
 
     pub async fn search_tracks(&self, query: &str, limit: u32) -> Vec<Track> {
@@ -22,7 +65,7 @@ impl SpotifyClient {
             .search(
                 query,
                 rspotify::model::SearchType::Track,
-                None,
+                self.market,
                 None,
                 Some(limit),
                 None,
@@ -43,11 +86,12 @@ impl SpotifyClient {
                 .map(|track| {
                     let artists: Vec<String> =
                         track.artists.iter().map(|a| a.name.clone()).collect();
-                    let duration_ms = track.duration.num_milliseconds();
-                    let minutes = duration_ms / 60_000;
-                    let seconds = (duration_ms % 60_000) / 1000;
+                    let duration = track.duration.to_std().ok();
 
-                    let thumbnail_url = track.album.images.first().map(|img| img.url.clone());
+                    let images = &track.album.images;
+                    let thumbnail_url = images.first().map(|img| img.url.clone());
+                    let thumbnail_small = album_art(images, SMALL_ART);
+                    let thumbnail_large = album_art(images, LARGE_ART);
 
                     let url = track
                         .id
@@ -58,10 +102,20 @@ impl SpotifyClient {
                     Track {
                         title: track.name,
                         artist: artists.join(", "),
+                        album: Some(track.album.name),
                         url,
                         source: TrackSource::Spotify,
-                        duration: Some(format!("{minutes}:{seconds:02}")),
+                        duration,
                         thumbnail_url,
+                        thumbnail_small,
+                        thumbnail_large,
+                        requested_by: None,
+                        handle_id: None,
+                        enqueued_at: None,
+                        resolved_url: None,
+                        loudness: None,
+                        span: None,
+                        chapters: Vec::new(),
                     }
                 })
                 .collect()
@@ -72,103 +126,206 @@ impl SpotifyClient {
 
     pub async fn get_track(&self, id: &str) -> Option<Track> {
         let track_id = TrackId::from_id(id).ok()?;
-        let full_track = self.client.track(track_id, None).await.ok()?;
+        let full_track = self.client.track(track_id, self.market).await.ok()?;
 
         let artists: Vec<String> = full_track.artists.iter().map(|a| a.name.clone()).collect();
-        let duration_ms = full_track.duration.num_milliseconds();
-        let minutes = duration_ms / 60_000;
-        let seconds = (duration_ms % 60_000) / 1000;
+        let duration = full_track.duration.to_std().ok();
 
-        let thumbnail_url = full_track.album.images.first().map(|img| img.url.clone());
+        let images = &full_track.album.images;
+        let thumbnail_url = images.first().map(|img| img.url.clone());
+        let thumbnail_small = album_art(images, SMALL_ART);
+        let thumbnail_large = album_art(images, LARGE_ART);
 
         let url = format!("https://open.spotify.com/track/{id}");
 
         Some(Track {
             title: full_track.name,
             artist: artists.join(", "),
+            album: Some(full_track.album.name),
             url,
             source: TrackSource::Spotify,
-            duration: Some(format!("{minutes}:{seconds:02}")),
+            duration,
             thumbnail_url,
+            thumbnail_small,
+            thumbnail_large,
+            requested_by: None,
+            handle_id: None,
+            enqueued_at: None,
+            resolved_url: None,
+            loudness: None,
+            span: None,
+            chapters: Vec::new(),
         })
     }
 
-    pub async fn get_playlist_tracks(&self, id: &str) -> Vec<Track> {
-        let playlist_id = match rspotify::model::PlaylistId::from_id(id) {
-            Ok(id) => id,
-            Err(_) => return Vec::new(),
+    /// Lists one page of a playlist starting at `offset`, reading at most
+    /// `limit` entries. A failed request comes back empty and incomplete.
+    pub async fn get_playlist_page(&self, id: &str, offset: usize, limit: usize) -> PlaylistTracks {
+        let Ok(playlist_id) = PlaylistId::from_id(id) else {
+            return PlaylistTracks::default();
         };
 
-        let stream = self.client.playlist_items(playlist_id, None, None);
-        futures::pin_mut!(stream);
+        let page = match self
+            .client
+            .playlist_items_manual(
+                playlist_id,
+                None,
+                self.market,
+                Some(limit.min(PLAYLIST_PAGE) as u32),
+                Some(offset as u32),
+            )
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::warn!("Spotify playlist_items failed at offset {offset}: {e}");
+                return PlaylistTracks::default();
+            }
+        };
 
+        let read = page.items.len();
         let mut tracks = Vec::new();
-        while let Ok(Some(item)) = stream.try_next().await {
-            if let Some(PlayableItem::Track(full_track)) = item.track {
-                let artists: Vec<String> =
-                    full_track.artists.iter().map(|a| a.name.clone()).collect();
-                let duration_ms = full_track.duration.num_milliseconds();
-                let minutes = duration_ms / 60_000;
-                let seconds = (duration_ms % 60_000) / 1000;
-
-                let thumbnail_url = full_track.album.images.first().map(|img| img.url.clone());
-
-                let url = full_track
-                    .id
-                    .as_ref()
-                    .map(|id| format!("https://open.spotify.com/track/{}", id.id()))
-                    .unwrap_or_default();
-
-                tracks.push(Track {
-                    title: full_track.name,
-                    artist: artists.join(", "),
-                    url,
-                    source: TrackSource::Spotify,
-                    duration: Some(format!("{minutes}:{seconds:02}")),
-                    thumbnail_url,
-                });
-            }
+        let mut skipped = 0;
+        for item in page.items {
+            // Local files and region-locked tracks have no id to look up,
+            // and podcast episodes aren't music we can find on YouTube
+            let full_track = match item.track {
+                Some(PlayableItem::Track(track)) if !track.is_local && track.id.is_some() => track,
+                Some(PlayableItem::Episode(_)) | Some(PlayableItem::Track(_)) | None => {
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let artists: Vec<String> = full_track.artists.iter().map(|a| a.name.clone()).collect();
+            let duration = full_track.duration.to_std().ok();
+
+            let images = &full_track.album.images;
+            let thumbnail_url = images.first().map(|img| img.url.clone());
+            let thumbnail_small = album_art(images, SMALL_ART);
+            let thumbnail_large = album_art(images, LARGE_ART);
+
+            let url = full_track
+                .id
+                .as_ref()
+                .map(|id| format!("https://open.spotify.com/track/{}", id.id()))
+                .unwrap_or_default();
+
+            tracks.push(Track {
+                title: full_track.name,
+                artist: artists.join(", "),
+                album: Some(full_track.album.name),
+                url,
+                source: TrackSource::Spotify,
+                duration,
+                thumbnail_url,
+                thumbnail_small,
+                thumbnail_large,
+                requested_by: None,
+                handle_id: None,
+                enqueued_at: None,
+                resolved_url: None,
+                loudness: None,
+                span: None,
+                chapters: Vec::new(),
+            });
+        }
+
+        let next = (page.next.is_some() && read > 0).then(|| PageCursor::SpotifyPlaylist {
+            id: id.to_string(),
+            offset: offset + read,
+        });
+        PlaylistTracks {
+            tracks,
+            complete: true,
+            truncated: false,
+            skipped,
+            next,
         }
-        tracks
     }
 
-    pub async fn get_playlist_name(&self, id: &str) -> Option<String> {
+    pub async fn get_playlist_info(&self, id: &str) -> Option<PlaylistInfo> {
         let playlist_id = PlaylistId::from_id(id).ok()?;
         let playlist = self
             .client
-            .playlist(playlist_id, None, None)
+            .playlist(playlist_id, None, self.market)
             .await
             .ok()?;
-        Some(playlist.name)
+        Some(PlaylistInfo {
+            name: playlist.name,
+            owner: playlist.owner.display_name,
+            image: playlist.images.first().map(|img| img.url.clone()),
+            track_count: Some(playlist.tracks.total as usize),
+            incomplete: false,
+            truncated_at: None,
+            skipped: 0,
+            still_listing: false,
+            capped_at: None,
+        })
     }
 
-    pub async fn get_album_name(&self, id: &str) -> Option<String> {
+    pub async fn get_album_info(&self, id: &str) -> Option<PlaylistInfo> {
         let album_id = AlbumId::from_id(id).ok()?;
-        let album = self.client.album(album_id, None).await.ok()?;
-        Some(album.name)
+        let album = self.client.album(album_id, self.market).await.ok()?;
+        let artists: Vec<String> = album.artists.iter().map(|a| a.name.clone()).collect();
+        Some(PlaylistInfo {
+            name: album.name,
+            owner: Some(artists.join(", ")),
+            image: album.images.first().map(|img| img.url.clone()),
+            track_count: Some(album.tracks.total as usize),
+            incomplete: false,
+            truncated_at: None,
+            skipped: 0,
+            still_listing: false,
+            capped_at: None,
+        })
     }
 
-    pub async fn get_album_tracks(&self, id: &str) -> Vec<Track> {
-        let album_id = match AlbumId::from_id(id) {
-            Ok(id) => id,
-            Err(_) => return Vec::new(),
+    /// Lists one page of an album's tracks starting at `offset`, reading at
+    /// most `limit`. Album tracks come back without album details; the caller
+    /// fills in `Track::album` from `get_album_info`.
+    pub async fn get_album_page(&self, id: &str, offset: usize, limit: usize) -> PlaylistTracks {
+        let Ok(album_id) = AlbumId::from_id(id) else {
+            return PlaylistTracks::default();
         };
 
-        let stream = self.client.album_track(album_id, None);
-        futures::pin_mut!(stream);
+        let page = match self
+            .client
+            .album_track_manual(
+                album_id,
+                self.market,
+                Some(limit.min(ALBUM_PAGE) as u32),
+                Some(offset as u32),
+            )
+            .await
+        {
+            Ok(page) => page,
+            Err(e) => {
+                tracing::warn!("Spotify album_track failed at offset {offset}: {e}");
+                return PlaylistTracks::default();
+            }
+        };
 
-        let mut tracks = Vec::new();
-        while let Ok(Some(track)) = stream.try_next().await {
-            tracks.push(self.simplified_track_to_track(&track, id));
+        let read = page.items.len();
+        let next = (page.next.is_some() && read > 0).then(|| PageCursor::SpotifyAlbum {
+            id: id.to_string(),
+            offset: offset + read,
+        });
+        PlaylistTracks {
+            tracks: page
+                .items
+                .iter()
+                .map(|track| self.simplified_track_to_track(track, id))
+                .collect(),
+            complete: true,
+            truncated: false,
+            skipped: 0,
+            next,
         }
-        tracks
     }
 
     fn simplified_track_to_track(&self, track: &SimplifiedTrack, album_id: &str) -> Track {
         let artists: Vec<String> = track.artists.iter().map(|a| a.name.clone()).collect();
-        let duration_ms = track.duration.num_milliseconds();
-        let minutes = duration_ms / 60_000;
-        let seconds = (duration_ms % 60_000) / 1000;
+        let duration = track.duration.to_std().ok();
 
         let url = track
             .id
@@ -182,10 +339,188 @@ impl SpotifyClient {
         Track {
             title: track.name.clone(),
             artist: artists.join(", "),
+            album: None,
             url,
             source: TrackSource::Spotify,
-            duration: Some(format!("{minutes}:{seconds:02}")),
+            duration,
             thumbnail_url: None,
+            thumbnail_small: None,
+            thumbnail_large: None,
+            requested_by: None,
+            handle_id: None,
+            enqueued_at: None,
+            resolved_url: None,
+            loudness: None,
+            span: None,
+            chapters: Vec::new(),
+        }
+    }
+}
+
+/// Most items Spotify accepts in one "add to playlist" request.
+const ADD_ITEMS_BATCH: usize = 100;
+
+/// Lets users link their own Spotify account, so the bot can create
+/// playlists for them. Uses the authorization-code flow with PKCE: the user
+/// approves in their browser, then pastes back the address they were
+/// redirected to, since the bot doesn't serve the redirect itself. Linked
+/// accounts are kept in `store` when there is one.
+pub struct SpotifyAccounts {
+    client_id: String,
+    redirect_uri: String,
+    store: Option<SpotifyTokenStore>,
+    /// Flows started but not finished, each holding its own verifier and
+    /// state.
+    pending: Mutex<HashMap<UserId, AuthCodePkceSpotify>>,
+    linked: Mutex<HashMap<UserId, AuthCodePkceSpotify>>,
+}
+
+impl SpotifyAccounts {
+    pub fn new(client_id: &str, redirect_uri: &str, store: Option<SpotifyTokenStore>) -> Self {
+        Self {
+            client_id: client_id.to_string(),
+            redirect_uri: redirect_uri.to_string(),
+            store,
+            pending: Mutex::new(HashMap::new()),
+            linked: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A client for this app that hasn't been authorized yet.
+    fn client(&self) -> AuthCodePkceSpotify {
+        let oauth = OAuth {
+            redirect_uri: self.redirect_uri.clone(),
+            scopes: scopes!("playlist-modify-private", "playlist-modify-public"),
+            ..Default::default()
+        };
+        AuthCodePkceSpotify::new(Credentials::new_pkce(&self.client_id), oauth)
+    }
+
+    /// Links the accounts saved in the store before the last restart.
+    pub async fn restore(&self) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let tokens = store.load().await;
+        let mut linked = self.linked.lock().await;
+        for (user, token) in tokens {
+            let client = self.client();
+            *client.token.lock().await.expect("token lock poisoned") = Some(token);
+            linked.insert(user, client);
+        }
+        if !linked.is_empty() {
+            tracing::info!("Restored {} linked Spotify accounts", linked.len());
+        }
+    }
+
+    /// Saves the linked accounts' current tokens. Spotify hands out a new
+    /// refresh token whenever one is used, so this runs after every export
+    /// too.
+    async fn persist(&self, linked: &HashMap<UserId, AuthCodePkceSpotify>) {
+        let Some(store) = &self.store else {
+            return;
+        };
+        let mut tokens = HashMap::new();
+        for (&user, client) in linked {
+            if let Some(token) = client.token.lock().await.expect("token lock poisoned").clone() {
+                tokens.insert(user, token);
+            }
+        }
+        store.save(&tokens).await;
+    }
+
+    /// Starts linking `user`'s account and returns the address to approve
+    /// it at. Starting again replaces an unfinished attempt.
+    pub async fn authorize_url(&self, user: UserId) -> Result<String, MusicError> {
+        let mut client = self.client();
+        let url = client.get_authorize_url(None).map_err(|e| {
+            tracing::warn!("Failed to build Spotify authorize URL: {e}");
+            MusicError::SpotifyLinkFailed
+        })?;
+        self.pending.lock().await.insert(user, client);
+        Ok(url)
+    }
+
+    /// Finishes linking with the address `user` was redirected to.
+    pub async fn finish(&self, user: UserId, redirected_to: &str) -> Result<(), MusicError> {
+        let client = self
+            .pending
+            .lock()
+            .await
+            .remove(&user)
+            .ok_or(MusicError::SpotifyLinkNotStarted)?;
+        // Also rejects an address from someone else's attempt, whose state
+        // won't match
+        let code = client
+            .parse_response_code(redirected_to.trim())
+            .ok_or(MusicError::SpotifyLinkFailed)?;
+        client.request_token(&code).await.map_err(|e| {
+            tracing::warn!("Spotify token request failed for {user}: {e}");
+            MusicError::SpotifyLinkFailed
+        })?;
+        let mut linked = self.linked.lock().await;
+        linked.insert(user, client);
+        self.persist(&linked).await;
+        Ok(())
+    }
+
+    pub async fn is_linked(&self, user: UserId) -> bool {
+        self.linked.lock().await.contains_key(&user)
+    }
+
+    /// Forgets `user`'s account. Returns whether one was linked.
+    pub async fn unlink(&self, user: UserId) -> bool {
+        self.pending.lock().await.remove(&user);
+        let mut linked = self.linked.lock().await;
+        let removed = linked.remove(&user).is_some();
+        if removed {
+            self.persist(&linked).await;
+        }
+        removed
+    }
+
+    /// Creates a private playlist on `user`'s account holding `track_ids`, in
+    /// order, and returns its link.
+    pub async fn create_playlist(
+        &self,
+        user: UserId,
+        name: &str,
+        description: &str,
+        track_ids: &[String],
+    ) -> Result<String, MusicError> {
+        let linked = self.linked.lock().await;
+        let client = linked.get(&user).ok_or(MusicError::SpotifyNotLinked)?;
+        let failed = |e: rspotify::ClientError| {
+            tracing::warn!("Spotify export failed for {user}: {e}");
+            MusicError::SpotifyExportFailed
+        };
+
+        let created = async {
+            let me = client.current_user().await.map_err(failed)?;
+            let playlist = client
+                .user_playlist_create(me.id, name, Some(false), None, Some(description))
+                .await
+                .map_err(failed)?;
+
+            let ids: Vec<_> = track_ids
+                .iter()
+                .filter_map(|id| TrackId::from_id(id.as_str()).ok())
+                .collect();
+            for batch in ids.chunks(ADD_ITEMS_BATCH) {
+                let items = batch.iter().map(|id| PlayableId::Track(id.as_ref()));
+                client
+                    .playlist_add_items(playlist.id.as_ref(), items, None)
+                    .await
+                    .map_err(failed)?;
+            }
+
+            let fallback = || format!("https://open.spotify.com/playlist/{}", playlist.id.id());
+            Ok(playlist.external_urls.get("spotify").cloned().unwrap_or_else(fallback))
         }
+        .await;
+        // Saved even when the export failed, since the token may have been
+        // refreshed before it did
+        self.persist(&linked).await;
+        created
     }
 }
@@ -0,0 +1,49 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use poise::serenity_prelude::UserId;
+use rspotify::Token;
+use serde::{Deserialize, Serialize};
+
+use crate::infrastructure::json_store::JsonStore;
+
+#[derive(Serialize, Deserialize)]
+struct StoredToken {
+    user_id: UserId,
+    token: Token,
+}
+
+/// Keeps the tokens of Spotify accounts linked for `/export spotify` on
+/// disk, so users don't have to link again after a restart. The refresh
+/// tokens in it grant access to those accounts, so keep the file private.
+pub struct SpotifyTokenStore {
+    store: JsonStore<Vec<StoredToken>>,
+}
+
+impl SpotifyTokenStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            store: JsonStore::new(path, "Spotify tokens"),
+        }
+    }
+
+    /// Replaces the stored tokens with `tokens`. At worst a failed write
+    /// means the accounts linked since the last save have to be linked
+    /// again after a restart.
+    pub async fn save(&self, tokens: &HashMap<UserId, Token>) {
+        let stored: Vec<StoredToken> = tokens
+            .iter()
+            .map(|(&user_id, token)| StoredToken {
+                user_id,
+                token: token.clone(),
+            })
+            .collect();
+        self.store.save(&stored).await;
+    }
+
+    /// Returns the stored tokens, empty if there are none yet.
+    pub async fn load(&self) -> HashMap<UserId, Token> {
+        let stored = self.store.load().await.unwrap_or_default();
+        stored.into_iter().map(|s| (s.user_id, s.token)).collect()
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use poise::serenity_prelude::GuildId;
+use serde::{Deserialize, Serialize};
+
+use crate::domain::stats::GuildStats;
+use crate::infrastructure::json_store::JsonStore;
+
+#[derive(Serialize, Deserialize)]
+struct StoredStats {
+    guild_id: GuildId,
+    stats: GuildStats,
+}
+
+/// Keeps each guild's `/top` stats on disk, so they survive restarts.
+pub struct StatsStore {
+    store: JsonStore<Vec<StoredStats>>,
+    /// Set when the stats changed since the last save.
+    changed: AtomicBool,
+}
+
+impl StatsStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            store: JsonStore::new(path, "play stats"),
+            changed: AtomicBool::new(false),
+        }
+    }
+
+    /// Notes that the stats need saving again.
+    pub fn mark_changed(&self) {
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the stats changed since this was last asked, clearing the
+    /// mark.
+    pub fn take_changed(&self) -> bool {
+        self.changed.swap(false, Ordering::Relaxed)
+    }
+
+    /// Replaces the stored stats with `stats`. At worst a failed write loses
+    /// the plays since the last save.
+    pub async fn save(&self, stats: &HashMap<GuildId, GuildStats>) {
+        let stored: Vec<StoredStats> = stats
+            .iter()
+            .map(|(&guild_id, stats)| StoredStats {
+                guild_id,
+                stats: stats.clone(),
+            })
+            .collect();
+        self.store.save(&stored).await;
+    }
+
+    /// Returns the stored stats, empty if there are none yet.
+    pub async fn load(&self) -> HashMap<GuildId, GuildStats> {
+        let stored = self.store.load().await.unwrap_or_default();
+        stored.into_iter().map(|s| (s.guild_id, s.stats)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use poise::serenity_prelude::UserId;
+
+    use super::*;
+    use crate::domain::track::{test_track, Requester};
+
+    #[tokio::test]
+    async fn stats_read_back_the_same_after_a_restart() {
+        let path = std::env::temp_dir().join(format!("stats-{}.json", uuid::Uuid::new_v4()));
+        let mut stats = GuildStats::default();
+        let user = Requester::User(UserId::new(7));
+        stats.record(&test_track("a", Some(user)), Duration::from_secs(60));
+        stats.record(&test_track("b", Some(user)), Duration::from_secs(60));
+        stats.record(&test_track("a", None), Duration::from_secs(60));
+        let guild = GuildId::new(1);
+
+        StatsStore::new(path.clone()).save(&HashMap::from([(guild, stats)])).await;
+        let loaded = StatsStore::new(path.clone()).load().await;
+        tokio::fs::remove_file(&path).await.unwrap();
+
+        let stats = &loaded[&guild];
+        let top: Vec<(&str, u32)> =
+            stats.top_tracks(10).iter().map(|(url, s)| (*url, s.plays)).collect();
+        assert_eq!(top, [("a", 2), ("b", 1)]);
+        assert_eq!(stats.top_requesters(10), [(UserId::new(7), 2)]);
+        assert_eq!(stats.listening, Duration::from_secs(180));
+        assert_eq!(stats.total_plays(), 3);
+    }
+
+    #[test]
+    fn changes_are_saved_once_per_batch() {
+        let store = StatsStore::new(std::env::temp_dir().join("unused-stats.json"));
+        assert!(!store.take_changed());
+
+        store.mark_changed();
+        store.mark_changed();
+        assert!(store.take_changed());
+        assert!(!store.take_changed());
+    }
+}
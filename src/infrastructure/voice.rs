@@ -0,0 +1,316 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use poise::serenity_prelude::{ChannelId, GuildId};
+use songbird::tracks::{Track, TrackHandle, TrackState};
+use songbird::{Call, Songbird};
+use tokio::sync::Mutex;
+use uuid::Uuid;
+
+use crate::services::error::MusicError;
+
+/// The voice connection commands play through, kept behind a trait so they
+/// don't fetch songbird from the serenity typemap themselves and a scripted
+/// backend can stand in for it.
+#[async_trait]
+pub trait VoiceBackend: Send + Sync {
+    /// Whether the bot has a call in this guild.
+    fn is_connected(&self, guild_id: GuildId) -> bool;
+
+    /// The guild's call, if the bot has one.
+    fn call(&self, guild_id: GuildId) -> Option<Arc<Mutex<Call>>>;
+
+    /// Joins `channel_id`, or moves there when already in a call, and
+    /// returns the guild's call.
+    async fn join(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Result<Arc<Mutex<Call>>, MusicError>;
+
+    /// Adds `track` to the end of `call`'s queue, loading it `preload`
+    /// before it's due. Returns its handle and its place in the queue, 0
+    /// being the track that plays right away.
+    async fn enqueue(
+        &self,
+        call: &Arc<Mutex<Call>>,
+        track: Track,
+        preload: Option<Duration>,
+    ) -> (TrackHandle, usize);
+
+    /// Ids of the tracks in the guild's queue, the playing one first. Fails
+    /// with `NotConnected` when the bot has no call.
+    async fn queued(&self, guild_id: GuildId) -> Result<Vec<Uuid>, MusicError>;
+
+    /// The playing track, if connected and something is queued.
+    async fn current(&self, guild_id: GuildId) -> Option<TrackHandle>;
+
+    /// Play/pause state and position of the playing track.
+    async fn current_track_info(&self, guild_id: GuildId) -> Option<TrackState>;
+
+    /// Pauses the playing track. Fails with `EmptyQueue` when there's none.
+    async fn pause(&self, guild_id: GuildId) -> Result<(), MusicError>;
+
+    /// Skips the playing track. Fails with `NotConnected` when the bot has no
+    /// call, or `EmptyQueue` when nothing is queued.
+    async fn skip(&self, guild_id: GuildId) -> Result<(), MusicError>;
+
+    /// Stops and clears playback, if connected.
+    async fn stop(&self, guild_id: GuildId);
+
+    /// Disconnects from the guild's voice channel.
+    async fn leave(&self, guild_id: GuildId) -> Result<(), MusicError>;
+}
+
+/// Plays through the songbird instance registered with the client.
+pub struct SongbirdBackend {
+    manager: Arc<Songbird>,
+}
+
+impl SongbirdBackend {
+    pub fn new(manager: Arc<Songbird>) -> Self {
+        Self { manager }
+    }
+}
+
+#[async_trait]
+impl VoiceBackend for SongbirdBackend {
+    fn is_connected(&self, guild_id: GuildId) -> bool {
+        self.manager.get(guild_id).is_some()
+    }
+
+    fn call(&self, guild_id: GuildId) -> Option<Arc<Mutex<Call>>> {
+        self.manager.get(guild_id)
+    }
+
+    async fn join(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Result<Arc<Mutex<Call>>, MusicError> {
+        self.manager
+            .join(guild_id, channel_id)
+            .await
+            .map_err(|e| MusicError::JoinError(e.to_string()))
+    }
+
+    async fn enqueue(
+        &self,
+        call: &Arc<Mutex<Call>>,
+        track: Track,
+        preload: Option<Duration>,
+    ) -> (TrackHandle, usize) {
+        let mut handler = call.lock().await;
+        let handle = handler.enqueue_with_preload(track, preload);
+        (handle, handler.queue().len() - 1)
+    }
+
+    async fn queued(&self, guild_id: GuildId) -> Result<Vec<Uuid>, MusicError> {
+        let handler_lock = self.manager.get(guild_id).ok_or(MusicError::NotConnected)?;
+        let handler = handler_lock.lock().await;
+        Ok(handler.queue().current_queue().iter().map(TrackHandle::uuid).collect())
+    }
+
+    async fn current(&self, guild_id: GuildId) -> Option<TrackHandle> {
+        let handler_lock = self.manager.get(guild_id)?;
+        let handler = handler_lock.lock().await;
+        handler.queue().current()
+    }
+
+    async fn current_track_info(&self, guild_id: GuildId) -> Option<TrackState> {
+        self.current(guild_id).await?.get_info().await.ok()
+    }
+
+    async fn pause(&self, guild_id: GuildId) -> Result<(), MusicError> {
+        let current = self.current(guild_id).await.ok_or(MusicError::EmptyQueue)?;
+        let _ = current.pause();
+        Ok(())
+    }
+
+    async fn skip(&self, guild_id: GuildId) -> Result<(), MusicError> {
+        let handler_lock = self.manager.get(guild_id).ok_or(MusicError::NotConnected)?;
+        let handler = handler_lock.lock().await;
+        let queue = handler.queue();
+        if queue.is_empty() {
+            return Err(MusicError::EmptyQueue);
+        }
+        let _ = queue.skip();
+        Ok(())
+    }
+
+    async fn stop(&self, guild_id: GuildId) {
+        if let Some(handler_lock) = self.manager.get(guild_id) {
+            let handler = handler_lock.lock().await;
+            handler.queue().stop();
+        }
+    }
+
+    async fn leave(&self, guild_id: GuildId) -> Result<(), MusicError> {
+        self.manager
+            .leave(guild_id)
+            .await
+            .map_err(|e| MusicError::JoinError(e.to_string()))
+    }
+}
+
+/// A voice backend for tests. Calls are songbird's standalone ones, which
+/// never connect, so the fake keeps its own queue and play state and logs
+/// what it was asked to do.
+#[cfg(test)]
+pub(crate) mod fake {
+    use std::collections::HashMap;
+    use std::io::Cursor;
+    use std::sync::Mutex as StdMutex;
+
+    use poise::serenity_prelude::UserId;
+    use songbird::input::{Input, RawAdapter};
+    use songbird::tracks::PlayMode;
+
+    use super::*;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum Action {
+        Join(ChannelId),
+        Enqueue,
+        Pause,
+        Skip,
+        Stop,
+        Leave,
+    }
+
+    struct FakeCall {
+        call: Arc<Mutex<Call>>,
+        queue: Vec<TrackHandle>,
+        paused: bool,
+    }
+
+    #[derive(Default)]
+    pub(crate) struct FakeBackend {
+        calls: StdMutex<HashMap<GuildId, FakeCall>>,
+        actions: StdMutex<Vec<Action>>,
+    }
+
+    impl FakeBackend {
+        /// Everything the backend was asked to do, in order.
+        pub(crate) fn actions(&self) -> Vec<Action> {
+            self.actions.lock().unwrap().clone()
+        }
+
+        /// Queues `count` silent tracks, returning their ids.
+        pub(crate) async fn queue_silence(&self, guild_id: GuildId, count: usize) -> Vec<Uuid> {
+            let call = self.calls.lock().unwrap()[&guild_id].call.clone();
+            let mut ids = Vec::new();
+            for _ in 0..count {
+                let input: Input = RawAdapter::new(Cursor::new(vec![0u8; 64]), 48_000, 2).into();
+                let (handle, _) = self.enqueue(&call, Track::from(input), None).await;
+                ids.push(handle.uuid());
+            }
+            ids
+        }
+
+        /// Marks the playing track as resumed, as a real resume would.
+        pub(crate) fn resume(&self, guild_id: GuildId) {
+            if let Some(fake) = self.calls.lock().unwrap().get_mut(&guild_id) {
+                fake.paused = false;
+            }
+        }
+
+        fn log(&self, action: Action) {
+            self.actions.lock().unwrap().push(action);
+        }
+    }
+
+    #[async_trait]
+    impl VoiceBackend for FakeBackend {
+        fn is_connected(&self, guild_id: GuildId) -> bool {
+            self.calls.lock().unwrap().contains_key(&guild_id)
+        }
+
+        fn call(&self, guild_id: GuildId) -> Option<Arc<Mutex<Call>>> {
+            Some(self.calls.lock().unwrap().get(&guild_id)?.call.clone())
+        }
+
+        async fn join(
+            &self,
+            guild_id: GuildId,
+            channel_id: ChannelId,
+        ) -> Result<Arc<Mutex<Call>>, MusicError> {
+            self.log(Action::Join(channel_id));
+            let mut calls = self.calls.lock().unwrap();
+            let fake = calls.entry(guild_id).or_insert_with(|| FakeCall {
+                call: Arc::new(Mutex::new(Call::standalone(guild_id, UserId::new(1)))),
+                queue: Vec::new(),
+                paused: false,
+            });
+            Ok(fake.call.clone())
+        }
+
+        async fn enqueue(
+            &self,
+            call: &Arc<Mutex<Call>>,
+            track: Track,
+            preload: Option<Duration>,
+        ) -> (TrackHandle, usize) {
+            self.log(Action::Enqueue);
+            let handle = call.lock().await.enqueue_with_preload(track, preload);
+            let mut calls = self.calls.lock().unwrap();
+            let fake = calls
+                .values_mut()
+                .find(|fake| Arc::ptr_eq(&fake.call, call))
+                .expect("enqueued on a call the fake didn't join");
+            fake.queue.push(handle.clone());
+            (handle, fake.queue.len() - 1)
+        }
+
+        async fn queued(&self, guild_id: GuildId) -> Result<Vec<Uuid>, MusicError> {
+            let calls = self.calls.lock().unwrap();
+            let fake = calls.get(&guild_id).ok_or(MusicError::NotConnected)?;
+            Ok(fake.queue.iter().map(TrackHandle::uuid).collect())
+        }
+
+        async fn current(&self, guild_id: GuildId) -> Option<TrackHandle> {
+            self.calls.lock().unwrap().get(&guild_id)?.queue.first().cloned()
+        }
+
+        async fn current_track_info(&self, guild_id: GuildId) -> Option<TrackState> {
+            let calls = self.calls.lock().unwrap();
+            let fake = calls.get(&guild_id).filter(|fake| !fake.queue.is_empty())?;
+            let playing = if fake.paused { PlayMode::Pause } else { PlayMode::Play };
+            Some(TrackState { playing, ..TrackState::default() })
+        }
+
+        async fn pause(&self, guild_id: GuildId) -> Result<(), MusicError> {
+            self.log(Action::Pause);
+            let mut calls = self.calls.lock().unwrap();
+            let fake = calls.get_mut(&guild_id).filter(|fake| !fake.queue.is_empty());
+            fake.ok_or(MusicError::EmptyQueue)?.paused = true;
+            Ok(())
+        }
+
+        async fn skip(&self, guild_id: GuildId) -> Result<(), MusicError> {
+            self.log(Action::Skip);
+            let mut calls = self.calls.lock().unwrap();
+            let fake = calls.get_mut(&guild_id).ok_or(MusicError::NotConnected)?;
+            if fake.queue.is_empty() {
+                return Err(MusicError::EmptyQueue);
+            }
+            fake.queue.remove(0);
+            fake.paused = false;
+            Ok(())
+        }
+
+        async fn stop(&self, guild_id: GuildId) {
+            self.log(Action::Stop);
+            if let Some(fake) = self.calls.lock().unwrap().get_mut(&guild_id) {
+                fake.queue.clear();
+            }
+        }
+
+        async fn leave(&self, guild_id: GuildId) -> Result<(), MusicError> {
+            self.log(Action::Leave);
+            self.calls.lock().unwrap().remove(&guild_id);
+            Ok(())
+        }
+    }
+}
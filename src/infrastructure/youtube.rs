@@ -1,13 +1,55 @@
+use std::time::Duration;
+
 use reqwest::Client;
 use serde::Deserialize;
 
+use crate::domain::chapters::parse_chapters;
+use crate::domain::playlist::{PageCursor, PlaylistInfo, PlaylistTracks};
 use crate::domain::track::{Track, TrackSource};
 
+/// The plain `watch?v=` URL for a video, without share or tracking params,
+/// so tracks from different link forms compare equal.
+pub fn canonicalize_youtube_url(video_id: &str) -> String {
+    format!("https://www.youtube.com/watch?v={video_id}")
+}
+
 #[derive(Deserialize)]
 struct SearchResponse {
     items: Vec<SearchItem>,
 }
 
+impl SearchResponse {
+    /// The videos among the results, as tracks.
+    fn into_tracks(self) -> Vec<Track> {
+        self.items
+            .into_iter()
+            .filter_map(|item| {
+                let video_id = item.id.video_id?;
+                let thumbnails = item.snippet.thumbnails.unwrap_or_default();
+
+                Some(Track {
+                    title: item.snippet.title,
+                    artist: item.snippet.channel_title,
+                    album: None,
+                    url: canonicalize_youtube_url(&video_id),
+                    source: TrackSource::YouTube,
+                    duration: None,
+                    thumbnail_url: thumbnails.standard(),
+                    thumbnail_small: thumbnails.small(),
+                    thumbnail_large: thumbnails.large(),
+                    requested_by: None,
+                    handle_id: None,
+                    enqueued_at: None,
+                    resolved_url: None,
+                    loudness: None,
+                    span: None,
+                    chapters: Vec::new(),
+                })
+            })
+            .collect()
+    }
+}
+
 #[derive(Deserialize)]
 struct SearchItem {
     id: VideoId,
@@ -26,12 +68,18 @@ struct Snippet {
     title: String,
     channel_title: String,
     thumbnails: Option<Thumbnails>,
+    #[serde(default)]
+    description: String,
+    /// `upcoming` for a scheduled premiere or stream, `live` while it airs.
+    live_broadcast_content: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 struct Thumbnails {
     high: Option<Thumbnail>,
     default: Option<Thumbnail>,
+    medium: Option<Thumbnail>,
+    maxres: Option<Thumbnail>,
 }
 
 #[derive(Deserialize)]
@@ -39,6 +87,27 @@ struct Thumbnail {
     url: String,
 }
 
+impl Thumbnails {
+    fn pick(options: [&Option<Thumbnail>; 2]) -> Option<String> {
+        options.into_iter().flatten().next().map(|t| t.url.clone())
+    }
+
+    /// 480x360, letterboxed for widescreen videos.
+    fn standard(&self) -> Option<String> {
+        Self::pick([&self.high, &self.default])
+    }
+
+    /// 320x180, no letterboxing.
+    fn small(&self) -> Option<String> {
+        Self::pick([&self.medium, &self.default])
+    }
+
+    /// 1280x720 when the upload has it.
+    fn large(&self) -> Option<String> {
+        Self::pick([&self.maxres, &self.high])
+    }
+}
+
 #[derive(Deserialize)]
 struct VideoResponse {
     items: Vec<VideoItem>,
@@ -71,19 +140,55 @@ struct ResourceId {
     video_id: Option<String>,
 }
 
+#[derive(Deserialize)]
+struct ApiErrorResponse {
+    error: ApiError,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    #[serde(default)]
+    errors: Vec<ApiErrorDetail>,
+}
+
+#[derive(Deserialize)]
+struct ApiErrorDetail {
+    reason: String,
+}
+
+/// Why a playlist couldn't be fetched at all.
+#[derive(Debug, thiserror::Error)]
+pub enum PlaylistError {
+    #[error("playlist is private or deleted")]
+    Unavailable,
+    #[error("YouTube API error: {0}")]
+    Api(String),
+}
+
 #[derive(Deserialize)]
 struct PlaylistResponse {
     items: Vec<PlaylistDetail>,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct PlaylistDetail {
     snippet: PlaylistDetailSnippet,
+    content_details: Option<PlaylistContentDetails>,
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct PlaylistDetailSnippet {
     title: String,
+    channel_title: Option<String>,
+    thumbnails: Option<Thumbnails>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlaylistContentDetails {
+    item_count: Option<usize>,
 }
 
 #[derive(Deserialize)]
@@ -94,49 +199,104 @@ struct VideoItem {
 }
 
 #[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
 struct ContentDetails {
     duration: String,
+    region_restriction: Option<RegionRestriction>,
+}
+
+/// Countries a video is limited to or kept from, as two-letter codes. Only
+/// one of the lists is set.
+#[derive(Deserialize)]
+struct RegionRestriction {
+    allowed: Option<Vec<String>>,
+    blocked: Option<Vec<String>>,
+}
+
+impl RegionRestriction {
+    fn blocks(&self, region: &str) -> bool {
+        let listed = |codes: &Option<Vec<String>>| {
+            codes.as_ref().map(|codes| codes.iter().any(|code| code.eq_ignore_ascii_case(region)))
+        };
+        listed(&self.blocked) == Some(true) || listed(&self.allowed) == Some(false)
+    }
 }
 
-fn parse_iso8601_duration(duration: &str) -> Option<String> {
+/// Why a video that was looked up can't be queued.
+#[derive(Debug, thiserror::Error)]
+pub enum VideoUnavailable {
+    #[error("This video premieres later — queue it once it's out")]
+    Upcoming,
+    /// The API returns nothing for a well-formed id that's members-only,
+    /// private or deleted, and doesn't say which.
+    #[error("This video is members-only, private or deleted")]
+    Missing,
+    #[error("This video is blocked in the bot's region ({0})")]
+    RegionBlocked(String),
+}
+
+/// Parses the `PT#H#M#S` durations the Data API returns.
+fn parse_iso8601_duration(duration: &str) -> Option<Duration> {
     let d = duration.strip_prefix("PT")?;
-    let mut minutes = 0u64;
-    let mut seconds = 0u64;
+    let mut secs = 0u64;
 
     let mut num_buf = String::new();
     for ch in d.chars() {
-        match ch {
-            'H' => {
-                let hours: u64 = num_buf.parse().ok()?;
-                minutes += hours * 60;
-                num_buf.clear();
-            }
-            'M' => {
-                minutes += num_buf.parse::<u64>().ok()?;
-                num_buf.clear();
+        let unit = match ch {
+            'H' => 3600,
+            'M' => 60,
+            'S' => 1,
+            _ => {
+                num_buf.push(ch);
+                continue;
             }
-            'S' => {
-                seconds = num_buf.parse().ok()?;
-                num_buf.clear();
-            }
-            _ => num_buf.push(ch),
-        }
+        };
+        secs += num_buf.parse::<u64>().ok()? * unit;
+        num_buf.clear();
     }
 
-    Some(format!("{minutes}:{seconds:02}"))
+    Some(Duration::from_secs(secs))
+}
+
+/// YouTube's suffix for the auto-generated channels that carry label
+/// uploads ("Artist - Topic").
+const TOPIC_SUFFIX: &str = " - Topic";
+/// Results fetched when looking for topic uploads.
+const TOPIC_SEARCH_DEPTH: u32 = 10;
+
+/// Keeps the results from topic channels, listed under the plain artist
+/// name, or all of `tracks` when none are.
+fn topic_uploads(tracks: Vec<Track>) -> Vec<Track> {
+    if !tracks.iter().any(|t| t.artist.ends_with(TOPIC_SUFFIX)) {
+        return tracks;
+    }
+    tracks
+        .into_iter()
+        .filter_map(|track| {
+            let artist = track.artist.strip_suffix(TOPIC_SUFFIX)?.to_string();
+            Some(Track { artist, ..track })
+        })
+        .collect()
 }
 
 pub struct YouTubeClient {
     http: Client,
     api_key: String,
+    /// Two-letter country code the bot plays from, for region restrictions.
+    region: Option<String>,
 }
 
 impl YouTubeClient {
-    pub fn new(http: Client, api_key: String) -> Self {
-        Self { http, api_key }
+    pub fn new(http: Client, api_key: String, region: Option<String>) -> Self {
+        Self { http, api_key, region }
     }
 
-    pub async fn search_tracks(&self, query: &str, limit: u32) -> Vec<Track> {
+    /// Searches for videos. With `prefer_topic`, only uploads from
+    /// auto-generated "Artist - Topic" channels are returned, unless there
+    /// are none among the results.
+    pub async fn search_tracks(&self, query: &str, limit: u32, prefer_topic: bool) -> Vec<Track> {
+        // Topic uploads are often a few results down, so look further
+        let fetch = if prefer_topic { limit.max(TOPIC_SEARCH_DEPTH) } else { limit };
         let resp = self
             .http
             .get("https://www.googleapis.com/youtube/v3/search")
@@ -144,7 +304,7 @@ impl YouTubeClient {
                 ("part", "snippet"),
                 ("type", "video"),
                 ("q", query),
-                ("maxResults", &limit.to_string()),
+                ("maxResults", &fetch.to_string()),
                 ("key", &self.api_key),
             ])
             .send()
@@ -166,101 +326,109 @@ impl YouTubeClient {
             }
         };
 
-        search
-            .items
-            .into_iter()
-            .filter_map(|item| {
-                let video_id = item.id.video_id?;
-                let thumbnail_url = item
-                    .snippet
-                    .thumbnails
-                    .and_then(|t| t.high.or(t.default))
-                    .map(|t| t.url);
-
-                Some(Track {
-                    title: item.snippet.title,
-                    artist: item.snippet.channel_title,
-                    url: format!("https://www.youtube.com/watch?v={video_id}"),
-                    source: TrackSource::YouTube,
-                    duration: None,
-                    thumbnail_url,
-                })
-            })
-            .collect()
+        let tracks = search.into_tracks();
+        let mut tracks = if prefer_topic { topic_uploads(tracks) } else { tracks };
+        tracks.truncate(limit as usize);
+        tracks
     }
 
-    pub async fn get_playlist_tracks(&self, playlist_id: &str) -> Vec<Track> {
-        let mut tracks = Vec::new();
-        let mut page_token: Option<String> = None;
-
-        loop {
-            let mut params = vec![
-                ("part", "snippet".to_string()),
-                ("playlistId", playlist_id.to_string()),
-                ("maxResults", "50".to_string()),
-                ("key", self.api_key.clone()),
-            ];
-            if let Some(token) = &page_token {
-                params.push(("pageToken", token.clone()));
-            }
+    /// Fetches one page of a playlist; `page_token` is `None` for the first.
+    pub async fn get_playlist_page(
+        &self,
+        playlist_id: &str,
+        page_token: Option<&str>,
+    ) -> Result<PlaylistTracks, PlaylistError> {
+        let mut params = vec![
+            ("part", "snippet".to_string()),
+            ("playlistId", playlist_id.to_string()),
+            ("maxResults", "50".to_string()),
+            ("key", self.api_key.clone()),
+        ];
+        if let Some(token) = page_token {
+            params.push(("pageToken", token.to_string()));
+        }
 
-            let resp = self
-                .http
-                .get("https://www.googleapis.com/youtube/v3/playlistItems")
-                .query(&params)
-                .send()
-                .await;
-
-            let resp = match resp {
-                Ok(r) => r,
-                Err(e) => {
-                    tracing::warn!("YouTube playlistItems API request failed: {e}");
-                    break;
-                }
+        let resp = self
+            .http
+            .get("https://www.googleapis.com/youtube/v3/playlistItems")
+            .query(&params)
+            .send()
+            .await
+            .map_err(|e| {
+                tracing::warn!("YouTube playlistItems API request failed: {e}");
+                PlaylistError::Api(e.to_string())
+            })?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let reasons: Vec<String> = match resp.json::<ApiErrorResponse>().await {
+                Ok(body) => body.error.errors.into_iter().map(|e| e.reason).collect(),
+                Err(_) => Vec::new(),
             };
+            tracing::warn!("YouTube playlistItems API returned {status}: {reasons:?}");
+
+            let unavailable = reasons
+                .iter()
+                .any(|r| r == "playlistNotFound" || r == "playlistForbidden");
+            return Err(if unavailable {
+                PlaylistError::Unavailable
+            } else {
+                PlaylistError::Api(status.to_string())
+            });
+        }
 
-            let playlist_resp: PlaylistItemsResponse = match resp.json().await {
-                Ok(p) => p,
-                Err(e) => {
-                    tracing::warn!("YouTube playlistItems API parse failed: {e}");
-                    break;
-                }
-            };
+        let playlist_resp: PlaylistItemsResponse = resp.json().await.map_err(|e| {
+            tracing::warn!("YouTube playlistItems API parse failed: {e}");
+            PlaylistError::Api(e.to_string())
+        })?;
 
-            for item in playlist_resp.items {
-                if let Some(video_id) = item.snippet.resource_id.video_id {
-                    let thumbnail_url = item
-                        .snippet
-                        .thumbnails
-                        .and_then(|t| t.high.or(t.default))
-                        .map(|t| t.url);
-
-                    tracks.push(Track {
-                        title: item.snippet.title,
-                        artist: item.snippet.channel_title,
-                        url: format!("https://www.youtube.com/watch?v={video_id}"),
-                        source: TrackSource::YouTube,
-                        duration: None,
-                        thumbnail_url,
-                    });
-                }
-            }
+        let mut tracks = Vec::new();
+        for item in playlist_resp.items {
+            if let Some(video_id) = item.snippet.resource_id.video_id {
+                let thumbnails = item.snippet.thumbnails.unwrap_or_default();
 
-            match playlist_resp.next_page_token {
-                Some(token) => page_token = Some(token),
-                None => break,
+                tracks.push(Track {
+                    title: item.snippet.title,
+                    artist: item.snippet.channel_title,
+                    album: None,
+                    url: canonicalize_youtube_url(&video_id),
+                    source: TrackSource::YouTube,
+                    duration: None,
+                    thumbnail_url: thumbnails.standard(),
+                    thumbnail_small: thumbnails.small(),
+                    thumbnail_large: thumbnails.large(),
+                    requested_by: None,
+                    handle_id: None,
+                    enqueued_at: None,
+                    resolved_url: None,
+                    loudness: None,
+                    span: None,
+                    chapters: Vec::new(),
+                });
             }
         }
 
-        tracks
+        let next = playlist_resp
+            .next_page_token
+            .map(|token| PageCursor::YouTubePlaylist {
+                id: playlist_id.to_string(),
+                token: Some(token),
+            });
+        Ok(PlaylistTracks {
+            tracks,
+            complete: true,
+            truncated: false,
+            skipped: 0,
+            next,
+        })
     }
 
-    pub async fn get_playlist_name(&self, playlist_id: &str) -> Option<String> {
+    pub async fn get_playlist_info(&self, playlist_id: &str) -> Option<PlaylistInfo> {
         let resp = self
             .http
             .get("https://www.googleapis.com/youtube/v3/playlists")
             .query(&[
-                ("part", "snippet"),
+                ("part", "snippet,contentDetails"),
                 ("id", playlist_id),
                 ("key", &self.api_key),
             ])
@@ -269,14 +437,63 @@ impl YouTubeClient {
             .ok()?;
 
         let playlist_resp: PlaylistResponse = resp.json().await.ok()?;
-        playlist_resp
-            .items
-            .into_iter()
-            .next()
-            .map(|item| item.snippet.title)
+        let item = playlist_resp.items.into_iter().next()?;
+        let image = item
+            .snippet
+            .thumbnails
+            .and_then(|t| t.high.or(t.default))
+            .map(|t| t.url);
+
+        Some(PlaylistInfo {
+            name: item.snippet.title,
+            owner: item.snippet.channel_title,
+            image,
+            track_count: item.content_details.and_then(|d| d.item_count),
+            incomplete: false,
+            truncated_at: None,
+            skipped: 0,
+            still_listing: false,
+            capped_at: None,
+        })
     }
 
     pub async fn get_video(&self, video_id: &str) -> Option<Track> {
+        self.get_video_details(video_id).await.map(|(track, _)| track)
+    }
+
+    /// Like [`Self::get_video`], also returning the video's description.
+    pub async fn get_video_details(&self, video_id: &str) -> Option<(Track, String)> {
+        let item = self.fetch_video(video_id).await??;
+        Some(video_track(video_id, item))
+    }
+
+    /// Like [`Self::get_video_details`], but for a video about to be queued:
+    /// one that can't be played is an error, so it's turned away now rather
+    /// than failing when it comes up. `Ok(None)` means the lookup itself
+    /// failed, which says nothing about the video.
+    pub async fn playable_video(
+        &self,
+        video_id: &str,
+    ) -> Result<Option<(Track, String)>, VideoUnavailable> {
+        let Some(item) = self.fetch_video(video_id).await else {
+            return Ok(None);
+        };
+        let item = item.ok_or(VideoUnavailable::Missing)?;
+        if item.snippet.live_broadcast_content.as_deref() == Some("upcoming") {
+            return Err(VideoUnavailable::Upcoming);
+        }
+        if let Some(region) = &self.region {
+            let restriction = item.content_details.region_restriction.as_ref();
+            if restriction.is_some_and(|restriction| restriction.blocks(region)) {
+                return Err(VideoUnavailable::RegionBlocked(region.clone()));
+            }
+        }
+        Ok(Some(video_track(video_id, item)))
+    }
+
+    /// The video's API entry, `Some(None)` when there's none for the id and
+    /// `None` when the request failed.
+    async fn fetch_video(&self, video_id: &str) -> Option<Option<VideoItem>> {
         let resp = self
             .http
             .get("https://www.googleapis.com/youtube/v3/videos")
@@ -290,23 +507,127 @@ impl YouTubeClient {
             .ok()?;
 
         let video_resp: VideoResponse = resp.json().await.ok()?;
-        let item = video_resp.items.into_iter().next()?;
+        Some(video_resp.items.into_iter().next())
+    }
+}
 
-        let thumbnail_url = item
-            .snippet
-            .thumbnails
-            .and_then(|t| t.high.or(t.default))
-            .map(|t| t.url);
+/// The track and description for a video's API entry.
+fn video_track(video_id: &str, item: VideoItem) -> (Track, String) {
+    let thumbnails = item.snippet.thumbnails.unwrap_or_default();
+
+    let duration = parse_iso8601_duration(&item.content_details.duration);
+
+    let track = Track {
+        title: item.snippet.title,
+        artist: item.snippet.channel_title,
+        album: None,
+        url: canonicalize_youtube_url(video_id),
+        source: TrackSource::YouTube,
+        duration,
+        thumbnail_url: thumbnails.standard(),
+        thumbnail_small: thumbnails.small(),
+        thumbnail_large: thumbnails.large(),
+        requested_by: None,
+        handle_id: None,
+        enqueued_at: None,
+        resolved_url: None,
+        loudness: None,
+        span: None,
+        chapters: parse_chapters(&item.snippet.description, duration),
+    };
+
+    (track, item.snippet.description)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::chapters::parse_timestamp;
+    use crate::domain::track::format_secs;
+
+    #[test]
+    fn durations_parse_from_any_combination_of_units() {
+        let secs = |d: &str| parse_iso8601_duration(d).map(|d| d.as_secs());
+        assert_eq!(secs("PT3M7S"), Some(187));
+        assert_eq!(secs("PT45S"), Some(45));
+        assert_eq!(secs("PT4M"), Some(240));
+        assert_eq!(secs("PT1H"), Some(3600));
+        assert_eq!(secs("PT1H2M45S"), Some(3765));
+        assert_eq!(secs("PT10H0M1S"), Some(36_001));
+    }
 
-        let duration = parse_iso8601_duration(&item.content_details.duration);
+    #[test]
+    fn live_and_malformed_durations_are_unknown() {
+        // Live streams report P0D
+        assert_eq!(parse_iso8601_duration("P0D"), None);
+        assert_eq!(parse_iso8601_duration(""), None);
+        assert_eq!(parse_iso8601_duration("PTxM"), None);
+    }
 
-        Some(Track {
-            title: item.snippet.title,
-            artist: item.snippet.channel_title,
-            url: format!("https://www.youtube.com/watch?v={video_id}"),
-            source: TrackSource::YouTube,
-            duration,
-            thumbnail_url,
-        })
+    #[test]
+    fn durations_round_trip_through_the_display_format() {
+        for (iso, shown) in [("PT3M7S", "3:07"), ("PT59M59S", "59:59"), ("PT1H2M45S", "1:02:45")] {
+            let duration = parse_iso8601_duration(iso).unwrap();
+            assert_eq!(format_secs(duration.as_secs()), shown);
+            assert_eq!(parse_timestamp(shown), Some(duration));
+        }
+    }
+
+    /// A search response as the Data API returns it, with one result per
+    /// `(video id, channel title)`.
+    fn search_response(results: &[(&str, &str)]) -> SearchResponse {
+        let items: Vec<String> = results
+            .iter()
+            .map(|(id, channel)| {
+                format!(
+                    r#"{{"id": {{"kind": "youtube#video", "videoId": "{id}"}},
+                        "snippet": {{"title": "Song {id}", "channelTitle": "{channel}",
+                                     "description": "", "liveBroadcastContent": "none"}}}}"#
+                )
+            })
+            .collect();
+        let json = format!(r#"{{"items": [{}]}}"#, items.join(","));
+        serde_json::from_str(&json).unwrap()
+    }
+
+    fn found(tracks: &[Track]) -> Vec<(&str, &str)> {
+        tracks
+            .iter()
+            .map(|t| (t.url.rsplit('=').next().unwrap_or_default(), t.artist.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn topic_uploads_win_over_regular_channels() {
+        let search = search_response(&[
+            ("vid00000001", "ArtistVEVO"),
+            ("vid00000002", "Artist - Topic"),
+            ("vid00000003", "Some Fan Channel"),
+            ("vid00000004", "Artist - Topic"),
+        ]);
+        let tracks = topic_uploads(search.into_tracks());
+        // Listed under the plain artist name
+        assert_eq!(found(&tracks), [("vid00000002", "Artist"), ("vid00000004", "Artist")]);
+    }
+
+    #[test]
+    fn without_topic_uploads_every_result_is_kept() {
+        let search = search_response(&[("vid00000001", "ArtistVEVO"), ("vid00000002", "Topical")]);
+        let tracks = topic_uploads(search.into_tracks());
+        assert_eq!(found(&tracks), [("vid00000001", "ArtistVEVO"), ("vid00000002", "Topical")]);
+    }
+
+    #[test]
+    fn channel_and_playlist_results_are_skipped() {
+        let json = r#"{"items": [
+            {"id": {"kind": "youtube#channel", "channelId": "UC1"},
+             "snippet": {"title": "Artist", "channelTitle": "Artist - Topic"}},
+            {"id": {"kind": "youtube#video", "videoId": "vid00000001"},
+             "snippet": {"title": "Song", "channelTitle": "Artist - Topic"}}
+        ]}"#;
+        let search: SearchResponse = serde_json::from_str(json).unwrap();
+        let tracks = topic_uploads(search.into_tracks());
+        assert_eq!(found(&tracks), [("vid00000001", "Artist")]);
+        assert_eq!(tracks[0].url, "https://www.youtube.com/watch?v=vid00000001");
     }
 }
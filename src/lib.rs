@@ -0,0 +1,11 @@
+//! The queue, search and playback machinery behind renoir-bot, usable
+//! without the Discord side. The bot binary wires poise commands on top.
+//!
+//! - [`domain`]: tracks, queues, settings and the other plain data types.
+//! - [`infrastructure`]: clients for YouTube, Spotify, yt-dlp and songbird.
+//! - [`services`]: resolving links and searches, and the per-guild state
+//!   built on the domain types.
+
+pub mod domain;
+pub mod infrastructure;
+pub mod services;
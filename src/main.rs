@@ -1,41 +1,103 @@
 mod commands;
 mod config;
-mod domain;
-mod infrastructure;
-mod services;
+
+// Bound at the crate root so the bot's modules reach the library as
+// `crate::domain` and so on, the same as the library's own modules do
+use renoir_bot::{domain, infrastructure, services};
 
 use poise::serenity_prelude as serenity;
 use songbird::SerenityInit;
+use tracing::Instrument;
 
+use commands::channel_status::ChannelStatuses;
+use commands::server_mute::ServerMutes;
+use commands::stale_input::InputClocks;
 use config::Config;
-use infrastructure::spotify::SpotifyClient;
+use infrastructure::enqueue_store::EnqueueStore;
+use infrastructure::now_playing_store::NowPlayingStore;
+use infrastructure::preferences_store::PreferencesStore;
+use infrastructure::schedule_store::ScheduleStore;
+use infrastructure::settings_store::SettingsStore;
+use infrastructure::spotify::{SpotifyAccounts, SpotifyClient};
+use infrastructure::spotify_token_store::SpotifyTokenStore;
+use infrastructure::stats_store::StatsStore;
+use infrastructure::voice::{SongbirdBackend, VoiceBackend};
 use infrastructure::youtube::YouTubeClient;
+use services::guild_state::{
+    BoardRenders, EnqueueCancels, EnqueueLocks, InactivityHandles, JoinLocks, NowPlayingLocks,
+    NowPlayingMessages, QueueWarnings, RepeatStates, SessionLocks, Sessions, Standbys,
+    TextChannels,
+};
+use services::history_service::{HistoryService, PlayHistories};
+use services::incident_service::{IncidentService, PlaybackIncidents};
 use services::music_service::MusicService;
+use services::preferences_service::{PreferencesService, UserPreferencesMap};
 use services::queue_service::{GuildQueues, QueueService};
+use services::schedule_service::{ScheduleService, ScheduledJobs};
+use services::settings_service::{GuildSettingsMap, SettingsService};
+use services::stats_service::{GuildStatsMap, StatsService};
+use services::vote_service::{VotePolls, VoteService};
 
 use std::collections::HashMap;
-use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
-use tokio::sync::{Mutex, Notify, RwLock};
-
-pub type InactivityHandles = Arc<RwLock<HashMap<serenity::GuildId, Arc<Notify>>>>;
-pub type EnqueueLocks = Arc<RwLock<HashMap<serenity::GuildId, Arc<Mutex<()>>>>>;
-pub type EnqueueCancels = Arc<RwLock<HashMap<serenity::GuildId, Vec<Arc<AtomicBool>>>>>;
-pub type JoinLocks = Arc<RwLock<HashMap<serenity::GuildId, Arc<Mutex<()>>>>>;
-pub type NowPlayingMessages =
-    Arc<RwLock<HashMap<serenity::GuildId, (serenity::ChannelId, serenity::MessageId)>>>;
-pub type RepeatStates = Arc<RwLock<HashMap<serenity::GuildId, bool>>>;
+use tokio::sync::RwLock;
 
+/// Shared bot state. Every field is reference-counted, so cloning is cheap and
+/// lets background tasks and songbird event handlers hold their own handle.
+/// Most per-guild maps are defined in `services::guild_state`, along with the
+/// lock order. The few holding the commands' own types are defined next to
+/// those commands, which is why `Data` stays with the bot.
+#[derive(Clone)]
 pub struct Data {
-    pub music_service: MusicService,
+    pub music_service: Arc<MusicService>,
+    pub voice: Arc<dyn VoiceBackend>,
     pub guild_queues: GuildQueues,
     pub http_client: reqwest::Client,
+    /// yt-dlp format selector for every input.
+    pub ytdlp_format: Arc<str>,
+    /// Opus bitrate set on each voice connection, or songbird's default.
+    pub voice_bitrate: Option<i32>,
     pub inactivity_handles: InactivityHandles,
     pub enqueue_locks: EnqueueLocks,
     pub enqueue_cancels: EnqueueCancels,
     pub join_locks: JoinLocks,
     pub now_playing_messages: NowPlayingMessages,
+    pub now_playing_locks: NowPlayingLocks,
+    /// Records `now_playing_messages` on disk, unless turned off.
+    pub now_playing_store: Option<Arc<NowPlayingStore>>,
+    /// Records what background enqueues still have to add, unless turned off.
+    pub enqueue_store: Option<Arc<EnqueueStore>>,
     pub repeat_states: RepeatStates,
+    pub text_channels: TextChannels,
+    pub sessions: Sessions,
+    pub queue_warnings: QueueWarnings,
+    pub playback_incidents: PlaybackIncidents,
+    pub play_histories: PlayHistories,
+    pub vote_polls: VotePolls,
+    /// Most entries kept in each guild's play history.
+    pub history_limit: usize,
+    pub server_mutes: ServerMutes,
+    pub session_locks: SessionLocks,
+    pub standbys: Standbys,
+    pub board_renders: BoardRenders,
+    pub input_clocks: InputClocks,
+    pub channel_statuses: ChannelStatuses,
+    /// Spotify accounts linked for `/export`, when it's configured.
+    pub spotify_accounts: Option<Arc<SpotifyAccounts>>,
+    pub guild_settings: GuildSettingsMap,
+    /// Keeps `guild_settings` across restarts, unless turned off.
+    pub settings_store: Option<Arc<SettingsStore>>,
+    pub user_preferences: UserPreferencesMap,
+    /// Keeps `user_preferences` across restarts, unless turned off.
+    pub preferences_store: Option<Arc<PreferencesStore>>,
+    pub guild_stats: GuildStatsMap,
+    /// Keeps `guild_stats` on disk, unless turned off.
+    pub stats_store: Option<Arc<StatsStore>>,
+    pub scheduled_jobs: ScheduledJobs,
+    /// Keeps `scheduled_jobs` on disk, unless turned off.
+    pub schedule_store: Option<Arc<ScheduleStore>>,
+    /// Post a welcome message when added to a new server.
+    pub onboarding_messages: bool,
 }
 
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -50,11 +112,72 @@ async fn main() {
 
     let http_client = reqwest::Client::new();
 
-    let spotify = SpotifyClient::new(&config.spotify_client_id, &config.spotify_client_secret).await;
-    let youtube = YouTubeClient::new(http_client.clone(), config.youtube_api_key);
-    let music_service = MusicService::new(spotify, youtube);
+    let spotify_accounts = match config.spotify_redirect_uri.as_deref() {
+        Some(uri) => {
+            let store = config.spotify_token_file.map(SpotifyTokenStore::new);
+            let accounts = SpotifyAccounts::new(&config.spotify_client_id, uri, store);
+            accounts.restore().await;
+            Some(Arc::new(accounts))
+        }
+        None => None,
+    };
+    let spotify = SpotifyClient::new(
+        &config.spotify_client_id,
+        &config.spotify_client_secret,
+        config.spotify_market.as_deref(),
+    )
+    .await;
+    let youtube =
+        YouTubeClient::new(http_client.clone(), config.youtube_api_key, config.youtube_region);
+    let music_service = Arc::new(MusicService::new(
+        spotify,
+        youtube,
+        config.max_playlist_items,
+        config.search_result_limit,
+        config.search_timeout,
+    ));
+
+    let songbird = songbird::Songbird::serenity();
+    let voice: Arc<dyn VoiceBackend> = Arc::new(SongbirdBackend::new(songbird.clone()));
 
     let guild_queues = QueueService::new_guild_queues();
+    let settings_store = config.settings_file.map(|path| Arc::new(SettingsStore::new(path)));
+    let guild_settings = match &settings_store {
+        Some(store) => SettingsService::load(store).await,
+        None => SettingsService::new_guild_settings(),
+    };
+    let preferences_store = config
+        .preferences_file
+        .map(|path| Arc::new(PreferencesStore::new(path)));
+    let user_preferences = match &preferences_store {
+        Some(store) => PreferencesService::load(store).await,
+        None => PreferencesService::new_user_preferences(),
+    };
+    let stats_store = config.stats_file.map(|path| Arc::new(StatsStore::new(path)));
+    let guild_stats = match &stats_store {
+        Some(store) => StatsService::load(store).await,
+        None => StatsService::new_guild_stats(),
+    };
+    let playback_incidents = IncidentService::new_playback_incidents();
+    let play_histories = HistoryService::new_play_histories();
+    let vote_polls = VoteService::new_vote_polls();
+    let schedule_store = config.schedule_file.map(|path| Arc::new(ScheduleStore::new(path)));
+    let scheduled_jobs = match &schedule_store {
+        Some(store) => ScheduleService::load(store).await,
+        None => ScheduleService::new_scheduled_jobs(),
+    };
+    let onboarding_messages = config.onboarding_messages;
+    let history_limit = config.history_limit;
+    let ytdlp_format: Arc<str> = config.ytdlp_format.into();
+    let voice_bitrate = config.voice_bitrate;
+    let dev_guild_id = config.dev_guild_id;
+    let commands_hash_file = config.commands_hash_file;
+    let now_playing_store = config
+        .now_playing_file
+        .map(|path| Arc::new(NowPlayingStore::new(path)));
+    let enqueue_store = config
+        .enqueue_file
+        .map(|path| Arc::new(EnqueueStore::new(path)));
 
     let intents =
         serenity::GatewayIntents::non_privileged() | serenity::GatewayIntents::GUILD_VOICE_STATES;
@@ -63,33 +186,107 @@ async fn main() {
         .options(poise::FrameworkOptions {
             commands: vec![
                 commands::play::play(),
+                commands::play::playnow(),
+                commands::join::join(),
                 commands::stop::stop(),
                 commands::next::next(),
                 commands::skip::skip(),
+                commands::seek::seek(),
                 commands::list::list(),
+                commands::queue::queue(),
+                commands::clean::clean(),
+                commands::prune::prune(),
+                commands::settings::settings(),
+                commands::my_settings::mysettings(),
+                commands::blocklist::blocklist(),
+                commands::reset::reset(),
+                commands::schedule::schedule(),
+                commands::top::top(),
+                commands::move_user::moveuser(),
+                commands::vote::vote(),
+                commands::history::history(),
+                commands::resolve::resolve(),
+                commands::export::export(),
+                commands::debug::debug(),
+                commands::session::session(),
             ],
             event_handler: |ctx, event, _framework, data| {
+                let span = tracing::info_span!("event", shard = ctx.shard_id.0);
                 Box::pin(async move {
-                    if let serenity::FullEvent::InteractionCreate { interaction } = event {
-                        if let Some(component) = interaction.as_message_component() {
-                            if component.data.custom_id.starts_with("np_") {
-                                commands::now_playing::handle_now_playing_interaction(
-                                    ctx, component, data,
-                                )
-                                .await;
+                    match event {
+                        serenity::FullEvent::Ready { data_about_bot } => {
+                            let total = data_about_bot.shard.map_or(1, |shard| shard.total);
+                            tracing::info!(
+                                "Shard {} of {total} ready with {} guilds",
+                                ctx.shard_id.0,
+                                data_about_bot.guilds.len()
+                            );
+                        }
+                        serenity::FullEvent::Resume { .. } => {
+                            tracing::info!("Shard {} resumed its session", ctx.shard_id.0);
+                        }
+                        serenity::FullEvent::ShardStageUpdate { event } => {
+                            tracing::info!(
+                                "Shard {} connection: {} -> {}",
+                                event.shard_id.0,
+                                event.old,
+                                event.new
+                            );
+                        }
+                        serenity::FullEvent::InteractionCreate { interaction } => {
+                            if let Some(component) = interaction.as_message_component() {
+                                if component.data.custom_id.starts_with("np_") {
+                                    tokio::spawn(
+                                        commands::now_playing::handle_now_playing_interaction(
+                                            ctx.clone(),
+                                            component.clone(),
+                                            data.clone(),
+                                        ),
+                                    );
+                                }
                             }
                         }
+                        serenity::FullEvent::GuildCreate { guild, is_new } => {
+                            commands::onboarding::handle_guild_create(ctx, guild, *is_new, data)
+                                .await;
+                        }
+                        serenity::FullEvent::MessageDelete {
+                            deleted_message_id,
+                            guild_id,
+                            ..
+                        } => {
+                            commands::now_playing::handle_message_delete(
+                                *guild_id,
+                                *deleted_message_id,
+                                data,
+                            )
+                            .await;
+                        }
+                        serenity::FullEvent::VoiceStateUpdate { old, new } => {
+                            commands::server_mute::handle_voice_state_update(ctx, new, data)
+                                .await;
+                            commands::clean::handle_voice_state_update(ctx, new, data).await;
+                            commands::session::handle_voice_state_update(ctx, new, data).await;
+                            commands::cleanup::handle_voice_state_update(ctx, new, data).await;
+                            commands::standby::handle_voice_state_update(
+                                ctx,
+                                old.as_ref(),
+                                new,
+                                data,
+                            )
+                            .await;
+                        }
+                        _ => {}
                     }
                     Ok(())
-                })
+                }
+                .instrument(span))
             },
             on_error: |error| {
                 Box::pin(async move {
                     match error {
                         poise::FrameworkError::Command { error, ctx, .. } => {
-                            let msg = error.to_string();
-                            tracing::warn!("Command error: {msg}");
-                            let _ = ctx.say(format!("❌ {msg}")).await;
+                            commands::errors::reply_error(ctx, &error).await;
                         }
                         other => {
                             if let Err(e) = poise::builtins::on_error(other).await {
@@ -101,36 +298,116 @@ async fn main() {
             },
             ..Default::default()
         })
-        .setup(move |ctx, _ready, framework| {
+        .setup(move |ctx, ready, framework| {
             Box::pin(async move {
-                poise::builtins::register_globally(ctx, &framework.options().commands).await?;
+                commands::registration::register_commands(
+                    ctx,
+                    ready.application.id,
+                    &framework.options().commands,
+                    dev_guild_id,
+                    commands_hash_file.as_deref(),
+                )
+                .await?;
                 tracing::info!("Bot is ready!");
+                // Anything still recorded was left live by a crash or restart
+                if let Some(store) = &now_playing_store {
+                    let orphans = store.take().await;
+                    if !orphans.is_empty() {
+                        let http = ctx.http.clone();
+                        tokio::spawn(async move {
+                            commands::cleanup::retire_orphaned_messages(&http, orphans).await;
+                        });
+                    }
+                }
+                // Taken before anything new is recorded, and held for the
+                // guilds' next sessions once `Data` is built
+                let unfinished = match &enqueue_store {
+                    Some(store) => store.take().await,
+                    None => Vec::new(),
+                };
                 let inactivity_handles = Arc::new(RwLock::new(HashMap::new()));
                 let enqueue_locks = Arc::new(RwLock::new(HashMap::new()));
                 let enqueue_cancels = Arc::new(RwLock::new(HashMap::new()));
                 let join_locks = Arc::new(RwLock::new(HashMap::new()));
                 let now_playing_messages = Arc::new(RwLock::new(HashMap::new()));
+                let now_playing_locks = Arc::new(RwLock::new(HashMap::new()));
                 let repeat_states = Arc::new(RwLock::new(HashMap::new()));
-                Ok(Data {
+                let text_channels = Arc::new(RwLock::new(HashMap::new()));
+                let sessions = Arc::new(RwLock::new(HashMap::new()));
+                let queue_warnings = Arc::new(RwLock::new(HashMap::new()));
+                let server_mutes = Arc::new(RwLock::new(HashMap::new()));
+                let session_locks = Arc::new(RwLock::new(HashMap::new()));
+                let standbys = Arc::new(RwLock::new(HashMap::new()));
+                let board_renders = Arc::new(RwLock::new(HashMap::new()));
+                let input_clocks = Arc::new(RwLock::new(HashMap::new()));
+                let channel_statuses = Arc::new(RwLock::new(HashMap::new()));
+                let data = Data {
                     music_service,
+                    voice,
                     guild_queues,
                     http_client,
+                    ytdlp_format,
+                    voice_bitrate,
                     inactivity_handles,
                     enqueue_locks,
                     enqueue_cancels,
                     join_locks,
                     now_playing_messages,
+                    now_playing_locks,
+                    now_playing_store,
+                    enqueue_store,
                     repeat_states,
-                })
+                    text_channels,
+                    sessions,
+                    queue_warnings,
+                    playback_incidents,
+                    play_histories,
+                    vote_polls,
+                    history_limit,
+                    server_mutes,
+                    session_locks,
+                    standbys,
+                    board_renders,
+                    input_clocks,
+                    channel_statuses,
+                    spotify_accounts,
+                    guild_settings,
+                    settings_store,
+                    user_preferences,
+                    preferences_store,
+                    guild_stats,
+                    stats_store,
+                    scheduled_jobs,
+                    schedule_store,
+                    onboarding_messages,
+                };
+                // Arms the jobs loaded from the store along with new ones
+                commands::schedule::spawn_scheduler(ctx.clone(), data.clone());
+                commands::top::spawn_stats_saver(data.clone());
+                if let Some(store) = data.enqueue_store.clone().filter(|_| !unfinished.is_empty()) {
+                    tokio::spawn(commands::cleanup::hold_unfinished_enqueues(
+                        ctx.http.clone(),
+                        store,
+                        unfinished,
+                    ));
+                }
+                Ok(data)
             })
         })
         .build();
 
     let mut client = serenity::ClientBuilder::new(&config.discord_token, intents)
         .framework(framework)
-        .register_songbird()
+        .register_songbird_with(songbird)
         .await
         .expect("Failed to create client");
 
-    client.start().await.expect("Client error");
+    // Discord requires sharding past 2500 guilds. Every shard shares the one
+    // framework, so `Data` and songbird are set up once and the per-guild
+    // maps are keyed the same whichever shard a guild lives on.
+    let started = match config.shard_count {
+        Some(shards) => client.start_shards(shards).await,
+        None => client.start_autosharded().await,
+    };
+    started.expect("Client error");
 }
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long autocomplete results are reused before searching again.
+const CACHE_TTL: Duration = Duration::from_secs(10 * 60);
+/// Most queries kept at once, across every server.
+const MAX_ENTRIES: usize = 500;
+
+/// `(name, value)` autocomplete choices, as from
+/// [`autocomplete_choices`](crate::services::music_service::autocomplete_choices).
+pub type Choices = Vec<(String, String)>;
+
+/// Recent `/play` autocomplete results by query, so retyping a query skips
+/// the providers, and a slow search can offer what an earlier keystroke
+/// found instead of nothing.
+#[derive(Default)]
+pub struct AutocompleteCache {
+    /// Keyed by search scope, then the normalized query.
+    entries: Mutex<HashMap<(String, String), (Instant, Choices)>>,
+}
+
+/// Lowercased with runs of whitespace collapsed, so "Daft  Punk" and "daft
+/// punk" share an entry.
+fn normalize(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase()
+}
+
+impl AutocompleteCache {
+    /// The results for `query`, searched within `scope` (the providers and
+    /// options that shape them), unless they're stale.
+    pub fn get(&self, scope: &str, query: &str) -> Option<Choices> {
+        let entries = self.entries.lock().unwrap();
+        let (at, choices) = entries.get(&(scope.to_string(), normalize(query)))?;
+        (at.elapsed() < CACHE_TTL).then(|| choices.clone())
+    }
+
+    /// The results for the longest earlier query that `query` extends, e.g.
+    /// "daft pu" for "daft punk".
+    pub fn longest_prefix(&self, scope: &str, query: &str) -> Option<Choices> {
+        let query = normalize(query);
+        let entries = self.entries.lock().unwrap();
+        entries
+            .iter()
+            .filter(|((s, q), (at, _))| {
+                s == scope && query.starts_with(q.as_str()) && at.elapsed() < CACHE_TTL
+            })
+            .max_by_key(|((_, q), _)| q.len())
+            .map(|(_, (_, choices))| choices.clone())
+    }
+
+    pub fn insert(&self, scope: &str, query: &str, choices: Choices) {
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_ENTRIES {
+            entries.retain(|_, (at, _)| at.elapsed() < CACHE_TTL);
+        }
+        if entries.len() >= MAX_ENTRIES {
+            let oldest = entries.iter().min_by_key(|(_, (at, _))| *at).map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert((scope.to_string(), normalize(query)), (Instant::now(), choices));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn choices(name: &str) -> Choices {
+        vec![(name.to_string(), format!("https://youtu.be/{name}"))]
+    }
+
+    #[test]
+    fn a_retyped_query_is_answered_from_the_cache() {
+        let cache = AutocompleteCache::default();
+        cache.insert("yt", "Daft  Punk", choices("a"));
+
+        assert_eq!(cache.get("yt", "daft punk"), Some(choices("a")));
+        assert_eq!(cache.get("spotify", "daft punk"), None);
+        assert_eq!(cache.get("yt", "daft punk one"), None);
+    }
+
+    #[test]
+    fn a_longer_query_falls_back_to_its_longest_cached_prefix() {
+        let cache = AutocompleteCache::default();
+        cache.insert("yt", "daf", choices("short"));
+        cache.insert("yt", "daft pu", choices("long"));
+        cache.insert("yt", "daft x", choices("other"));
+
+        assert_eq!(cache.longest_prefix("yt", "Daft Punk"), Some(choices("long")));
+        assert_eq!(cache.longest_prefix("yt", "dafne"), Some(choices("short")));
+        assert_eq!(cache.longest_prefix("yt", "justice"), None);
+        assert_eq!(cache.longest_prefix("spotify", "daft punk"), None);
+    }
+
+    #[test]
+    fn a_full_cache_makes_room_for_a_new_query() {
+        let cache = AutocompleteCache::default();
+        for i in 0..MAX_ENTRIES {
+            cache.insert("yt", &format!("query {i}"), choices("x"));
+        }
+        cache.insert("yt", "one more", choices("y"));
+
+        assert_eq!(cache.entries.lock().unwrap().len(), MAX_ENTRIES);
+        assert_eq!(cache.get("yt", "one more"), Some(choices("y")));
+    }
+}
@@ -0,0 +1,233 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::sync::{watch, Mutex, MutexGuard};
+
+/// Serializes a guild's queue insertions. Background enqueues take a ticket
+/// when they're started and add their tracks in ticket order, so two
+/// playlists never interleave, but they only hold the mutex for one track at
+/// a time. Cleanup and other queue edits get in between tracks instead of
+/// waiting out a whole collection.
+#[derive(Default)]
+pub struct EnqueueLock {
+    mutex: Mutex<()>,
+    next_ticket: AtomicU64,
+    /// Ticket whose turn it is.
+    serving: watch::Sender<u64>,
+}
+
+impl EnqueueLock {
+    /// Held while a single track is added to the queue.
+    pub async fn lock(&self) -> MutexGuard<'_, ()> {
+        self.mutex.lock().await
+    }
+
+    /// Takes the next place in line for a background enqueue that stops once
+    /// `cancel` is set.
+    pub fn take_ticket(self: &Arc<Self>, cancel: Arc<AtomicBool>) -> Ticket {
+        Ticket {
+            number: self.next_ticket.fetch_add(1, Ordering::Relaxed),
+            lock: self.clone(),
+            cancel,
+        }
+    }
+}
+
+/// Adds a new enqueue's `flag` to a guild's cancel flags, dropping the ones
+/// already cancelled so the list can't grow without bound.
+pub fn track_cancel_flag(flags: &mut Vec<Arc<AtomicBool>>, flag: Arc<AtomicBool>) {
+    flags.retain(|flag| !flag.load(Ordering::Relaxed));
+    flags.push(flag);
+}
+
+/// Drops an enqueue's `flag` from a guild's cancel flags once it's finished.
+pub fn release_cancel_flag(flags: &mut Vec<Arc<AtomicBool>>, flag: &Arc<AtomicBool>) {
+    flags.retain(|tracked| !Arc::ptr_eq(tracked, flag));
+}
+
+/// A background enqueue's place in line. Dropping it hands the turn to the
+/// next ticket.
+pub struct Ticket {
+    lock: Arc<EnqueueLock>,
+    number: u64,
+    cancel: Arc<AtomicBool>,
+}
+
+impl Ticket {
+    /// Waits until every earlier ticket has finished.
+    pub async fn wait_turn(&self) {
+        let mut serving = self.lock.serving.subscribe();
+        let _ = serving.wait_for(|serving| *serving >= self.number).await;
+    }
+
+    pub async fn lock(&self) -> MutexGuard<'_, ()> {
+        self.lock.lock().await
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+
+    /// Place in line, unique within the guild.
+    pub fn number(&self) -> u64 {
+        self.number
+    }
+
+    pub fn cancel_flag(&self) -> &Arc<AtomicBool> {
+        &self.cancel
+    }
+}
+
+impl Drop for Ticket {
+    fn drop(&mut self) {
+        let next = self.number + 1;
+        self.lock.serving.send_modify(|serving| *serving = (*serving).max(next));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::domain::queue::MusicQueue;
+    use crate::domain::track::test_track;
+
+    /// Queues `count` tracks the way a background enqueue does: one at a
+    /// time under the lock, checking for a cancel first, and slowly.
+    async fn slow_enqueue(ticket: Ticket, queue: Arc<Mutex<MusicQueue>>, count: usize) -> usize {
+        ticket.wait_turn().await;
+        let mut added = 0;
+        for i in 0..count {
+            let _guard = ticket.lock().await;
+            if ticket.is_cancelled() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(2)).await;
+            queue.lock().await.push(test_track(&format!("t{i}"), None));
+            added += 1;
+        }
+        added
+    }
+
+    /// Cancels like `cleanup_guild`: sets the flag, waits out the track
+    /// being added, then clears.
+    async fn cleanup(lock: &EnqueueLock, cancel: &AtomicBool, queue: &Mutex<MusicQueue>) {
+        cancel.store(true, Ordering::Relaxed);
+        drop(lock.lock().await);
+        queue.lock().await.clear();
+    }
+
+    #[test]
+    fn cancel_flags_stay_bounded_across_sequential_enqueues() {
+        let mut flags = Vec::new();
+        for _ in 0..100 {
+            let flag = Arc::new(AtomicBool::new(false));
+            track_cancel_flag(&mut flags, flag.clone());
+            assert_eq!(flags.len(), 1);
+            release_cancel_flag(&mut flags, &flag);
+        }
+        assert!(flags.is_empty());
+    }
+
+    #[test]
+    fn cancelled_flags_are_dropped_when_the_next_enqueue_starts() {
+        let mut flags = Vec::new();
+        for _ in 0..10 {
+            let flag = Arc::new(AtomicBool::new(false));
+            track_cancel_flag(&mut flags, flag.clone());
+            flag.store(true, Ordering::Relaxed);
+        }
+        assert_eq!(flags.len(), 1);
+
+        let running = Arc::new(AtomicBool::new(false));
+        track_cancel_flag(&mut flags, running.clone());
+        let other = Arc::new(AtomicBool::new(false));
+        track_cancel_flag(&mut flags, other.clone());
+        release_cancel_flag(&mut flags, &other);
+        assert_eq!(flags.len(), 1);
+        assert!(Arc::ptr_eq(&flags[0], &running));
+    }
+
+    #[tokio::test]
+    async fn cleanup_during_an_enqueue_leaves_the_queue_empty() {
+        let lock = Arc::new(EnqueueLock::default());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let queue = Arc::new(Mutex::new(MusicQueue::default()));
+        let ticket = lock.take_ticket(cancel.clone());
+        let task = tokio::spawn(slow_enqueue(ticket, queue.clone(), 100));
+
+        tokio::time::sleep(Duration::from_millis(15)).await;
+        cleanup(&lock, &cancel, &queue).await;
+
+        let added = task.await.unwrap();
+        assert!(added < 100, "the enqueue ran to the end");
+        assert!(queue.lock().await.is_empty());
+    }
+
+    fn urls(queue: &MusicQueue) -> Vec<String> {
+        queue.list().iter().map(|t| t.url.clone()).collect()
+    }
+
+    /// Queues `urls` in ticket order, one track per lock, yielding between
+    /// tracks so concurrent enqueues get the chance to interleave.
+    async fn enqueue(ticket: Ticket, queue: Arc<Mutex<MusicQueue>>, urls: &[&str]) {
+        ticket.wait_turn().await;
+        for url in urls {
+            let _guard = ticket.lock().await;
+            queue.lock().await.push(test_track(url, None));
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn collections_land_whole_in_ticket_order() {
+        let lock = Arc::new(EnqueueLock::default());
+        let queue = Arc::new(Mutex::new(MusicQueue::default()));
+        let [a, b, c] = std::array::from_fn(|_| lock.take_ticket(Arc::new(AtomicBool::new(false))));
+
+        // Started last-first, still served first-first
+        tokio::join!(
+            enqueue(c, queue.clone(), &["c1", "c2"]),
+            enqueue(b, queue.clone(), &["b1", "b2", "b3"]),
+            enqueue(a, queue.clone(), &["a1", "a2"]),
+        );
+
+        assert_eq!(urls(&*queue.lock().await), ["a1", "a2", "b1", "b2", "b3", "c1", "c2"]);
+    }
+
+    #[tokio::test]
+    async fn a_ticket_dropped_before_its_turn_lets_the_next_one_through() {
+        let lock = Arc::new(EnqueueLock::default());
+        let queue = Arc::new(Mutex::new(MusicQueue::default()));
+        let first = lock.take_ticket(Arc::new(AtomicBool::new(false)));
+        let second = lock.take_ticket(Arc::new(AtomicBool::new(false)));
+
+        // E.g. a collection that failed to resolve
+        drop(first);
+        let queued = enqueue(second, queue.clone(), &["b1"]);
+        tokio::time::timeout(Duration::from_secs(1), queued).await.unwrap();
+        assert_eq!(urls(&*queue.lock().await), ["b1"]);
+    }
+
+    #[tokio::test]
+    async fn a_cancel_takes_effect_within_one_track() {
+        let lock = Arc::new(EnqueueLock::default());
+        let cancel = Arc::new(AtomicBool::new(false));
+        let queue = Arc::new(Mutex::new(MusicQueue::default()));
+        let ticket = lock.take_ticket(cancel.clone());
+        let task = tokio::spawn(slow_enqueue(ticket, queue.clone(), 100));
+        tokio::time::sleep(Duration::from_millis(15)).await;
+
+        // Once the track being added is done, nothing more is
+        cancel.store(true, Ordering::Relaxed);
+        let at_cancel = {
+            let _guard = lock.lock().await;
+            queue.lock().await.len()
+        };
+
+        let added = task.await.unwrap();
+        assert_eq!(added, at_cancel);
+        assert_eq!(queue.lock().await.len(), at_cancel);
+    }
+}
@@ -1,3 +1,7 @@
+use poise::serenity_prelude::{ChannelId, UserId};
+
+use crate::infrastructure::youtube::VideoUnavailable;
+
 #[derive(Debug, thiserror::Error)]
 pub enum MusicError {
     #[error("You must be in a voice channel")]
@@ -6,8 +10,136 @@ pub enum MusicError {
     NotInGuild,
     #[error("No results found for your query")]
     NoResults,
-    #[error("The queue is empty")]
+    #[error("That link isn't supported — use a YouTube video or playlist, or a Spotify track, album, or playlist")]
+    UnsupportedLink,
+    #[error("Couldn't resolve this Spotify short link")]
+    ShortLinkUnresolved,
+    #[error("That playlist is private or has been deleted")]
+    PlaylistUnavailable,
+    #[error("This playlist has no tracks playable in the configured market")]
+    NoPlayableTracks,
+    #[error("I'm not in a voice channel")]
+    NotConnected,
+    #[error("Nothing is queued right now")]
     EmptyQueue,
+    #[error("Switching tracks — try again in a moment")]
+    SwitchingTracks,
+    #[error("Nothing is queued after the current track")]
+    NothingUpNext,
+    #[error("You need the DJ role or Manage Server permission to do that")]
+    NotDj,
+    #[error("/playnow plays a single track — use /play for playlists and albums")]
+    NotSingleTrack,
+    #[error("The queue is locked by <@{0}> for this session")]
+    SessionLocked(UserId),
+    #[error("There's no music session to lock — start one with /play first")]
+    NoSession,
+    #[error("I'm currently playing in <#{0}> — join that channel or wait until it's free")]
+    InUseElsewhere(ChannelId),
+    #[error("I'm currently playing in <#{0}> — use `/join` to move me to your channel first")]
+    InUseElsewhereDj(ChannelId),
+    #[error("The queue is full ({0} tracks) — wait for some to play first")]
+    QueueFull(usize),
+    #[error("That's about {0} tracks, but this server allows at most {1} from a single /play")]
+    CollectionTooLarge(usize, usize),
+    #[error("That track is blocked on this server (matched {0})")]
+    Blocked(String),
+    #[error("That rule can't be used: {0}")]
+    InvalidBlockRule(String),
+    #[error("The blocklist can hold at most {0} rules — remove some first")]
+    BlocklistFull(usize),
+    #[error("Couldn't import that file: {0}. Nothing was changed")]
+    InvalidBlocklistFile(String),
+    #[error("Invalid colour `{0}` — use a hex value like #1DB954")]
+    InvalidColour(String),
+    #[error("Couldn't read `{0}` as a time — use HH:MM or a date like 2026-10-23T20:00")]
+    InvalidTime(String),
+    #[error("That time has already passed")]
+    TimeInPast,
+    #[error("This server already has {0} scheduled plays — remove one first")]
+    TooManySchedules(usize),
+    #[error("No scheduled play with id `{0}`")]
+    ScheduleNotFound(u32),
+    #[error("Couldn't read `{0}` as a position — use a time like 1:23, or +30 / -30 seconds")]
+    InvalidPosition(String),
+    #[error("That's past the end of the track ({0} long)")]
+    SeekPastEnd(String),
+    #[error("Couldn't seek in the current track")]
+    SeekFailed,
+    #[error("Spotify export isn't set up on this bot")]
+    SpotifyExportDisabled,
+    #[error("Link your Spotify account first with `/export spotify`")]
+    SpotifyNotLinked,
+    #[error("Start linking with `/export spotify` first, then paste the address here")]
+    SpotifyLinkNotStarted,
+    #[error("Couldn't link your Spotify account — run `/export spotify` and try again")]
+    SpotifyLinkFailed,
+    #[error("Spotify wouldn't create the playlist — try again, or relink with `/export unlink`")]
+    SpotifyExportFailed,
+    #[error("<@{0}> has nothing waiting in the queue")]
+    NothingQueuedBy(UserId),
+    #[error("Give a position to move the tracks to, e.g. 3")]
+    MissingPosition,
+    #[error("A poll is already open — wait for it to close, or use `/vote cancel`")]
+    VoteInProgress,
+    #[error("There's no poll open")]
+    NoVoteOpen,
+    #[error("A poll needs at least 2 choices — queue more tracks or give links to vote on")]
+    NotEnoughVoteOptions,
+    #[error("{0}")]
+    VideoUnavailable(VideoUnavailable),
+    #[error("Could not get track info.")]
+    NoTrackInfo,
     #[error("Failed to join voice channel: {0}")]
     JoinError(String),
 }
+
+impl MusicError {
+    /// Whether the error comes down to how the command was used, as opposed
+    /// to the bot failing at something it should be able to do.
+    pub fn is_user_error(&self) -> bool {
+        !matches!(self, MusicError::JoinError(_) | MusicError::NoTrackInfo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mistakes_in_using_a_command_are_user_errors() {
+        let user_errors = [
+            MusicError::NotInVoiceChannel,
+            MusicError::NotInGuild,
+            MusicError::NoResults,
+            MusicError::UnsupportedLink,
+            MusicError::NotConnected,
+            MusicError::EmptyQueue,
+            MusicError::NotDj,
+            MusicError::SessionLocked(UserId::new(1)),
+            MusicError::InUseElsewhere(ChannelId::new(1)),
+            MusicError::QueueFull(500),
+            MusicError::CollectionTooLarge(900, 200),
+            MusicError::Blocked("word".to_string()),
+            MusicError::InvalidTime("soon".to_string()),
+            MusicError::InvalidPosition("1:xx".to_string()),
+            MusicError::SpotifyNotLinked,
+            MusicError::VoteInProgress,
+            MusicError::VideoUnavailable(VideoUnavailable::Missing),
+        ];
+        for error in user_errors {
+            assert!(error.is_user_error(), "{error:?}");
+        }
+    }
+
+    #[test]
+    fn failures_of_the_bot_itself_are_operational() {
+        let operational = [
+            MusicError::JoinError("timed out connecting to the voice server".to_string()),
+            MusicError::NoTrackInfo,
+        ];
+        for error in operational {
+            assert!(!error.is_user_error(), "{error:?}");
+        }
+    }
+}
@@ -0,0 +1,41 @@
+//! Per-guild state shared between the bot's commands, songbird event
+//! handlers and background tasks. Each map is cheap to clone and is emptied
+//! for a guild when its session ends.
+//!
+//! Lock order: these maps and per-guild locks may be held while locking a
+//! songbird `Call`, never the other way round. A `Call` lock is only held
+//! for work on the call itself, so copy what's needed out of it and drop it
+//! before awaiting anything else.
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
+
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, UserId};
+use tokio::sync::{Mutex, Notify, RwLock};
+
+use crate::domain::now_playing::NowPlayingCards;
+use crate::services::enqueue_lock::EnqueueLock;
+
+/// Wakes each guild's inactivity monitor when something starts playing.
+pub type InactivityHandles = Arc<RwLock<HashMap<GuildId, Arc<Notify>>>>;
+pub type EnqueueLocks = Arc<RwLock<HashMap<GuildId, Arc<EnqueueLock>>>>;
+/// Flags that stop each guild's background playlist enqueues.
+pub type EnqueueCancels = Arc<RwLock<HashMap<GuildId, Vec<Arc<AtomicBool>>>>>;
+pub type JoinLocks = Arc<RwLock<HashMap<GuildId, Arc<Mutex<()>>>>>;
+/// Each guild's live Now Playing messages, one per channel.
+pub type NowPlayingMessages = Arc<RwLock<HashMap<GuildId, NowPlayingCards>>>;
+/// Serializes Now Playing delete/send pairs per guild.
+pub type NowPlayingLocks = Arc<RwLock<HashMap<GuildId, Arc<Mutex<()>>>>>;
+/// Id of each guild's live voice session, bumped whenever one starts.
+pub type Sessions = Arc<RwLock<HashMap<GuildId, u64>>>;
+/// Session in which each guild was last warned that its queue is nearly full.
+pub type QueueWarnings = Arc<RwLock<HashMap<GuildId, u64>>>;
+pub type RepeatStates = Arc<RwLock<HashMap<GuildId, bool>>>;
+pub type TextChannels = Arc<RwLock<HashMap<GuildId, ChannelId>>>;
+/// Host each guild's session is locked to.
+pub type SessionLocks = Arc<RwLock<HashMap<GuildId, UserId>>>;
+/// Fingerprint of what each session board message last showed.
+pub type BoardRenders = Arc<RwLock<HashMap<GuildId, HashMap<MessageId, u64>>>>;
+/// Guilds holding playback until someone joins, with who asked for it.
+pub type Standbys = Arc<RwLock<HashMap<GuildId, UserId>>>;
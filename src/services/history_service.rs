@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::Utc;
+use poise::serenity_prelude::GuildId;
+use tokio::sync::RwLock;
+use uuid::Uuid;
+
+use crate::domain::history::PlayHistory;
+use crate::domain::track::Track;
+
+pub type PlayHistories = Arc<RwLock<HashMap<GuildId, PlayHistory>>>;
+
+pub struct HistoryService;
+
+impl HistoryService {
+    pub fn new_play_histories() -> PlayHistories {
+        Arc::new(RwLock::new(HashMap::new()))
+    }
+
+    /// Records `track` as starting now, keeping at most `limit` entries.
+    pub async fn started(histories: &PlayHistories, guild_id: GuildId, track: Track, limit: usize) {
+        let mut map = histories.write().await;
+        map.entry(guild_id).or_default().start(track, Utc::now(), limit);
+    }
+
+    /// Records that the track played as `handle_id` finished now.
+    pub async fn finished(histories: &PlayHistories, guild_id: GuildId, handle_id: Uuid) {
+        let mut map = histories.write().await;
+        if let Some(history) = map.get_mut(&guild_id) {
+            history.finish(handle_id, Utc::now());
+        }
+    }
+
+    pub async fn replace_handle(histories: &PlayHistories, guild_id: GuildId, old: Uuid, new: Uuid) {
+        let mut map = histories.write().await;
+        if let Some(history) = map.get_mut(&guild_id) {
+            history.replace_handle(old, new);
+        }
+    }
+
+    /// Returns a copy of the guild's history, empty if nothing played yet.
+    pub async fn get(histories: &PlayHistories, guild_id: GuildId) -> PlayHistory {
+        let map = histories.read().await;
+        map.get(&guild_id).cloned().unwrap_or_default()
+    }
+
+    pub async fn clear(histories: &PlayHistories, guild_id: GuildId) {
+        histories.write().await.remove(&guild_id);
+    }
+}
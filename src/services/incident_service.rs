@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use poise::serenity_prelude::GuildId;
+use tokio::sync::RwLock;
+
+use crate::domain::incident::{IncidentLog, PlaybackIncident};
+
+pub type PlaybackIncidents = Arc<RwLock<HashMap<GuildId, IncidentLog>>>;
+
+pub struct IncidentService;
+
+impl IncidentService {
+    pub fn new_playback_incidents() -> PlaybackIncidents {
+        Arc::new(RwLock::new(HashMap::new()))
+    }
+
+    pub async fn record(incidents: &PlaybackIncidents, guild_id: GuildId, incident: PlaybackIncident) {
+        let mut map = incidents.write().await;
+        map.entry(guild_id).or_default().push(incident);
+    }
+
+    /// Returns a copy of the guild's log, empty if nothing failed this session.
+    pub async fn get(incidents: &PlaybackIncidents, guild_id: GuildId) -> IncidentLog {
+        let map = incidents.read().await;
+        map.get(&guild_id).cloned().unwrap_or_default()
+    }
+
+    pub async fn clear(incidents: &PlaybackIncidents, guild_id: GuildId) {
+        incidents.write().await.remove(&guild_id);
+    }
+}
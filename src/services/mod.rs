@@ -1,4 +1,16 @@
-pub mod cleanup;
+pub mod autocomplete_cache;
+pub mod enqueue_lock;
 pub mod error;
+pub mod guild_state;
+pub mod history_service;
+pub mod incident_service;
 pub mod music_service;
+pub mod now_playing_service;
+pub mod playback_service;
+pub mod preferences_service;
 pub mod queue_service;
+pub mod schedule_service;
+pub mod session_service;
+pub mod settings_service;
+pub mod stats_service;
+pub mod vote_service;
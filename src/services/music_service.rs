@@ -1,9 +1,15 @@
 use regex::Regex;
+use std::collections::HashSet;
 use std::sync::LazyLock;
+use std::time::Duration;
 
+use crate::domain::playlist::{PageCursor, PlaylistTracks};
+use crate::domain::settings::SearchPreference;
 use crate::domain::track::Track;
 use crate::infrastructure::spotify::SpotifyClient;
-use crate::infrastructure::youtube::YouTubeClient;
+use crate::infrastructure::youtube::{PlaylistError, YouTubeClient};
+use crate::services::autocomplete_cache::AutocompleteCache;
+use crate::services::error::MusicError;
 
 static YOUTUBE_PLAYLIST_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r"youtube\.com/(?:playlist\?|watch\?.*list=)").unwrap()
@@ -14,31 +20,198 @@ static YOUTUBE_PLAYLIST_ID_RE: LazyLock<Regex> = LazyLock::new(|| {
 });
 
 static YOUTUBE_URL_RE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"(?:youtube\.com/watch|youtu\.be/|youtube\.com/shorts/)").unwrap()
+    Regex::new(r"(?:youtube\.com/(?:watch|shorts/|live/|embed/)|youtube-nocookie\.com/embed/|youtu\.be/)").unwrap()
 });
 
 static YOUTUBE_VIDEO_ID_RE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"(?:youtube\.com/watch\?.*v=|youtu\.be/|youtube\.com/shorts/)([a-zA-Z0-9_-]{11})").unwrap()
+    Regex::new(r"(?:youtube\.com/watch\?(?:.*&)?v=|youtube\.com/(?:shorts|live|embed)/|youtube-nocookie\.com/embed/|youtu\.be/)([a-zA-Z0-9_-]{11})(?:[?&#/]|$)").unwrap()
 });
 
 static SPOTIFY_URL_RE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"open\.spotify\.com/(track|playlist|album)/([a-zA-Z0-9]+)").unwrap()
+    // Share links may carry a locale segment (`/intl-de/`) or be embeds, and
+    // the desktop app copies `spotify:track:<id>` URIs
+    Regex::new(r"(?:open\.spotify\.com/(?:intl-[a-zA-Z-]+/)?(?:embed/)?|spotify:)(track|playlist|album)[/:]([a-zA-Z0-9]+)").unwrap()
 });
 
+static SPOTIFY_SHORT_LINK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^https?://(?:spotify\.link|spotify\.app\.link)/\S+$").unwrap()
+});
+
+/// Bracketed tags in video titles, like "(Official Video)" or "[4K]".
+static VIDEO_TAG_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\s*(?:\([^)]*\)|\[[^\]]*\])").unwrap()
+});
+
+/// Redirects followed when expanding a Spotify short link.
+const SHORT_LINK_HOPS: usize = 3;
+
+/// What a Spotify link points at, with its id.
 pub enum SpotifyUrl {
     Track(String),
     Playlist(String),
     Album(String),
 }
 
+/// Runs two providers' searches at once and returns whichever finds
+/// something first, or the other's results if the first comes back empty.
+async fn race(
+    first: impl Future<Output = Vec<Track>>,
+    second: impl Future<Output = Vec<Track>>,
+) -> Vec<Track> {
+    tokio::pin!(first);
+    tokio::pin!(second);
+
+    tokio::select! {
+        found = &mut first => {
+            if !found.is_empty() { return found; }
+            second.await
+        }
+        found = &mut second => {
+            if !found.is_empty() { return found; }
+            first.await
+        }
+    }
+}
+
+/// Searches `preferred`, and only starts `fallback` when it finds nothing.
+async fn with_fallback(
+    preferred: impl Future<Output = Vec<Track>>,
+    fallback: impl Future<Output = Vec<Track>>,
+) -> Vec<Track> {
+    let found = preferred.await;
+    if !found.is_empty() {
+        return found;
+    }
+    fallback.await
+}
+
+/// Waits up to `timeout` for a provider's results, treating one that takes
+/// longer as having found nothing, so a slow provider can't hold up the
+/// other.
+async fn within_timeout(
+    timeout: Duration,
+    provider: &str,
+    search: impl Future<Output = Vec<Track>>,
+) -> Vec<Track> {
+    match tokio::time::timeout(timeout, search).await {
+        Ok(tracks) => tracks,
+        Err(_) => {
+            tracing::warn!("{provider} search gave up after {}ms", timeout.as_millis());
+            Vec::new()
+        }
+    }
+}
+
+/// Cuts a page fetched with `budget` entries left under the item cap down to
+/// the budget, marking the listing truncated if the provider had more.
+fn fit_to_budget(page: &mut PlaylistTracks, budget: usize) {
+    // YouTube pages are a fixed 50, so the last one can overshoot
+    if page.read() >= budget {
+        page.truncated = page.read() > budget || page.next.is_some();
+        page.tracks.truncate(budget.saturating_sub(page.skipped));
+        page.next = None;
+    }
+}
+
+/// Follows `first` through `fetch_page` for [`MusicService::fetch_pages`].
+/// Stops asking once the cap ends the listing, so no page past it is
+/// requested.
+async fn list_pages<F>(
+    mut fetched: PlaylistTracks,
+    read: usize,
+    whole: bool,
+    fetch_page: impl Fn(PageCursor, usize) -> F,
+) -> PlaylistTracks
+where
+    F: Future<Output = Result<PlaylistTracks, PlaylistError>>,
+{
+    while whole || fetched.tracks.is_empty() {
+        let Some(next) = fetched.next.take() else {
+            break;
+        };
+        match fetch_page(next, read + fetched.read()).await {
+            Ok(page) => fetched.extend(page),
+            Err(_) => {
+                fetched.complete = false;
+                break;
+            }
+        }
+    }
+    fetched
+}
+
+/// Discord's limit on an autocomplete choice's name and value, in characters.
+const CHOICE_MAX_CHARS: usize = 100;
+
+/// Turns search results into `(name, value)` autocomplete choices: one per
+/// song, named with its source tag, truncated to Discord's limits. URLs are
+/// stripped of their query string when that's what makes them too long, and
+/// tracks whose URL still doesn't fit are left out since a cut URL won't play.
+pub fn autocomplete_choices(tracks: Vec<Track>) -> Vec<(String, String)> {
+    let mut seen = HashSet::new();
+    tracks
+        .into_iter()
+        .filter(|track| seen.insert(track.dedup_key()))
+        .filter_map(|track| {
+            let value = if track.url.chars().count() > CHOICE_MAX_CHARS {
+                track.url.split(['?', '#']).next().unwrap_or_default().to_string()
+            } else {
+                track.url.clone()
+            };
+            if value.chars().count() > CHOICE_MAX_CHARS {
+                return None;
+            }
+
+            let name = track.to_string();
+            let name = if name.chars().count() > CHOICE_MAX_CHARS {
+                format!("{}...", name.chars().take(CHOICE_MAX_CHARS - 3).collect::<String>())
+            } else {
+                name
+            };
+            Some((name, value))
+        })
+        .take(25)
+        .collect()
+}
+
+/// Turns links and search queries into [`Track`]s: parses YouTube and
+/// Spotify URLs, lists playlists and albums, and searches both providers.
 pub struct MusicService {
     pub spotify: SpotifyClient,
     pub youtube: YouTubeClient,
+    /// Cap on tracks read from a single playlist or album.
+    pub max_playlist_items: usize,
+    /// Results asked of each provider for a text search.
+    pub search_limit: u32,
+    /// How long one provider's search may take before it's given up on.
+    search_timeout: Duration,
+    /// Doesn't follow redirects, so short links can be expanded hop by hop.
+    short_links: reqwest::Client,
+    /// Recent `/play` autocomplete results.
+    pub autocomplete_cache: AutocompleteCache,
 }
 
 impl MusicService {
-    pub fn new(spotify: SpotifyClient, youtube: YouTubeClient) -> Self {
-        Self { spotify, youtube }
+    pub fn new(
+        spotify: SpotifyClient,
+        youtube: YouTubeClient,
+        max_playlist_items: usize,
+        search_limit: u32,
+        search_timeout: Duration,
+    ) -> Self {
+        let short_links = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Failed to build short link client");
+        Self {
+            spotify,
+            youtube,
+            max_playlist_items,
+            search_limit,
+            search_timeout,
+            short_links,
+            autocomplete_cache: AutocompleteCache::default(),
+        }
     }
 
     pub fn is_youtube_playlist_url(query: &str) -> bool {
@@ -56,6 +229,17 @@ impl MusicService {
         true
     }
 
+    /// The video and playlist ids of a `watch?v=…&list=…` link, which could
+    /// mean either. Mixes aren't included, those always play as the video.
+    pub fn video_in_playlist(query: &str) -> Option<(String, String)> {
+        if !Self::is_youtube_playlist_url(query) {
+            return None;
+        }
+        let video_id = Self::extract_youtube_video_id(query)?;
+        let playlist_id = Self::extract_youtube_playlist_id(query)?;
+        Some((video_id, playlist_id))
+    }
+
     pub fn extract_youtube_playlist_id(query: &str) -> Option<String> {
         let caps = YOUTUBE_PLAYLIST_ID_RE.captures(query)?;
         Some(caps.get(1)?.as_str().to_string())
@@ -74,6 +258,38 @@ impl MusicService {
         SPOTIFY_URL_RE.is_match(query)
     }
 
+    /// Whether `/play url` knows how to play this link.
+    pub fn is_supported_link(query: &str) -> bool {
+        Self::is_youtube_playlist_url(query)
+            || Self::is_youtube_url(query)
+            || Self::parse_spotify_url(query).is_some()
+            || Self::is_spotify_short_link(query)
+    }
+
+    /// `spotify.link` / `spotify.app.link` URLs from mobile shares.
+    pub fn is_spotify_short_link(query: &str) -> bool {
+        SPOTIFY_SHORT_LINK_RE.is_match(query)
+    }
+
+    /// Follows a Spotify short link's `Location` headers until it lands on an
+    /// `open.spotify.com` URL that [`Self::parse_spotify_url`] understands.
+    pub async fn resolve_spotify_short_link(&self, url: &str) -> Option<String> {
+        let mut current = reqwest::Url::parse(url).ok()?;
+        for _ in 0..SHORT_LINK_HOPS {
+            let response = self.short_links.get(current.clone()).send().await.ok()?;
+            let location = response
+                .headers()
+                .get(reqwest::header::LOCATION)?
+                .to_str()
+                .ok()?;
+            current = current.join(location).ok()?;
+            if Self::parse_spotify_url(current.as_str()).is_some() {
+                return Some(current.into());
+            }
+        }
+        None
+    }
+
     pub fn parse_spotify_url(query: &str) -> Option<SpotifyUrl> {
         let caps = SPOTIFY_URL_RE.captures(query)?;
         let kind = caps.get(1)?.as_str();
@@ -86,25 +302,686 @@ impl MusicService {
         }
     }
 
-    pub async fn search(&self, query: &str, limit: u32) -> Vec<Track> {
-        let yt_fut = self.youtube.search_tracks(query, limit);
-        let sp_fut = self.spotify.search_tracks(query, limit);
-        tokio::pin!(yt_fut);
-        tokio::pin!(sp_fut);
+    pub async fn search(&self, query: &str, limit: u32, prefer_topic: bool) -> Vec<Track> {
+        race(
+            self.youtube_search(query, limit, prefer_topic),
+            self.spotify_search(query, limit),
+        )
+        .await
+    }
+
+    /// Asks both providers, each within the search timeout, and alternates
+    /// their results YouTube first, up to `limit`. A song both found is only
+    /// listed once, as whichever provider's result came first. Slower than
+    /// [`Self::search`], so it's for when the user picks from the results.
+    pub async fn search_merged(&self, query: &str, limit: u32, prefer_topic: bool) -> Vec<Track> {
+        let (yt, sp) = tokio::join!(
+            self.youtube_search(query, limit, prefer_topic),
+            self.spotify_search(query, limit),
+        );
+
+        let mut seen = HashSet::new();
+        let mut yt = yt.into_iter();
+        let mut sp = sp.into_iter();
+        let mut merged = Vec::new();
+        while merged.len() < limit as usize {
+            let (next_yt, next_sp) = (yt.next(), sp.next());
+            if next_yt.is_none() && next_sp.is_none() {
+                break;
+            }
+            for track in next_yt.into_iter().chain(next_sp) {
+                if merged.len() < limit as usize && seen.insert(track.dedup_key()) {
+                    merged.push(track);
+                }
+            }
+        }
+        merged
+    }
+
+    /// Searches the guild's preferred provider, falling back to the other one
+    /// when it comes back empty. `Fastest` races both like [`Self::search`].
+    /// `prefer_topic` applies to the YouTube side.
+    pub async fn search_with_preference(
+        &self,
+        query: &str,
+        limit: u32,
+        preference: SearchPreference,
+        prefer_topic: bool,
+    ) -> Vec<Track> {
+        match preference {
+            SearchPreference::Fastest => self.search(query, limit, prefer_topic).await,
+            SearchPreference::YouTube => {
+                with_fallback(
+                    self.youtube_search(query, limit, prefer_topic),
+                    self.spotify_search(query, limit),
+                )
+                .await
+            }
+            SearchPreference::Spotify => {
+                with_fallback(
+                    self.spotify_search(query, limit),
+                    self.youtube_search(query, limit, prefer_topic),
+                )
+                .await
+            }
+        }
+    }
+
+    async fn youtube_search(&self, query: &str, limit: u32, prefer_topic: bool) -> Vec<Track> {
+        let search = self.youtube.search_tracks(query, limit, prefer_topic);
+        within_timeout(self.search_timeout, "YouTube", search).await
+    }
+
+    async fn spotify_search(&self, query: &str, limit: u32) -> Vec<Track> {
+        let search = self.spotify.search_tracks(query, limit);
+        within_timeout(self.search_timeout, "Spotify", search).await
+    }
+
+    /// Resolves a link or search to the tracks it stands for, for callers
+    /// without a command to reply to. Searches take the top result.
+    pub async fn resolve_tracks(
+        &self,
+        query: &str,
+        preference: SearchPreference,
+        prefer_topic: bool,
+    ) -> Result<Vec<Track>, MusicError> {
+        let expanded;
+        let query = if Self::is_spotify_short_link(query) {
+            expanded = self
+                .resolve_spotify_short_link(query)
+                .await
+                .ok_or(MusicError::ShortLinkUnresolved)?;
+            expanded.as_str()
+        } else {
+            query
+        };
+
+        let tracks = if Self::is_youtube_playlist_url(query) {
+            let id = Self::extract_youtube_playlist_id(query).ok_or(MusicError::NoResults)?;
+            self.fetch_pages(&PageCursor::YouTubePlaylist { id, token: None }, 0, true)
+                .await
+                .map_err(|e| match e {
+                    PlaylistError::Unavailable => MusicError::PlaylistUnavailable,
+                    PlaylistError::Api(_) => MusicError::NoResults,
+                })?
+                .tracks
+        } else if Self::is_youtube_url(query) {
+            let video_id = Self::extract_youtube_video_id(query).ok_or(MusicError::NoResults)?;
+            self.youtube.get_video(&video_id).await.into_iter().collect()
+        } else if let Some(spotify_url) = Self::parse_spotify_url(query) {
+            match spotify_url {
+                SpotifyUrl::Track(id) => self.spotify.get_track(&id).await.into_iter().collect(),
+                SpotifyUrl::Playlist(id) => self
+                    .fetch_pages(&PageCursor::SpotifyPlaylist { id, offset: 0 }, 0, true)
+                    .await
+                    .map(|fetched| fetched.tracks)
+                    .unwrap_or_default(),
+                SpotifyUrl::Album(id) => {
+                    let cursor = PageCursor::SpotifyAlbum { id: id.clone(), offset: 0 };
+                    let (fetched, info) = tokio::join!(
+                        self.fetch_pages(&cursor, 0, true),
+                        self.spotify.get_album_info(&id),
+                    );
+                    let album = info.map(|info| info.name);
+                    fetched
+                        .map(|fetched| fetched.tracks)
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(|track| Track { album: album.clone(), ..track })
+                        .collect()
+                }
+            }
+        } else {
+            self.search_with_preference(query, 1, preference, prefer_topic)
+                .await
+                .into_iter()
+                .take(1)
+                .collect()
+        };
+
+        if tracks.is_empty() {
+            return Err(MusicError::NoResults);
+        }
+        Ok(tracks)
+    }
 
-        tokio::select! {
-            yt = &mut yt_fut => {
-                if !yt.is_empty() { return yt; }
-                sp_fut.await
+    /// Fetches the page `cursor` points at, `read` entries into the
+    /// collection. Stops at the item cap, marking the listing truncated if
+    /// the provider had more.
+    pub async fn fetch_page(
+        &self,
+        cursor: &PageCursor,
+        read: usize,
+    ) -> Result<PlaylistTracks, PlaylistError> {
+        let budget = self.max_playlist_items.saturating_sub(read);
+        let mut page = match cursor {
+            PageCursor::SpotifyPlaylist { id, offset } => {
+                self.spotify.get_playlist_page(id, *offset, budget).await
+            }
+            PageCursor::SpotifyAlbum { id, offset } => {
+                self.spotify.get_album_page(id, *offset, budget).await
             }
-            sp = &mut sp_fut => {
-                if !sp.is_empty() { return sp; }
-                yt_fut.await
+            PageCursor::YouTubePlaylist { id, token } => {
+                self.youtube.get_playlist_page(id, token.as_deref()).await?
             }
+        };
+
+        fit_to_budget(&mut page, budget);
+        Ok(page)
+    }
+
+    /// Lists a collection from `cursor` on, `read` entries in. With `whole`
+    /// every page up to the cap is listed; otherwise listing stops at the
+    /// first page with a playable track and `next` says where the rest
+    /// starts. Fails only if the first page does; later failures keep what
+    /// was listed with `complete: false`.
+    pub async fn fetch_pages(
+        &self,
+        cursor: &PageCursor,
+        read: usize,
+        whole: bool,
+    ) -> Result<PlaylistTracks, PlaylistError> {
+        let first = self.fetch_page(cursor, read).await?;
+        let fetch_page = |next: PageCursor, read| async move { self.fetch_page(&next, read).await };
+        Ok(list_pages(first, read, whole, fetch_page).await)
+    }
+
+    /// The Spotify search that finds a YouTube upload's track. Video titles
+    /// often carry the artist and tags like "(Official Video)", and Topic
+    /// channels a " - Topic" suffix.
+    pub fn youtube_to_spotify_query(track: &Track) -> String {
+        let title = VIDEO_TAG_RE.replace_all(&track.title, "");
+        // "Artist - Song" already names the artist
+        if let Some((artist, song)) = title.split_once(" - ") {
+            return format!("{} {}", song.trim(), artist.trim());
+        }
+        let artist = track.artist.strip_suffix(" - Topic").unwrap_or(&track.artist);
+        format!("{} {artist}", title.trim())
+    }
+
+    /// The Spotify track id for `track`: its own for Spotify tracks,
+    /// otherwise the best Spotify search match for its title and artist.
+    pub async fn spotify_track_id(&self, track: &Track) -> Option<String> {
+        if let Some(SpotifyUrl::Track(id)) = Self::parse_spotify_url(&track.url) {
+            return Some(id);
+        }
+        let query = Self::youtube_to_spotify_query(track);
+        let found = self.spotify.search_tracks(&query, 1).await.into_iter().next()?;
+        match Self::parse_spotify_url(&found.url)? {
+            SpotifyUrl::Track(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The yt-dlp search that finds a Spotify track's audio. With
+    /// `prefer_topic` it asks for the artist's auto-generated "Artist -
+    /// Topic" upload, which is the label's studio audio.
+    pub fn spotify_to_youtube_query(track: &Track, prefer_topic: bool) -> String {
+        let suffix = if prefer_topic { "- Topic" } else { "audio" };
+        // Self-titled tracks ("Bad Company" by Bad Company) are ambiguous on
+        // their own, so lean on the album to pick the right one.
+        match &track.album {
+            Some(album) if track.title.eq_ignore_ascii_case(&track.artist) => {
+                format!("{} {} {} {suffix}", track.title, track.artist, album)
+            }
+            _ => format!("{} {} {suffix}", track.title, track.artist),
+        }
+    }
+
+    /// The top `limit` YouTube results for `query`, in the order YouTube
+    /// ranks them and with their lengths, for looking into what a Spotify
+    /// track's search turns up. yt-dlp plays the first one.
+    pub async fn youtube_candidates(&self, query: &str, limit: u32) -> Vec<Track> {
+        let found = self.youtube.search_tracks(query, limit, false).await;
+        // Search results carry no length, so look each video up
+        let details = futures::future::join_all(found.iter().map(|track| async {
+            let video_id = Self::extract_youtube_video_id(&track.url)?;
+            self.youtube.get_video(&video_id).await
+        }))
+        .await;
+        found
+            .into_iter()
+            .zip(details)
+            .map(|(track, detailed)| detailed.unwrap_or(track))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+    use crate::domain::track::test_track;
+    use crate::infrastructure::youtube::canonicalize_youtube_url;
+
+    /// A provider that answers with `urls` after `delay`.
+    async fn provider(urls: &[&str], delay: Duration) -> Vec<Track> {
+        tokio::time::sleep(delay).await;
+        urls.iter().map(|url| test_track(url, None)).collect()
+    }
+
+    fn urls(tracks: &[Track]) -> Vec<&str> {
+        tracks.iter().map(|track| track.url.as_str()).collect()
+    }
+
+    const FAST: Duration = Duration::from_millis(1);
+    const SLOW: Duration = Duration::from_millis(50);
+
+    #[tokio::test]
+    async fn race_takes_the_first_provider_to_find_something() {
+        let found = race(provider(&["yt"], SLOW), provider(&["sp"], FAST)).await;
+        assert_eq!(urls(&found), ["sp"]);
+        let found = race(provider(&["yt"], FAST), provider(&["sp"], SLOW)).await;
+        assert_eq!(urls(&found), ["yt"]);
+    }
+
+    #[tokio::test]
+    async fn race_waits_for_the_other_provider_when_the_first_is_empty() {
+        let found = race(provider(&[], FAST), provider(&["sp"], SLOW)).await;
+        assert_eq!(urls(&found), ["sp"]);
+    }
+
+    #[tokio::test]
+    async fn race_is_empty_when_neither_provider_finds_anything() {
+        assert!(race(provider(&[], FAST), provider(&[], FAST)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn fallback_is_not_asked_when_the_preferred_provider_finds_something() {
+        let asked = AtomicBool::new(false);
+        let fallback = async {
+            asked.store(true, Ordering::Relaxed);
+            provider(&["sp"], FAST).await
+        };
+        let found = with_fallback(provider(&["yt"], SLOW), fallback).await;
+        assert_eq!(urls(&found), ["yt"]);
+        assert!(!asked.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn fallback_answers_when_the_preferred_provider_is_empty() {
+        let found = with_fallback(provider(&[], FAST), provider(&["sp"], FAST)).await;
+        assert_eq!(urls(&found), ["sp"]);
+    }
+
+    #[tokio::test]
+    async fn a_provider_past_the_timeout_counts_as_finding_nothing() {
+        let found = within_timeout(FAST, "YouTube", provider(&["yt"], SLOW)).await;
+        assert!(found.is_empty());
+        let found = within_timeout(SLOW, "YouTube", provider(&["yt"], FAST)).await;
+        assert_eq!(urls(&found), ["yt"]);
+    }
+
+    #[tokio::test]
+    async fn a_hung_provider_only_holds_a_race_up_to_the_timeout() {
+        let hung = provider(&["yt"], Duration::from_secs(3600));
+        let started = std::time::Instant::now();
+        let found = race(
+            within_timeout(SLOW, "YouTube", hung),
+            within_timeout(SLOW, "Spotify", provider(&[], FAST)),
+        )
+        .await;
+        assert!(found.is_empty());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    /// A YouTube-style playlist of `total` entries, served 50 per page
+    /// whatever the budget, counting the pages asked for.
+    struct FakePlaylist {
+        total: usize,
+        max_items: usize,
+        requests: std::sync::atomic::AtomicUsize,
+    }
+
+    impl FakePlaylist {
+        fn new(total: usize, max_items: usize) -> Self {
+            Self { total, max_items, requests: Default::default() }
+        }
+
+        async fn page(
+            &self,
+            cursor: PageCursor,
+            read: usize,
+        ) -> Result<PlaylistTracks, PlaylistError> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let PageCursor::YouTubePlaylist { id, token } = cursor else {
+                unreachable!("only YouTube cursors are handed out");
+            };
+            let offset: usize = token.map_or(0, |t| t.parse().unwrap());
+            let end = (offset + 50).min(self.total);
+            let next = (end < self.total).then(|| PageCursor::YouTubePlaylist {
+                id,
+                token: Some(end.to_string()),
+            });
+            let mut page = PlaylistTracks {
+                tracks: (offset..end).map(|i| test_track(&format!("t{i}"), None)).collect(),
+                complete: true,
+                next,
+                ..PlaylistTracks::default()
+            };
+            fit_to_budget(&mut page, self.max_items.saturating_sub(read));
+            Ok(page)
+        }
+
+        async fn list(&self, whole: bool) -> PlaylistTracks {
+            let start = PageCursor::YouTubePlaylist { id: "PL".to_string(), token: None };
+            let first = self.page(start, 0).await.unwrap();
+            list_pages(first, 0, whole, |next, read| self.page(next, read)).await
+        }
+
+        fn requests(&self) -> usize {
+            self.requests.load(Ordering::SeqCst)
+        }
+    }
+
+    #[tokio::test]
+    async fn an_enormous_playlist_stops_at_the_cap_without_fetching_further() {
+        let playlist = FakePlaylist::new(10_000, 120);
+        let listed = playlist.list(true).await;
+
+        assert_eq!(listed.tracks.len(), 120);
+        assert!(listed.truncated);
+        assert!(listed.next.is_none());
+        assert_eq!(playlist.requests(), 3);
+    }
+
+    #[tokio::test]
+    async fn a_playlist_that_exactly_fills_the_cap_is_not_truncated() {
+        let playlist = FakePlaylist::new(100, 100);
+        let listed = playlist.list(true).await;
+
+        assert_eq!(listed.tracks.len(), 100);
+        assert!(!listed.truncated);
+        assert_eq!(playlist.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn more_pages_past_a_full_cap_count_as_truncated_unfetched() {
+        let playlist = FakePlaylist::new(150, 100);
+        let listed = playlist.list(true).await;
+
+        assert_eq!(listed.tracks.len(), 100);
+        assert!(listed.truncated);
+        assert_eq!(playlist.requests(), 2);
+    }
+
+    #[tokio::test]
+    async fn listing_only_the_head_leaves_the_rest_to_the_background() {
+        let playlist = FakePlaylist::new(10_000, 1000);
+        let listed = playlist.list(false).await;
+
+        assert_eq!(listed.tracks.len(), 50);
+        assert!(!listed.truncated);
+        assert!(matches!(
+            listed.next,
+            Some(PageCursor::YouTubePlaylist { token: Some(ref t), .. }) if t == "50"
+        ));
+        assert_eq!(playlist.requests(), 1);
+    }
+
+    const VIDEO: &str = "dQw4w9WgXcQ";
+    const LIST: &str = "PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG";
+
+    /// A YouTube link and what each check should make of it: whether it's a
+    /// video link, whether it plays as a playlist, and the ids found in it.
+    struct YouTubeCase {
+        url: &'static str,
+        video_link: bool,
+        playlist: bool,
+        video_id: Option<&'static str>,
+        playlist_id: Option<&'static str>,
+    }
+
+    const fn video(url: &'static str) -> YouTubeCase {
+        YouTubeCase {
+            url,
+            video_link: true,
+            playlist: false,
+            video_id: Some(VIDEO),
+            playlist_id: None,
+        }
+    }
+
+    const fn video_in(url: &'static str, list: &'static str) -> YouTubeCase {
+        YouTubeCase {
+            url,
+            video_link: true,
+            playlist: true,
+            video_id: Some(VIDEO),
+            playlist_id: Some(list),
+        }
+    }
+
+    const fn playlist(url: &'static str, list: &'static str) -> YouTubeCase {
+        YouTubeCase {
+            url,
+            video_link: false,
+            playlist: true,
+            video_id: None,
+            playlist_id: Some(list),
+        }
+    }
+
+    const fn neither(url: &'static str) -> YouTubeCase {
+        YouTubeCase {
+            url,
+            video_link: false,
+            playlist: false,
+            video_id: None,
+            playlist_id: None,
+        }
+    }
+
+    const YOUTUBE_CASES: &[YouTubeCase] = &[
+        video("https://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+        video("https://youtube.com/watch?v=dQw4w9WgXcQ"),
+        video("http://www.youtube.com/watch?v=dQw4w9WgXcQ"),
+        video("www.youtube.com/watch?v=dQw4w9WgXcQ"),
+        video("https://m.youtube.com/watch?v=dQw4w9WgXcQ"),
+        video("https://music.youtube.com/watch?v=dQw4w9WgXcQ"),
+        video("https://music.youtube.com/watch?v=dQw4w9WgXcQ&si=AbCdEf12345"),
+        video("https://www.youtube.com/watch?v=dQw4w9WgXcQ&t=42s"),
+        video("https://www.youtube.com/watch?feature=share&v=dQw4w9WgXcQ"),
+        video("https://www.youtube.com/watch?v=dQw4w9WgXcQ&si=AbCdEf12345"),
+        video("https://www.youtube.com/watch?v=dQw4w9WgXcQ#t=30"),
+        video("https://m.youtube.com/watch?v=dQw4w9WgXcQ&feature=youtu.be"),
+        video("https://youtu.be/dQw4w9WgXcQ"),
+        video("https://youtu.be/dQw4w9WgXcQ?si=AbCdEf12345"),
+        video("https://youtu.be/dQw4w9WgXcQ?t=42"),
+        video("https://youtu.be/dQw4w9WgXcQ?si=AbCdEf12345&t=42"),
+        video("youtu.be/dQw4w9WgXcQ"),
+        video("https://www.youtube.com/shorts/dQw4w9WgXcQ"),
+        video("https://youtube.com/shorts/dQw4w9WgXcQ?si=AbCdEf12345"),
+        video("https://m.youtube.com/shorts/dQw4w9WgXcQ"),
+        video("https://www.youtube.com/live/dQw4w9WgXcQ"),
+        video("https://www.youtube.com/live/dQw4w9WgXcQ?si=AbCdEf12345"),
+        video("https://www.youtube.com/embed/dQw4w9WgXcQ"),
+        video("https://www.youtube.com/embed/dQw4w9WgXcQ?start=10"),
+        video("https://www.youtube-nocookie.com/embed/dQw4w9WgXcQ?start=10"),
+        video_in("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG", LIST),
+        video_in(
+            "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG&index=3&si=AbCdEf12345",
+            LIST,
+        ),
+        video_in("https://www.youtube.com/watch?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG&v=dQw4w9WgXcQ", LIST),
+        video_in("https://music.youtube.com/watch?v=dQw4w9WgXcQ&list=OLAK5uy_kE1x3nU0A7", "OLAK5uy_kE1x3nU0A7"),
+        video_in("https://m.youtube.com/watch?v=dQw4w9WgXcQ&list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG", LIST),
+        // A mix plays as its video, since the API can't list it
+        YouTubeCase {
+            url: "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=RDdQw4w9WgXcQ&start_radio=1",
+            video_link: true,
+            playlist: false,
+            video_id: Some(VIDEO),
+            playlist_id: Some("RDdQw4w9WgXcQ"),
+        },
+        YouTubeCase {
+            url: "https://music.youtube.com/watch?v=dQw4w9WgXcQ&list=RDAMVMdQw4w9WgXcQ",
+            video_link: true,
+            playlist: false,
+            video_id: Some(VIDEO),
+            playlist_id: Some("RDAMVMdQw4w9WgXcQ"),
+        },
+        playlist("https://www.youtube.com/playlist?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG", LIST),
+        playlist("https://youtube.com/playlist?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG&si=AbCdEf12345", LIST),
+        playlist("https://youtube.com/playlist?si=AbCdEf12345&list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG", LIST),
+        playlist("https://m.youtube.com/playlist?list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG", LIST),
+        playlist("https://music.youtube.com/playlist?list=OLAK5uy_kE1x3nU0A7", "OLAK5uy_kE1x3nU0A7"),
+        // A mix on its own has no video to fall back to
+        playlist("https://www.youtube.com/playlist?list=RDCLAK5uy_kmPRjHDECIcuVwnKsx", "RDCLAK5uy_kmPRjHDECIcuVwnKsx"),
+        // Too short to be a video id
+        YouTubeCase {
+            url: "https://www.youtube.com/watch?v=dQw4w9WgXc",
+            video_link: true,
+            playlist: false,
+            video_id: None,
+            playlist_id: None,
+        },
+        neither("https://www.youtube.com/@RickAstleyYT"),
+        neither("https://www.youtube.com/channel/UCuAXFkgsw1L7xaCfnd5JJOw"),
+        neither("https://www.youtube.com/results?search_query=never+gonna+give+you+up"),
+        neither("https://www.youtube.com/"),
+        neither("never gonna give you up"),
+        neither("https://open.spotify.com/track/4cOdK2wGLETKBW3PvgPWqT"),
+        neither("https://vimeo.com/76979871"),
+    ];
+
+    #[test]
+    fn youtube_links() {
+        for case in YOUTUBE_CASES {
+            let url = case.url;
+            let video_link = MusicService::is_youtube_url(url);
+            assert_eq!(video_link, case.video_link, "is_youtube_url({url})");
+            let playlist = MusicService::is_youtube_playlist_url(url);
+            assert_eq!(playlist, case.playlist, "is_youtube_playlist_url({url})");
+            let video_id = MusicService::extract_youtube_video_id(url);
+            assert_eq!(video_id.as_deref(), case.video_id, "extract_youtube_video_id({url})");
+            let playlist_id = MusicService::extract_youtube_playlist_id(url);
+            let expected = case.playlist_id;
+            assert_eq!(playlist_id.as_deref(), expected, "extract_youtube_playlist_id({url})");
+        }
+    }
+
+    #[test]
+    fn share_links_canonicalize_to_one_url() {
+        let canonical = "https://www.youtube.com/watch?v=dQw4w9WgXcQ";
+        for case in YOUTUBE_CASES.iter().filter(|case| case.video_id.is_some()) {
+            let id = MusicService::extract_youtube_video_id(case.url).unwrap();
+            assert_eq!(canonicalize_youtube_url(&id), canonical, "{}", case.url);
         }
+        // The canonical form reads back as the same plain video
+        assert_eq!(MusicService::extract_youtube_video_id(canonical).as_deref(), Some(VIDEO));
+        assert!(!MusicService::is_youtube_playlist_url(canonical));
+    }
+
+    #[test]
+    fn a_video_opened_from_a_playlist_offers_both() {
+        let url = "https://www.youtube.com/watch?v=dQw4w9WgXcQ&si=AbCdEf12345&list=PLx0sYbCqOb8TBPRdmBHs5Iftvv9TPboYG";
+        let (video, list) = MusicService::video_in_playlist(url).unwrap();
+        assert_eq!((video.as_str(), list.as_str()), (VIDEO, LIST));
+
+        // Mixes always play as the video, so there's nothing to ask
+        let mix = "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=RDdQw4w9WgXcQ&start_radio=1";
+        assert_eq!(MusicService::video_in_playlist(mix), None);
+    }
+
+    fn spotify(url: &str) -> Option<(&'static str, String)> {
+        match MusicService::parse_spotify_url(url)? {
+            SpotifyUrl::Track(id) => Some(("track", id)),
+            SpotifyUrl::Playlist(id) => Some(("playlist", id)),
+            SpotifyUrl::Album(id) => Some(("album", id)),
+        }
+    }
+
+    const TRACK: &str = "4cOdK2wGLETKBW3PvgPWqT";
+    const PLAYLIST: &str = "37i9dQZF1DXcBWIGoYBM5M";
+    const ALBUM: &str = "6N9PS4QXF1D0OWPk0Sxtb4";
+
+    const SPOTIFY_CASES: &[(&str, Option<(&str, &str)>)] = &[
+        ("https://open.spotify.com/track/4cOdK2wGLETKBW3PvgPWqT", Some(("track", TRACK))),
+        ("http://open.spotify.com/track/4cOdK2wGLETKBW3PvgPWqT", Some(("track", TRACK))),
+        ("open.spotify.com/track/4cOdK2wGLETKBW3PvgPWqT", Some(("track", TRACK))),
+        ("https://open.spotify.com/track/4cOdK2wGLETKBW3PvgPWqT?si=9f2b1c0e5d8a4b7c", Some(("track", TRACK))),
+        ("https://open.spotify.com/intl-de/track/4cOdK2wGLETKBW3PvgPWqT", Some(("track", TRACK))),
+        ("https://open.spotify.com/intl-id/track/4cOdK2wGLETKBW3PvgPWqT?si=9f2b1c0e5d8a4b7c", Some(("track", TRACK))),
+        ("https://open.spotify.com/intl-pt/track/4cOdK2wGLETKBW3PvgPWqT", Some(("track", TRACK))),
+        ("https://open.spotify.com/intl-zh-hant/track/4cOdK2wGLETKBW3PvgPWqT", Some(("track", TRACK))),
+        ("https://open.spotify.com/embed/track/4cOdK2wGLETKBW3PvgPWqT", Some(("track", TRACK))),
+        ("spotify:track:4cOdK2wGLETKBW3PvgPWqT", Some(("track", TRACK))),
+        ("https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M", Some(("playlist", PLAYLIST))),
+        (
+            "https://open.spotify.com/playlist/37i9dQZF1DXcBWIGoYBM5M?si=9f2b1c0e5d8a4b7c&pi=a-xyz",
+            Some(("playlist", PLAYLIST)),
+        ),
+        ("https://open.spotify.com/intl-ja/playlist/37i9dQZF1DXcBWIGoYBM5M", Some(("playlist", PLAYLIST))),
+        (
+            "https://open.spotify.com/embed/playlist/37i9dQZF1DXcBWIGoYBM5M?utm_source=generator",
+            Some(("playlist", PLAYLIST)),
+        ),
+        ("spotify:playlist:37i9dQZF1DXcBWIGoYBM5M", Some(("playlist", PLAYLIST))),
+        ("https://open.spotify.com/album/6N9PS4QXF1D0OWPk0Sxtb4", Some(("album", ALBUM))),
+        ("https://open.spotify.com/intl-fr/album/6N9PS4QXF1D0OWPk0Sxtb4?si=9f2b1c0e5d8a4b7c", Some(("album", ALBUM))),
+        ("spotify:album:6N9PS4QXF1D0OWPk0Sxtb4", Some(("album", ALBUM))),
+        ("https://open.spotify.com/artist/0gxyHStUsqpMadRV0Di1Qt", None),
+        ("https://open.spotify.com/show/5CfCWKI5pZ28U0uOzXkDHe", None),
+        ("https://open.spotify.com/episode/512ojhOuo1ktJprKbVcKyQ", None),
+        ("https://open.spotify.com/user/spotify", None),
+        ("https://spotify.link/abcDEF123", None),
+        ("https://www.youtube.com/watch?v=dQw4w9WgXcQ", None),
+        ("never gonna give you up", None),
+    ];
+
+    #[test]
+    fn spotify_links() {
+        for (url, expected) in SPOTIFY_CASES {
+            let parsed = spotify(url);
+            let parsed = parsed.as_ref().map(|(kind, id)| (*kind, id.as_str()));
+            assert_eq!(parsed, *expected, "parse_spotify_url({url})");
+        }
+    }
+
+    #[test]
+    fn spotify_short_links_are_expanded_rather_than_parsed() {
+        assert!(MusicService::is_spotify_short_link("https://spotify.link/abcDEF123"));
+        assert!(MusicService::is_spotify_short_link("https://spotify.app.link/abcDEF123?_p=c"));
+        let full = "https://open.spotify.com/track/4cOdK2wGLETKBW3PvgPWqT";
+        assert!(!MusicService::is_spotify_short_link(full));
+        assert!(MusicService::is_supported_link("https://spotify.link/abcDEF123"));
+    }
+
+    #[test]
+    fn autocomplete_offers_each_song_once() {
+        let mut reupload = test_track("https://youtu.be/bbbbbbbbbbb", None);
+        reupload.title = "Song".into();
+        reupload.artist = "Band".into();
+        let mut original = test_track("https://youtu.be/aaaaaaaaaaa", None);
+        original.title = "SONG!".into();
+        original.artist = "band".into();
+
+        let choices = autocomplete_choices(vec![original, reupload]);
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices[0].1, "https://youtu.be/aaaaaaaaaaa");
+    }
+
+    #[test]
+    fn autocomplete_choices_fit_discords_limits() {
+        let mut long_name = test_track("https://youtu.be/aaaaaaaaaaa", None);
+        long_name.title = "t".repeat(150);
+        let query = format!("?si={}", "x".repeat(120));
+        let long_query = test_track(&format!("https://youtu.be/bbbbbbbbbbb{query}"), None);
+        let long_path = test_track(&format!("https://example.com/{}", "p".repeat(120)), None);
+
+        let choices = autocomplete_choices(vec![long_name, long_query, long_path]);
+        assert_eq!(choices.len(), 2);
+        assert_eq!(choices[0].0.chars().count(), CHOICE_MAX_CHARS);
+        assert!(choices[0].0.ends_with("..."));
+        assert_eq!(choices[1].1, "https://youtu.be/bbbbbbbbbbb");
     }
 
-    pub fn spotify_to_youtube_query(track: &Track) -> String {
-        format!("{} {} audio", track.title, track.artist)
+    #[test]
+    fn autocomplete_offers_at_most_25_choices() {
+        let tracks = (0..40).map(|i| test_track(&format!("https://youtu.be/{i}"), None));
+        assert_eq!(autocomplete_choices(tracks.collect()).len(), 25);
     }
 }
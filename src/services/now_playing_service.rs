@@ -0,0 +1,123 @@
+use std::sync::Arc;
+
+use poise::serenity_prelude::GuildId;
+use tokio::sync::Mutex;
+
+use crate::domain::now_playing::NowPlayingCards;
+use crate::infrastructure::now_playing_store::NowPlayingStore;
+use crate::services::guild_state::{NowPlayingLocks, NowPlayingMessages};
+
+/// Keeps each guild down to one set of live Now Playing cards.
+pub struct NowPlayingService;
+
+impl NowPlayingService {
+    /// Returns the guild's Now Playing lock. Hold it while replacing or
+    /// removing the Now Playing message so two track changes can't interleave.
+    pub async fn lock(locks: &NowPlayingLocks, guild_id: GuildId) -> Arc<Mutex<()>> {
+        let mut locks = locks.write().await;
+        locks.entry(guild_id).or_default().clone()
+    }
+
+    /// Replaces the guild's cards under its Now Playing lock. `replace` gets
+    /// the live cards, retires the ones it doesn't keep and returns the ones
+    /// live now, which are recorded and written to `store`.
+    pub async fn swap<F>(
+        locks: &NowPlayingLocks,
+        messages: &NowPlayingMessages,
+        store: Option<&NowPlayingStore>,
+        guild_id: GuildId,
+        replace: impl FnOnce(NowPlayingCards) -> F,
+    ) where
+        F: Future<Output = NowPlayingCards>,
+    {
+        let lock = Self::lock(locks, guild_id).await;
+        let _guard = lock.lock().await;
+
+        let previous = messages.write().await.remove(&guild_id).unwrap_or_default();
+        let cards = replace(previous).await;
+        if !cards.is_empty() {
+            messages.write().await.insert(guild_id, cards);
+        }
+        // Still under the lock, so an older snapshot can't land last
+        if let Some(store) = store {
+            let snapshot = messages.read().await.clone();
+            store.save(&snapshot).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::sync::atomic::{AtomicU64, Ordering};
+    use std::sync::Mutex as StdMutex;
+
+    use poise::serenity_prelude::{ChannelId, MessageId};
+
+    use super::*;
+
+    const GUILD: GuildId = GuildId::new(1);
+    const MUSIC: ChannelId = ChannelId::new(2);
+
+    /// Stands in for the channel: which cards are posted and not yet deleted.
+    #[derive(Default)]
+    struct Channel {
+        live: StdMutex<HashSet<MessageId>>,
+        next_id: AtomicU64,
+    }
+
+    impl Channel {
+        /// What the notifier does on a track change, with the HTTP calls
+        /// yielding so concurrent swaps get to interleave.
+        async fn track_change(&self, previous: NowPlayingCards) -> NowPlayingCards {
+            for (_, message_id) in previous.iter() {
+                tokio::task::yield_now().await;
+                self.live.lock().unwrap().remove(&message_id);
+            }
+            tokio::task::yield_now().await;
+            let id = MessageId::new(self.next_id.fetch_add(1, Ordering::SeqCst) + 1);
+            self.live.lock().unwrap().insert(id);
+            let mut cards = NowPlayingCards::default();
+            cards.insert(MUSIC, id);
+            cards
+        }
+
+        fn live(&self) -> Vec<MessageId> {
+            self.live.lock().unwrap().iter().copied().collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn a_burst_of_track_changes_leaves_one_card() {
+        let locks = NowPlayingLocks::default();
+        let messages = NowPlayingMessages::default();
+        let channel = Channel::default();
+
+        let changes = (0..20).map(|_| {
+            NowPlayingService::swap(&locks, &messages, None, GUILD, |previous| {
+                channel.track_change(previous)
+            })
+        });
+        futures::future::join_all(changes).await;
+
+        let live = channel.live();
+        assert_eq!(live.len(), 1);
+        let recorded = messages.read().await.get(&GUILD).and_then(|c| c.get(MUSIC));
+        assert_eq!(recorded, Some(live[0]));
+    }
+
+    #[tokio::test]
+    async fn a_card_that_fails_to_post_leaves_nothing_recorded() {
+        let locks = NowPlayingLocks::default();
+        let messages = NowPlayingMessages::default();
+        let channel = Channel::default();
+        NowPlayingService::swap(&locks, &messages, None, GUILD, |p| channel.track_change(p)).await;
+
+        NowPlayingService::swap(&locks, &messages, None, GUILD, |previous| async move {
+            assert!(!previous.is_empty());
+            NowPlayingCards::default()
+        })
+        .await;
+        assert!(!messages.read().await.contains_key(&GUILD));
+    }
+}
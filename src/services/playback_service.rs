@@ -0,0 +1,310 @@
+use std::sync::Arc;
+
+use poise::serenity_prelude::GuildId;
+use songbird::tracks::PlayMode;
+use songbird::Call;
+use tokio::sync::Mutex;
+
+use crate::domain::track::Track;
+use crate::infrastructure::voice::VoiceBackend;
+use crate::services::error::MusicError;
+use crate::services::guild_state::JoinLocks;
+use crate::services::queue_service::{GuildQueues, QueueService};
+
+/// What a skip did, for the reply.
+#[derive(Debug)]
+pub struct Skipped {
+    /// The track that was playing, if the domain queue knew it.
+    pub skipped: Option<Track>,
+    /// The track playing now, if the domain queue knew it.
+    pub next: Option<Track>,
+    /// Tracks songbird had queued before the skip, the skipped one included.
+    pub queued: usize,
+}
+
+/// Playback controls shared by the commands and the Now Playing buttons,
+/// through the [`VoiceBackend`] so they can run without a voice connection.
+pub struct PlaybackService;
+
+impl PlaybackService {
+    /// Returns the guild's call, joining through `join` when `live` says
+    /// there's no session yet. A fresh join runs `setup` while still holding
+    /// the guild's join lock, so concurrent `/play`s can't both tear down
+    /// and set up the session.
+    pub async fn connect<L, S>(
+        voice: &dyn VoiceBackend,
+        join_locks: &JoinLocks,
+        guild_id: GuildId,
+        live: impl Fn() -> L,
+        join: impl Future<Output = Result<Arc<Mutex<Call>>, MusicError>>,
+        setup: impl FnOnce(Arc<Mutex<Call>>) -> S,
+    ) -> Result<Arc<Mutex<Call>>, MusicError>
+    where
+        L: Future<Output = bool>,
+        S: Future<Output = ()>,
+    {
+        // Fast path: already connected with a session running
+        if live().await {
+            if let Some(call) = voice.call(guild_id) {
+                return Ok(call);
+            }
+        }
+
+        let lock = join_locks.write().await.entry(guild_id).or_default().clone();
+        let _guard = lock.lock().await;
+
+        // Another /play may have set the session up while this one waited
+        if live().await {
+            if let Some(call) = voice.call(guild_id) {
+                return Ok(call);
+            }
+        }
+
+        let call = join.await?;
+        setup(call.clone()).await;
+        Ok(call)
+    }
+
+    /// Why there's nothing to show: the bot isn't in voice, or it is but the
+    /// queue is empty.
+    pub fn idle_error(voice: &dyn VoiceBackend, guild_id: GuildId) -> MusicError {
+        if voice.is_connected(guild_id) {
+            MusicError::EmptyQueue
+        } else {
+            MusicError::NotConnected
+        }
+    }
+
+    /// Skips the playing track. Fails with `NotConnected`, `EmptyQueue`, or
+    /// `SwitchingTracks` while pending tracks are still being handed to
+    /// songbird, since waiting for one would skip it the moment it starts.
+    pub async fn skip(
+        voice: &dyn VoiceBackend,
+        queues: &GuildQueues,
+        guild_id: GuildId,
+    ) -> Result<Skipped, MusicError> {
+        // Name the tracks by songbird's handles rather than the domain
+        // queue's current track, which lags behind until Now Playing fires
+        let queued = voice.queued(guild_id).await?;
+        if queued.is_empty() {
+            if QueueService::pending_len(queues, guild_id).await > 0 {
+                return Err(MusicError::SwitchingTracks);
+            }
+            return Err(MusicError::EmptyQueue);
+        }
+        let skipped = QueueService::skip(queues, guild_id, queued.first().copied()).await;
+        let next = match queued.get(1) {
+            Some(&id) => QueueService::find_by_handle(queues, guild_id, id).await,
+            None => None,
+        };
+        voice.skip(guild_id).await?;
+        Ok(Skipped {
+            skipped,
+            next,
+            queued: queued.len(),
+        })
+    }
+
+    /// Stops playback and leaves the voice channel. Fails with
+    /// `NotConnected` when there's no call, without touching anything.
+    pub async fn stop(voice: &dyn VoiceBackend, guild_id: GuildId) -> Result<(), MusicError> {
+        if !voice.is_connected(guild_id) {
+            return Err(MusicError::NotConnected);
+        }
+        // Clears any last track that slipped through before leaving
+        voice.stop(guild_id).await;
+        voice.leave(guild_id).await
+    }
+
+    /// Pauses the playing track, or awaits `resume` when it's paused.
+    /// Returns whether playback is paused now.
+    pub async fn toggle_pause(
+        voice: &dyn VoiceBackend,
+        guild_id: GuildId,
+        resume: impl Future<Output = ()>,
+    ) -> Result<bool, MusicError> {
+        if !voice.is_connected(guild_id) {
+            return Err(MusicError::NotConnected);
+        }
+        let info = voice
+            .current_track_info(guild_id)
+            .await
+            .ok_or(MusicError::NoTrackInfo)?;
+        if matches!(info.playing, PlayMode::Play) {
+            voice.pause(guild_id).await?;
+            Ok(true)
+        } else {
+            resume.await;
+            Ok(false)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use poise::serenity_prelude::ChannelId;
+
+    use super::*;
+    use crate::domain::track::test_track;
+    use crate::infrastructure::voice::fake::{Action, FakeBackend};
+
+    const GUILD: GuildId = GuildId::new(1);
+    const CHANNEL: ChannelId = ChannelId::new(2);
+
+    async fn connected() -> FakeBackend {
+        let voice = FakeBackend::default();
+        voice.join(GUILD, CHANNEL).await.unwrap();
+        voice
+    }
+
+    /// Mirrors `/play`: connects, leaving any stale call on a fresh join,
+    /// then queues one track.
+    async fn play(voice: &FakeBackend, locks: &JoinLocks, monitors: &AtomicUsize) {
+        let live = || async { monitors.load(Ordering::SeqCst) > 0 };
+        let join = async {
+            voice.leave(GUILD).await.ok();
+            // Holds the join open long enough for the other /play to arrive
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            voice.join(GUILD, CHANNEL).await
+        };
+        let setup = |_| async {
+            monitors.fetch_add(1, Ordering::SeqCst);
+        };
+        PlaybackService::connect(voice, locks, GUILD, live, join, setup).await.unwrap();
+        voice.queue_silence(GUILD, 1).await;
+    }
+
+    #[tokio::test]
+    async fn simultaneous_plays_join_once_and_keep_both_tracks() {
+        let voice = FakeBackend::default();
+        let locks = JoinLocks::default();
+        let monitors = AtomicUsize::new(0);
+
+        tokio::join!(
+            play(&voice, &locks, &monitors),
+            play(&voice, &locks, &monitors),
+        );
+
+        assert_eq!(monitors.load(Ordering::SeqCst), 1);
+        assert_eq!(voice.queued(GUILD).await.unwrap().len(), 2);
+        let actions = voice.actions();
+        let joins = actions.iter().filter(|a| matches!(a, Action::Join(_))).count();
+        assert_eq!(joins, 1);
+        assert!(!actions.contains(&Action::Stop));
+    }
+
+    #[tokio::test]
+    async fn nothing_to_show_says_whether_the_bot_is_in_voice() {
+        let voice = FakeBackend::default();
+        let idle = || PlaybackService::idle_error(&voice, GUILD);
+        assert!(matches!(idle(), MusicError::NotConnected));
+
+        voice.join(GUILD, CHANNEL).await.unwrap();
+        assert!(matches!(idle(), MusicError::EmptyQueue));
+
+        PlaybackService::stop(&voice, GUILD).await.unwrap();
+        assert!(matches!(idle(), MusicError::NotConnected));
+    }
+
+    #[tokio::test]
+    async fn skip_on_an_empty_queue_says_so_without_skipping() {
+        let voice = connected().await;
+        let queues = QueueService::new_guild_queues();
+
+        let result = PlaybackService::skip(&voice, &queues, GUILD).await;
+        assert!(matches!(result, Err(MusicError::EmptyQueue)));
+        assert!(!voice.actions().contains(&Action::Skip));
+    }
+
+    #[tokio::test]
+    async fn skip_while_the_next_track_is_being_handed_over_asks_to_retry() {
+        let voice = connected().await;
+        let queues = QueueService::new_guild_queues();
+        QueueService::add_track(&queues, GUILD, test_track("pending", None), false).await;
+
+        let result = PlaybackService::skip(&voice, &queues, GUILD).await;
+        assert!(matches!(result, Err(MusicError::SwitchingTracks)));
+    }
+
+    #[tokio::test]
+    async fn skip_without_a_call_is_not_connected() {
+        let voice = FakeBackend::default();
+        let queues = QueueService::new_guild_queues();
+
+        let result = PlaybackService::skip(&voice, &queues, GUILD).await;
+        assert!(matches!(result, Err(MusicError::NotConnected)));
+    }
+
+    #[tokio::test]
+    async fn skip_names_the_skipped_and_next_tracks() {
+        let voice = connected().await;
+        let queues = QueueService::new_guild_queues();
+        let ids = voice.queue_silence(GUILD, 3).await;
+        for (i, id) in ids.iter().enumerate() {
+            let mut track = test_track(&format!("t{i}"), None);
+            track.handle_id = Some(*id);
+            QueueService::add_track(&queues, GUILD, track, false).await;
+        }
+        QueueService::advance(&queues, GUILD).await;
+
+        let skipped = PlaybackService::skip(&voice, &queues, GUILD).await.unwrap();
+        assert_eq!(skipped.skipped.map(|t| t.url).as_deref(), Some("t0"));
+        assert_eq!(skipped.next.map(|t| t.url).as_deref(), Some("t1"));
+        assert_eq!(skipped.queued, 3);
+        assert_eq!(voice.queued(GUILD).await.unwrap(), ids[1..]);
+    }
+
+    #[tokio::test]
+    async fn stop_when_not_connected_leaves_everything_alone() {
+        let voice = FakeBackend::default();
+
+        let result = PlaybackService::stop(&voice, GUILD).await;
+        assert!(matches!(result, Err(MusicError::NotConnected)));
+        assert!(voice.actions().is_empty());
+    }
+
+    #[tokio::test]
+    async fn stop_clears_playback_then_leaves() {
+        let voice = connected().await;
+        voice.queue_silence(GUILD, 2).await;
+
+        PlaybackService::stop(&voice, GUILD).await.unwrap();
+        assert_eq!(voice.actions()[3..], [Action::Stop, Action::Leave]);
+        assert!(!voice.is_connected(GUILD));
+    }
+
+    #[tokio::test]
+    async fn pause_button_toggles_between_paused_and_playing() {
+        let voice = connected().await;
+        voice.queue_silence(GUILD, 1).await;
+        let resumed = AtomicBool::new(false);
+        let resume = || async {
+            resumed.store(true, Ordering::Relaxed);
+            voice.resume(GUILD);
+        };
+
+        let paused = PlaybackService::toggle_pause(&voice, GUILD, resume()).await.unwrap();
+        assert!(paused);
+        assert!(!resumed.load(Ordering::Relaxed));
+
+        let paused = PlaybackService::toggle_pause(&voice, GUILD, resume()).await.unwrap();
+        assert!(!paused);
+        assert!(resumed.load(Ordering::Relaxed));
+
+        let paused = PlaybackService::toggle_pause(&voice, GUILD, resume()).await.unwrap();
+        assert!(paused);
+        let pauses = voice.actions().iter().filter(|a| **a == Action::Pause).count();
+        assert_eq!(pauses, 2);
+    }
+
+    #[tokio::test]
+    async fn pause_button_without_a_call_is_not_connected() {
+        let voice = FakeBackend::default();
+
+        let result = PlaybackService::toggle_pause(&voice, GUILD, async {}).await;
+        assert!(matches!(result, Err(MusicError::NotConnected)));
+    }
+}
@@ -0,0 +1,48 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use poise::serenity_prelude::UserId;
+use tokio::sync::RwLock;
+
+use crate::domain::preferences::UserPreferences;
+use crate::infrastructure::preferences_store::PreferencesStore;
+
+pub type UserPreferencesMap = Arc<RwLock<HashMap<UserId, UserPreferences>>>;
+
+pub struct PreferencesService;
+
+impl PreferencesService {
+    pub fn new_user_preferences() -> UserPreferencesMap {
+        Arc::new(RwLock::new(HashMap::new()))
+    }
+
+    /// Every user's preferences as `store` last saved them.
+    pub async fn load(store: &PreferencesStore) -> UserPreferencesMap {
+        Arc::new(RwLock::new(store.load().await))
+    }
+
+    /// Writes every user's preferences to `store`. Holds the map while
+    /// writing, so the file can't fall behind a change made meanwhile.
+    pub async fn save(preferences: &UserPreferencesMap, store: &PreferencesStore) {
+        let map = preferences.read().await;
+        store.save(&map).await;
+    }
+
+    /// Returns a copy of the user's preferences, or the defaults if none were saved.
+    pub async fn get(preferences: &UserPreferencesMap, user_id: UserId) -> UserPreferences {
+        let map = preferences.read().await;
+        map.get(&user_id).cloned().unwrap_or_default()
+    }
+
+    /// Applies `f` to the user's preferences and returns the updated copy.
+    pub async fn update(
+        preferences: &UserPreferencesMap,
+        user_id: UserId,
+        f: impl FnOnce(&mut UserPreferences),
+    ) -> UserPreferences {
+        let mut map = preferences.write().await;
+        let entry = map.entry(user_id).or_default();
+        f(entry);
+        entry.clone()
+    }
+}
@@ -1,14 +1,18 @@
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use poise::serenity_prelude::GuildId;
 use tokio::sync::RwLock;
+use uuid::Uuid;
 
 use crate::domain::queue::MusicQueue;
 use crate::domain::track::Track;
 
+/// Every guild's [`MusicQueue`].
 pub type GuildQueues = Arc<RwLock<HashMap<GuildId, MusicQueue>>>;
 
+/// Operations on [`GuildQueues`], taking the lock for each call.
 pub struct QueueService;
 
 impl QueueService {
@@ -16,9 +20,47 @@ impl QueueService {
         Arc::new(RwLock::new(HashMap::new()))
     }
 
-    pub async fn add_track(queues: &GuildQueues, guild_id: GuildId, track: Track) {
+    /// Adds a track to the end of the queue, or into the requester rotation
+    /// when `fair` is set, stamped with the time it was added. Returns how
+    /// many tracks are pending now.
+    pub async fn add_track(
+        queues: &GuildQueues,
+        guild_id: GuildId,
+        mut track: Track,
+        fair: bool,
+    ) -> usize {
+        track.enqueued_at = Some(SystemTime::now());
         let mut map = queues.write().await;
-        map.entry(guild_id).or_default().push(track);
+        let queue = map.entry(guild_id).or_default();
+        if fair {
+            queue.push_fair(track);
+        } else {
+            queue.push(track);
+        }
+        queue.len()
+    }
+
+    /// Number of tracks waiting behind the current one.
+    pub async fn pending_len(queues: &GuildQueues, guild_id: GuildId) -> usize {
+        let map = queues.read().await;
+        map.get(&guild_id).map_or(0, |queue| queue.len())
+    }
+
+    /// Re-runs the fair-play rotation over the pending tracks.
+    pub async fn interleave(queues: &GuildQueues, guild_id: GuildId) {
+        let mut map = queues.write().await;
+        if let Some(queue) = map.get_mut(&guild_id) {
+            queue.interleave();
+        }
+    }
+
+    /// Songbird handle ids of the pending tracks, in queue order.
+    pub async fn pending_handle_ids(queues: &GuildQueues, guild_id: GuildId) -> Vec<Uuid> {
+        let map = queues.read().await;
+        match map.get(&guild_id) {
+            Some(queue) => queue.list().iter().filter_map(|t| t.handle_id).collect(),
+            None => Vec::new(),
+        }
     }
 
     /// Advances the queue: pops the next track into `current` and returns a clone.
@@ -34,10 +76,176 @@ impl QueueService {
         map.get(&guild_id)?.current().cloned()
     }
 
-    /// Takes the currently playing track out of the queue (used for skip messages).
-    pub async fn skip(queues: &GuildQueues, guild_id: GuildId) -> Option<Track> {
+    /// Returns a clone of the next pending track without advancing.
+    pub async fn peek(queues: &GuildQueues, guild_id: GuildId) -> Option<Track> {
+        let map = queues.read().await;
+        map.get(&guild_id)?.list().front().cloned()
+    }
+
+    /// The first `n` pending tracks.
+    pub async fn upcoming(queues: &GuildQueues, guild_id: GuildId, n: usize) -> Vec<Track> {
+        let map = queues.read().await;
+        match map.get(&guild_id) {
+            Some(queue) => queue.list().iter().take(n).cloned().collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Takes the current track out of the queue and returns the one songbird
+    /// is actually playing, `playing`, which may not have become current yet
+    /// if its Now Playing hasn't fired. Falls back to the current track.
+    pub async fn skip(queues: &GuildQueues, guild_id: GuildId, playing: Option<Uuid>) -> Option<Track> {
+        let mut map = queues.write().await;
+        let queue = map.get_mut(&guild_id)?;
+        let found = playing.and_then(|id| queue.find_by_handle(id).cloned());
+        let taken = queue.take_current();
+        found.or(taken)
+    }
+
+    /// Returns a clone of the current or pending track queued as `handle_id`.
+    pub async fn find_by_handle(
+        queues: &GuildQueues,
+        guild_id: GuildId,
+        handle_id: Uuid,
+    ) -> Option<Track> {
+        let map = queues.read().await;
+        map.get(&guild_id)?.find_by_handle(handle_id).cloned()
+    }
+
+    /// Records the loudness yt-dlp reported for the track queued as
+    /// `handle_id`, returning the updated track if it's still queued.
+    pub async fn set_loudness(
+        queues: &GuildQueues,
+        guild_id: GuildId,
+        handle_id: Uuid,
+        loudness: f64,
+    ) -> Option<Track> {
+        let mut map = queues.write().await;
+        let track = map.get_mut(&guild_id)?.find_by_handle_mut(handle_id)?;
+        track.loudness = Some(loudness);
+        Some(track.clone())
+    }
+
+    /// Records which YouTube upload the current track resolved to, and its
+    /// length when yt-dlp knew it. Returns `false` if `handle_id` is no
+    /// longer the current track.
+    pub async fn set_resolved(
+        queues: &GuildQueues,
+        guild_id: GuildId,
+        handle_id: Uuid,
+        url: String,
+        duration: Option<Duration>,
+    ) -> bool {
+        let mut map = queues.write().await;
+        match map.get_mut(&guild_id).and_then(|queue| queue.current_mut()) {
+            Some(track) if track.handle_id == Some(handle_id) => {
+                track.resolved_url = Some(url);
+                if duration.is_some() {
+                    track.duration = duration;
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves the pending track queued as `handle_id` to `index`, clamped to
+    /// the end of the queue. Returns whether the track was found.
+    pub async fn move_track(
+        queues: &GuildQueues,
+        guild_id: GuildId,
+        handle_id: Uuid,
+        index: usize,
+    ) -> bool {
+        let mut map = queues.write().await;
+        match map.get_mut(&guild_id) {
+            Some(queue) => queue.move_track(|t| t.handle_id == Some(handle_id), index),
+            None => false,
+        }
+    }
+
+    /// Moves every pending track matching `predicate` together to `index`,
+    /// keeping their order. Returns how many moved and where the first one
+    /// landed, or `None` if none matched.
+    pub async fn move_where(
+        queues: &GuildQueues,
+        guild_id: GuildId,
+        predicate: impl FnMut(&Track) -> bool,
+        index: usize,
+    ) -> Option<(usize, usize)> {
         let mut map = queues.write().await;
-        map.get_mut(&guild_id)?.take_current()
+        map.get_mut(&guild_id)?.move_where(predicate, index)
+    }
+
+    /// Removes every pending track matching `predicate` and returns them.
+    pub async fn remove_where(
+        queues: &GuildQueues,
+        guild_id: GuildId,
+        predicate: impl FnMut(&Track) -> bool,
+    ) -> Vec<Track> {
+        let mut map = queues.write().await;
+        match map.get_mut(&guild_id) {
+            Some(queue) => queue.remove_where(predicate),
+            None => Vec::new(),
+        }
+    }
+
+    /// Swaps the songbird handle a queued track points at, for when its input
+    /// is rebuilt. Returns whether the track was found.
+    pub async fn replace_handle(queues: &GuildQueues, guild_id: GuildId, old: Uuid, new: Uuid) -> bool {
+        let mut map = queues.write().await;
+        map.get_mut(&guild_id)
+            .is_some_and(|queue| queue.replace_handle(old, new))
+    }
+
+    /// Removes pending tracks picked by position and URL from an earlier
+    /// listing, in one go. See [`MusicQueue::remove_picked`].
+    pub async fn remove_picked(
+        queues: &GuildQueues,
+        guild_id: GuildId,
+        picks: &[(usize, String)],
+    ) -> (Vec<Track>, usize) {
+        let mut map = queues.write().await;
+        match map.get_mut(&guild_id) {
+            Some(queue) => queue.remove_picked(picks),
+            None => (Vec::new(), picks.len()),
+        }
+    }
+
+    /// Removes the track queued as songbird handle `handle_id`, whether it's
+    /// pending or the one playing.
+    pub async fn remove_by_handle(
+        queues: &GuildQueues,
+        guild_id: GuildId,
+        handle_id: Uuid,
+    ) -> Option<Track> {
+        let mut map = queues.write().await;
+        let queue = map.get_mut(&guild_id)?;
+        if queue.current().is_some_and(|t| t.handle_id == Some(handle_id)) {
+            return queue.take_current();
+        }
+        queue
+            .remove_where(|t| t.handle_id == Some(handle_id))
+            .into_iter()
+            .next()
+    }
+
+    /// The `n` most recently added pending tracks, newest first, paired with
+    /// their 1-based queue position. Only the returned tracks are cloned.
+    pub async fn recent(queues: &GuildQueues, guild_id: GuildId, n: usize) -> Vec<(usize, Track)> {
+        let map = queues.read().await;
+        let Some(queue) = map.get(&guild_id) else {
+            return Vec::new();
+        };
+
+        let tracks = queue.list();
+        let mut order: Vec<usize> = (0..tracks.len()).collect();
+        order.sort_by_key(|&i| std::cmp::Reverse(tracks[i].enqueued_at));
+        order
+            .into_iter()
+            .take(n)
+            .map(|i| (i + 1, tracks[i].clone()))
+            .collect()
     }
 
     pub async fn clear(queues: &GuildQueues, guild_id: GuildId) {
@@ -55,3 +263,79 @@ impl QueueService {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::track::test_track;
+
+    const GUILD: GuildId = GuildId::new(1);
+
+    /// Queues one track per URL, each with its own handle id, and starts the
+    /// first one.
+    async fn playing(urls: &[&str]) -> (GuildQueues, Vec<Uuid>) {
+        let queues = QueueService::new_guild_queues();
+        let mut ids = Vec::new();
+        for url in urls {
+            let mut track = test_track(url, None);
+            let id = Uuid::new_v4();
+            track.handle_id = Some(id);
+            QueueService::add_track(&queues, GUILD, track, false).await;
+            ids.push(id);
+        }
+        QueueService::advance(&queues, GUILD).await;
+        (queues, ids)
+    }
+
+    async fn listed(queues: &GuildQueues) -> Vec<String> {
+        let tracks = QueueService::list(queues, GUILD).await;
+        tracks.into_iter().map(|t| t.url).collect()
+    }
+
+    #[tokio::test]
+    async fn a_pending_track_that_fails_leaves_the_list() {
+        let (queues, ids) = playing(&["a", "b", "c", "d"]).await;
+
+        let failed = QueueService::remove_by_handle(&queues, GUILD, ids[2]).await;
+        assert_eq!(failed.map(|t| t.url).as_deref(), Some("c"));
+        assert_eq!(listed(&queues).await, ["b", "d"]);
+        assert_eq!(QueueService::pending_handle_ids(&queues, GUILD).await, [ids[1], ids[3]]);
+    }
+
+    #[tokio::test]
+    async fn the_playing_track_failing_clears_current_without_shifting_the_queue() {
+        let (queues, ids) = playing(&["a", "b", "c"]).await;
+
+        let failed = QueueService::remove_by_handle(&queues, GUILD, ids[0]).await;
+        assert_eq!(failed.map(|t| t.url).as_deref(), Some("a"));
+        assert!(QueueService::current(&queues, GUILD).await.is_none());
+        assert_eq!(listed(&queues).await, ["b", "c"]);
+
+        // Songbird moves on to b, which Now Playing then advances to
+        let next = QueueService::advance(&queues, GUILD).await;
+        assert_eq!(next.map(|t| t.url).as_deref(), Some("b"));
+    }
+
+    #[tokio::test]
+    async fn skipping_after_a_failure_still_matches_songbird() {
+        let (queues, ids) = playing(&["a", "b", "c"]).await;
+        QueueService::remove_by_handle(&queues, GUILD, ids[1]).await;
+
+        // Songbird dropped b as well, so c is next behind a
+        let skipped = QueueService::skip(&queues, GUILD, Some(ids[0])).await;
+        assert_eq!(skipped.map(|t| t.url).as_deref(), Some("a"));
+        let next = QueueService::find_by_handle(&queues, GUILD, ids[2]).await;
+        assert_eq!(next.map(|t| t.url).as_deref(), Some("c"));
+    }
+
+    #[tokio::test]
+    async fn an_unknown_handle_removes_nothing() {
+        let (queues, _) = playing(&["a", "b"]).await;
+
+        let failed = QueueService::remove_by_handle(&queues, GUILD, Uuid::new_v4()).await;
+        assert!(failed.is_none());
+        assert_eq!(listed(&queues).await, ["b"]);
+        let other = QueueService::remove_by_handle(&queues, GuildId::new(2), Uuid::new_v4()).await;
+        assert!(other.is_none());
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use poise::serenity_prelude::GuildId;
+use tokio::sync::RwLock;
+
+use crate::domain::schedule::ScheduledJob;
+use crate::infrastructure::schedule_store::ScheduleStore;
+
+pub type ScheduledJobs = Arc<RwLock<HashMap<GuildId, Vec<ScheduledJob>>>>;
+
+pub struct ScheduleService;
+
+impl ScheduleService {
+    pub fn new_scheduled_jobs() -> ScheduledJobs {
+        Arc::new(RwLock::new(HashMap::new()))
+    }
+
+    /// Every guild's jobs as `store` last saved them.
+    pub async fn load(store: &ScheduleStore) -> ScheduledJobs {
+        Arc::new(RwLock::new(store.load().await))
+    }
+
+    /// Writes every guild's jobs to `store`. Holds the map while writing, so
+    /// the file can't fall behind a change made meanwhile.
+    pub async fn save(jobs: &ScheduledJobs, store: &ScheduleStore) {
+        let map = jobs.read().await;
+        store.save(&map).await;
+    }
+
+    /// Stores `job` under the next free id and returns it with that id set.
+    pub async fn add(jobs: &ScheduledJobs, guild_id: GuildId, mut job: ScheduledJob) -> ScheduledJob {
+        let mut map = jobs.write().await;
+        let guild_jobs = map.entry(guild_id).or_default();
+        job.id = guild_jobs.iter().map(|j| j.id).max().unwrap_or(0) + 1;
+        guild_jobs.push(job.clone());
+        job
+    }
+
+    /// The guild's pending jobs, soonest first.
+    pub async fn list(jobs: &ScheduledJobs, guild_id: GuildId) -> Vec<ScheduledJob> {
+        let map = jobs.read().await;
+        let mut guild_jobs = map.get(&guild_id).cloned().unwrap_or_default();
+        guild_jobs.sort_by_key(|job| job.at);
+        guild_jobs
+    }
+
+    pub async fn remove(jobs: &ScheduledJobs, guild_id: GuildId, id: u32) -> Option<ScheduledJob> {
+        let mut map = jobs.write().await;
+        let guild_jobs = map.get_mut(&guild_id)?;
+        let index = guild_jobs.iter().position(|job| job.id == id)?;
+        Some(guild_jobs.remove(index))
+    }
+
+    /// Removes and returns every job that is due at `now`.
+    pub async fn take_due(jobs: &ScheduledJobs, now: DateTime<Utc>) -> Vec<(GuildId, ScheduledJob)> {
+        let mut map = jobs.write().await;
+        let mut due = Vec::new();
+        for (guild_id, guild_jobs) in map.iter_mut() {
+            let (ready, pending): (Vec<_>, Vec<_>) =
+                guild_jobs.drain(..).partition(|job| job.at <= now);
+            *guild_jobs = pending;
+            due.extend(ready.into_iter().map(|job| (*guild_id, job)));
+        }
+        map.retain(|_, guild_jobs| !guild_jobs.is_empty());
+        due
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeDelta;
+    use poise::serenity_prelude::{ChannelId, UserId};
+
+    use super::*;
+
+    const GUILD: GuildId = GuildId::new(1);
+
+    fn job(at: DateTime<Utc>, query: &str) -> ScheduledJob {
+        ScheduledJob {
+            id: 0,
+            at,
+            voice_channel: ChannelId::new(10),
+            text_channel: ChannelId::new(11),
+            query: query.to_string(),
+            created_by: UserId::new(12),
+        }
+    }
+
+    #[tokio::test]
+    async fn only_due_jobs_are_taken_and_the_rest_stay_armed() {
+        let jobs = ScheduleService::new_scheduled_jobs();
+        let now = Utc::now();
+        ScheduleService::add(&jobs, GUILD, job(now + TimeDelta::hours(1), "later")).await;
+        ScheduleService::add(&jobs, GUILD, job(now - TimeDelta::seconds(5), "due")).await;
+
+        let due = ScheduleService::take_due(&jobs, now).await;
+        let due: Vec<(u32, &str)> = due.iter().map(|(_, j)| (j.id, j.query.as_str())).collect();
+        assert_eq!(due, [(2, "due")]);
+
+        let left = ScheduleService::list(&jobs, GUILD).await;
+        assert_eq!(left.len(), 1);
+        assert_eq!(left[0].query, "later");
+        assert!(ScheduleService::take_due(&jobs, now).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn ids_keep_counting_up_past_removed_jobs() {
+        let jobs = ScheduleService::new_scheduled_jobs();
+        let at = Utc::now() + TimeDelta::hours(1);
+        ScheduleService::add(&jobs, GUILD, job(at, "a")).await;
+        let b = ScheduleService::add(&jobs, GUILD, job(at, "b")).await;
+        ScheduleService::remove(&jobs, GUILD, 1).await.unwrap();
+
+        let c = ScheduleService::add(&jobs, GUILD, job(at, "c")).await;
+        assert_eq!((b.id, c.id), (2, 3));
+        assert!(ScheduleService::remove(&jobs, GUILD, 1).await.is_none());
+    }
+}
@@ -0,0 +1,71 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use poise::serenity_prelude::GuildId;
+
+use crate::services::guild_state::Sessions;
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Voice session ids, which let the several handlers watching a session
+/// agree on which one of them ends it.
+pub struct SessionService;
+
+impl SessionService {
+    /// Starts a new session for the guild and returns its id.
+    pub async fn start(sessions: &Sessions, guild_id: GuildId) -> u64 {
+        let session = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+        sessions.write().await.insert(guild_id, session);
+        session
+    }
+
+    /// Ends `session` if it's still the guild's live one. Returns whether
+    /// this call ended it, which only one caller per session ever sees.
+    pub async fn end(sessions: &Sessions, guild_id: GuildId, session: u64) -> bool {
+        let mut sessions = sessions.write().await;
+        if sessions.get(&guild_id) != Some(&session) {
+            return false;
+        }
+        sessions.remove(&guild_id);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use super::*;
+
+    const GUILD: GuildId = GuildId::new(1);
+
+    #[tokio::test]
+    async fn the_monitor_and_the_disconnect_handler_clean_up_once() {
+        let sessions = Sessions::default();
+        let session = SessionService::start(&sessions, GUILD).await;
+        let cleanups = AtomicUsize::new(0);
+
+        // Both see the same session end and race to tear it down
+        let cleanup = || async {
+            tokio::task::yield_now().await;
+            if SessionService::end(&sessions, GUILD, session).await {
+                cleanups.fetch_add(1, Ordering::SeqCst);
+            }
+        };
+        tokio::join!(cleanup(), cleanup());
+
+        assert_eq!(cleanups.load(Ordering::SeqCst), 1);
+        assert!(!sessions.read().await.contains_key(&GUILD));
+    }
+
+    #[tokio::test]
+    async fn a_late_handler_from_the_last_session_leaves_the_new_one_alone() {
+        let sessions = Sessions::default();
+        let old = SessionService::start(&sessions, GUILD).await;
+        assert!(SessionService::end(&sessions, GUILD, old).await);
+        let new = SessionService::start(&sessions, GUILD).await;
+
+        assert_ne!(old, new);
+        assert!(!SessionService::end(&sessions, GUILD, old).await);
+        assert_eq!(sessions.read().await.get(&GUILD), Some(&new));
+    }
+}
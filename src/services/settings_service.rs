@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use poise::serenity_prelude::GuildId;
+use tokio::sync::RwLock;
+
+use crate::domain::settings::GuildSettings;
+use crate::infrastructure::settings_store::SettingsStore;
+
+pub type GuildSettingsMap = Arc<RwLock<HashMap<GuildId, GuildSettings>>>;
+
+pub struct SettingsService;
+
+impl SettingsService {
+    pub fn new_guild_settings() -> GuildSettingsMap {
+        Arc::new(RwLock::new(HashMap::new()))
+    }
+
+    /// Every guild's settings as `store` last saved them.
+    pub async fn load(store: &SettingsStore) -> GuildSettingsMap {
+        Arc::new(RwLock::new(store.load().await))
+    }
+
+    /// Writes every guild's settings to `store`. Holds the map while
+    /// writing, so the file can't fall behind a change made meanwhile.
+    pub async fn save(settings: &GuildSettingsMap, store: &SettingsStore) {
+        let map = settings.read().await;
+        store.save(&map).await;
+    }
+
+    /// Returns a copy of the guild's settings, or the defaults if none were saved.
+    pub async fn get(settings: &GuildSettingsMap, guild_id: GuildId) -> GuildSettings {
+        let map = settings.read().await;
+        map.get(&guild_id).cloned().unwrap_or_default()
+    }
+
+    /// Applies `f` to the guild's settings and returns the updated copy.
+    pub async fn update(
+        settings: &GuildSettingsMap,
+        guild_id: GuildId,
+        f: impl FnOnce(&mut GuildSettings),
+    ) -> GuildSettings {
+        let mut map = settings.write().await;
+        let entry = map.entry(guild_id).or_default();
+        f(entry);
+        entry.clone()
+    }
+
+    /// Parses a hex colour such as `#1DB954`, `1db954` or `0x1DB954`.
+    pub fn parse_hex_colour(value: &str) -> Option<u32> {
+        let value = value.trim();
+        let hex = value
+            .strip_prefix('#')
+            .or_else(|| value.strip_prefix("0x"))
+            .unwrap_or(value);
+        if hex.len() != 6 {
+            return None;
+        }
+        u32::from_str_radix(hex, 16).ok()
+    }
+}
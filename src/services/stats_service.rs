@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use poise::serenity_prelude::GuildId;
+use tokio::sync::RwLock;
+
+use crate::domain::stats::GuildStats;
+use crate::domain::track::Track;
+use crate::infrastructure::stats_store::StatsStore;
+
+pub type GuildStatsMap = Arc<RwLock<HashMap<GuildId, GuildStats>>>;
+
+pub struct StatsService;
+
+impl StatsService {
+    pub fn new_guild_stats() -> GuildStatsMap {
+        Arc::new(RwLock::new(HashMap::new()))
+    }
+
+    /// Every guild's stats as `store` last saved them.
+    pub async fn load(store: &StatsStore) -> GuildStatsMap {
+        Arc::new(RwLock::new(store.load().await))
+    }
+
+    /// Writes a copy of every guild's stats to `store`, so plays can still
+    /// be recorded during the write. Only the stats saver calls this, so an
+    /// older copy can't land after a newer one.
+    pub async fn save(stats: &GuildStatsMap, store: &StatsStore) {
+        let snapshot = stats.read().await.clone();
+        store.save(&snapshot).await;
+    }
+
+    /// Records a listen of `track`. Returns whether it counted as a play.
+    pub async fn record(
+        stats: &GuildStatsMap,
+        guild_id: GuildId,
+        track: &Track,
+        listened: Duration,
+    ) -> bool {
+        let mut map = stats.write().await;
+        map.entry(guild_id).or_default().record(track, listened)
+    }
+
+    /// Returns a copy of the guild's stats, empty if nothing was played yet.
+    pub async fn get(stats: &GuildStatsMap, guild_id: GuildId) -> GuildStats {
+        let map = stats.read().await;
+        map.get(&guild_id).cloned().unwrap_or_default()
+    }
+
+    pub async fn reset(stats: &GuildStatsMap, guild_id: GuildId) {
+        stats.write().await.remove(&guild_id);
+    }
+}
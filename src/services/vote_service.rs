@@ -0,0 +1,90 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use poise::serenity_prelude::{GuildId, MessageId, UserId};
+use tokio::sync::{Notify, RwLock};
+
+use crate::domain::vote::VotePoll;
+
+/// A poll that is still taking votes.
+pub struct OpenPoll {
+    poll: VotePoll,
+    /// Woken when the poll is cancelled before it closes.
+    cancelled: Arc<Notify>,
+}
+
+/// Open `/vote` polls, keyed by the poll message.
+pub type VotePolls = Arc<RwLock<HashMap<MessageId, OpenPoll>>>;
+
+pub struct VoteService;
+
+impl VoteService {
+    pub fn new_vote_polls() -> VotePolls {
+        Arc::new(RwLock::new(HashMap::new()))
+    }
+
+    /// Who started the guild's open poll, if it has one.
+    pub async fn open_in(polls: &VotePolls, guild_id: GuildId) -> Option<UserId> {
+        let map = polls.read().await;
+        map.values()
+            .find(|open| open.poll.guild_id == guild_id)
+            .map(|open| open.poll.started_by)
+    }
+
+    /// Opens `poll` on `message_id`, unless its guild already has a poll
+    /// open. Returns the signal woken when the poll is cancelled.
+    pub async fn open(
+        polls: &VotePolls,
+        message_id: MessageId,
+        poll: VotePoll,
+    ) -> Option<Arc<Notify>> {
+        let mut map = polls.write().await;
+        if map.values().any(|open| open.poll.guild_id == poll.guild_id) {
+            return None;
+        }
+        let cancelled = Arc::new(Notify::new());
+        map.insert(
+            message_id,
+            OpenPoll {
+                poll,
+                cancelled: cancelled.clone(),
+            },
+        );
+        Some(cancelled)
+    }
+
+    /// Records `user`'s vote, returning the new tally. `None` once the poll
+    /// has closed or for an option it doesn't have.
+    pub async fn vote(
+        polls: &VotePolls,
+        message_id: MessageId,
+        user: UserId,
+        choice: usize,
+    ) -> Option<Vec<usize>> {
+        let mut map = polls.write().await;
+        let open = map.get_mut(&message_id)?;
+        open.poll.vote(user, choice).then(|| open.poll.tally())
+    }
+
+    /// Closes the poll, returning how it ended. `None` if it was cancelled.
+    pub async fn close(polls: &VotePolls, message_id: MessageId) -> Option<VotePoll> {
+        polls.write().await.remove(&message_id).map(|open| open.poll)
+    }
+
+    /// Cancels the guild's open poll. Returns whether there was one.
+    pub async fn cancel(polls: &VotePolls, guild_id: GuildId) -> bool {
+        let mut map = polls.write().await;
+        let Some(message_id) = map
+            .iter()
+            .find(|(_, open)| open.poll.guild_id == guild_id)
+            .map(|(id, _)| *id)
+        else {
+            return false;
+        };
+        if let Some(open) = map.remove(&message_id) {
+            // A stored permit, so the poll sees it even if it isn't waiting yet
+            open.cancelled.notify_one();
+        }
+        true
+    }
+}